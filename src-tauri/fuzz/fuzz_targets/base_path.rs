@@ -0,0 +1,22 @@
+#![no_main]
+
+use apiflow_lib::rewrite::{matches_base_path, normalize_base_path, rewrite_path};
+use libfuzzer_sys::fuzz_target;
+
+// 输入格式：`base_path \0 请求路径 \0 上游地址`
+fuzz_target!(|data: &str| {
+    let mut parts = data.splitn(3, '\0');
+    let (Some(base), Some(path), Some(upstream)) = (parts.next(), parts.next(), parts.next()) else {
+        return;
+    };
+
+    let base = normalize_base_path(base);
+    assert!(base.starts_with('/'));
+    assert!(base == "/" || !base.ends_with('/'));
+    assert_eq!(normalize_base_path(&base), base);
+
+    if matches_base_path(path, &base) {
+        let url = rewrite_path(path, &base, upstream);
+        assert!(url.starts_with(upstream.trim_end_matches('/')));
+    }
+});
@@ -0,0 +1,43 @@
+#![no_main]
+
+use apiflow_lib::rewrite::{format_upstream_headers, rewrite_upstream_headers};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use libfuzzer_sys::fuzz_target;
+
+// 输入格式：首行为上游 api key（空行表示未配置），其余每行为 `name: value` 形式的客户端请求头
+fuzz_target!(|data: &[u8]| {
+    let mut lines = data.split(|b| *b == b'\n');
+    let api_key = lines
+        .next()
+        .and_then(|l| std::str::from_utf8(l).ok())
+        .filter(|k| !k.is_empty());
+
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        let Some(pos) = line.iter().position(|b| *b == b':') else {
+            continue;
+        };
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(&line[..pos]),
+            HeaderValue::from_bytes(line[pos + 1..].trim_ascii()),
+        ) else {
+            continue;
+        };
+        headers.append(name, value);
+    }
+
+    let rewritten = rewrite_upstream_headers(&headers, api_key);
+    assert!(!rewritten.contains_key("host"));
+    assert!(!rewritten.contains_key("content-length"));
+    assert!(!rewritten.contains_key("x-proxy-key"));
+    assert!(rewritten.get_all("authorization").iter().count() <= 1);
+    if let Some(key) = api_key {
+        // 配置了 key 时发出的凭证只能是该 key，绝不能把客户端的凭证转发给上游
+        for name in ["authorization", "x-goog-api-key"] {
+            if let Some(sent) = rewritten.get(name) {
+                assert!(sent.as_bytes().ends_with(key.as_bytes()));
+            }
+        }
+    }
+    let _ = format_upstream_headers(&rewritten);
+});
@@ -0,0 +1,101 @@
+//! 服务的禁用时段：在设定的本地时间段内拒绝该服务的全部请求并返回 403，
+//! 例如夜间无人值守时禁止无人看管的代理程序调用昂贵的模型。
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/QuietWindow.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct QuietWindow {
+    /// 开始时间（本地时间 HH:MM）
+    pub start: String,
+    /// 结束时间（HH:MM），早于开始时间时表示跨越午夜到次日
+    pub end: String,
+    /// 生效的星期（1 为周一，7 为周日），按开始时间所在的日期判断；未设置时每天生效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub days: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/AccessSchedule.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct AccessSchedule {
+    pub quiet_hours: Vec<QuietWindow>,
+    /// 拒绝请求时返回的提示，未设置时使用默认提示
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub message: Option<String>,
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+impl QuietWindow {
+    fn bounds(&self) -> Option<(NaiveTime, NaiveTime)> {
+        Some((parse_time(&self.start)?, parse_time(&self.end)?))
+    }
+
+    fn applies_on(&self, weekday: u32) -> bool {
+        self.days
+            .as_ref()
+            .is_none_or(|days| days.contains(&weekday))
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let Some((start, end)) = self.bounds() else {
+            return false;
+        };
+        let time = now.time();
+        let today = now.weekday().number_from_monday();
+        if start <= end {
+            return start <= time && time < end && self.applies_on(today);
+        }
+        // 跨越午夜：开始当天的晚段，或前一天开始、延续到今天的早段
+        let yesterday = (now - Duration::days(1)).weekday().number_from_monday();
+        (time >= start && self.applies_on(today)) || (time < end && self.applies_on(yesterday))
+    }
+}
+
+impl AccessSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for window in &self.quiet_hours {
+            let (start, end) = window.bounds().ok_or_else(|| {
+                format!(
+                    "禁用时段的时间格式应为 HH:MM: {}-{}",
+                    window.start, window.end
+                )
+            })?;
+            if start == end {
+                return Err(format!(
+                    "禁用时段的开始与结束时间不能相同: {}",
+                    window.start
+                ));
+            }
+            if window.days.iter().flatten().any(|d| !(1..=7).contains(d)) {
+                return Err("禁用时段的星期需在 1（周一）到 7（周日）之间".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// 当前处于禁用时段时返回拒绝请求的提示
+    pub fn rejection_at(&self, service_name: &str, now: NaiveDateTime) -> Option<String> {
+        let window = self.quiet_hours.iter().find(|w| w.contains(now))?;
+        Some(
+            self.message
+                .clone()
+                .filter(|m| !m.trim().is_empty())
+                .unwrap_or_else(|| {
+                    format!(
+                        "服务「{service_name}」在 {}-{} 禁止访问，请稍后再试",
+                        window.start.trim(),
+                        window.end.trim()
+                    )
+                }),
+        )
+    }
+}
@@ -0,0 +1,425 @@
+//! 管理 REST API：在本机单独端口上以 HTTP 形式暴露启停、重载、日志与统计等命令，
+//! 供脚本、CI 或浏览器在不打开桌面界面时控制 ApiFlow。所有请求需携带管理令牌。
+
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use arc_swap::ArcSwap;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State as TauriState};
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+use crate::admin_auth::{authorize, AdminScope, AdminToken};
+use crate::persistence::load_config;
+use crate::stats::StatsGroupBy;
+use crate::{error_response, ProxyConfig, ProxyState};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/AdminApiConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct AdminApiConfig {
+    /// 仅监听 127.0.0.1，需与所有代理端口不同
+    pub port: u16,
+}
+
+impl AdminApiConfig {
+    pub fn validate(&self, listen_ports: &[u16], tokens: Option<&[AdminToken]>) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("管理接口端口无效".into());
+        }
+        if listen_ports.contains(&self.port) {
+            return Err("管理接口端口不能与代理端口相同".into());
+        }
+        if tokens.is_none_or(|t| t.is_empty()) {
+            return Err("启用管理接口前需至少配置一个管理令牌".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct AdminState {
+    tokens: Arc<ArcSwap<Vec<AdminToken>>>,
+}
+
+struct RunningAdmin {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static SERVER: Mutex<Option<RunningAdmin>> = Mutex::new(None);
+
+fn admin_tokens() -> &'static Arc<ArcSwap<Vec<AdminToken>>> {
+    static TOKENS: OnceLock<Arc<ArcSwap<Vec<AdminToken>>>> = OnceLock::new();
+    TOKENS.get_or_init(Arc::default)
+}
+
+/// 应用启动时调用：记录 AppHandle 供接口调用命令，并按已保存的配置启动管理接口
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+    if let Some(config) = load_config().ok().flatten() {
+        if let Err(err) = configure(config.admin_api.as_ref(), config.admin_tokens.as_deref()) {
+            eprintln!("{err}");
+        }
+    }
+}
+
+/// 按配置启动、切换端口或关闭管理接口；令牌变更立即生效，无需重启
+pub fn configure(
+    config: Option<&AdminApiConfig>,
+    tokens: Option<&[AdminToken]>,
+) -> Result<(), String> {
+    admin_tokens().store(Arc::new(tokens.unwrap_or_default().to_vec()));
+
+    let mut server = SERVER.lock().unwrap_or_else(|e| e.into_inner());
+    let port = config.map(|c| c.port);
+    if server.as_ref().map(|s| s.port) == port {
+        return Ok(());
+    }
+    if let Some(previous) = server.take() {
+        let _ = previous.shutdown.send(());
+    }
+    let Some(port) = port else {
+        return Ok(());
+    };
+
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .and_then(|l| l.set_nonblocking(true).map(|_| l))
+        .map_err(|e| format!("管理接口监听端口 {port} 失败: {e}"))?;
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let app = router(AdminState {
+        tokens: admin_tokens().clone(),
+    });
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("管理接口启动失败: {err}");
+                return;
+            }
+        };
+        let served = axum::serve(listener, app).with_graceful_shutdown(async move {
+            let _ = shutdown_rx.await;
+        });
+        if let Err(err) = served.await {
+            eprintln!("管理接口异常退出: {err}");
+        }
+    });
+    *server = Some(RunningAdmin { port, shutdown });
+    Ok(())
+}
+
+pub(crate) fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/api/status", get(status))
+        .route("/api/proxy/start", post(start))
+        .route("/api/proxy/stop", post(stop))
+        .route("/api/proxy/reload", post(reload))
+        .route("/api/settings", get(load_settings).put(save_settings))
+        .route("/api/services/:id/pause", post(pause_service))
+        .route("/api/services/:id/resume", post(resume_service))
+        .route(
+            "/api/services/:id/upstreams/:upstream_id/enable",
+            post(enable_upstream),
+        )
+        .route(
+            "/api/services/:id/upstreams/:upstream_id/disable",
+            post(disable_upstream),
+        )
+        .route("/api/logs", get(logs).delete(clear_logs))
+        .route("/api/logs/:id", get(log_detail))
+        .route("/api/stats", get(stats).delete(clear_stats))
+        .route("/api/stats/breakdown", get(stats_breakdown))
+        .route("/api/spend", get(spend))
+        .route("/api/budgets", get(budgets))
+        .with_state(state)
+}
+
+fn json_response<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(payload) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(payload))
+            .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "序列化失败")),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "序列化失败"),
+    }
+}
+
+/// 校验令牌权限后调用对应命令，命令返回的错误以 400 返回
+async fn call<T, F, Fut>(
+    admin: &AdminState,
+    headers: &HeaderMap,
+    scope: AdminScope,
+    command: F,
+) -> Response<Body>
+where
+    T: Serialize,
+    F: FnOnce(TauriState<'static, ProxyState>) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    if let Err((status, msg)) = authorize(&admin.tokens.load(), headers, scope) {
+        return error_response(status, msg);
+    }
+    let Some(app) = APP.get() else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "管理接口尚未就绪");
+    };
+    match command(app.state::<ProxyState>()).await {
+        Ok(value) => json_response(&value),
+        Err(msg) => error_response(StatusCode::BAD_REQUEST, &msg),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminStatus {
+    running_ports: Vec<u16>,
+}
+
+async fn status(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(
+        &admin,
+        &headers,
+        AdminScope::ReadStats,
+        |state| async move {
+            let mut running_ports: Vec<u16> = state.inner.lock().await.keys().copied().collect();
+            running_ports.sort_unstable();
+            Ok(AdminStatus { running_ports })
+        },
+    )
+    .await
+}
+
+/// 钩子会执行本地命令，新配置中的钩子与已保存的不同时还需具备管理钩子的权限
+fn authorize_hooks(
+    admin: &AdminState,
+    headers: &HeaderMap,
+    config: &ProxyConfig,
+) -> Result<(), (StatusCode, &'static str)> {
+    let saved = load_config().ok().flatten().and_then(|c| c.hooks);
+    if config.hooks.as_deref().unwrap_or_default() == saved.as_deref().unwrap_or_default() {
+        return Ok(());
+    }
+    authorize(&admin.tokens.load(), headers, AdminScope::ManageHooks).map(|_| ())
+}
+
+/// 未提供配置时使用已保存的设置启动；携带配置启动会保存配置、替换管理令牌并执行钩子命令，
+/// 因此还需具备修改配置的权限
+async fn start(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    config: Option<Json<ProxyConfig>>,
+) -> Response<Body> {
+    if let Some(Json(config)) = &config {
+        if let Err((status, msg)) =
+            authorize(&admin.tokens.load(), &headers, AdminScope::WriteConfig)
+        {
+            return error_response(status, msg);
+        }
+        if let Err((status, msg)) = authorize_hooks(&admin, &headers, config) {
+            return error_response(status, msg);
+        }
+    }
+    call(&admin, &headers, AdminScope::Control, |state| async move {
+        let config = match config {
+            Some(Json(config)) => config,
+            None => load_config()?.ok_or("尚未保存任何配置")?,
+        };
+        crate::start_proxy(config, state).await
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StopQuery {
+    listen_port: Option<u16>,
+}
+
+async fn stop(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Query(query): Query<StopQuery>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, |state| {
+        crate::stop_proxy(query.listen_port, state)
+    })
+    .await
+}
+
+async fn reload(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Json(config): Json<ProxyConfig>,
+) -> Response<Body> {
+    if let Err((status, msg)) = authorize_hooks(&admin, &headers, &config) {
+        return error_response(status, msg);
+    }
+    call(&admin, &headers, AdminScope::WriteConfig, |state| {
+        crate::reload_proxy(config, state)
+    })
+    .await
+}
+
+/// 配置中含上游 key，读取同样需要写配置权限
+async fn load_settings(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(&admin, &headers, AdminScope::WriteConfig, |_| {
+        crate::load_settings()
+    })
+    .await
+}
+
+async fn save_settings(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Json(config): Json<ProxyConfig>,
+) -> Response<Body> {
+    if let Err((status, msg)) = authorize_hooks(&admin, &headers, &config) {
+        return error_response(status, msg);
+    }
+    call(&admin, &headers, AdminScope::WriteConfig, |state| {
+        crate::save_settings(config, state)
+    })
+    .await
+}
+
+async fn pause_service(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, |state| {
+        crate::pause_service(id, state)
+    })
+    .await
+}
+
+async fn resume_service(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, |state| {
+        crate::resume_service(id, state)
+    })
+    .await
+}
+
+async fn enable_upstream(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Path((id, upstream_id)): Path<(String, String)>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, |state| {
+        crate::set_upstream_enabled(id, upstream_id, true, state)
+    })
+    .await
+}
+
+async fn disable_upstream(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Path((id, upstream_id)): Path<(String, String)>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, |state| {
+        crate::set_upstream_enabled(id, upstream_id, false, state)
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogsQuery {
+    limit: Option<usize>,
+    listen_port: Option<u16>,
+    before_id: Option<String>,
+    after_id: Option<String>,
+}
+
+async fn logs(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Query(query): Query<LogsQuery>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::ReadLogs, |state| {
+        crate::get_logs(
+            query.limit,
+            query.listen_port,
+            None,
+            query.before_id,
+            query.after_id,
+            state,
+        )
+    })
+    .await
+}
+
+async fn log_detail(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::ReadLogs, |state| {
+        crate::get_log_detail(id, state)
+    })
+    .await
+}
+
+async fn clear_logs(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, crate::clear_logs).await
+}
+
+async fn stats(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(&admin, &headers, AdminScope::ReadStats, crate::get_stats).await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BreakdownQuery {
+    group_by: StatsGroupBy,
+}
+
+async fn stats_breakdown(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    Query(query): Query<BreakdownQuery>,
+) -> Response<Body> {
+    call(&admin, &headers, AdminScope::ReadStats, |state| {
+        crate::get_stats_breakdown(query.group_by, state)
+    })
+    .await
+}
+
+async fn clear_stats(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(&admin, &headers, AdminScope::Control, crate::clear_stats).await
+}
+
+async fn spend(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(
+        &admin,
+        &headers,
+        AdminScope::ReadStats,
+        crate::get_spend_summary,
+    )
+    .await
+}
+
+async fn budgets(State(admin): State<AdminState>, headers: HeaderMap) -> Response<Body> {
+    call(
+        &admin,
+        &headers,
+        AdminScope::ReadStats,
+        crate::get_budget_status,
+    )
+    .await
+}
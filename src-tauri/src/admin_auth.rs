@@ -0,0 +1,95 @@
+use http::{header, HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// 管理令牌的最短长度，避免配置出容易被猜中的令牌
+const MIN_TOKEN_LEN: usize = 16;
+
+/// 管理接口的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/AdminScope.ts")]
+#[serde(rename_all = "kebab-case")]
+pub enum AdminScope {
+    /// 查看请求日志
+    ReadLogs,
+    /// 查看统计与费用
+    ReadStats,
+    /// 修改配置（含上游 key），携带新配置启动代理时也需要
+    WriteConfig,
+    /// 启停代理、清空日志/统计
+    Control,
+    /// 新增或修改钩子；钩子可执行本地命令，修改配置的权限不包含此项
+    ManageHooks,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/AdminToken.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct AdminToken {
+    /// 仅用于展示和审计的名称
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<AdminScope>,
+}
+
+impl AdminToken {
+    pub fn allows(&self, scope: AdminScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+pub fn validate_admin_tokens(tokens: &[AdminToken]) -> Result<(), String> {
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.name.trim().is_empty() {
+            return Err("管理令牌名称不能为空".into());
+        }
+        if token.token.trim().len() < MIN_TOKEN_LEN {
+            return Err(format!(
+                "管理令牌 {} 长度不能少于 {MIN_TOKEN_LEN} 个字符",
+                token.name
+            ));
+        }
+        if token.scopes.is_empty() {
+            return Err(format!("管理令牌 {} 至少需要一个权限", token.name));
+        }
+        if tokens[..idx].iter().any(|t| t.token == token.token) {
+            return Err(format!("管理令牌 {} 与其他令牌重复", token.name));
+        }
+    }
+    Ok(())
+}
+
+/// 逐字节比较且耗时与内容无关，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 从 `Authorization: Bearer <token>` 中取出管理令牌
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// 校验管理请求：令牌缺失或无效返回 401，权限不足返回 403
+pub fn authorize<'a>(
+    tokens: &'a [AdminToken],
+    headers: &HeaderMap,
+    scope: AdminScope,
+) -> Result<&'a AdminToken, (StatusCode, &'static str)> {
+    let provided = bearer_token(headers).ok_or((StatusCode::UNAUTHORIZED, "缺少管理令牌"))?;
+    let token = tokens
+        .iter()
+        .find(|t| constant_time_eq(t.token.as_bytes(), provided.as_bytes()))
+        .ok_or((StatusCode::UNAUTHORIZED, "管理令牌无效"))?;
+    if !token.allows(scope) {
+        return Err((StatusCode::FORBIDDEN, "管理令牌无此操作权限"));
+    }
+    Ok(token)
+}
@@ -0,0 +1,198 @@
+//! 应用自身的资源占用：进程内存、进行中的请求与流式转发数、内存中的日志与持久化存储大小。
+//! 日志缓冲或流式响应收集的内存超过阈值时给出提醒，便于排查长时间运行后内存持续增长的问题。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use ts_rs::TS;
+
+use crate::ProxyLogEntry;
+
+const LOG_BUFFER_WARN_BYTES: u64 = 256 * 1024 * 1024;
+const STREAM_BUFFER_WARN_BYTES: u64 = 128 * 1024 * 1024;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static ACTIVE_REQUESTS: AtomicU64 = AtomicU64::new(0);
+static ACTIVE_STREAMS: AtomicU64 = AtomicU64::new(0);
+static STREAM_BUFFER_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/AppMetrics.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct AppMetrics {
+    /// 进程常驻内存，当前平台无法获取时为空
+    #[ts(type = "number | null")]
+    pub resident_bytes: Option<u64>,
+    /// 正在处理的客户端请求数
+    #[ts(type = "number")]
+    pub active_requests: u64,
+    /// 正在转发的流式响应数
+    #[ts(type = "number")]
+    pub active_streams: u64,
+    /// 流式响应为写入日志而收集的响应体字节数
+    #[ts(type = "number")]
+    pub stream_buffer_bytes: u64,
+    /// 内存中的日志条数
+    #[ts(type = "number")]
+    pub log_entries: u64,
+    /// 内存中日志的估算大小
+    #[ts(type = "number")]
+    pub log_buffer_bytes: u64,
+    /// 日志持久化存储占用的磁盘空间，不在本地存储时为空
+    #[ts(type = "number | null")]
+    pub log_store_bytes: Option<u64>,
+    /// 超出阈值的提醒
+    pub warnings: Vec<String>,
+}
+
+/// 进行中的计数，释放时自动减一
+pub struct InFlight(&'static AtomicU64);
+
+impl InFlight {
+    pub fn request() -> Self {
+        ACTIVE_REQUESTS.fetch_add(1, Ordering::Relaxed);
+        Self(&ACTIVE_REQUESTS)
+    }
+
+    pub fn stream() -> Self {
+        ACTIVE_STREAMS.fetch_add(1, Ordering::Relaxed);
+        Self(&ACTIVE_STREAMS)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 流式响应收集的字节数，释放时从总量中扣除
+#[derive(Default)]
+pub struct CollectedBytes(u64);
+
+impl CollectedBytes {
+    pub fn add(&mut self, len: usize) {
+        self.0 += len as u64;
+        STREAM_BUFFER_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
+impl Drop for CollectedBytes {
+    fn drop(&mut self) {
+        STREAM_BUFFER_BYTES.fetch_sub(self.0, Ordering::Relaxed);
+    }
+}
+
+/// 单条日志的估算内存占用：结构体本身加上各文本字段的长度
+pub fn entry_size(entry: &ProxyLogEntry) -> u64 {
+    let texts = [
+        &entry.request_headers,
+        &entry.request_body,
+        &entry.response_headers,
+        &entry.response_body,
+        &entry.outbound_request,
+        &entry.error,
+    ];
+    let text_bytes: usize = texts
+        .iter()
+        .filter_map(|t| t.as_ref())
+        .map(String::len)
+        .sum();
+    (std::mem::size_of::<ProxyLogEntry>()
+        + text_bytes
+        + entry.path.len()
+        + entry.upstream_url.len()
+        + entry.timeline.len() * 64) as u64
+}
+
+/// 按当前计数生成指标与提醒
+pub fn collect(logs: &VecDeque<ProxyLogEntry>, log_store_bytes: Option<u64>) -> AppMetrics {
+    let log_buffer_bytes = logs.iter().map(entry_size).sum();
+    let stream_buffer_bytes = STREAM_BUFFER_BYTES.load(Ordering::Relaxed);
+    AppMetrics {
+        resident_bytes: resident_bytes(),
+        active_requests: ACTIVE_REQUESTS.load(Ordering::Relaxed),
+        active_streams: ACTIVE_STREAMS.load(Ordering::Relaxed),
+        stream_buffer_bytes,
+        log_entries: logs.len() as u64,
+        log_buffer_bytes,
+        log_store_bytes,
+        warnings: warnings(log_buffer_bytes, stream_buffer_bytes),
+    }
+}
+
+pub fn warnings(log_buffer_bytes: u64, stream_buffer_bytes: u64) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if log_buffer_bytes > LOG_BUFFER_WARN_BYTES {
+        warnings.push(format!(
+            "内存中的日志约占 {} MB，建议减少保留条数或关闭请求体记录",
+            log_buffer_bytes / (1024 * 1024)
+        ));
+    }
+    if stream_buffer_bytes > STREAM_BUFFER_WARN_BYTES {
+        warnings.push(format!(
+            "流式响应收集的响应体约占 {} MB，建议对长时间流式的服务关闭请求体记录",
+            stream_buffer_bytes / (1024 * 1024)
+        ));
+    }
+    warnings
+}
+
+/// 读取进程常驻内存，目前支持 Linux 与 macOS
+fn resident_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kb = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+            .output()
+            .ok()?;
+        let kb = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// 每分钟检查一次，新出现超出阈值的情况时输出提醒
+pub fn spawn_watch_task(logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let log_buffer_bytes: u64 = logs.lock().await.iter().map(entry_size).sum();
+            let warnings = warnings(
+                log_buffer_bytes,
+                STREAM_BUFFER_BYTES.load(Ordering::Relaxed),
+            );
+            if WARNED.swap(!warnings.is_empty(), Ordering::Relaxed) || warnings.is_empty() {
+                continue;
+            }
+            for warning in warnings {
+                eprintln!("资源占用提醒: {warning}");
+            }
+        }
+    });
+}
@@ -0,0 +1,111 @@
+//! 同一上游重试前的等待：指数退避加随机抖动，上游在 429 / 503 响应中给出 Retry-After 时以其为准。
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use http::{header, HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+const DEFAULT_BASE_DELAY_MS: u64 = 200;
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.2;
+const DEFAULT_MAX_DELAY_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BackoffConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct BackoffConfig {
+    /// 第一次重试前的等待，默认 200 毫秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub base_delay_ms: Option<u64>,
+    /// 每次重试等待时间的倍数，默认 2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub multiplier: Option<f64>,
+    /// 随机抖动比例（0 到 1），默认 0.2，即在 ±20% 范围内浮动
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub jitter: Option<f64>,
+    /// 单次等待上限，默认 10 秒；Retry-After 超过该值时不再在同一上游重试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_delay_ms: Option<u64>,
+}
+
+impl BackoffConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.multiplier.is_some_and(|m| !m.is_finite() || m < 1.0) {
+            return Err("退避倍数不能小于 1".into());
+        }
+        if self.jitter.is_some_and(|j| !(0.0..=1.0).contains(&j)) {
+            return Err("退避抖动比例需在 0 到 1 之间".into());
+        }
+        if self.max_delay_ms == Some(0) {
+            return Err("最大退避时间必须大于 0".into());
+        }
+        Ok(())
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms.unwrap_or(DEFAULT_MAX_DELAY_MS))
+    }
+
+    /// 第 retry 次重试（从 0 开始）前的等待时间，unit 为 [0, 1) 的随机数
+    pub fn delay(&self, retry: u32, unit: f64) -> Duration {
+        let base = self.base_delay_ms.unwrap_or(DEFAULT_BASE_DELAY_MS) as f64;
+        let multiplier = self.multiplier.unwrap_or(DEFAULT_MULTIPLIER);
+        let jitter = self.jitter.unwrap_or(DEFAULT_JITTER);
+        let max = self.max_delay().as_millis() as f64;
+        let delay = (base * multiplier.powi(retry as i32)).min(max);
+        let delay = delay * (1.0 + jitter * (unit * 2.0 - 1.0));
+        Duration::from_millis(delay.clamp(0.0, max) as u64)
+    }
+}
+
+/// 计算下一次重试前的等待：未配置退避时立即重试，但仍遵守上游的 Retry-After。
+/// 返回 None 表示上游要求等待的时间超过上限，不宜在同一上游重试
+pub fn retry_delay(
+    config: Option<&BackoffConfig>,
+    retry: u32,
+    status: Option<StatusCode>,
+    headers: Option<&HeaderMap>,
+) -> Option<Duration> {
+    let backoff = config
+        .map(|c| c.delay(retry, random_unit()))
+        .unwrap_or_default();
+    let max = config
+        .map(BackoffConfig::max_delay)
+        .unwrap_or(Duration::from_millis(DEFAULT_MAX_DELAY_MS));
+    let honors_retry_after = matches!(
+        status,
+        Some(StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+    );
+    match headers
+        .filter(|_| honors_retry_after)
+        .and_then(|h| retry_after(h, Utc::now()))
+    {
+        Some(wait) if wait > max => None,
+        Some(wait) => Some(wait.max(backoff)),
+        None => Some(backoff),
+    }
+}
+
+/// 解析 Retry-After：秒数或 HTTP 日期
+pub fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or_default())
+}
+
+/// 取 UUID v4 低 53 位（不含版本与变体位）作为 [0, 1) 的随机数
+pub(crate) fn random_unit() -> f64 {
+    const BITS: u32 = 53;
+    (uuid::Uuid::new_v4().as_u128() & ((1 << BITS) - 1)) as f64 / (1u64 << BITS) as f64
+}
@@ -0,0 +1,255 @@
+//! 服务商账户余额轮询：为上游配置余额查询后，后台定期用该上游的 key 查询剩余额度，
+//! 低于阈值时推送提醒。目前支持 OpenRouter、DeepSeek 与 SiliconFlow 的查询接口。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use ts_rs::TS;
+
+use crate::persistence::cached_config;
+use crate::{env_vars, events, timestamp, ProxyConfig, UpstreamEntry};
+
+const DEFAULT_INTERVAL_SECS: u64 = 600;
+const MIN_INTERVAL_SECS: u64 = 60;
+const TICK: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BalanceProvider.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum BalanceProvider {
+    OpenRouter,
+    DeepSeek,
+    SiliconFlow,
+}
+
+impl BalanceProvider {
+    fn default_url(self) -> &'static str {
+        match self {
+            Self::OpenRouter => "https://openrouter.ai/api/v1/credits",
+            Self::DeepSeek => "https://api.deepseek.com/user/balance",
+            Self::SiliconFlow => "https://api.siliconflow.cn/v1/user/info",
+        }
+    }
+
+    /// 从查询接口的响应中取出余额与币种
+    fn parse(self, body: &Value) -> Option<(f64, Option<String>)> {
+        let number = |v: &Value| v.as_f64().or_else(|| v.as_str()?.trim().parse().ok());
+        match self {
+            Self::OpenRouter => {
+                let data = &body["data"];
+                let remaining = number(&data["total_credits"])? - number(&data["total_usage"])?;
+                Some((remaining, Some("USD".into())))
+            }
+            Self::DeepSeek => {
+                let infos = body["balance_infos"].as_array()?;
+                let info = infos
+                    .iter()
+                    .find(|i| i["currency"] == "USD")
+                    .or_else(|| infos.first())?;
+                Some((
+                    number(&info["total_balance"])?,
+                    info["currency"].as_str().map(str::to_string),
+                ))
+            }
+            Self::SiliconFlow => {
+                let data = &body["data"];
+                let balance = number(&data["totalBalance"]).or_else(|| number(&data["balance"]))?;
+                Some((balance, Some("CNY".into())))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BalanceConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceConfig {
+    pub provider: BalanceProvider,
+    /// 覆盖默认的查询地址，例如经由自建网关访问时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub url: Option<String>,
+    /// 查询间隔，默认 600 秒，最少 60 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub interval_secs: Option<u64>,
+    /// 余额低于该值时提醒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub low_threshold: Option<f64>,
+}
+
+impl BalanceConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs.is_some_and(|s| s < MIN_INTERVAL_SECS) {
+            return Err(format!("余额查询间隔不能小于 {MIN_INTERVAL_SECS} 秒"));
+        }
+        if let Some(url) = &self.url {
+            reqwest::Url::parse(url).map_err(|e| format!("余额查询地址无效 `{url}`: {e}"))?;
+        }
+        if self.low_threshold.is_some_and(|t| !t.is_finite()) {
+            return Err("余额提醒阈值无效".into());
+        }
+        Ok(())
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS))
+    }
+}
+
+/// 上游最近一次查询到的余额
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/UpstreamBalance.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamBalance {
+    pub upstream_id: String,
+    pub upstream_label: Option<String>,
+    pub provider: BalanceProvider,
+    pub balance: Option<f64>,
+    pub currency: Option<String>,
+    pub checked_at: String,
+    pub error: Option<String>,
+    /// 余额低于配置的提醒阈值
+    pub low: bool,
+}
+
+#[derive(Default)]
+struct Poller {
+    balances: HashMap<String, UpstreamBalance>,
+    last_polled: HashMap<String, Instant>,
+}
+
+fn poller() -> &'static Mutex<Poller> {
+    static POLLER: OnceLock<Mutex<Poller>> = OnceLock::new();
+    POLLER.get_or_init(Default::default)
+}
+
+/// 各上游最近一次查询结果，按上游 id 排序
+pub fn snapshot() -> Vec<UpstreamBalance> {
+    let guard = poller().lock().unwrap_or_else(|e| e.into_inner());
+    let mut balances: Vec<UpstreamBalance> = guard.balances.values().cloned().collect();
+    balances.sort_by(|a, b| a.upstream_id.cmp(&b.upstream_id));
+    balances
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    config: &BalanceConfig,
+    api_key: &str,
+) -> Result<(f64, Option<String>), String> {
+    let url = config
+        .url
+        .as_deref()
+        .unwrap_or(config.provider.default_url());
+    let resp = client
+        .get(url)
+        .bearer_auth(api_key)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("查询余额失败: {e}"))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("查询余额失败: HTTP {}", status.as_u16()));
+    }
+    let body: Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("余额响应不是有效的 JSON: {e}"))?;
+    config
+        .provider
+        .parse(&body)
+        .ok_or_else(|| "余额响应中没有找到余额字段".into())
+}
+
+async fn poll_upstream(client: &reqwest::Client, upstream: &UpstreamEntry, config: &BalanceConfig) {
+    let result = match upstream.api_key.as_deref().map(env_vars::expand) {
+        Some(Ok(key)) => fetch(client, config, &key).await,
+        Some(Err(err)) => Err(err),
+        None => Err("上游未配置 API key，无法查询余额".into()),
+    };
+    let (balance, currency, error) = match result {
+        Ok((balance, currency)) => (Some(balance), currency, None),
+        Err(err) => (None, None, Some(err)),
+    };
+    let low = balance
+        .zip(config.low_threshold)
+        .is_some_and(|(b, t)| b < t);
+    let entry = UpstreamBalance {
+        upstream_id: upstream.id.clone(),
+        upstream_label: upstream.label.clone(),
+        provider: config.provider,
+        balance,
+        currency,
+        checked_at: timestamp::now(),
+        error,
+        low,
+    };
+    let previous = poller()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .balances
+        .insert(upstream.id.clone(), entry.clone());
+    // 只在刚低于阈值时提醒，恢复后再次低于阈值会重新提醒
+    if low && !previous.is_some_and(|p| p.low) {
+        eprintln!("上游 {} 余额不足: {:?}", upstream.id, entry.balance);
+        events::emit_balance_alert(entry);
+    }
+}
+
+/// 查询所有到期需要刷新的上游；force 为 true 时忽略查询间隔
+pub async fn poll_due(client: &reqwest::Client, config: &ProxyConfig, force: bool) {
+    let now = Instant::now();
+    let due: Vec<(UpstreamEntry, BalanceConfig)> = {
+        let mut guard = poller().lock().unwrap_or_else(|e| e.into_inner());
+        let mut configured = HashSet::new();
+        let mut due = Vec::new();
+        for upstream in config.all_services().flat_map(|s| &s.upstreams) {
+            let Some(balance) = upstream.balance.as_ref() else {
+                continue;
+            };
+            if !configured.insert(upstream.id.clone()) {
+                continue;
+            }
+            let fresh = guard
+                .last_polled
+                .get(&upstream.id)
+                .is_some_and(|at| now.duration_since(*at) < balance.interval());
+            if force || !fresh {
+                guard.last_polled.insert(upstream.id.clone(), now);
+                due.push((upstream.clone(), balance.clone()));
+            }
+        }
+        // 移除已删除或不再查询余额的上游
+        guard.balances.retain(|id, _| configured.contains(id));
+        guard.last_polled.retain(|id, _| configured.contains(id));
+        due
+    };
+    for (upstream, balance) in &due {
+        poll_upstream(client, upstream, balance).await;
+    }
+}
+
+/// 后台定期查询余额，使用与代理请求相同的出站代理设置
+pub fn spawn_poll_task(
+    client: Arc<ArcSwap<reqwest::Client>>,
+    config: Arc<RwLock<Option<ProxyConfig>>>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            ticker.tick().await;
+            let current = config.read().await.clone();
+            if let Some(current) = current.or_else(cached_config) {
+                poll_due(&client.load_full(), &current, false).await;
+            }
+        }
+    });
+}
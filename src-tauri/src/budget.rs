@@ -0,0 +1,243 @@
+//! 按天 / 按月的费用与 token 预算。用量只在内存中累计，周期切换或重启后从零开始。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::events;
+use crate::usage::TokenUsage;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BudgetScope.ts")]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BudgetScope {
+    Global,
+    Service { name: String },
+    Upstream { id: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BudgetPeriod.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetPeriod {
+    Daily,
+    Monthly,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BudgetAction.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetAction {
+    /// 超出后仅推送提醒
+    #[default]
+    Warn,
+    /// 超出后拒绝该范围内的请求（上游范围则跳过该上游）
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BudgetRule.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetRule {
+    /// 规则名称，需唯一，用于提醒和错误信息
+    pub name: String,
+    pub scope: BudgetScope,
+    pub period: BudgetPeriod,
+    /// 费用上限，按价格表估算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_cost: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub action: BudgetAction,
+}
+
+impl BudgetRule {
+    fn matches(&self, service_name: Option<&str>, upstream_id: Option<&str>) -> bool {
+        match &self.scope {
+            BudgetScope::Global => true,
+            BudgetScope::Service { name } => service_name == Some(name.as_str()),
+            BudgetScope::Upstream { id } => upstream_id == Some(id.as_str()),
+        }
+    }
+
+    fn period_key(&self, now: DateTime<Local>) -> String {
+        match self.period {
+            BudgetPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            BudgetPeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    fn is_exceeded(&self, spent: &PeriodUsage) -> bool {
+        self.max_cost.is_some_and(|max| spent.cost >= max)
+            || self.max_tokens.is_some_and(|max| spent.tokens >= max)
+    }
+}
+
+pub fn validate_budgets(rules: &[BudgetRule]) -> Result<(), String> {
+    for (idx, rule) in rules.iter().enumerate() {
+        if rule.name.trim().is_empty() {
+            return Err("预算规则名称不能为空".into());
+        }
+        if rules[..idx].iter().any(|r| r.name == rule.name) {
+            return Err(format!("预算规则名称重复: {}", rule.name));
+        }
+        if rule.max_cost.is_none() && rule.max_tokens.is_none() {
+            return Err(format!("预算规则 {} 需要设置费用或 token 上限", rule.name));
+        }
+        if rule.max_cost.is_some_and(|c| !c.is_finite() || c < 0.0) {
+            return Err(format!("预算规则 {} 的费用上限必须为非负数", rule.name));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+struct PeriodUsage {
+    period_key: String,
+    cost: f64,
+    tokens: u64,
+    alerted: bool,
+}
+
+/// 某条预算规则在当前周期的用量
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/BudgetStatus.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub rule: String,
+    /// 当前周期，如 `2024-05-01`（按天）或 `2024-05`（按月）
+    pub period_key: String,
+    pub spent_cost: f64,
+    #[ts(type = "number")]
+    pub spent_tokens: u64,
+    pub exceeded: bool,
+    pub action: BudgetAction,
+}
+
+#[derive(Default)]
+pub struct BudgetTracker {
+    usage: Mutex<HashMap<String, PeriodUsage>>,
+}
+
+impl BudgetTracker {
+    /// 累计一次请求的用量，返回本次新超出预算的规则状态（每个周期只提醒一次）
+    pub fn record_at(
+        &self,
+        rules: &[BudgetRule],
+        service_name: Option<&str>,
+        upstream_id: &str,
+        usage: &TokenUsage,
+        cost: Option<f64>,
+        now: DateTime<Local>,
+    ) -> Vec<BudgetStatus> {
+        let mut guard = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let mut crossed = Vec::new();
+        for rule in rules
+            .iter()
+            .filter(|r| r.matches(service_name, Some(upstream_id)))
+        {
+            let period_key = rule.period_key(now);
+            let spent = guard.entry(rule.name.clone()).or_default();
+            if spent.period_key != period_key {
+                *spent = PeriodUsage {
+                    period_key,
+                    ..PeriodUsage::default()
+                };
+            }
+            spent.cost += cost.unwrap_or(0.0);
+            spent.tokens += usage.total_tokens;
+            if !spent.alerted && rule.is_exceeded(spent) {
+                spent.alerted = true;
+                crossed.push(status_of(rule, spent));
+            }
+        }
+        crossed
+    }
+
+    /// 返回第一条已超出且设置为拒绝的规则名。传入 None 的维度只匹配全局和服务规则。
+    pub fn blocked_by_at(
+        &self,
+        rules: &[BudgetRule],
+        service_name: Option<&str>,
+        upstream_id: Option<&str>,
+        now: DateTime<Local>,
+    ) -> Option<String> {
+        let guard = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        rules
+            .iter()
+            .filter(|r| r.action == BudgetAction::Block && r.matches(service_name, upstream_id))
+            .find(|r| {
+                guard.get(&r.name).is_some_and(|spent| {
+                    spent.period_key == r.period_key(now) && r.is_exceeded(spent)
+                })
+            })
+            .map(|r| r.name.clone())
+    }
+
+    pub fn status_at(&self, rules: &[BudgetRule], now: DateTime<Local>) -> Vec<BudgetStatus> {
+        let guard = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        rules
+            .iter()
+            .map(|rule| {
+                let period_key = rule.period_key(now);
+                match guard.get(&rule.name).filter(|s| s.period_key == period_key) {
+                    Some(spent) => status_of(rule, spent),
+                    None => status_of(
+                        rule,
+                        &PeriodUsage {
+                            period_key,
+                            ..PeriodUsage::default()
+                        },
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+fn status_of(rule: &BudgetRule, spent: &PeriodUsage) -> BudgetStatus {
+    BudgetStatus {
+        rule: rule.name.clone(),
+        period_key: spent.period_key.clone(),
+        spent_cost: spent.cost,
+        spent_tokens: spent.tokens,
+        exceeded: rule.is_exceeded(spent),
+        action: rule.action,
+    }
+}
+
+pub fn tracker() -> &'static BudgetTracker {
+    static TRACKER: OnceLock<BudgetTracker> = OnceLock::new();
+    TRACKER.get_or_init(BudgetTracker::default)
+}
+
+/// 请求结束后累计用量，新超出的预算推送提醒
+pub fn record(
+    rules: Option<&[BudgetRule]>,
+    service_name: Option<&str>,
+    upstream_id: &str,
+    usage: &TokenUsage,
+    cost: Option<f64>,
+) {
+    let Some(rules) = rules.filter(|r| !r.is_empty()) else {
+        return;
+    };
+    for status in tracker().record_at(rules, service_name, upstream_id, usage, cost, Local::now()) {
+        eprintln!("预算 {} 已超出（{}）", status.rule, status.period_key);
+        events::emit_budget_alert(status);
+    }
+}
+
+pub fn blocked_by(
+    rules: Option<&[BudgetRule]>,
+    service_name: Option<&str>,
+    upstream_id: Option<&str>,
+) -> Option<String> {
+    tracker().blocked_by_at(rules?, service_name, upstream_id, Local::now())
+}
@@ -0,0 +1,211 @@
+//! 按服务商维护的能力表：最大输出 token 数，以及是否支持工具调用、图片输入与 JSON 模式。
+//! 上游标注所属服务商后，路由时跳过无法处理该请求的上游，并按上限补全或截断请求的 max_tokens。
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+/// OpenAI Chat / Responses 与 Anthropic 请求中限制输出长度的字段
+const MAX_TOKEN_FIELDS: [&str; 3] = ["max_tokens", "max_completion_tokens", "max_output_tokens"];
+/// 表示图片输入的内容块类型
+const IMAGE_PART_TYPES: [&str; 3] = ["image_url", "image", "input_image"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ProviderCapabilities.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapabilities {
+    /// 服务商名称，上游通过 provider 字段引用
+    pub provider: String,
+    /// 单次响应最多输出的 token 数：请求未指定时按此补全，超出时截断
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_output_tokens: Option<u32>,
+    /// 是否支持工具 / 函数调用，未设置时视为支持
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub supports_tools: Option<bool>,
+    /// 是否支持图片输入，未设置时视为支持
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub supports_vision: Option<bool>,
+    /// 是否支持 JSON 模式 / 结构化输出，未设置时视为支持
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub supports_json_mode: Option<bool>,
+}
+
+/// 请求用到的需要服务商支持的能力
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestNeeds {
+    pub tools: bool,
+    pub vision: bool,
+    pub json_mode: bool,
+}
+
+impl RequestNeeds {
+    /// 从 OpenAI / Anthropic / Gemini 格式的 JSON 请求体中识别，非 JSON 请求体视为无特殊需求
+    pub fn from_body(body: &[u8]) -> Self {
+        let Ok(json) = serde_json::from_slice::<Value>(body) else {
+            return Self::default();
+        };
+        let non_empty = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_array)
+                .is_some_and(|a| !a.is_empty())
+        };
+        let json_mode = matches!(
+            json.pointer("/response_format/type")
+                .and_then(Value::as_str),
+            Some("json_object" | "json_schema")
+        ) || matches!(
+            json.pointer("/text/format/type").and_then(Value::as_str),
+            Some("json_object" | "json_schema")
+        ) || json
+            .pointer("/generationConfig/responseMimeType")
+            .and_then(Value::as_str)
+            == Some("application/json");
+        Self {
+            tools: non_empty("tools") || non_empty("functions"),
+            vision: ["messages", "input", "contents"]
+                .iter()
+                .filter_map(|key| json.get(*key))
+                .any(contains_image),
+            json_mode,
+        }
+    }
+}
+
+fn contains_image(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(contains_image),
+        Value::Object(object) => {
+            let typed_image = object
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|t| IMAGE_PART_TYPES.contains(&t));
+            let inline_image = ["inlineData", "inline_data", "fileData", "file_data"]
+                .iter()
+                .filter_map(|key| object.get(*key))
+                .any(|data| {
+                    ["mimeType", "mime_type"]
+                        .iter()
+                        .filter_map(|key| data.get(*key).and_then(Value::as_str))
+                        .any(|mime| mime.starts_with("image/"))
+                });
+            typed_image || inline_image || object.values().any(contains_image)
+        }
+        _ => false,
+    }
+}
+
+impl ProviderCapabilities {
+    /// 无法处理该请求时返回缺少的能力
+    pub fn missing(&self, needs: &RequestNeeds) -> Option<&'static str> {
+        let unsupported = |flag: Option<bool>| flag == Some(false);
+        if needs.vision && unsupported(self.supports_vision) {
+            Some("图片输入")
+        } else if needs.tools && unsupported(self.supports_tools) {
+            Some("工具调用")
+        } else if needs.json_mode && unsupported(self.supports_json_mode) {
+            Some("JSON 模式")
+        } else {
+            None
+        }
+    }
+
+    /// 按最大输出 token 数补全或截断请求体中的输出上限，无需改动时返回 None
+    pub fn adjust_body(&self, body: &[u8]) -> Option<Vec<u8>> {
+        let max = u64::from(self.max_output_tokens?);
+        let mut value: Value = serde_json::from_slice(body).ok()?;
+        let object = value.as_object_mut()?;
+        if !clamp_max_tokens(object, max) {
+            return None;
+        }
+        serde_json::to_vec(&value).ok()
+    }
+}
+
+/// 截断超出上限的字段；一个都没有时按请求格式补上。返回是否改动了请求体
+fn clamp_max_tokens(object: &mut Map<String, Value>, max: u64) -> bool {
+    let gemini = object.contains_key("contents");
+    let present: Vec<&mut Value> = if gemini {
+        object
+            .get_mut("generationConfig")
+            .and_then(|c| c.get_mut("maxOutputTokens"))
+            .into_iter()
+            .collect()
+    } else {
+        object
+            .iter_mut()
+            .filter(|(key, _)| MAX_TOKEN_FIELDS.contains(&key.as_str()))
+            .map(|(_, value)| value)
+            .collect()
+    };
+    if !present.is_empty() {
+        let mut changed = false;
+        for value in present {
+            if value.as_u64().is_some_and(|v| v > max) {
+                *value = max.into();
+                changed = true;
+            }
+        }
+        return changed;
+    }
+
+    if gemini {
+        let config = object
+            .entry("generationConfig")
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Some(config) = config.as_object_mut() else {
+            return false;
+        };
+        config.insert("maxOutputTokens".into(), max.into());
+    } else if object.contains_key("input") {
+        object.insert("max_output_tokens".into(), max.into());
+    } else if object.contains_key("messages") {
+        object.insert("max_tokens".into(), max.into());
+    } else {
+        return false;
+    }
+    true
+}
+
+/// 校验能力表，并确认上游引用的服务商都在表中
+pub fn validate_capabilities<'a>(
+    table: &[ProviderCapabilities],
+    providers: impl IntoIterator<Item = &'a str>,
+) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for entry in table {
+        let name = entry.provider.trim();
+        if name.is_empty() {
+            return Err("服务商能力表中的服务商名称不能为空".into());
+        }
+        if !names.insert(name) {
+            return Err(format!("服务商能力表中重复的服务商: {name}"));
+        }
+        if entry.max_output_tokens == Some(0) {
+            return Err(format!("服务商 {name} 的最大输出 token 数必须大于 0"));
+        }
+    }
+    for provider in providers {
+        if !names.contains(provider.trim()) {
+            return Err(format!(
+                "上游引用了能力表中不存在的服务商: {}",
+                provider.trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 按名称查找服务商的能力
+pub fn lookup<'a>(
+    table: Option<&'a [ProviderCapabilities]>,
+    provider: Option<&str>,
+) -> Option<&'a ProviderCapabilities> {
+    let provider = provider?.trim();
+    table?.iter().find(|c| c.provider.trim() == provider)
+}
@@ -0,0 +1,149 @@
+//! 按上游配置的故障注入，用于验证客户端的重试逻辑：发送前增加延迟、按比例直接返回 500 / 429、
+//! 按比例在流式响应中途断开连接。
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::{header, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::backoff::random_unit;
+
+/// 注入的响应带有该响应头，便于客户端区分真实故障
+pub const CHAOS_HEADER: &str = "x-apiflow-chaos";
+const MAX_LATENCY_MS: u64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ChaosConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosConfig {
+    /// 发送请求前额外等待的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub latency_ms: Option<u64>,
+    /// 在额外等待的基础上随机增加 0 到该值的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub latency_jitter_ms: Option<u64>,
+    /// 不请求上游、直接返回错误的比例（0 到 100）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub error_percent: Option<f64>,
+    /// 注入的错误状态码，仅支持 500 与 429，默认 500
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub error_status: Option<u16>,
+    /// 响应体转发中途断开的比例（0 到 100）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub disconnect_percent: Option<f64>,
+    /// 断开前转发的字节数，默认在第一个数据块之后断开
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub disconnect_after_bytes: Option<u64>,
+}
+
+impl ChaosConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("错误注入比例", self.error_percent),
+            ("断开比例", self.disconnect_percent),
+        ] {
+            if value.is_some_and(|v| !(0.0..=100.0).contains(&v)) {
+                return Err(format!("故障注入的{name}需在 0 到 100 之间"));
+            }
+        }
+        if self.error_status.is_some_and(|s| s != 500 && s != 429) {
+            return Err("故障注入的错误状态码只支持 500 或 429".into());
+        }
+        if self.latency_ms.unwrap_or(0) + self.latency_jitter_ms.unwrap_or(0) > MAX_LATENCY_MS {
+            return Err("故障注入的延迟不能超过 10 分钟".into());
+        }
+        Ok(())
+    }
+
+    /// 本次请求需要额外等待的时间
+    pub fn latency(&self) -> Option<Duration> {
+        let base = self.latency_ms.unwrap_or(0);
+        let jitter = self
+            .latency_jitter_ms
+            .map(|j| (random_unit() * (j + 1) as f64) as u64)
+            .unwrap_or(0);
+        let total = base + jitter;
+        (total > 0).then(|| Duration::from_millis(total))
+    }
+
+    /// 按比例生成注入的错误响应
+    pub fn injected_error(&self) -> Option<reqwest::Response> {
+        if !hits(self.error_percent) {
+            return None;
+        }
+        let status = match self.error_status {
+            Some(429) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({
+            "error": { "message": "故障注入", "type": "apiflow_chaos" }
+        });
+        let mut response = http::Response::new(reqwest::Body::from(body.to_string()));
+        *response.status_mut() = status;
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        headers.insert(CHAOS_HEADER, HeaderValue::from_static("error"));
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+        }
+        Some(reqwest::Response::from(response))
+    }
+
+    /// 按比例让成功响应的响应体在转发中途报错断开
+    pub fn maybe_disconnect(&self, resp: reqwest::Response) -> reqwest::Response {
+        if !resp.status().is_success() || !hits(self.disconnect_percent) {
+            return resp;
+        }
+        let limit = self.disconnect_after_bytes.filter(|b| *b > 0);
+        let status = resp.status();
+        let version = resp.version();
+        let mut headers = resp.headers().clone();
+        headers.insert(CHAOS_HEADER, HeaderValue::from_static("disconnect"));
+
+        let mut sent = 0u64;
+        let body = resp
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(std::io::Error::other))
+            .scan(false, move |done, chunk| {
+                if *done {
+                    return futures_util::future::ready(None);
+                }
+                let item = chunk.map(|bytes| {
+                    let len = bytes.len() as u64;
+                    let keep = limit.map_or(len, |limit| limit - sent).min(len);
+                    sent += keep;
+                    *done = limit.is_none_or(|limit| sent >= limit);
+                    bytes.slice(..keep as usize)
+                });
+                futures_util::future::ready(Some(item))
+            })
+            .chain(futures_util::stream::once(async {
+                Err::<Bytes, _>(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "故障注入：连接中途断开",
+                ))
+            }));
+
+        let mut rebuilt = http::Response::new(reqwest::Body::wrap_stream(body));
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        reqwest::Response::from(rebuilt)
+    }
+}
+
+fn hits(percent: Option<f64>) -> bool {
+    percent.is_some_and(|p| random_unit() * 100.0 < p)
+}
@@ -0,0 +1,109 @@
+//! 端到端内容校验：计算发往上游的请求体与转发给客户端的响应体的 SHA-256，
+//! 并与对端通过 Content-Digest（RFC 9530）声明的摘要比对，用于发现局域网 / VPN 链路上
+//! 被中间设备改写或损坏的数据。
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use ts_rs::TS;
+
+pub const CONTENT_DIGEST: &str = "content-digest";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ChecksumReport.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumReport {
+    /// 发往上游的请求体摘要（十六进制）
+    pub request_sha256: String,
+    /// 转发给客户端的响应体摘要，响应未完成时为空
+    pub response_sha256: Option<String>,
+    /// 与对端声明摘要不一致的说明；为空表示一致或对端未声明
+    pub mismatches: Vec<String>,
+}
+
+impl ChecksumReport {
+    /// 计算请求体摘要，并与客户端声明的 Content-Digest 比对
+    pub fn for_request(body: &[u8], client_headers: &HeaderMap) -> Self {
+        let request_sha256 = hex(Sha256::digest(body));
+        let mut mismatches = Vec::new();
+        if let Some(declared) = declared_sha256(client_headers) {
+            if declared != request_sha256 {
+                mismatches.push(format!(
+                    "请求体与客户端声明的摘要不一致: 声明 {declared}，实际 {request_sha256}"
+                ));
+            }
+        }
+        Self {
+            request_sha256,
+            response_sha256: None,
+            mismatches,
+        }
+    }
+
+    /// 记录响应体摘要，并与上游声明的 Content-Digest 比对
+    pub fn record_response(&mut self, sha256: String, upstream_headers: &HeaderMap) {
+        if let Some(declared) = declared_sha256(upstream_headers) {
+            if declared != sha256 {
+                self.mismatches.push(format!(
+                    "响应体与上游声明的摘要不一致: 声明 {declared}，实际 {sha256}"
+                ));
+            }
+        }
+        self.response_sha256 = Some(sha256);
+    }
+
+    /// 汇总不一致的说明，用作日志错误信息
+    pub fn mismatch_summary(&self) -> Option<String> {
+        (!self.mismatches.is_empty()).then(|| self.mismatches.join("；"))
+    }
+}
+
+/// 流式响应边转发边计算摘要
+#[derive(Default)]
+pub struct BodyHasher(Sha256);
+
+impl BodyHasher {
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    pub fn finish(self) -> String {
+        hex(self.0.finalize())
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex(Sha256::digest(bytes))
+}
+
+/// 生成 `sha-256=:<base64>:` 形式的 Content-Digest 头
+pub fn digest_header(sha256_hex: &str) -> Option<HeaderValue> {
+    let raw: Vec<u8> = (0..sha256_hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(sha256_hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<_>>()?;
+    HeaderValue::from_str(&format!("sha-256=:{}:", STANDARD.encode(raw))).ok()
+}
+
+/// 解析 Content-Digest 中的 sha-256 摘要并转为十六进制；未声明或格式无效时返回 None
+pub fn declared_sha256(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(CONTENT_DIGEST)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|item| {
+            let (alg, value) = item.split_once('=')?;
+            if !alg.trim().eq_ignore_ascii_case("sha-256") {
+                return None;
+            }
+            let encoded = value.trim().strip_prefix(':')?.strip_suffix(':')?;
+            STANDARD.decode(encoded).ok().map(hex)
+        })
+}
+
+fn hex(bytes: impl AsRef<[u8]>) -> String {
+    bytes.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,324 @@
+//! 命令行入口：`apiflow logs [--tail]`、`apiflow stats`、`apiflow reload`，
+//! 通过本机的管理接口与运行中的应用交互，无需打开桌面界面。
+//! 管理端口与令牌默认取自已保存的配置，也可用 `--port`、`--token` 或 `APIFLOW_ADMIN_TOKEN` 指定。
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::admin_auth::AdminScope;
+use crate::logging::LogPage;
+use crate::persistence::load_config;
+use crate::{ProxyConfig, ProxyLogEntry, UpstreamStats};
+
+const TOKEN_ENV: &str = "APIFLOW_ADMIN_TOKEN";
+const DEFAULT_LOG_LIMIT: usize = 20;
+/// 跟随日志时每次拉取的条数，足以覆盖两次轮询之间完成的请求
+const TAIL_WINDOW: usize = 200;
+const TAIL_INTERVAL: Duration = Duration::from_secs(1);
+
+const USAGE: &str = "用法: apiflow <命令> [选项]
+
+命令:
+  logs [--tail] [--limit N]   显示最近的请求日志，--tail 持续输出新完成的请求
+  stats                       显示各上游的请求统计
+  reload                      按已保存的配置重载运行中的代理
+
+选项:
+  --port P                    管理接口端口，默认取已保存配置中的端口
+  --token T                   管理令牌，也可通过 APIFLOW_ADMIN_TOKEN 环境变量指定
+
+启动桌面应用时加上 --safe-mode 可停用所有扩展功能，只做转发与失败切换";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Logs { tail: bool, limit: usize },
+    Stats,
+    Reload,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub command: Command,
+    pub port: Option<u16>,
+    pub token: Option<String>,
+}
+
+impl Command {
+    fn scope(&self) -> AdminScope {
+        match self {
+            Command::Logs { .. } => AdminScope::ReadLogs,
+            Command::Stats | Command::Help => AdminScope::ReadStats,
+            Command::Reload => AdminScope::WriteConfig,
+        }
+    }
+}
+
+/// 解析命令行参数（不含程序名）；第一个参数不是已知命令时返回 None，按桌面应用启动
+pub fn parse_args(args: &[String]) -> Option<Result<Invocation, String>> {
+    let (name, rest) = args.split_first()?;
+    let mut command = match name.as_str() {
+        "logs" => Command::Logs {
+            tail: false,
+            limit: DEFAULT_LOG_LIMIT,
+        },
+        "stats" => Command::Stats,
+        "reload" => Command::Reload,
+        "help" | "--help" | "-h" => Command::Help,
+        _ => return None,
+    };
+    Some(
+        parse_options(&mut command, rest).map(|(port, token)| Invocation {
+            command,
+            port,
+            token,
+        }),
+    )
+}
+
+fn parse_options(
+    command: &mut Command,
+    args: &[String],
+) -> Result<(Option<u16>, Option<String>), String> {
+    let mut port = None;
+    let mut token = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{name} 缺少参数值"))
+        };
+        match (arg.as_str(), &mut *command) {
+            ("--port", _) => {
+                let raw = value("--port")?;
+                port = Some(raw.parse().map_err(|_| format!("端口无效: {raw}"))?);
+            }
+            ("--token", _) => token = Some(value("--token")?),
+            ("--tail" | "-f", Command::Logs { tail, .. }) => *tail = true,
+            ("--limit" | "-n", Command::Logs { limit, .. }) => {
+                let raw = value("--limit")?;
+                *limit = raw.parse().map_err(|_| format!("条数无效: {raw}"))?;
+            }
+            _ => return Err(format!("未知选项: {arg}\n\n{USAGE}")),
+        }
+    }
+    Ok((port, token))
+}
+
+/// Windows 发布版以 GUI 子系统构建，没有控制台；从终端运行命令时挂到父进程的控制台上，
+/// 输出与错误信息才能显示。双击启动时没有父控制台，调用失败不影响后续执行
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+/// 命令行参数是 CLI 命令时执行并返回退出码，否则返回 None
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let invocation = parse_args(&args)?;
+    attach_parent_console();
+    let invocation = match invocation {
+        Ok(invocation) => invocation,
+        Err(err) => {
+            eprintln!("{err}");
+            return Some(2);
+        }
+    };
+    if invocation.command == Command::Help {
+        println!("{USAGE}");
+        return Some(0);
+    }
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("apiflow: {err}");
+            return Some(1);
+        }
+    };
+    match runtime.block_on(execute(invocation)) {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("apiflow: {err}");
+            Some(1)
+        }
+    }
+}
+
+/// 未指定令牌时取已保存配置中第一个具备所需权限的管理令牌
+pub fn pick_token(
+    explicit: Option<String>,
+    config: Option<&ProxyConfig>,
+    scope: AdminScope,
+) -> Result<String, String> {
+    explicit
+        .or_else(|| std::env::var(TOKEN_ENV).ok())
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| {
+            config?
+                .admin_tokens
+                .as_deref()?
+                .iter()
+                .find(|t| t.allows(scope))
+                .map(|t| t.token.clone())
+        })
+        .ok_or_else(|| format!("未找到可用的管理令牌，请通过 --token 或 {TOKEN_ENV} 指定"))
+}
+
+struct AdminClient {
+    base: String,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl AdminClient {
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, String> {
+        let resp = request
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .map_err(|e| format!("无法连接管理接口 {}（应用是否在运行？）: {e}", self.base))?;
+        let status = resp.status();
+        let body: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| format!("管理接口返回了无效的响应: {e}"))?;
+        if !status.is_success() {
+            let msg = body["error"].as_str().unwrap_or("请求失败");
+            return Err(format!("{msg}（{status}）"));
+        }
+        serde_json::from_value(body).map_err(|e| format!("管理接口返回了无效的响应: {e}"))
+    }
+
+    async fn logs(&self, limit: usize) -> Result<Vec<ProxyLogEntry>, String> {
+        let url = format!("{}/api/logs?limit={limit}", self.base);
+        let page: LogPage = self.send(self.http.get(url)).await?;
+        Ok(page.entries)
+    }
+}
+
+async fn execute(invocation: Invocation) -> Result<(), String> {
+    let config = load_config()?;
+    let port = invocation
+        .port
+        .or_else(|| config.as_ref()?.admin_api.as_ref().map(|api| api.port))
+        .ok_or("管理接口未启用，请在设置中开启或通过 --port 指定端口")?;
+    let client = AdminClient {
+        base: format!("http://127.0.0.1:{port}"),
+        token: pick_token(
+            invocation.token,
+            config.as_ref(),
+            invocation.command.scope(),
+        )?,
+        http: reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(|e| e.to_string())?,
+    };
+
+    match invocation.command {
+        Command::Logs { tail, limit } => {
+            // 跟随时先拉取完整窗口，早于最近 limit 条的请求记为已输出，避免下一轮补打
+            let window = if tail { limit.max(TAIL_WINDOW) } else { limit };
+            let entries = client.logs(window).await?;
+            let skip = entries.len().saturating_sub(limit);
+            let mut printed: HashSet<String> = entries[..skip]
+                .iter()
+                .filter(|e| e.status.is_some())
+                .map(|e| e.id.clone())
+                .collect();
+            print_new_logs(entries, &mut printed);
+            if tail {
+                loop {
+                    tokio::time::sleep(TAIL_INTERVAL).await;
+                    let entries = client.logs(TAIL_WINDOW).await?;
+                    print_new_logs(entries, &mut printed);
+                }
+            }
+        }
+        Command::Stats => {
+            let stats: Vec<UpstreamStats> = client
+                .send(client.http.get(format!("{}/api/stats", client.base)))
+                .await?;
+            println!("{}", format_stats(&stats));
+        }
+        Command::Reload => {
+            let config = config.ok_or("尚未保存任何配置")?;
+            let url = format!("{}/api/proxy/reload", client.base);
+            let _: serde_json::Value = client.send(client.http.post(url).json(&config)).await?;
+            println!("已按保存的配置重载代理");
+        }
+        Command::Help => println!("{USAGE}"),
+    }
+    Ok(())
+}
+
+/// 只输出已完成且尚未输出过的请求，进行中的请求完成后再输出
+fn print_new_logs(entries: Vec<ProxyLogEntry>, printed: &mut HashSet<String>) {
+    for entry in entries {
+        if entry.status.is_some() && printed.insert(entry.id.clone()) {
+            println!("{}", format_log_line(&entry));
+        }
+    }
+}
+
+pub fn format_log_line(entry: &ProxyLogEntry) -> String {
+    let status = entry.status.map_or("-".to_string(), |s| s.to_string());
+    let upstream = entry
+        .upstream_label
+        .as_deref()
+        .or(entry.upstream_id.as_deref())
+        .unwrap_or("-");
+    let mut line = format!(
+        "{} {} {} {} {}ms {}",
+        entry.timestamp, entry.method, entry.path, status, entry.duration_ms, upstream
+    );
+    if let Some(model) = &entry.model {
+        line.push_str(&format!(" model={model}"));
+    }
+    if let Some(error) = &entry.error {
+        line.push_str(&format!(" error={error}"));
+    }
+    line
+}
+
+pub fn format_stats(stats: &[UpstreamStats]) -> String {
+    let mut lines = vec![format!(
+        "{:<24} {:>8} {:>8} {:>8} {:>8} {:>10} {:>10}",
+        "上游", "请求", "成功率", "p50", "p95", "tokens", "费用"
+    )];
+    for s in stats.iter().filter(|s| !s.archived) {
+        let success_rate = if s.total_requests > 0 {
+            s.success_count as f64 / s.total_requests as f64 * 100.0
+        } else {
+            0.0
+        };
+        lines.push(format!(
+            "{:<24} {:>8} {:>7.1}% {:>6}ms {:>6}ms {:>10} {:>10.4}",
+            s.upstream_label.as_deref().unwrap_or(&s.upstream_id),
+            s.total_requests,
+            success_rate,
+            s.p50_ms,
+            s.p95_ms,
+            s.total_tokens,
+            s.total_cost
+        ));
+    }
+    lines.join("\n")
+}
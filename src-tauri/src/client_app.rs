@@ -0,0 +1,53 @@
+//! 按 User-Agent 识别发起请求的客户端工具（Claude Code、Cursor、LangChain、各语言 SDK 等），
+//! 记录在日志的 client_app 字段并作为统计维度，无需客户端额外添加请求头。
+
+use std::sync::OnceLock;
+
+use http::{header, HeaderMap};
+use regex::Regex;
+
+/// 按顺序匹配，先匹配更具体的工具；捕获组为版本号
+const FINGERPRINTS: &[(&str, &str)] = &[
+    (r"^claude-cli/([\w.\-]+)", "Claude Code"),
+    (r"(?i)\bcursor/([\w.\-]+)", "Cursor"),
+    (r"(?i)\bcline/([\w.\-]+)", "Cline"),
+    (r"(?i)\bcontinue/([\w.\-]+)", "Continue"),
+    (r"(?i)\baider/([\w.\-]+)", "Aider"),
+    (r"(?i)\blangchain", "LangChain"),
+    (r"(?i)\blitellm", "LiteLLM"),
+    (r"^OpenAI/Python ([\w.\-]+)", "OpenAI Python SDK"),
+    (r"^OpenAI/JS ([\w.\-]+)", "OpenAI JS SDK"),
+    (r"^OpenAI/Go ([\w.\-]+)", "OpenAI Go SDK"),
+    (r"^Anthropic/Python ([\w.\-]+)", "Anthropic Python SDK"),
+    (r"^Anthropic/JS ([\w.\-]+)", "Anthropic JS SDK"),
+    (r"^python-requests/([\w.\-]+)", "python-requests"),
+    (r"^curl/([\w.\-]+)", "curl"),
+];
+
+fn fingerprints() -> &'static [(Regex, &'static str)] {
+    static COMPILED: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        FINGERPRINTS
+            .iter()
+            .map(|(pattern, name)| (Regex::new(pattern).expect("valid regex"), *name))
+            .collect()
+    })
+}
+
+/// 识别 User-Agent 对应的客户端，带版本号时附在名称后，如 `OpenAI Python SDK 1.30.1`
+pub fn classify_user_agent(user_agent: &str) -> Option<String> {
+    fingerprints().iter().find_map(|(regex, name)| {
+        let caps = regex.captures(user_agent)?;
+        Some(match caps.get(1) {
+            Some(version) => format!("{name} {}", version.as_str()),
+            None => name.to_string(),
+        })
+    })
+}
+
+pub fn classify(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(classify_user_agent)
+}
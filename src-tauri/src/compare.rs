@@ -0,0 +1,211 @@
+//! 对比模式：把同一个请求同时发给服务中选定的两个上游，客户端只收到主上游的响应，
+//! 对比上游的响应与两者的 JSON 结构差异记录在一条关联日志中，便于验证服务商迁移。
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use ts_rs::TS;
+
+use crate::helpers::truncate_body;
+use crate::linked::Outcome;
+use crate::stats::StatsStore;
+use crate::{
+    linked, logging, redaction, ProxyConfig, ProxyLogEntry, ResolvedUpstream, UpstreamEntry,
+};
+
+/// 单次对比最多记录的差异条数
+const MAX_DIFFERENCES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/CompareConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct CompareConfig {
+    /// 响应返回给客户端的上游
+    pub primary_upstream_id: String,
+    /// 只用于对比的上游，其响应不会返回给客户端
+    pub secondary_upstream_id: String,
+}
+
+impl CompareConfig {
+    pub fn validate(&self, upstreams: &[UpstreamEntry]) -> Result<(), String> {
+        if self.primary_upstream_id == self.secondary_upstream_id {
+            return Err("对比模式需要选择两个不同的上游".into());
+        }
+        for id in [&self.primary_upstream_id, &self.secondary_upstream_id] {
+            if !upstreams.iter().any(|u| &u.id == id) {
+                return Err(format!("对比模式引用了不存在的上游: {id}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/JsonDifference.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDifference {
+    /// 差异所在位置，如 `$.choices[0].message.content`
+    pub path: String,
+    /// 主上游响应中的值（JSON 文本），缺失时为空
+    pub primary: Option<String>,
+    /// 对比上游响应中的值（JSON 文本），缺失时为空
+    pub secondary: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/CompareReport.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct CompareReport {
+    /// 返回给客户端的那条日志
+    pub primary_log_id: String,
+    pub primary_upstream_id: String,
+    pub secondary_upstream_id: String,
+    pub primary_status: u16,
+    pub secondary_status: Option<u16>,
+    pub primary_body: Option<String>,
+    pub secondary_body: Option<String>,
+    /// 对比上游请求失败的原因
+    pub secondary_error: Option<String>,
+    /// 超过 200 条时只保留前 200 条
+    pub differences: Vec<JsonDifference>,
+}
+
+/// 主上游响应读完后等待对比请求结束，写入关联日志；不记录响应体时报告只保留状态与差异位置
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_report(
+    logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
+    stats: Arc<StatsStore>,
+    primary: ProxyLogEntry,
+    primary_status: u16,
+    primary_body: Bytes,
+    secondary: ResolvedUpstream,
+    task: JoinHandle<Outcome>,
+    log_response_body: bool,
+    config: Arc<ProxyConfig>,
+    started_at: Instant,
+) {
+    tokio::spawn(async move {
+        let outcome = task
+            .await
+            .unwrap_or_else(|err| Outcome::failed(format!("对比请求中断: {err}")));
+        let rules = redaction::rules(&config);
+        let redact = |body: &[u8]| {
+            log_response_body
+                .then(|| truncate_body(body, 8000).map(|b| rules.redact_body(b)))
+                .flatten()
+        };
+
+        let (secondary_status, secondary_body, secondary_error, differences) = match &outcome.result
+        {
+            Ok((status, body)) => (
+                Some(*status),
+                redact(body),
+                None,
+                diff_bodies(&primary_body, body, log_response_body),
+            ),
+            Err(err) => (None, None, Some(err.clone()), Vec::new()),
+        };
+        let report = CompareReport {
+            primary_log_id: primary.id.clone(),
+            primary_upstream_id: primary.upstream_id.clone().unwrap_or_default(),
+            secondary_upstream_id: secondary.upstream_id.clone(),
+            primary_status,
+            secondary_status,
+            primary_body: redact(&primary_body),
+            secondary_body: secondary_body.clone(),
+            secondary_error,
+            differences,
+        };
+
+        let detail = format!("与主上游响应有 {} 处差异", report.differences.len());
+        let linked = ProxyLogEntry {
+            compare: Some(report),
+            ..linked::record(
+                primary,
+                "compare",
+                &secondary,
+                &outcome,
+                secondary_body,
+                detail,
+                started_at,
+                &config,
+                &stats,
+            )
+        };
+        logging::upsert_log(logs, linked).await;
+    });
+}
+
+/// 比较两个响应体：都是 JSON 时逐字段比较，否则整体比较；`with_values` 为 false 时只记录差异位置
+pub fn diff_bodies(primary: &[u8], secondary: &[u8], with_values: bool) -> Vec<JsonDifference> {
+    let mut differences = match (
+        serde_json::from_slice::<Value>(primary),
+        serde_json::from_slice::<Value>(secondary),
+    ) {
+        (Ok(a), Ok(b)) => {
+            let mut differences = Vec::new();
+            diff_values("$", &a, &b, &mut differences);
+            differences
+        }
+        _ if primary == secondary => Vec::new(),
+        _ => vec![JsonDifference {
+            path: "$".into(),
+            primary: truncate_body(primary, 8000),
+            secondary: truncate_body(secondary, 8000),
+        }],
+    };
+    if !with_values {
+        for difference in &mut differences {
+            difference.primary = None;
+            difference.secondary = None;
+        }
+    }
+    differences
+}
+
+fn diff_values(path: &str, a: &Value, b: &Value, out: &mut Vec<JsonDifference>) {
+    if out.len() >= MAX_DIFFERENCES {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let child = format!("{path}.{key}");
+                match b.get(key) {
+                    Some(other) => diff_values(&child, value, other, out),
+                    None => record(out, child, Some(value), None),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                record(out, format!("{path}.{key}"), None, Some(value));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let child = format!("{path}[{i}]");
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_values(&child, x, y, out),
+                    (x, y) => record(out, child, x, y),
+                }
+            }
+        }
+        _ if a == b => {}
+        _ => record(out, path.to_string(), Some(a), Some(b)),
+    }
+}
+
+fn record(out: &mut Vec<JsonDifference>, path: String, a: Option<&Value>, b: Option<&Value>) {
+    if out.len() < MAX_DIFFERENCES {
+        out.push(JsonDifference {
+            path,
+            primary: a.map(Value::to_string),
+            secondary: b.map(Value::to_string),
+        });
+    }
+}
@@ -0,0 +1,315 @@
+//! 配置导出 / 导入：把完整配置写成 JSON 文件，便于团队成员共享路由设置。
+//! 不导出密钥时，全局 key、上游 key、管理令牌、敏感请求头、上游查询参数、
+//! ClickHouse 账号密码与代理地址中的账号密码替换为占位符；
+//! 导入时占位符沿用本机现有配置中对应的值，所属的上游或服务地址变了则不沿用，
+//! 找不到的留空并列出，由用户补填。钩子推送地址与延迟重试回调地址的查询参数常带令牌，同样替换。
+//! 钩子命令会在本机执行，导入时只保留本机已有的命令，其余列出由用户确认后手动添加。
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::query_params::QueryParamRule;
+use crate::storage::LogStorageConfig;
+use crate::{env_vars, ProxyConfig};
+
+pub const SECRET_PLACEHOLDER: &str = "<secret>";
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ConfigImport.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImport {
+    pub config: ProxyConfig,
+    /// 文件中是占位符、本机没有对应值或所属地址已变的密钥，如 `upstream:<id>`
+    pub missing_secrets: Vec<String>,
+    /// 文件中本机没有的钩子命令，未导入
+    pub dropped_hook_commands: Vec<String>,
+}
+
+/// 一项密钥；`scope` 是密钥所属的地址，导入时地址与本机不同就不沿用本机的值
+struct Secret<'a> {
+    name: String,
+    scope: String,
+    value: &'a mut String,
+}
+
+impl<'a> Secret<'a> {
+    fn new(name: String, scope: &str, value: &'a mut String) -> Self {
+        Self {
+            name,
+            scope: scope.to_string(),
+            value,
+        }
+    }
+}
+
+/// 配置中的各项密钥；名称在导出前后保持不变，导入时据此找回本机已有的值
+fn secrets(config: &mut ProxyConfig) -> Vec<Secret<'_>> {
+    let redaction = config.redaction.clone().unwrap_or_default();
+    let mut slots = Vec::new();
+    if let Some(key) = config.global_key.as_mut() {
+        slots.push(Secret::new("global-key".into(), "", key));
+    }
+    for token in config.admin_tokens.iter_mut().flatten() {
+        slots.push(Secret::new(
+            format!("admin-token:{}", token.name),
+            "",
+            &mut token.token,
+        ));
+    }
+    if let Some(LogStorageConfig::ClickHouse {
+        url,
+        user,
+        password,
+        ..
+    }) = config.log_storage.as_mut()
+    {
+        if let Some(user) = user.as_mut() {
+            slots.push(Secret::new("clickhouse:user".into(), url, user));
+        }
+        if let Some(password) = password.as_mut() {
+            slots.push(Secret::new("clickhouse:password".into(), url, password));
+        }
+    }
+    let services = config.services.iter_mut().chain(
+        config
+            .listeners
+            .iter_mut()
+            .flatten()
+            .flat_map(|l| l.services.iter_mut()),
+    );
+    for service in services {
+        for (name, value) in service.headers.iter_mut().flatten() {
+            if redaction.is_sensitive_header(name) {
+                slots.push(Secret::new(
+                    format!("service:{}:header:{name}", service.id),
+                    "",
+                    value,
+                ));
+            }
+        }
+        for upstream in service.upstreams.iter_mut() {
+            let base = upstream.upstream_base.as_str();
+            if let Some(key) = upstream.api_key.as_mut() {
+                slots.push(Secret::new(format!("upstream:{}", upstream.id), base, key));
+            }
+            for (name, value) in upstream.headers.iter_mut().flatten() {
+                if redaction.is_sensitive_header(name) {
+                    let name = format!("upstream:{}:header:{name}", upstream.id);
+                    slots.push(Secret::new(name, base, value));
+                }
+            }
+            for rule in upstream.query_params.iter_mut().flatten() {
+                if let QueryParamRule::Add { name, value }
+                | QueryParamRule::Override { name, value } = rule
+                {
+                    let name = format!("upstream:{}:query:{name}", upstream.id);
+                    slots.push(Secret::new(name, base, value));
+                }
+            }
+        }
+    }
+    slots
+}
+
+/// 查询参数常带令牌的地址：钩子推送地址（以去掉查询参数的地址命名）与延迟重试回调地址
+fn token_urls(config: &mut ProxyConfig) -> Vec<(String, &mut String)> {
+    let mut urls = Vec::new();
+    for hook in config.hooks.iter_mut().flatten() {
+        if let Some(url) = hook.url.as_mut() {
+            let base = url.split('?').next().unwrap_or_default();
+            urls.push((format!("hook:{base}"), url));
+        }
+    }
+    let services = config.services.iter_mut().chain(
+        config
+            .listeners
+            .iter_mut()
+            .flatten()
+            .flat_map(|l| l.services.iter_mut()),
+    );
+    for service in services {
+        let callback = service
+            .retry_queue
+            .as_mut()
+            .and_then(|q| q.callback_url.as_mut());
+        if let Some(url) = callback {
+            urls.push((format!("service:{}:retry-callback", service.id), url));
+        }
+    }
+    urls
+}
+
+/// 拆出代理地址中的账号密码：(`scheme://`, 账号密码, 主机部分)
+fn proxy_credentials(url: &str) -> Option<(&str, &str, &str)> {
+    let scheme_end = url.find("://")? + 3;
+    let (scheme, rest) = url.split_at(scheme_end);
+    let (userinfo, host) = rest.rsplit_once('@')?;
+    (!userinfo.is_empty()).then_some((scheme, userinfo, host))
+}
+
+/// 把所有非空的密钥替换为占位符，引用环境变量的值不含密钥，原样导出
+pub fn strip_secrets(config: &mut ProxyConfig) {
+    for secret in secrets(config) {
+        if !secret.value.is_empty() && !env_vars::has_placeholder(secret.value) {
+            *secret.value = SECRET_PLACEHOLDER.to_string();
+        }
+    }
+    for (_, url) in token_urls(config) {
+        if let Some((base, query)) = url.split_once('?') {
+            if !query.is_empty() && !env_vars::has_placeholder(query) {
+                *url = format!("{base}?{SECRET_PLACEHOLDER}");
+            }
+        }
+    }
+    if let Some(url) = config.proxy_url.as_mut() {
+        if let Some((scheme, userinfo, host)) = proxy_credentials(url) {
+            if !env_vars::has_placeholder(userinfo) {
+                *url = format!("{scheme}{SECRET_PLACEHOLDER}@{host}");
+            }
+        }
+    }
+}
+
+/// 用 current 中同名且所属地址相同的密钥替换占位符，返回找不到对应值的密钥名；
+/// 这些位置的上游 key、请求头与查询参数会被清空，管理令牌会被移除，代理地址去掉账号密码
+pub fn restore_secrets(config: &mut ProxyConfig, current: Option<&ProxyConfig>) -> Vec<String> {
+    let mut current = current.cloned().unwrap_or_default();
+    let mut known: HashMap<String, (String, String)> = secrets(&mut current)
+        .into_iter()
+        .filter(|s| s.value.as_str() != SECRET_PLACEHOLDER)
+        .map(|s| (s.name, (s.scope, s.value.clone())))
+        .collect();
+    let mut missing = Vec::new();
+    for secret in secrets(config) {
+        if secret.value.as_str() == SECRET_PLACEHOLDER {
+            match known.remove(&secret.name) {
+                Some((scope, value)) if scope == secret.scope => *secret.value = value,
+                _ => {
+                    secret.value.clear();
+                    missing.push(secret.name);
+                }
+            }
+        }
+    }
+    if let Some(url) = config.proxy_url.as_mut() {
+        if let Some((scheme, SECRET_PLACEHOLDER, host)) = proxy_credentials(url) {
+            let local = current
+                .proxy_url
+                .as_deref()
+                .and_then(proxy_credentials)
+                .filter(|&(s, _, h)| s == scheme && h == host);
+            *url = match local {
+                Some((_, userinfo, _)) => format!("{scheme}{userinfo}@{host}"),
+                None => {
+                    missing.push("proxy-url".into());
+                    format!("{scheme}{host}")
+                }
+            };
+        }
+    }
+    let known_urls: HashMap<String, String> = token_urls(&mut current)
+        .into_iter()
+        .map(|(name, url)| (name, url.clone()))
+        .collect();
+    for (name, url) in token_urls(config) {
+        let Some(base) = url.strip_suffix(&format!("?{SECRET_PLACEHOLDER}")) else {
+            continue;
+        };
+        let base = base.to_string();
+        *url = match known_urls.get(&name) {
+            Some(local) if local.split_once('?').is_some_and(|(b, _)| b == base) => local.clone(),
+            _ => {
+                missing.push(name);
+                base
+            }
+        };
+    }
+    drop_empty_secrets(config);
+    missing
+}
+
+fn drop_empty_secrets(config: &mut ProxyConfig) {
+    if config.global_key.as_deref() == Some("") {
+        config.global_key = None;
+    }
+    if let Some(tokens) = config.admin_tokens.as_mut() {
+        tokens.retain(|t| !t.token.is_empty());
+    }
+    let services = config.services.iter_mut().chain(
+        config
+            .listeners
+            .iter_mut()
+            .flatten()
+            .flat_map(|l| l.services.iter_mut()),
+    );
+    for service in services {
+        if let Some(headers) = service.headers.as_mut() {
+            headers.retain(|_, v| !v.is_empty());
+        }
+        for upstream in service.upstreams.iter_mut() {
+            if upstream.api_key.as_deref() == Some("") {
+                upstream.api_key = None;
+            }
+            if let Some(headers) = upstream.headers.as_mut() {
+                headers.retain(|_, v| !v.is_empty());
+            }
+            if let Some(rules) = upstream.query_params.as_mut() {
+                rules.retain(|rule| {
+                    !matches!(rule, QueryParamRule::Add { value, .. } | QueryParamRule::Override { value, .. } if value.is_empty())
+                });
+            }
+        }
+    }
+}
+
+/// 移除 current 中没有的钩子命令并返回它们，避免共享的配置在本机植入命令；
+/// 只剩命令的钩子整个移除
+pub fn drop_hook_commands(config: &mut ProxyConfig, current: Option<&ProxyConfig>) -> Vec<String> {
+    let local: Vec<&str> = current
+        .and_then(|c| c.hooks.as_deref())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|h| h.command.as_deref())
+        .collect();
+    let mut dropped = Vec::new();
+    let Some(hooks) = config.hooks.as_mut() else {
+        return dropped;
+    };
+    for hook in hooks.iter_mut() {
+        if let Some(command) = hook.command.take() {
+            if local.contains(&command.as_str()) {
+                hook.command = Some(command);
+            } else if !command.trim().is_empty() {
+                dropped.push(command);
+            }
+        }
+    }
+    hooks.retain(|h| h.url.is_some() || h.command.is_some());
+    dropped
+}
+
+pub fn export_to(path: &Path, config: &ProxyConfig, include_secrets: bool) -> Result<(), String> {
+    let mut config = config.clone();
+    if !include_secrets {
+        strip_secrets(&mut config);
+    }
+    let json = serde_json::to_string_pretty(&config).map_err(|e| format!("序列化失败: {e}"))?;
+    fs::write(path, json).map_err(|e| format!("写入导出文件失败: {e}"))
+}
+
+pub fn import_from(path: &Path, current: Option<&ProxyConfig>) -> Result<ConfigImport, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("读取导入文件失败: {e}"))?;
+    let mut config: ProxyConfig =
+        serde_json::from_str(&data).map_err(|e| format!("解析导入文件失败: {e}"))?;
+    let missing_secrets = restore_secrets(&mut config, current);
+    let dropped_hook_commands = drop_hook_commands(&mut config, current);
+    Ok(ConfigImport {
+        config,
+        missing_secrets,
+        dropped_hook_commands,
+    })
+}
@@ -0,0 +1,100 @@
+//! 上下文超长时的自动重试：按服务配置缩小 `max_tokens`，或删除最早的非系统消息，
+//! 在同一上游重试一次，并在日志中记录具体改动。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+const DEFAULT_MAX_TOKENS_RATIO: f64 = 0.5;
+const MAX_TOKEN_FIELDS: [&str; 3] = ["max_tokens", "max_completion_tokens", "max_output_tokens"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/OverflowStrategy.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum OverflowStrategy {
+    /// 按比例缩小请求的 max_tokens
+    ReduceMaxTokens,
+    /// 删除最早的非系统消息，保留最后一条
+    TrimMessages,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ContextOverflowRetry.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ContextOverflowRetry {
+    pub strategy: OverflowStrategy,
+    /// 缩小后的 max_tokens 占原值的比例，默认 0.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_tokens_ratio: Option<f64>,
+    /// 删除的消息条数，默认删除一半的非系统消息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub trim_count: Option<u32>,
+}
+
+impl ContextOverflowRetry {
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .max_tokens_ratio
+            .is_some_and(|r| r.is_nan() || r <= 0.0 || r >= 1.0)
+        {
+            return Err("上下文超长重试的 max_tokens 比例需在 0 到 1 之间".into());
+        }
+        if self.trim_count == Some(0) {
+            return Err("上下文超长重试删除的消息条数必须大于 0".into());
+        }
+        Ok(())
+    }
+
+    /// 缩减请求体，返回新的请求体与改动说明；无法缩减时返回 None
+    pub fn shrink(&self, body: &[u8]) -> Option<(Vec<u8>, String)> {
+        let mut value: Value = serde_json::from_slice(body).ok()?;
+        let object = value.as_object_mut()?;
+        let change = match self.strategy {
+            OverflowStrategy::ReduceMaxTokens => {
+                let ratio = self.max_tokens_ratio.unwrap_or(DEFAULT_MAX_TOKENS_RATIO);
+                let (field, current) = MAX_TOKEN_FIELDS
+                    .iter()
+                    .find_map(|f| object.get(*f).and_then(Value::as_u64).map(|v| (*f, v)))?;
+                let reduced = ((current as f64 * ratio) as u64).max(1);
+                if reduced >= current {
+                    return None;
+                }
+                object.insert(field.into(), reduced.into());
+                format!("{field} 由 {current} 缩小为 {reduced}")
+            }
+            OverflowStrategy::TrimMessages => {
+                let messages = object.get_mut("messages")?.as_array_mut()?;
+                let is_system = |m: &Value| {
+                    matches!(
+                        m.get("role").and_then(Value::as_str),
+                        Some("system" | "developer")
+                    )
+                };
+                // 最后一条消息是当前的提问，不删除
+                let removable: Vec<usize> = messages
+                    .iter()
+                    .enumerate()
+                    .take(messages.len().saturating_sub(1))
+                    .filter(|(_, m)| !is_system(m))
+                    .map(|(i, _)| i)
+                    .collect();
+                let non_system = messages.iter().filter(|m| !is_system(m)).count();
+                let count = self
+                    .trim_count
+                    .map(|c| c as usize)
+                    .unwrap_or(non_system / 2)
+                    .clamp(1, removable.len().max(1));
+                if removable.is_empty() {
+                    return None;
+                }
+                for idx in removable.into_iter().take(count).rev() {
+                    messages.remove(idx);
+                }
+                format!("删除最早的 {count} 条消息")
+            }
+        };
+        Some((serde_json::to_vec(&value).ok()?, change))
+    }
+}
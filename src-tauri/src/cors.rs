@@ -0,0 +1,188 @@
+//! 监听端口的跨域（CORS）处理：作为转发处理之前的中间层，直接应答浏览器的预检请求，
+//! 并为允许来源的普通响应补上跨域响应头。未配置时不做任何处理。
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use http::{header, HeaderValue, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ProxyConfig;
+
+const DEFAULT_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS, HEAD";
+const DEFAULT_MAX_AGE_SECS: u64 = 86400;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/CorsConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct CorsConfig {
+    /// 允许任意来源、方法与请求头，忽略下面的列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub permissive: Option<bool>,
+    /// 允许的来源，如 `http://localhost:3000`，`*` 表示任意来源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub allowed_origins: Option<Vec<String>>,
+    /// 允许的方法，默认允许常用方法
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub allowed_methods: Option<Vec<String>>,
+    /// 允许的请求头，默认允许预检请求中声明的全部请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub allowed_headers: Option<Vec<String>>,
+    /// 允许浏览器脚本读取的响应头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub expose_headers: Option<Vec<String>>,
+    /// 预检结果的缓存时间，默认 1 天
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl CorsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for origin in self.allowed_origins.iter().flatten() {
+            let origin = origin.trim();
+            let valid = origin == "*"
+                || reqwest::Url::parse(origin).is_ok_and(|url| {
+                    matches!(url.scheme(), "http" | "https")
+                        && url.path() == "/"
+                        && !origin.ends_with('/')
+                });
+            if !valid {
+                return Err(format!(
+                    "跨域来源需为 `*` 或 `协议://主机[:端口]` 格式: {origin}"
+                ));
+            }
+        }
+        for method in self.allowed_methods.iter().flatten() {
+            Method::from_bytes(method.trim().as_bytes())
+                .map_err(|_| format!("跨域允许的方法无效: {method}"))?;
+        }
+        for name in self
+            .allowed_headers
+            .iter()
+            .chain(&self.expose_headers)
+            .flatten()
+        {
+            if name.trim() != "*" {
+                header::HeaderName::from_bytes(name.trim().as_bytes())
+                    .map_err(|_| format!("跨域配置中的请求头名无效: {name}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_permissive(&self) -> bool {
+        self.permissive.unwrap_or(false)
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.is_permissive()
+            || self
+                .allowed_origins
+                .iter()
+                .flatten()
+                .any(|o| o.trim() == "*" || o.trim().eq_ignore_ascii_case(origin))
+    }
+
+    fn allowed_methods(&self) -> String {
+        match &self.allowed_methods {
+            Some(methods) if !self.is_permissive() && !methods.is_empty() => methods
+                .iter()
+                .map(|m| m.trim().to_ascii_uppercase())
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => DEFAULT_METHODS.to_string(),
+        }
+    }
+
+    /// 未配置列表时回显预检请求声明的请求头
+    fn allowed_headers(&self, requested: Option<&HeaderValue>) -> Option<HeaderValue> {
+        match &self.allowed_headers {
+            Some(names) if !self.is_permissive() => HeaderValue::from_str(
+                &names
+                    .iter()
+                    .map(|n| n.trim())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .ok(),
+            _ => requested.cloned(),
+        }
+    }
+}
+
+/// 浏览器的预检请求：OPTIONS 且带有 Access-Control-Request-Method
+fn is_preflight(req: &Request) -> bool {
+    req.method() == Method::OPTIONS
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// 预检请求在本地应答，不经过鉴权与转发；来源不被允许时返回不带跨域头的 403
+fn preflight_response(cors: &CorsConfig, origin: &HeaderValue, req: &Request) -> Response {
+    let origin_allowed = origin.to_str().is_ok_and(|o| cors.allows_origin(o));
+    let mut builder = Response::builder().header(header::VARY, "Origin");
+    if !origin_allowed {
+        builder = builder.status(StatusCode::FORBIDDEN);
+    } else {
+        builder = builder
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone())
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, cors.allowed_methods())
+            .header(
+                header::ACCESS_CONTROL_MAX_AGE,
+                cors.max_age_secs.unwrap_or(DEFAULT_MAX_AGE_SECS),
+            );
+        let requested = req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS);
+        if let Some(headers) = cors.allowed_headers(requested) {
+            builder = builder.header(header::ACCESS_CONTROL_ALLOW_HEADERS, headers);
+        }
+    }
+    builder.body(Body::empty()).unwrap_or_default()
+}
+
+/// 为允许来源的响应补上跨域响应头
+fn decorate(cors: &CorsConfig, origin: HeaderValue, response: &mut Response) {
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    let expose = cors.expose_headers.iter().flatten().map(|n| n.trim());
+    if let Ok(value) = HeaderValue::from_str(&expose.collect::<Vec<_>>().join(", ")) {
+        if !value.is_empty() {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+        }
+    }
+}
+
+/// 路由的中间层，每个请求读取最新配置，配置重载后立即生效
+pub async fn layer(
+    State(config): State<Arc<ArcSwap<ProxyConfig>>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = config.load_full();
+    let (Some(cors), Some(origin)) = (&config.cors, req.headers().get(header::ORIGIN).cloned())
+    else {
+        return next.run(req).await;
+    };
+    if is_preflight(&req) {
+        return preflight_response(cors, &origin, &req);
+    }
+    let allowed = origin.to_str().is_ok_and(|o| cors.allows_origin(o));
+    let mut response = next.run(req).await;
+    if allowed {
+        decorate(cors, origin, &mut response);
+    }
+    response
+}
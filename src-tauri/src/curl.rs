@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ProxyLogEntry;
+
+/// curl 命令的目标地址：经由本地代理，或直连解析后的上游
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/CurlTarget.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum CurlTarget {
+    Proxy,
+    Upstream,
+}
+
+const AUTH_HEADERS: [&str; 3] = ["authorization", "x-goog-api-key", "x-proxy-key"];
+
+/// 单引号包裹，适用于 POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// 保留首尾少量字符，便于辨认是哪把 key 而不泄露完整内容
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 12 {
+        return "****".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{head}****{tail}")
+}
+
+fn parse_logged_headers(raw: Option<&str>) -> Vec<(String, String)> {
+    raw.unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// 根据日志记录还原 curl 命令。
+/// `key` 为目标侧需要的凭证（代理的 global_key 或上游配置的 api_key），
+/// `reveal_key` 为 false 时以掩码形式输出。
+pub fn build_curl_command(
+    entry: &ProxyLogEntry,
+    target: CurlTarget,
+    key: Option<&str>,
+    reveal_key: bool,
+) -> String {
+    let url = match target {
+        CurlTarget::Proxy => format!("http://localhost:{}{}", entry.listen_port, entry.path),
+        CurlTarget::Upstream => entry.upstream_url.clone(),
+    };
+
+    let mut parts = vec!["curl".to_string()];
+    if entry.is_streaming {
+        parts.push("-N".to_string());
+    }
+    parts.push("-X".to_string());
+    parts.push(entry.method.clone());
+    parts.push(shell_quote(&url));
+
+    let headers = parse_logged_headers(entry.request_headers.as_deref());
+    let uses_goog_key = headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("x-goog-api-key"));
+
+    for (name, value) in headers.iter() {
+        if AUTH_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+            continue;
+        }
+        parts.push("-H".to_string());
+        parts.push(shell_quote(&format!("{name}: {value}")));
+    }
+
+    if let Some(key) = key {
+        let shown = if reveal_key {
+            key.to_string()
+        } else {
+            mask_secret(key)
+        };
+        let auth = if target == CurlTarget::Upstream && uses_goog_key {
+            format!("x-goog-api-key: {shown}")
+        } else {
+            format!("Authorization: Bearer {shown}")
+        };
+        parts.push("-H".to_string());
+        parts.push(shell_quote(&auth));
+    }
+
+    if let Some(body) = entry.request_body.as_deref() {
+        parts.push("--data-raw".to_string());
+        parts.push(shell_quote(body));
+    }
+
+    parts.join(" ")
+}
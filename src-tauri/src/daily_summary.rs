@@ -0,0 +1,206 @@
+//! 每日用量概览：每天在设定的时间推送一次当天的请求数、错误率、token 与费用（按服务汇总），
+//! 托盘菜单也可随时查看。当天的数据为服务统计与当天零点基线之差。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use ts_rs::TS;
+
+use crate::stats::{GroupStats, StatsGroupBy, StatsStore};
+use crate::{events, ProxyConfig};
+
+const DEFAULT_HOUR: u32 = 22;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/DailySummaryConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct DailySummaryConfig {
+    /// 为 false 时不推送每日概览，默认推送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub enabled: Option<bool>,
+    /// 推送时间（本地时间的整点，0 到 23），默认 22 点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub hour: Option<u32>,
+}
+
+impl DailySummaryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hour.is_some_and(|h| h > 23) {
+            return Err("每日概览的推送时间需在 0 到 23 点之间".into());
+        }
+        Ok(())
+    }
+}
+
+/// 单个服务当天的用量
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ServiceDailyUsage.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDailyUsage {
+    pub service_name: String,
+    #[ts(type = "number")]
+    pub requests: u64,
+    #[ts(type = "number")]
+    pub errors: u64,
+    #[ts(type = "number")]
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+/// 当天的用量概览，服务按请求数从多到少排序
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/DailySummary.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct DailySummary {
+    /// 本地日期，如 `2024-05-01`
+    pub date: String,
+    #[ts(type = "number")]
+    pub requests: u64,
+    #[ts(type = "number")]
+    pub errors: u64,
+    /// 错误请求占比（0 到 1），没有请求时为 0
+    pub error_rate: f64,
+    #[ts(type = "number")]
+    pub tokens: u64,
+    pub cost: f64,
+    pub services: Vec<ServiceDailyUsage>,
+}
+
+/// 基线中记录的累计值
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    requests: u64,
+    errors: u64,
+    tokens: u64,
+    cost: f64,
+}
+
+impl From<&GroupStats> for Totals {
+    fn from(stats: &GroupStats) -> Self {
+        Self {
+            requests: stats.total_requests,
+            errors: stats.error_count,
+            tokens: stats.total_tokens,
+            cost: stats.total_cost,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    /// 当天零点时各服务的累计值；应用当天启动时为空
+    baseline: Option<(NaiveDate, HashMap<String, Totals>)>,
+    /// 最近一次自动推送的日期
+    sent_on: Option<NaiveDate>,
+}
+
+#[derive(Default)]
+pub struct DailySummaryTracker {
+    state: Mutex<TrackerState>,
+}
+
+impl DailySummaryTracker {
+    /// 按服务统计生成当天的概览；跨天后以当前累计值作为新一天的基线
+    pub fn summary_at(&self, services: &[GroupStats], now: DateTime<Local>) -> DailySummary {
+        let today = now.date_naive();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let baseline = match &state.baseline {
+            Some((date, totals)) if *date == today => totals,
+            Some(_) => {
+                let totals = services
+                    .iter()
+                    .map(|s| (s.key.clone(), Totals::from(s)))
+                    .collect();
+                &state.baseline.insert((today, totals)).1
+            }
+            None => &state.baseline.insert((today, HashMap::new())).1,
+        };
+
+        let mut usage: Vec<ServiceDailyUsage> = services
+            .iter()
+            .map(|s| {
+                let base = baseline.get(&s.key).copied().unwrap_or_default();
+                ServiceDailyUsage {
+                    service_name: s.key.clone(),
+                    requests: s.total_requests.saturating_sub(base.requests),
+                    errors: s.error_count.saturating_sub(base.errors),
+                    tokens: s.total_tokens.saturating_sub(base.tokens),
+                    cost: (s.total_cost - base.cost).max(0.0),
+                }
+            })
+            .filter(|u| u.requests > 0)
+            .collect();
+        usage.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.service_name.cmp(&b.service_name))
+        });
+
+        let requests: u64 = usage.iter().map(|u| u.requests).sum();
+        let errors: u64 = usage.iter().map(|u| u.errors).sum();
+        DailySummary {
+            date: today.format("%Y-%m-%d").to_string(),
+            requests,
+            errors,
+            error_rate: if requests > 0 {
+                errors as f64 / requests as f64
+            } else {
+                0.0
+            },
+            tokens: usage.iter().map(|u| u.tokens).sum(),
+            cost: usage.iter().map(|u| u.cost).sum(),
+            services: usage,
+        }
+    }
+
+    /// 到达推送时间且当天尚未推送时返回 true，并记为已推送
+    pub fn due_at(&self, config: Option<&DailySummaryConfig>, now: DateTime<Local>) -> bool {
+        if config.is_some_and(|c| c.enabled == Some(false)) {
+            return false;
+        }
+        let hour = config.and_then(|c| c.hour).unwrap_or(DEFAULT_HOUR);
+        let today = now.date_naive();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if now.hour() < hour || state.sent_on == Some(today) {
+            return false;
+        }
+        state.sent_on = Some(today);
+        true
+    }
+}
+
+pub fn tracker() -> &'static DailySummaryTracker {
+    static TRACKER: OnceLock<DailySummaryTracker> = OnceLock::new();
+    TRACKER.get_or_init(DailySummaryTracker::default)
+}
+
+/// 当天到目前为止的用量概览
+pub fn current(stats: &StatsStore) -> DailySummary {
+    tracker().summary_at(&stats.breakdown(StatsGroupBy::Service), Local::now())
+}
+
+/// 每分钟检查一次，到达推送时间后推送当天的概览；同时负责在跨天后刷新基线
+pub fn spawn_task(config: Arc<RwLock<Option<ProxyConfig>>>, stats: Arc<StatsStore>) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let summary = current(&stats);
+            let settings = config
+                .read()
+                .await
+                .as_ref()
+                .and_then(|c| c.daily_summary.clone());
+            if tracker().due_at(settings.as_ref(), Local::now()) {
+                events::emit_daily_summary(summary);
+            }
+        }
+    });
+}
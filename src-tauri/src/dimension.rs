@@ -0,0 +1,119 @@
+//! 自定义统计维度：按规则从请求头、路径段或查询参数中取出一个值（如项目名），
+//! 写入日志并作为统计与费用汇总的分组依据，便于把共享代理的用量归到各个项目。
+
+use std::sync::OnceLock;
+
+use axum::http::HeaderMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/DimensionSource.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum DimensionSource {
+    Header,
+    /// 按 `/` 分隔的路径段，从 0 开始计数
+    PathSegment,
+    Query,
+}
+
+/// 单条取值规则
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/DimensionRule.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionRule {
+    pub source: DimensionSource,
+    /// 请求头名或查询参数名；来源为路径段时是段的序号
+    pub key: String,
+    /// 可选的正则，有捕获组时取第一个捕获组，否则取整个匹配；不匹配时视为未取到
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub pattern: Option<String>,
+    #[serde(skip)]
+    #[ts(skip)]
+    compiled: OnceLock<Option<Regex>>,
+}
+
+/// 自定义维度的名称与按顺序尝试的规则
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/CustomDimension.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct CustomDimension {
+    /// 显示名称，如「项目」
+    pub name: String,
+    pub rules: Vec<DimensionRule>,
+    /// 所有规则都未取到值时使用，未设置时该请求不计入此维度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub fallback: Option<String>,
+}
+
+impl CustomDimension {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("自定义维度需要填写名称".into());
+        }
+        if self.rules.is_empty() {
+            return Err("自定义维度至少需要一条取值规则".into());
+        }
+        for rule in &self.rules {
+            if rule.key.trim().is_empty() {
+                return Err("自定义维度规则需要填写请求头名或参数名".into());
+            }
+            if rule.source == DimensionSource::PathSegment
+                && rule.key.trim().parse::<usize>().is_err()
+            {
+                return Err(format!("路径段序号无效: {}", rule.key));
+            }
+            if let Some(pattern) = &rule.pattern {
+                Regex::new(pattern).map_err(|e| format!("自定义维度正则无效 `{pattern}`: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按顺序尝试规则，返回第一个非空的取值
+    pub fn classify(&self, headers: &HeaderMap, path: &str, query: Option<&str>) -> Option<String> {
+        self.rules
+            .iter()
+            .find_map(|rule| rule.extract(headers, path, query))
+            .or_else(|| self.fallback.clone())
+    }
+}
+
+impl DimensionRule {
+    fn extract(&self, headers: &HeaderMap, path: &str, query: Option<&str>) -> Option<String> {
+        let key = self.key.trim();
+        let raw = match self.source {
+            DimensionSource::Header => headers.get(key)?.to_str().ok()?.to_string(),
+            DimensionSource::PathSegment => {
+                let index: usize = key.parse().ok()?;
+                path.split('/')
+                    .filter(|s| !s.is_empty())
+                    .nth(index)?
+                    .to_string()
+            }
+            DimensionSource::Query => {
+                let url = reqwest::Url::parse(&format!("http://localhost/?{}", query?)).ok()?;
+                let (_, value) = url.query_pairs().find(|(name, _)| name == key)?;
+                value.into_owned()
+            }
+        };
+        let value = match self.regex() {
+            Some(re) => {
+                let caps = re.captures(&raw)?;
+                caps.get(1).or_else(|| caps.get(0))?.as_str().to_string()
+            }
+            None => raw,
+        };
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn regex(&self) -> Option<&Regex> {
+        self.compiled
+            .get_or_init(|| self.pattern.as_deref().and_then(|p| Regex::new(p).ok()))
+            .as_ref()
+    }
+}
@@ -0,0 +1,104 @@
+//! 故障演练：指定的上游在若干分钟内被视为故障，发往它的请求不再实际发出，直接以 503 失败，
+//! 随后按正常流程重试与切换上游，用于在真实故障前检查切换顺序、告警与客户端表现。
+//! 演练只保存在内存中，到期、手动结束或重启后即恢复。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use http::{header, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::timestamp;
+
+/// 演练产生的响应带有该响应头，便于与真实故障区分
+pub const DRILL_HEADER: &str = "x-apiflow-drill";
+const MAX_MINUTES: u64 = 24 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/FailoverDrill.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverDrill {
+    pub upstream_id: String,
+    pub started_at: String,
+    pub ends_at: String,
+}
+
+struct ActiveDrill {
+    info: FailoverDrill,
+    deadline: Instant,
+}
+
+fn drills() -> &'static Mutex<HashMap<String, ActiveDrill>> {
+    static DRILLS: OnceLock<Mutex<HashMap<String, ActiveDrill>>> = OnceLock::new();
+    DRILLS.get_or_init(Default::default)
+}
+
+/// 开始演练；同一上游已在演练时按新的时长重新计时
+pub fn start(upstream_id: &str, minutes: u64) -> Result<FailoverDrill, String> {
+    if upstream_id.is_empty() {
+        return Err("请选择要演练的上游".into());
+    }
+    if minutes == 0 || minutes > MAX_MINUTES {
+        return Err(format!("演练时长需在 1 到 {MAX_MINUTES} 分钟之间"));
+    }
+    let info = FailoverDrill {
+        upstream_id: upstream_id.to_string(),
+        started_at: timestamp::now(),
+        ends_at: timestamp::format_utc(Utc::now() + chrono::Duration::minutes(minutes as i64)),
+    };
+    let deadline = Instant::now() + Duration::from_secs(minutes * 60);
+    let mut guard = drills().lock().unwrap_or_else(|e| e.into_inner());
+    guard.insert(
+        upstream_id.to_string(),
+        ActiveDrill {
+            info: info.clone(),
+            deadline,
+        },
+    );
+    Ok(info)
+}
+
+/// 提前结束演练，返回该上游此前是否在演练中
+pub fn stop(upstream_id: &str) -> bool {
+    let mut guard = drills().lock().unwrap_or_else(|e| e.into_inner());
+    guard.remove(upstream_id).is_some()
+}
+
+/// 进行中的演练，已到期的同时清除
+pub fn active() -> Vec<FailoverDrill> {
+    let mut guard = drills().lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    guard.retain(|_, d| d.deadline > now);
+    let mut list: Vec<FailoverDrill> = guard.values().map(|d| d.info.clone()).collect();
+    list.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    list
+}
+
+pub fn is_active(upstream_id: &str) -> bool {
+    let guard = drills().lock().unwrap_or_else(|e| e.into_inner());
+    guard
+        .get(upstream_id)
+        .is_some_and(|d| d.deadline > Instant::now())
+}
+
+/// 演练中的上游代替真实请求返回的 503 响应
+pub fn simulated_outage(upstream_id: &str) -> Option<reqwest::Response> {
+    if !is_active(upstream_id) {
+        return None;
+    }
+    let body = serde_json::json!({
+        "error": { "message": "故障演练：上游被模拟为不可用", "type": "apiflow_drill" }
+    });
+    let mut response = http::Response::new(reqwest::Body::from(body.to_string()));
+    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    headers.insert(DRILL_HEADER, HeaderValue::from_static("outage"));
+    Some(reqwest::Response::from(response))
+}
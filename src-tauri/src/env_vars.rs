@@ -0,0 +1,90 @@
+//! 配置中的环境变量占位符：上游的 api_key、upstream_base 与全局 proxy_url 可写作 `${NAME}`
+//! （也可嵌在其他文本中，如 `https://${HOST}/v1`），代理启动与运行配置更新时替换为环境变量的值。
+//! 保存、导出的配置保留占位符，密钥可以只放在环境中，共享的配置文件不含明文。
+
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+use crate::ProxyConfig;
+
+fn pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex"))
+}
+
+pub fn has_placeholder(value: &str) -> bool {
+    pattern().is_match(value)
+}
+
+/// 用 lookup 替换占位符，同时返回找不到值的变量名；找不到的占位符保持原样
+pub fn substitute(value: &str, lookup: impl Fn(&str) -> Option<String>) -> (String, Vec<String>) {
+    let mut missing = Vec::new();
+    let expanded = pattern().replace_all(value, |caps: &Captures| {
+        let name = &caps[1];
+        lookup(name).unwrap_or_else(|| {
+            missing.push(name.to_string());
+            caps[0].to_string()
+        })
+    });
+    (expanded.into_owned(), missing)
+}
+
+fn lookup_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// 替换单个值中的占位符，引用的环境变量未设置时报错
+pub fn expand(value: &str) -> Result<String, String> {
+    let (expanded, missing) = substitute(value, lookup_env);
+    match missing.first() {
+        Some(name) => Err(format!("环境变量 {name} 未设置")),
+        None => Ok(expanded),
+    }
+}
+
+/// 配置中支持占位符的值
+fn slots(config: &mut ProxyConfig) -> Vec<&mut String> {
+    let mut slots: Vec<&mut String> = config.proxy_url.iter_mut().collect();
+    let services = config.services.iter_mut().chain(
+        config
+            .listeners
+            .iter_mut()
+            .flatten()
+            .flat_map(|l| l.services.iter_mut()),
+    );
+    for upstream in services.flat_map(|s| s.upstreams.iter_mut()) {
+        slots.push(&mut upstream.upstream_base);
+        slots.extend(upstream.api_key.as_mut());
+    }
+    slots
+}
+
+/// 配置引用了但当前环境中未设置的变量名，已去重
+pub fn unresolved(config: &ProxyConfig) -> Vec<String> {
+    let mut config = config.clone();
+    let mut names: Vec<String> = slots(&mut config)
+        .into_iter()
+        .flat_map(|value| substitute(value, lookup_env).1)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// 启动或更新代理前检查：引用的环境变量都已设置
+pub fn check(config: &ProxyConfig) -> Result<(), String> {
+    match unresolved(config).as_slice() {
+        [] => Ok(()),
+        names => Err(format!("配置引用的环境变量未设置: {}", names.join(", "))),
+    }
+}
+
+/// 把运行配置中的占位符替换为环境变量的值
+pub fn apply(config: &mut ProxyConfig) {
+    for value in slots(config) {
+        if has_placeholder(value) {
+            *value = substitute(value, lookup_env).0;
+        }
+    }
+}
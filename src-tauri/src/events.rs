@@ -0,0 +1,162 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use ts_rs::TS;
+
+use crate::balance::UpstreamBalance;
+use crate::budget::BudgetStatus;
+use crate::daily_summary::DailySummary;
+use crate::key_expiry::KeyExpiryStatus;
+use crate::provider_status::ProviderStatus;
+use crate::stats::StatsStore;
+use crate::ProxyLogEntry;
+
+pub const LOG_UPSERT_EVENT: &str = "log:upsert";
+pub const STATS_UPDATE_EVENT: &str = "stats:update";
+pub const PROXY_STATUS_EVENT: &str = "proxy:status";
+pub const BUDGET_ALERT_EVENT: &str = "budget:alert";
+pub const KEY_EXPIRY_EVENT: &str = "key:expiry";
+pub const BALANCE_ALERT_EVENT: &str = "balance:low";
+pub const DAILY_SUMMARY_EVENT: &str = "summary:daily";
+pub const PROVIDER_INCIDENT_EVENT: &str = "provider:incident";
+
+/// 流式请求期间日志会被频繁 upsert，按固定间隔合并后再推送给前端
+const COALESCE_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ProxyStatusEvent.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyStatusEvent {
+    pub running: bool,
+    pub listen_ports: Vec<u16>,
+}
+
+struct EventHub {
+    app: AppHandle,
+    stats: Arc<StatsStore>,
+    pending_logs: Mutex<Vec<ProxyLogEntry>>,
+    stats_dirty: AtomicBool,
+}
+
+static HUB: OnceLock<EventHub> = OnceLock::new();
+
+/// 在 setup 阶段调用一次，之后 upsert_log / StatsStore::record 的变更会被推送到前端
+pub fn init(app: AppHandle, stats: Arc<StatsStore>) {
+    let hub = EventHub {
+        app,
+        stats,
+        pending_logs: Mutex::new(Vec::new()),
+        stats_dirty: AtomicBool::new(false),
+    };
+    if HUB.set(hub).is_err() {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async {
+        let mut ticker = tokio::time::interval(COALESCE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            flush();
+        }
+    });
+}
+
+pub fn notify_log(entry: &ProxyLogEntry) {
+    if let Some(hub) = HUB.get() {
+        if let Ok(mut pending) = hub.pending_logs.lock() {
+            match pending.iter_mut().find(|e| e.id == entry.id) {
+                Some(existing) => *existing = entry.clone(),
+                None => pending.push(entry.clone()),
+            }
+        }
+    }
+}
+
+pub fn notify_stats() {
+    if let Some(hub) = HUB.get() {
+        hub.stats_dirty.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 代理启停属于低频事件，直接推送不做合并
+pub fn emit_proxy_status(listen_ports: Vec<u16>) {
+    if let Some(hub) = HUB.get() {
+        let payload = ProxyStatusEvent {
+            running: !listen_ports.is_empty(),
+            listen_ports,
+        };
+        if let Err(err) = hub.app.emit(PROXY_STATUS_EVENT, payload) {
+            eprintln!("推送代理状态失败: {err}");
+        }
+    }
+}
+
+/// 预算超出提醒，每条规则每个周期只推送一次
+pub fn emit_budget_alert(status: BudgetStatus) {
+    if let Some(hub) = HUB.get() {
+        if let Err(err) = hub.app.emit(BUDGET_ALERT_EVENT, status) {
+            eprintln!("推送预算提醒失败: {err}");
+        }
+    }
+}
+
+/// key 即将到期或已过期的提醒，每个 key 每种状态只推送一次
+pub fn emit_key_expiry_alert(statuses: Vec<KeyExpiryStatus>) {
+    if let Some(hub) = HUB.get() {
+        if let Err(err) = hub.app.emit(KEY_EXPIRY_EVENT, statuses) {
+            eprintln!("推送 key 到期提醒失败: {err}");
+        }
+    }
+}
+
+/// 上游余额低于提醒阈值，恢复前只推送一次
+pub fn emit_balance_alert(balance: UpstreamBalance) {
+    if let Some(hub) = HUB.get() {
+        if let Err(err) = hub.app.emit(BALANCE_ALERT_EVENT, balance) {
+            eprintln!("推送余额提醒失败: {err}");
+        }
+    }
+}
+
+/// 服务商状态页出现新的进行中故障，同一故障只推送一次
+pub fn emit_provider_incident(status: ProviderStatus) {
+    if let Some(hub) = HUB.get() {
+        if let Err(err) = hub.app.emit(PROVIDER_INCIDENT_EVENT, status) {
+            eprintln!("推送服务商故障提醒失败: {err}");
+        }
+    }
+}
+
+/// 每日用量概览，定时推送或由托盘菜单触发
+pub fn emit_daily_summary(summary: DailySummary) {
+    if let Some(hub) = HUB.get() {
+        if let Err(err) = hub.app.emit(DAILY_SUMMARY_EVENT, summary) {
+            eprintln!("推送每日用量概览失败: {err}");
+        }
+    }
+}
+
+fn flush() {
+    let Some(hub) = HUB.get() else {
+        return;
+    };
+
+    let batch: Vec<ProxyLogEntry> = match hub.pending_logs.lock() {
+        Ok(mut pending) => std::mem::take(&mut *pending),
+        Err(_) => Vec::new(),
+    };
+    if !batch.is_empty() {
+        if let Err(err) = hub.app.emit(LOG_UPSERT_EVENT, batch) {
+            eprintln!("推送日志更新失败: {err}");
+        }
+    }
+
+    if hub.stats_dirty.swap(false, Ordering::Relaxed) {
+        if let Err(err) = hub.app.emit(STATS_UPDATE_EVENT, hub.stats.snapshot()) {
+            eprintln!("推送统计更新失败: {err}");
+        }
+    }
+}
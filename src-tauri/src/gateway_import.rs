@@ -0,0 +1,323 @@
+//! 从其他网关迁移：读取 LiteLLM 的 `config.yaml` 或 one-api / new-api 导出的渠道 JSON，
+//! 按服务商分组转换为服务与上游，模型别名转换为上游的模型映射。
+//! 只生成配置，不直接保存，由界面确认后合并到现有配置。
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::scheduler::RateLimitConfig;
+use crate::split::RoutingMode;
+use crate::{timestamp, ServiceConfig, UpstreamEntry};
+
+const ENV_PREFIX: &str = "os.environ/";
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/GatewayImport.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayImport {
+    pub services: Vec<ServiceConfig>,
+    /// 无法自动转换、需要导入后手动处理的条目
+    pub warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LiteLlmConfig {
+    #[serde(default)]
+    model_list: Vec<LiteLlmModel>,
+}
+
+#[derive(Deserialize)]
+struct LiteLlmModel {
+    model_name: String,
+    litellm_params: LiteLlmParams,
+}
+
+#[derive(Deserialize)]
+struct LiteLlmParams {
+    model: String,
+    api_base: Option<String>,
+    api_key: Option<String>,
+    rpm: Option<u32>,
+    tpm: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneApiExport {
+    Wrapped { data: Vec<OneApiChannel> },
+    List(Vec<OneApiChannel>),
+}
+
+#[derive(Deserialize)]
+struct OneApiChannel {
+    #[serde(rename = "type", default)]
+    kind: i64,
+    name: Option<String>,
+    #[serde(default)]
+    key: String,
+    base_url: Option<String>,
+    /// JSON 字符串，如 `{"gpt-4":"gpt-4-0613"}`
+    model_mapping: Option<String>,
+    priority: Option<i64>,
+    weight: Option<u32>,
+    /// 1 为启用
+    status: Option<i64>,
+}
+
+/// 常见服务商的默认上游地址
+fn provider_defaults(provider: &str) -> Option<&'static str> {
+    Some(match provider {
+        "openai" => "https://api.openai.com",
+        "anthropic" => "https://api.anthropic.com",
+        "gemini" => "https://generativelanguage.googleapis.com",
+        "deepseek" => "https://api.deepseek.com",
+        "mistral" => "https://api.mistral.ai",
+        "groq" => "https://api.groq.com/openai",
+        "openrouter" => "https://openrouter.ai/api",
+        _ => return None,
+    })
+}
+
+/// one-api 的渠道类型
+fn one_api_provider(kind: i64) -> String {
+    match kind {
+        1 => "openai".into(),
+        3 => "azure".into(),
+        14 => "anthropic".into(),
+        24 => "gemini".into(),
+        other => format!("channel-{other}"),
+    }
+}
+
+/// 客户端请求的路径已带 `/v1`，上游地址末尾的 `/v1` 去掉以免重复
+fn normalize_base(base: &str) -> String {
+    let base = base.trim().trim_end_matches('/');
+    base.strip_suffix("/v1").unwrap_or(base).to_string()
+}
+
+/// 自动识别格式：JSON 按 one-api / new-api 渠道导出解析，否则按 LiteLLM 的 YAML 解析
+pub fn import(text: &str) -> Result<GatewayImport, String> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        if let Ok(export) = serde_json::from_str::<OneApiExport>(text) {
+            return Ok(from_one_api(export));
+        }
+    }
+    let config: LiteLlmConfig =
+        serde_yaml::from_str(text).map_err(|e| format!("无法识别的配置格式: {e}"))?;
+    if config.model_list.is_empty() {
+        return Err("配置中没有 model_list，无法导入".into());
+    }
+    Ok(from_litellm(config))
+}
+
+#[derive(Default)]
+struct Builder {
+    groups: BTreeMap<String, Vec<UpstreamEntry>>,
+    warnings: Vec<String>,
+}
+
+impl Builder {
+    fn push(&mut self, provider: &str, upstream: UpstreamEntry) {
+        self.groups
+            .entry(provider.to_string())
+            .or_default()
+            .push(upstream);
+    }
+
+    /// 加权分流只用于同一模型别名的多个部署；分组内有多个别名时改为按优先级，
+    /// 否则请求会被分到映射里没有该别名的上游
+    fn finish(self, routing: RoutingMode) -> GatewayImport {
+        let Builder {
+            groups,
+            mut warnings,
+        } = self;
+        let services = groups
+            .into_iter()
+            .map(|(provider, upstreams)| {
+                let aliases: BTreeSet<&str> =
+                    upstreams.iter().filter_map(|u| u.label.as_deref()).collect();
+                let routing = match routing {
+                    RoutingMode::Weighted if aliases.len() > 1 => {
+                        let aliases: Vec<&str> = aliases.into_iter().collect();
+                        warnings.push(format!(
+                            "{provider}: 包含多个模型别名（{}），已按优先级路由，需导入后手动按模型配置路由",
+                            aliases.join("、")
+                        ));
+                        None
+                    }
+                    RoutingMode::Priority => None,
+                    _ => (upstreams.len() > 1).then_some(routing),
+                };
+                ServiceConfig {
+                    id: Uuid::new_v4().to_string(),
+                    name: provider.clone(),
+                    base_path: format!("/{provider}"),
+                    enabled: true,
+                    routing,
+                    upstreams,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        GatewayImport { services, warnings }
+    }
+}
+
+fn from_litellm(config: LiteLlmConfig) -> GatewayImport {
+    let mut builder = Builder::default();
+    let created_at = timestamp::now();
+    for entry in config.model_list {
+        let params = entry.litellm_params;
+        let (provider, model) = params
+            .model
+            .split_once('/')
+            .unwrap_or(("openai", params.model.as_str()));
+        let Some(upstream_base) = params
+            .api_base
+            .as_deref()
+            .map(normalize_base)
+            .or_else(|| provider_defaults(provider).map(str::to_string))
+        else {
+            builder.warnings.push(format!(
+                "{}: 未识别的服务商 {provider} 且未填写 api_base，已跳过",
+                entry.model_name
+            ));
+            continue;
+        };
+        if provider == "azure" {
+            builder.warnings.push(format!(
+                "{}: Azure 部署的路径与 api-version 需要在导入后手动配置",
+                entry.model_name
+            ));
+        }
+        let api_key = match params.api_key {
+            Some(key) => match key.strip_prefix(ENV_PREFIX) {
+                Some(var) => {
+                    let value = std::env::var(var).ok().filter(|v| !v.is_empty());
+                    if value.is_none() {
+                        builder.warnings.push(format!(
+                            "{}: key 引用的环境变量 {var} 未设置，请导入后补填",
+                            entry.model_name
+                        ));
+                    }
+                    value
+                }
+                None => Some(key),
+            },
+            None => None,
+        };
+        let model_map = (entry.model_name != model)
+            .then(|| HashMap::from([(entry.model_name.clone(), model.to_string())]));
+        let rate_limit =
+            (params.rpm.is_some() || params.tpm.is_some()).then_some(RateLimitConfig {
+                requests_per_minute: params.rpm,
+                tokens_per_minute: params.tpm,
+                max_delay_ms: None,
+                max_concurrency: None,
+            });
+        builder.push(
+            provider,
+            UpstreamEntry {
+                id: Uuid::new_v4().to_string(),
+                label: Some(entry.model_name.clone()),
+                upstream_base,
+                api_key,
+                priority: 1,
+                enabled: true,
+                rate_limit,
+                model_map,
+                created_at: Some(created_at.clone()),
+                ..Default::default()
+            },
+        );
+    }
+    // LiteLLM 默认在同名部署间随机分流
+    builder.finish(RoutingMode::Weighted)
+}
+
+fn from_one_api(export: OneApiExport) -> GatewayImport {
+    let mut channels = match export {
+        OneApiExport::Wrapped { data } => data,
+        OneApiExport::List(list) => list,
+    };
+    // one-api 中优先级数值越大越先使用，ApiFlow 相反
+    channels.sort_by_key(|c| std::cmp::Reverse(c.priority.unwrap_or(0)));
+    let mut ranks: Vec<i64> = channels.iter().map(|c| c.priority.unwrap_or(0)).collect();
+    ranks.dedup();
+
+    let mut builder = Builder::default();
+    let created_at = timestamp::now();
+    for channel in channels {
+        let provider = one_api_provider(channel.kind);
+        let name = channel.name.clone().unwrap_or_else(|| provider.clone());
+        let Some(upstream_base) = channel
+            .base_url
+            .as_deref()
+            .filter(|b| !b.trim().is_empty())
+            .map(normalize_base)
+            .or_else(|| provider_defaults(&provider).map(str::to_string))
+        else {
+            builder.warnings.push(format!(
+                "渠道 {name}: 类型 {} 未填写地址，已跳过",
+                channel.kind
+            ));
+            continue;
+        };
+        let model_map = match channel.model_mapping.as_deref().map(str::trim) {
+            Some(raw) if !raw.is_empty() => {
+                match serde_json::from_str::<HashMap<String, String>>(raw) {
+                    Ok(map) => Some(map).filter(|m| !m.is_empty()),
+                    Err(_) => {
+                        builder
+                            .warnings
+                            .push(format!("渠道 {name}: 模型映射不是有效的 JSON，已忽略"));
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let rank = channel.priority.unwrap_or(0);
+        let priority = ranks.iter().position(|r| *r == rank).unwrap_or(0) as u32 + 1;
+        // 一个渠道可填写多个 key（每行一个），每个 key 生成一个上游
+        let keys: Vec<&str> = channel
+            .key
+            .lines()
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .collect();
+        if keys.is_empty() {
+            builder
+                .warnings
+                .push(format!("渠道 {name}: 导出中没有 key，请导入后补填"));
+        }
+        let keys = if keys.is_empty() { vec![""] } else { keys };
+        let multiple = keys.len() > 1;
+        for (idx, key) in keys.into_iter().enumerate() {
+            builder.push(
+                &provider,
+                UpstreamEntry {
+                    id: Uuid::new_v4().to_string(),
+                    label: Some(if multiple {
+                        format!("{name} #{}", idx + 1)
+                    } else {
+                        name.clone()
+                    }),
+                    upstream_base: upstream_base.clone(),
+                    api_key: Some(key.to_string()).filter(|k| !k.is_empty()),
+                    priority,
+                    enabled: channel.status.unwrap_or(1) == 1,
+                    weight: channel.weight.filter(|w| *w > 0),
+                    model_map: model_map.clone(),
+                    created_at: Some(created_at.clone()),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+    builder.finish(RoutingMode::Priority)
+}
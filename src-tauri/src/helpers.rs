@@ -1,17 +1,17 @@
 use http::header;
 
 pub fn normalize_base_path(path: &str) -> String {
-    let mut p = path.trim().to_string();
-    if p.is_empty() {
+    let trimmed = path
+        .trim()
+        .trim_end_matches(|c: char| c == '/' || c.is_whitespace());
+    if trimmed.is_empty() {
         return "/".to_string();
     }
-    if !p.starts_with('/') {
-        p.insert(0, '/');
+    if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{trimmed}")
     }
-    while p.ends_with('/') && p.len() > 1 {
-        p.pop();
-    }
-    p
 }
 
 pub fn strip_base_path<'a>(path: &'a str, base: &str) -> &'a str {
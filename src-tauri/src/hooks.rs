@@ -0,0 +1,268 @@
+//! 生命周期钩子：代理启动、停止、配置变更以及上游健康状态变化时，
+//! 向配置的地址 POST 一段 JSON，或执行本地命令（事件内容通过环境变量传入），
+//! 便于接入家庭自动化或运维脚本，无需轮询管理接口。
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use ts_rs::TS;
+
+use crate::stats::StatsStore;
+use crate::{safe_mode, status, timestamp, ProxyConfig};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/HookEvent.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    ProxyStarted,
+    ProxyStopped,
+    ConfigChanged,
+    /// 上游最近 5 分钟的错误率达到 50%
+    UpstreamUnhealthy,
+    UpstreamRecovered,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/HookConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct HookConfig {
+    /// 订阅的事件，为空时订阅全部事件
+    #[serde(default)]
+    pub events: Vec<HookEvent>,
+    /// 以 POST 推送事件 JSON 的地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub url: Option<String>,
+    /// 执行的本地命令（经系统 shell），事件名与 JSON 分别在 `APIFLOW_EVENT`、`APIFLOW_PAYLOAD` 环境变量中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub command: Option<String>,
+}
+
+impl HookConfig {
+    fn wants(&self, event: HookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+pub fn validate_hooks(hooks: &[HookConfig]) -> Result<(), String> {
+    for hook in hooks {
+        let command = hook.command.as_deref().map(str::trim);
+        if hook.url.is_none() && command.is_none_or(str::is_empty) {
+            return Err("钩子需要填写推送地址或本地命令".into());
+        }
+        if let Some(url) = &hook.url {
+            let valid =
+                reqwest::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+            if !valid {
+                return Err(format!("钩子的推送地址无效: {url}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 推送给钩子的事件内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/HookPayload.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub timestamp: String,
+    /// 代理启停后正在运行的端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub listen_ports: Option<Vec<u16>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub service_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub upstream_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub upstream_label: Option<String>,
+    /// 最近 5 分钟的错误率（0 到 1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub error_rate: Option<f64>,
+}
+
+impl HookPayload {
+    pub fn new(event: HookEvent) -> Self {
+        Self {
+            event,
+            timestamp: timestamp::now(),
+            listen_ports: None,
+            service_name: None,
+            upstream_id: None,
+            upstream_label: None,
+            error_rate: None,
+        }
+    }
+
+    pub fn ports(event: HookEvent, listen_ports: Vec<u16>) -> Self {
+        Self {
+            listen_ports: Some(listen_ports),
+            ..Self::new(event)
+        }
+    }
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// 执行单个钩子，失败时返回原因
+pub async fn deliver(hook: &HookConfig, payload: &HookPayload) -> Result<(), String> {
+    let body = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+    if let Some(url) = &hook.url {
+        let resp = client()
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .map_err(|e| format!("推送钩子失败: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("推送钩子失败: {url} 返回 {}", resp.status()));
+        }
+    }
+    if let Some(command) = hook
+        .command
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+    {
+        let event = serde_json::to_value(payload.event)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let mut child = shell(command)
+            .env("APIFLOW_EVENT", event)
+            .env("APIFLOW_PAYLOAD", body)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("执行钩子命令失败: {e}"))?;
+        // 命令与推送地址共用超时，卡住的脚本不会阻塞同一事件的后续钩子
+        let status = match tokio::time::timeout(DELIVERY_TIMEOUT, child.wait()).await {
+            Ok(status) => status.map_err(|e| format!("执行钩子命令失败: {e}"))?,
+            Err(_) => {
+                let _ = child.kill().await;
+                return Err(format!(
+                    "钩子命令超过 {} 秒未结束，已终止",
+                    DELIVERY_TIMEOUT.as_secs()
+                ));
+            }
+        };
+        if !status.success() {
+            return Err(format!("钩子命令退出码异常: {status}"));
+        }
+    }
+    Ok(())
+}
+
+fn shell(command: &str) -> tokio::process::Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    cmd.stdin(std::process::Stdio::null());
+    cmd
+}
+
+/// 在后台执行订阅了该事件的钩子，不阻塞调用方；安全模式下不执行
+pub fn fire(hooks: Option<&[HookConfig]>, payload: HookPayload) {
+    if safe_mode::enabled() {
+        return;
+    }
+    let hooks: Vec<HookConfig> = hooks
+        .unwrap_or_default()
+        .iter()
+        .filter(|h| h.wants(payload.event))
+        .cloned()
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        for hook in &hooks {
+            if let Err(err) = deliver(hook, &payload).await {
+                eprintln!("{err}");
+            }
+        }
+    });
+}
+
+/// 记录每个上游上一次的健康状态，只在状态变化时产生事件
+#[derive(Default)]
+pub struct HealthTracker {
+    healthy: HashMap<String, bool>,
+}
+
+impl HealthTracker {
+    /// 首次出现的上游视为健康，因此启动后就不健康的上游也会产生一次事件
+    pub fn observe(&mut self, status: &status::ProxyStatus) -> Vec<HookPayload> {
+        let mut changes = Vec::new();
+        for service in &status.services {
+            for upstream in &service.upstreams {
+                let previous = self.healthy.insert(upstream.id.clone(), upstream.healthy);
+                if previous.unwrap_or(true) == upstream.healthy {
+                    continue;
+                }
+                let event = if upstream.healthy {
+                    HookEvent::UpstreamRecovered
+                } else {
+                    HookEvent::UpstreamUnhealthy
+                };
+                changes.push(HookPayload {
+                    service_name: Some(service.name.clone()),
+                    upstream_id: Some(upstream.id.clone()),
+                    upstream_label: upstream.label.clone(),
+                    error_rate: upstream.recent_error_rate,
+                    ..HookPayload::new(event)
+                });
+            }
+        }
+        changes
+    }
+}
+
+/// 定期按统计窗口检查上游健康状态，状态变化时触发钩子
+pub fn spawn_health_task(config: Arc<RwLock<Option<ProxyConfig>>>, stats: Arc<StatsStore>) {
+    tauri::async_runtime::spawn(async move {
+        let mut tracker = HealthTracker::default();
+        let mut ticker = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let Some(config) = config.read().await.clone() else {
+                continue;
+            };
+            let snapshot = stats.snapshot();
+            for listener in config.per_listener() {
+                let status =
+                    status::build_status(&listener, Duration::ZERO, &Default::default(), &snapshot);
+                for payload in tracker.observe(&status) {
+                    fire(config.hooks.as_deref(), payload);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,73 @@
+//! 上游的 Host 覆盖：请求地址中的主机名换成配置的域名（Host 请求头与 TLS SNI 随之改变），
+//! 连接时仍解析到上游地址中原来的 IP / 主机，用于直连 IP 或绕过 CDN 的上游。
+//! 通过代理转发时由代理解析地址，覆盖只影响 Host 与 SNI。
+
+use std::net::SocketAddr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// 发送时使用的域名与实际连接的主机
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostOverride {
+    pub host: String,
+    pub connect_host: String,
+}
+
+pub fn validate(host: &str) -> Result<(), String> {
+    let valid = reqwest::Url::parse(&format!("http://{host}/")).is_ok_and(|url| {
+        url.host_str() == Some(host.to_ascii_lowercase().as_str())
+            && url.port().is_none()
+            && url.path() == "/"
+    });
+    if !valid {
+        return Err(format!(
+            "Host 覆盖只能填写域名，不含协议、端口和路径: {host}"
+        ));
+    }
+    Ok(())
+}
+
+/// 把地址中的主机名换成覆盖的域名；未配置或地址无法解析时原样返回
+pub fn target(url: &str, host: Option<&str>) -> (String, Option<HostOverride>) {
+    let Some(host) = host else {
+        return (url.to_string(), None);
+    };
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return (url.to_string(), None);
+    };
+    let Some(connect_host) = parsed
+        .host_str()
+        .map(|h| h.trim_matches(['[', ']']).to_string())
+    else {
+        return (url.to_string(), None);
+    };
+    if parsed.set_host(Some(host)).is_err() {
+        return (url.to_string(), None);
+    }
+    (
+        parsed.to_string(),
+        Some(HostOverride {
+            host: host.to_ascii_lowercase(),
+            connect_host,
+        }),
+    )
+}
+
+/// 覆盖的域名解析到原来的主机，其他域名正常解析
+pub struct OverrideResolver(pub HostOverride);
+
+impl Resolve for OverrideResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let lookup = if name.as_str().eq_ignore_ascii_case(&self.0.host) {
+            self.0.connect_host.clone()
+        } else {
+            name.as_str().to_string()
+        };
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup.as_str(), 0))
+                .await?
+                .collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,146 @@
+//! 请求中的图片输入：统计 OpenAI / Anthropic / Gemini 格式的内联（base64）与 URL 图片，
+//! 记录日志时用图片摘要代替 base64 内容，并按服务配置限制单张内联图片的大小。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ImageSummary.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSummary {
+    /// 内联（base64）图片数
+    pub inline_count: u32,
+    /// 以 URL 引用的图片数
+    pub url_count: u32,
+    /// 内联图片解码后的总字节数
+    #[ts(type = "number")]
+    pub inline_bytes: u64,
+    /// 最大一张内联图片解码后的字节数
+    #[ts(type = "number")]
+    pub largest_bytes: u64,
+}
+
+/// 扫描结果：图片摘要与去掉 base64 内容后的请求体
+pub struct ImageScan {
+    pub summary: ImageSummary,
+    pub redacted: Vec<u8>,
+}
+
+/// 扫描 JSON 请求体中的图片，没有图片时返回 None
+pub fn scan(body: &[u8]) -> Option<ImageScan> {
+    let mut value: Value = serde_json::from_slice(body).ok()?;
+    let mut summary = ImageSummary::default();
+    visit(&mut value, &mut summary);
+    if summary.inline_count == 0 && summary.url_count == 0 {
+        return None;
+    }
+    Some(ImageScan {
+        summary,
+        redacted: serde_json::to_vec(&value).ok()?,
+    })
+}
+
+impl ImageSummary {
+    /// 超出上限时返回错误信息
+    pub fn check_limit(&self, max_bytes: u64) -> Result<(), String> {
+        if self.largest_bytes > max_bytes {
+            return Err(format!(
+                "图片过大：{}，单张上限为 {}",
+                format_size(self.largest_bytes),
+                format_size(max_bytes)
+            ));
+        }
+        Ok(())
+    }
+
+    fn record_inline(&mut self, bytes: u64) {
+        self.inline_count += 1;
+        self.inline_bytes += bytes;
+        self.largest_bytes = self.largest_bytes.max(bytes);
+    }
+}
+
+fn visit(value: &mut Value, summary: &mut ImageSummary) {
+    match value {
+        Value::String(text) => {
+            // OpenAI `image_url.url` / Responses `image_url` 的 data URL
+            if let Some((mime, data)) = parse_data_url(text) {
+                let bytes = decoded_len(data);
+                summary.record_inline(bytes);
+                *text = placeholder(mime, bytes);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| visit(item, summary)),
+        Value::Object(object) => {
+            // Anthropic `source: {type: base64, media_type, data}`、Gemini `inlineData: {mimeType, data}`
+            let mime = ["media_type", "mimeType", "mime_type"]
+                .iter()
+                .find_map(|key| object.get(*key).and_then(Value::as_str))
+                .filter(|mime| mime.starts_with("image/"))
+                .map(str::to_string);
+            if let (Some(mime), Some(Value::String(data))) = (&mime, object.get_mut("data")) {
+                let bytes = decoded_len(data);
+                summary.record_inline(bytes);
+                *data = placeholder(mime, bytes);
+            }
+            if is_url_image(object) {
+                summary.url_count += 1;
+            }
+            object.values_mut().for_each(|item| visit(item, summary));
+        }
+        _ => {}
+    }
+}
+
+fn is_url_image(object: &serde_json::Map<String, Value>) -> bool {
+    let is_http = |v: &Value| {
+        v.as_str()
+            .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+    };
+    let openai = object
+        .get("image_url")
+        .is_some_and(|v| is_http(v) || v.get("url").is_some_and(is_http));
+    let anthropic = object.get("type").and_then(Value::as_str) == Some("url")
+        && object.get("url").is_some_and(is_http);
+    let gemini = ["fileData", "file_data"].iter().any(|key| {
+        object.get(*key).is_some_and(|data| {
+            ["mimeType", "mime_type"].iter().any(|m| {
+                data.get(*m)
+                    .and_then(Value::as_str)
+                    .is_some_and(|s| s.starts_with("image/"))
+            })
+        })
+    });
+    openai || anthropic || gemini
+}
+
+/// 解析 `data:image/png;base64,...`，返回 MIME 类型与 base64 内容
+fn parse_data_url(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let mime = meta.strip_suffix(";base64")?;
+    mime.starts_with("image/").then_some((mime, data))
+}
+
+/// base64 内容解码后的字节数
+fn decoded_len(data: &str) -> u64 {
+    let len = data
+        .trim_end_matches('=')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count() as u64;
+    len * 3 / 4
+}
+
+fn placeholder(mime: &str, bytes: u64) -> String {
+    format!("[图片 {mime} {}]", format_size(bytes))
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
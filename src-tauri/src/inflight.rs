@@ -0,0 +1,73 @@
+//! 合并相同的并发请求：同一服务下方法、路径与请求体都相同的非流式请求同时到达时，
+//! 只有第一个发往上游，其余请求等待并共享它的响应。首个请求未拿到响应就失败时，
+//! 等待者各自照常转发。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use tokio::sync::oneshot;
+
+use crate::response_cache::CachedResponse;
+
+type Waiters = Vec<oneshot::Sender<CachedResponse>>;
+
+fn inflight() -> &'static Mutex<HashMap<String, Waiters>> {
+    static INFLIGHT: OnceLock<Mutex<HashMap<String, Waiters>>> = OnceLock::new();
+    INFLIGHT.get_or_init(Default::default)
+}
+
+pub enum Joined {
+    /// 没有相同的请求在进行，由当前请求转发并在完成后分发响应
+    Leader(LeaderGuard),
+    /// 已有相同的请求在进行，等待其响应
+    Follower(oneshot::Receiver<CachedResponse>),
+}
+
+pub fn join(key: String) -> Joined {
+    let mut guard = inflight().lock().unwrap_or_else(|e| e.into_inner());
+    match guard.get_mut(&key) {
+        Some(waiters) => {
+            let (tx, rx) = oneshot::channel();
+            waiters.push(tx);
+            Joined::Follower(rx)
+        }
+        None => {
+            guard.insert(key.clone(), Vec::new());
+            Joined::Leader(LeaderGuard { key: Some(key) })
+        }
+    }
+}
+
+/// 首个请求持有；未调用 complete 就被丢弃时移除登记，等待者收到错误后自行转发
+pub struct LeaderGuard {
+    key: Option<String>,
+}
+
+impl LeaderGuard {
+    fn take_waiters(&mut self) -> Waiters {
+        let Some(key) = self.key.take() else {
+            return Vec::new();
+        };
+        inflight()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key)
+            .unwrap_or_default()
+    }
+
+    /// 把响应分发给所有等待者，返回等待者数量
+    pub fn complete(mut self, response: CachedResponse) -> usize {
+        let waiters = self.take_waiters();
+        let count = waiters.len();
+        for waiter in waiters {
+            let _ = waiter.send(response.clone());
+        }
+        count
+    }
+}
+
+impl Drop for LeaderGuard {
+    fn drop(&mut self) {
+        self.take_waiters();
+    }
+}
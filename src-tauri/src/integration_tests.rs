@@ -0,0 +1,2447 @@
+//! 端到端测试：在临时端口上启动 axum 代理，并用 wiremock 模拟上游
+//! （延迟、失败、SSE），覆盖重试、切换上游、流式透传与鉴权。
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use tokio::sync::{oneshot, Mutex};
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::stats::StatsStore;
+use crate::{build_router, ProxyConfig, ProxyLogEntry, ServiceConfig, SharedState, UpstreamEntry};
+
+struct TestProxy {
+    addr: SocketAddr,
+    logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
+    stats: Arc<StatsStore>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl TestProxy {
+    fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    async fn logs(&self) -> Vec<ProxyLogEntry> {
+        self.logs.lock().await.iter().cloned().collect()
+    }
+
+    /// 流式响应的日志在后台任务中收尾，轮询等待目标日志完成
+    async fn wait_for_log<F>(&self, predicate: F) -> ProxyLogEntry
+    where
+        F: Fn(&ProxyLogEntry) -> bool,
+    {
+        for _ in 0..50 {
+            if let Some(entry) = self.logs().await.into_iter().find(|e| predicate(e)) {
+                return entry;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("log entry not found");
+    }
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder().no_proxy().build().expect("client")
+}
+
+async fn spawn_proxy(config: ProxyConfig) -> TestProxy {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind proxy");
+    let addr = listener.local_addr().expect("addr");
+
+    let logs = Arc::new(Mutex::new(VecDeque::new()));
+    let stats = Arc::new(StatsStore::default());
+    let shared = SharedState {
+        config: Arc::new(ArcSwap::from_pointee(ProxyConfig {
+            listen_port: addr.port(),
+            ..config
+        })),
+        client: Arc::new(ArcSwap::from_pointee(http_client())),
+        logs: logs.clone(),
+        stats: stats.clone(),
+        started_at: Instant::now(),
+    };
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let router = build_router(shared);
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            let _ = shutdown_rx.await;
+        })
+        .await
+        .expect("serve");
+    });
+
+    TestProxy {
+        addr,
+        logs,
+        stats,
+        _shutdown: shutdown_tx,
+    }
+}
+
+fn upstream(id: &str, base: &str, priority: u32) -> UpstreamEntry {
+    UpstreamEntry {
+        id: id.into(),
+        label: Some(id.into()),
+        upstream_base: base.into(),
+        api_key: Some(format!("key-{id}")),
+        priority,
+        enabled: true,
+        ..Default::default()
+    }
+}
+
+fn config_with(upstreams: Vec<UpstreamEntry>, fallback_retries: u32) -> ProxyConfig {
+    ProxyConfig {
+        listen_port: 0,
+        global_key: None,
+        proxy_url: None,
+        fallback_retries,
+        services: vec![ServiceConfig {
+            id: "svc".into(),
+            name: "svc".into(),
+            base_path: "/".into(),
+            enabled: true,
+            upstreams,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// 返回一个当前没有进程监听的地址，用于模拟连接失败的上游
+async fn dead_upstream() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    drop(listener);
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn forwards_request_with_configured_key() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(wiremock::matchers::header("authorization", "Bearer key-a"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"ok": true})))
+        .expect(1)
+        .mount(&mock)
+        .await;
+
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &mock.uri(), 1)], 0)).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("authorization", "Bearer client-key")
+        .json(&serde_json::json!({"model": "gpt-4o"}))
+        .send()
+        .await
+        .expect("send");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap()["ok"], true);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(entry.upstream_id.as_deref(), Some("a"));
+    assert!(entry.request_body.unwrap().contains("gpt-4o"));
+}
+
+#[tokio::test]
+async fn retries_same_upstream_on_server_error() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("recovered"))
+        .mount(&mock)
+        .await;
+
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &mock.uri(), 1)], 2)).await;
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "recovered");
+
+    let logs = proxy.logs().await;
+    assert!(logs
+        .iter()
+        .any(|e| e.status == Some(503) && e.retry_action.as_deref() == Some("retry")));
+    let stats = proxy.stats.snapshot();
+    assert_eq!(stats[0].total_requests, 2);
+    assert_eq!(stats[0].error_count, 1);
+}
+
+#[tokio::test]
+async fn falls_back_to_next_upstream_on_connect_error() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("from backup")
+                .set_delay(Duration::from_millis(50)),
+        )
+        .mount(&mock)
+        .await;
+
+    let dead = dead_upstream().await;
+    let proxy = spawn_proxy(config_with(
+        vec![upstream("primary", &dead, 1), upstream("backup", &mock.uri(), 2)],
+        1,
+    ))
+    .await;
+
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "from backup");
+
+    let final_entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(final_entry.retry_action.as_deref(), Some("fallback"));
+    assert_eq!(final_entry.upstream_id.as_deref(), Some("backup"));
+
+    use crate::timeline::TimelineEventKind::*;
+    let kinds: Vec<_> = final_entry.timeline.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        [Received, Routed, AttemptSent, Fallback, AttemptSent, FirstByte, Completed]
+    );
+    assert_eq!(final_entry.timeline[4].attempt, Some(2));
+    assert!(final_entry.timeline[6].offset_ms >= 50);
+}
+
+#[tokio::test]
+async fn falls_back_without_retrying_invalid_key() {
+    let bad = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+            "error": {"type": "invalid_request_error", "code": "invalid_api_key"}
+        })))
+        .expect(1)
+        .mount(&bad)
+        .await;
+    let good = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&good)
+        .await;
+
+    let proxy = spawn_proxy(config_with(
+        vec![upstream("bad", &bad.uri(), 1), upstream("good", &good.uri(), 2)],
+        3,
+    ))
+    .await;
+
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let failed = proxy.wait_for_log(|e| e.status == Some(401)).await;
+    assert_eq!(failed.error_kind, Some(crate::provider_error::ErrorKind::InvalidApiKey));
+    assert_eq!(failed.retry_action.as_deref(), Some("fallback"));
+}
+
+#[tokio::test]
+async fn returns_content_filter_errors_without_fallback() {
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+            "error": {"type": "invalid_request_error", "code": "content_policy_violation"}
+        })))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let backup = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&backup)
+        .await;
+
+    let proxy = spawn_proxy(config_with(
+        vec![upstream("primary", &primary.uri(), 1), upstream("backup", &backup.uri(), 2)],
+        3,
+    ))
+    .await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 400);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(400)).await;
+    assert_eq!(entry.error_kind, Some(crate::provider_error::ErrorKind::ContentFilter));
+    assert_eq!(entry.retry_action, None);
+}
+
+#[tokio::test]
+async fn returns_bad_gateway_when_all_upstreams_fail() {
+    let dead = dead_upstream().await;
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &dead, 1)], 0)).await;
+
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 502);
+
+    let entry = proxy.wait_for_log(|e| e.error.is_some() && e.status == Some(502)).await;
+    assert!(entry.error.unwrap().contains("上游请求失败"));
+}
+
+#[tokio::test]
+async fn streams_sse_responses_through() {
+    let mock = MockServer::start().await;
+    let sse = "data: {\"delta\":\"Hel\"}\n\ndata: {\"delta\":\"lo\"}\n\ndata: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(sse, "text/event-stream"),
+        )
+        .mount(&mock)
+        .await;
+
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &mock.uri(), 1)], 0)).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), sse);
+
+    let entry = proxy
+        .wait_for_log(|e| e.is_streaming && e.response_body.as_deref() == Some(sse))
+        .await;
+    assert_eq!(entry.status, Some(200));
+}
+
+#[tokio::test]
+async fn streams_sse_per_event_through_bounded_bridge() {
+    let mock = MockServer::start().await;
+    let sse = "data: {\"delta\":\"Hel\"}\r\n\r\ndata: {\"delta\":\"lo\"}\n\ndata: [DONE]";
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.stream_bridge = Some(crate::stream_bridge::StreamBridgeConfig {
+        buffer_chunks: Some(1),
+        flush_per_event: Some(true),
+    });
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+
+    // 按事件切分后内容不变，末尾不完整的事件也会在结束时发出
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), sse);
+    proxy.wait_for_log(|e| e.is_streaming && e.status == Some(200)).await;
+}
+
+#[tokio::test]
+async fn tees_sse_chunks_to_file_sink() {
+    let mock = MockServer::start().await;
+    let sse = "data: {\"delta\":\"Hi\"}\n\ndata: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .mount(&mock)
+        .await;
+
+    let path = std::env::temp_dir().join(format!("apiflow-tee-{}.jsonl", uuid::Uuid::new_v4()));
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.stream_tee = Some(crate::tee::TeeSink::File {
+        path: path.to_string_lossy().into_owned(),
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"gpt-4o","stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.text().await.unwrap(), sse);
+
+    let mut events = Vec::new();
+    for _ in 0..50 {
+        let content = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+        events = content
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .collect();
+        if events.last().is_some_and(|e| e["event"] == "end") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(events.first().unwrap()["event"], "start");
+    assert_eq!(events.first().unwrap()["model"], "gpt-4o");
+    let streamed: String = events
+        .iter()
+        .filter(|e| e["event"] == "chunk")
+        .map(|e| e["data"].as_str().unwrap())
+        .collect();
+    assert_eq!(streamed, sse);
+    assert_eq!(events.last().unwrap()["event"], "end");
+}
+
+#[tokio::test]
+async fn rejects_requests_without_global_key() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.global_key = Some("proxy-secret".into());
+    let proxy = spawn_proxy(config).await;
+
+    let denied = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(denied.status(), 401);
+
+    let allowed = http_client()
+        .get(proxy.url("/v1/models"))
+        .header("x-proxy-key", "proxy-secret")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(allowed.status(), 200);
+}
+
+#[tokio::test]
+async fn skips_upstream_whose_budget_is_exhausted() {
+    use crate::budget::{BudgetAction, BudgetPeriod, BudgetRule, BudgetScope};
+
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "choices": [],
+            "usage": {"prompt_tokens": 30, "completion_tokens": 12, "total_tokens": 42}
+        })))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let secondary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("secondary"))
+        .expect(1)
+        .mount(&secondary)
+        .await;
+
+    let budgets = vec![BudgetRule {
+        name: "budget-a-daily".into(),
+        scope: BudgetScope::Upstream { id: "budget-a".into() },
+        period: BudgetPeriod::Daily,
+        max_cost: None,
+        max_tokens: Some(10),
+        action: BudgetAction::Block,
+    }];
+    let proxy = spawn_proxy(ProxyConfig {
+        budgets: Some(budgets.clone()),
+        ..config_with(
+            vec![
+                upstream("budget-a", &primary.uri(), 1),
+                upstream("budget-b", &secondary.uri(), 2),
+            ],
+            1,
+        )
+    })
+    .await;
+
+    let send = |url: String| async move {
+        http_client()
+            .post(url)
+            .json(&serde_json::json!({"model": "gpt-4o"}))
+            .send()
+            .await
+            .expect("send")
+    };
+    assert_eq!(send(proxy.url("/v1/chat/completions")).await.status(), 200);
+    let resp = send(proxy.url("/v1/chat/completions")).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "secondary");
+
+    // 只剩被预算拦截的上游时直接拒绝
+    let only_primary = spawn_proxy(ProxyConfig {
+        budgets: Some(budgets),
+        ..config_with(vec![upstream("budget-a", &primary.uri(), 1)], 0)
+    })
+    .await;
+    let resp = send(only_primary.url("/v1/chat/completions")).await;
+    assert_eq!(resp.status(), 429);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("budget-a-daily"));
+}
+
+#[tokio::test]
+async fn serves_health_and_status_without_reaching_upstreams() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let proxy = spawn_proxy(ProxyConfig {
+        global_key: Some("proxy-secret".into()),
+        ..config_with(vec![upstream("primary", &upstream_server.uri(), 1)], 0)
+    })
+    .await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("x-proxy-key", "proxy-secret")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let health = http_client().get(proxy.url("/_apiflow/healthz")).send().await.unwrap();
+    assert_eq!(health.status(), 200);
+    assert_eq!(health.json::<serde_json::Value>().await.unwrap()["status"], "ok");
+
+    let unauthorized = http_client().get(proxy.url("/_apiflow/status")).send().await.unwrap();
+    assert_eq!(unauthorized.status(), 401);
+
+    let status: serde_json::Value = http_client()
+        .get(proxy.url("/_apiflow/status"))
+        .header("x-proxy-key", "proxy-secret")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["inFlight"], 0);
+    let upstream_status = &status["services"][0]["upstreams"][0];
+    assert_eq!(upstream_status["id"], "primary");
+    assert_eq!(upstream_status["healthy"], true);
+    assert_eq!(upstream_status["totalRequests"], 1);
+}
+
+#[tokio::test]
+async fn audit_mode_records_exact_outbound_request() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&mock)
+        .await;
+
+    let mut audited = upstream("audited", &mock.uri(), 1);
+    audited.api_key = Some("sk-upstream-secret-1234".into());
+    audited.audit_outbound = Some(true);
+    audited.user_agent = Some("partner/2.0".into());
+    audited.query_params = Some(vec![crate::query_params::QueryParamRule::Add {
+        name: "key".into(),
+        value: "query-secret-abcd".into(),
+    }]);
+    audited.headers = Some(std::collections::HashMap::from([("x-api-key".into(), "header-secret-wxyz".into())]));
+    // 不记录请求体时审计报文同样不包含请求体
+    let mut config = config_with(vec![audited], 0);
+    config.services[0].capture_bodies = Some(false);
+    let proxy = spawn_proxy(config).await;
+
+    let body = r#"{"model":"gpt-4o","messages":[]}"#;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions?beta=1"))
+        .header("content-type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    resp.text().await.unwrap();
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    let outbound = entry.outbound_request.expect("outbound request");
+    assert!(outbound.starts_with("POST /v1/chat/completions?beta=1&key=quer****abcd HTTP/1.1\r\n"));
+    assert!(outbound.contains("authorization: Bearer sk-u****1234\r\n"));
+    assert!(outbound.contains("x-api-key: head****wxyz\r\n"));
+    assert!(outbound.contains("user-agent: partner/2.0\r\n"));
+    assert!(!outbound.contains("upstream-secret"));
+    assert!(!outbound.contains("query-secret") && !outbound.contains("header-secret"));
+    assert!(outbound.ends_with(&format!("content-length: {}\r\n\r\n", body.len())));
+    assert!(entry.request_body.is_none());
+}
+
+#[tokio::test]
+async fn checksum_verification_flags_tampered_response() {
+    use crate::checksum::{digest_header, sha256_hex};
+
+    let body = r#"{"model":"gpt-4o"}"#;
+    let request_digest = digest_header(&sha256_hex(body.as_bytes())).unwrap();
+    // 上游声明的摘要与实际响应体不符，模拟链路上被改写的响应
+    let declared = digest_header(&sha256_hex(b"original")).unwrap();
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(wiremock::matchers::header("content-digest", request_digest.to_str().unwrap()))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-digest", declared.to_str().unwrap())
+                .set_body_string("tampered"),
+        )
+        .expect(1)
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.verify_checksums = Some(true);
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("content-digest", request_digest.clone())
+        .body(body)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    resp.text().await.unwrap();
+
+    let entry = proxy
+        .wait_for_log(|e| e.checksum.as_ref().is_some_and(|c| c.response_sha256.is_some()))
+        .await;
+    let report = entry.checksum.expect("checksum");
+    assert_eq!(report.request_sha256, sha256_hex(body.as_bytes()));
+    assert_eq!(report.response_sha256, Some(sha256_hex(b"tampered")));
+    assert_eq!(report.mismatches.len(), 1);
+    assert!(entry.error.is_some_and(|e| e.contains("响应体与上游声明的摘要不一致")));
+}
+
+#[tokio::test]
+async fn trace_headers_identify_each_attempt() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 2);
+    config.trace_headers = Some(true);
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let received = mock.received_requests().await.unwrap();
+    let header = |i: usize, name: &str| {
+        received[i].headers.get(name).unwrap().to_str().unwrap().to_string()
+    };
+    let request_id = header(0, "x-apiflow-request-id");
+    assert_eq!(header(1, "x-apiflow-request-id"), request_id);
+    assert_eq!(header(0, "x-apiflow-attempt-id"), format!("{request_id}-1"));
+    assert_eq!(header(1, "x-apiflow-attempt-id"), format!("{request_id}-2"));
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(entry.id, request_id);
+    assert_eq!(entry.trace_id, Some(format!("{request_id}-2")));
+}
+
+#[tokio::test]
+async fn synthetic_endpoints_answer_without_upstream() {
+    use crate::synthetic::SyntheticEndpoint;
+
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.global_key = Some("proxy-key".into());
+    config.synthetic_endpoints = Some(vec![
+        SyntheticEndpoint {
+            path: "/v1/models".into(),
+            method: Some("GET".into()),
+            status: None,
+            body: r#"{"data":[{"id":"gpt-4o"}],"path":"{{path}}"}"#.into(),
+            content_type: None,
+            public: None,
+        },
+        SyntheticEndpoint {
+            path: "/healthz".into(),
+            method: None,
+            status: Some(204),
+            body: String::new(),
+            content_type: Some("text/plain".into()),
+            public: Some(true),
+        },
+    ]);
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 401);
+    let resp = http_client()
+        .get(proxy.url("/v1/models?limit=1"))
+        .bearer_auth("proxy-key")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"][0]["id"], "gpt-4o");
+    assert_eq!(body["path"], "/v1/models?limit=1");
+
+    let resp = http_client().head(proxy.url("/healthz")).send().await.expect("send");
+    assert_eq!(resp.status(), 204);
+}
+
+#[tokio::test]
+async fn service_timeout_override_fails_over_slow_upstream() {
+    use crate::timeouts::TimeoutConfig;
+
+    let slow = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(3)))
+        .mount(&slow)
+        .await;
+    let fast = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("fast"))
+        .mount(&fast)
+        .await;
+
+    let mut config = config_with(
+        vec![upstream("slow", &slow.uri(), 1), upstream("fast", &fast.uri(), 2)],
+        1,
+    );
+    config.services[0].timeouts = Some(TimeoutConfig {
+        total_secs: Some(1),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let started = Instant::now();
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "fast");
+    assert!(started.elapsed() < Duration::from_secs(3));
+
+    let logs = proxy.logs().await;
+    assert!(logs
+        .iter()
+        .any(|e| e.upstream_id.as_deref() == Some("slow") && e.error.is_some()));
+}
+
+#[tokio::test]
+async fn retry_waits_for_upstream_retry_after() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock)
+        .await;
+
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &mock.uri(), 1)], 2)).await;
+    let started = Instant::now();
+    let resp = http_client().get(proxy.url("/v1/models")).send().await.expect("send");
+
+    assert_eq!(resp.status(), 200);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(entry.retry_action.as_deref(), Some("retry"));
+}
+
+#[tokio::test]
+async fn answers_options_and_head_locally_when_enabled() {
+    let mock = MockServer::start().await;
+    Mock::given(method("OPTIONS"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.global_key = Some("proxy-key".into());
+    config.services[0].answer_locally = Some(true);
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .request(reqwest::Method::OPTIONS, proxy.url("/v1/chat/completions"))
+        .header("origin", "http://localhost:3000")
+        .header("access-control-request-headers", "authorization, content-type")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 204);
+    let headers = resp.headers();
+    assert_eq!(headers["access-control-allow-origin"], "http://localhost:3000");
+    assert_eq!(headers["access-control-allow-headers"], "authorization, content-type");
+
+    let resp = http_client().head(proxy.url("/v1/models")).send().await.expect("send");
+    assert_eq!(resp.status(), 401);
+    let resp = http_client()
+        .head(proxy.url("/v1/models"))
+        .bearer_auth("proxy-key")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+}
+
+/// 返回一个只发送 SSE 响应头、之后不再发送数据的上游
+/// 返回响应头（以及可选的首个数据块）后不再发送数据的 SSE 上游
+async fn stalled_sse_upstream(first_chunk: Option<&'static str>) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let head = concat!(
+                    "HTTP/1.1 200 OK\r\n",
+                    "content-type: text/event-stream\r\n",
+                    "transfer-encoding: chunked\r\n\r\n",
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                if let Some(chunk) = first_chunk {
+                    let framed = format!("{:x}\r\n{chunk}\r\n", chunk.len());
+                    let _ = socket.write_all(framed.as_bytes()).await;
+                }
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn stalled_stream_fails_over_before_first_byte() {
+    use crate::timeouts::TimeoutConfig;
+
+    let stalled = stalled_sse_upstream(None).await;
+    let backup = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("data: ok\n\n", "text/event-stream"))
+        .expect(1)
+        .mount(&backup)
+        .await;
+
+    let mut config = config_with(
+        vec![upstream("stalled", &stalled, 1), upstream("backup", &backup.uri(), 2)],
+        1,
+    );
+    config.services[0].timeouts = Some(TimeoutConfig {
+        first_byte_secs: Some(1),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "data: ok\n\n");
+
+    let failed = proxy
+        .wait_for_log(|e| e.upstream_id.as_deref() == Some("stalled") && e.status == Some(502))
+        .await;
+    assert_eq!(failed.retry_action.as_deref(), Some("fallback"));
+    assert!(failed.error.is_some_and(|e| e.contains("首个数据块超时")));
+}
+
+#[tokio::test]
+async fn idle_stream_is_aborted_after_gap() {
+    use crate::timeline::TimelineEventKind;
+    use crate::timeouts::TimeoutConfig;
+
+    let stalled = stalled_sse_upstream(Some("data: partial\n\n")).await;
+    let mut config = config_with(vec![upstream("stalled", &stalled, 1)], 1);
+    config.services[0].timeouts = Some(TimeoutConfig {
+        stream_idle_secs: Some(1),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    let started = Instant::now();
+    assert!(resp.text().await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let entry = proxy
+        .wait_for_log(|e| e.timeline.iter().any(|t| t.kind == TimelineEventKind::Failed))
+        .await;
+    let failed = entry.timeline.last().unwrap();
+    assert!(failed.detail.as_deref().is_some_and(|d| d.contains("未发送数据")));
+}
+
+#[tokio::test]
+async fn request_rate_limit_rejects_bursts_with_429() {
+    use crate::request_limit::RequestRateLimit;
+
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(2)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.request_rate_limit = Some(RequestRateLimit {
+        requests_per_second: 0.01,
+        burst: Some(2),
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let client = http_client();
+    for _ in 0..2 {
+        let resp = client
+            .post(proxy.url("/v1/chat/completions"))
+            .send()
+            .await
+            .expect("send");
+        assert_eq!(resp.status(), 200);
+    }
+    let limited = client
+        .post(proxy.url("/v1/chat/completions"))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(limited.status(), 429);
+    let retry_after: u64 = limited.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 1);
+}
+
+#[tokio::test]
+async fn retry_rules_fail_over_on_matching_success_body() {
+    let a = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"error": {"code": "insufficient_quota"}})),
+        )
+        .expect(1)
+        .mount(&a)
+        .await;
+    let b = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("from b"))
+        .expect(1)
+        .mount(&b)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &a.uri(), 1), upstream("b", &b.uri(), 2)], 1);
+    config.services[0].retry_rules = Some(
+        serde_json::from_value(serde_json::json!([{
+            "statuses": [200],
+            "jsonPath": "error.code",
+            "jsonEquals": "insufficient_quota",
+            "action": "fallback",
+        }]))
+        .unwrap(),
+    );
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "from b");
+    let entry = proxy.wait_for_log(|e| e.upstream_id.as_deref() == Some("b")).await;
+    assert_eq!(entry.retry_action.as_deref(), Some("fallback"));
+}
+
+#[tokio::test]
+async fn paused_service_returns_maintenance_response() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].paused = Some(true);
+    config.services[0].paused_response = Some(r#"{"error":"maintenance"}"#.into());
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 503);
+    assert_eq!(resp.headers()["retry-after"], "60");
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap()["error"], "maintenance");
+    let entry = proxy.wait_for_log(|e| e.status == Some(503)).await;
+    assert_eq!(entry.service_name.as_deref(), Some("svc"));
+}
+
+#[tokio::test]
+async fn admin_api_requires_token_with_matching_scope() {
+    use crate::admin_api::{configure, AdminApiConfig};
+    use crate::admin_auth::{AdminScope, AdminToken};
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .expect("free port")
+        .port();
+    let tokens = vec![
+        AdminToken {
+            name: "ci".into(),
+            token: "ci-read-only-token-0001".into(),
+            scopes: vec![AdminScope::ReadStats],
+        },
+        AdminToken {
+            name: "ops".into(),
+            token: "ops-control-only-token-01".into(),
+            scopes: vec![AdminScope::Control],
+        },
+        AdminToken {
+            name: "editor".into(),
+            token: "editor-write-config-token".into(),
+            scopes: vec![AdminScope::WriteConfig],
+        },
+    ];
+    configure(Some(&AdminApiConfig { port }), Some(&tokens)).expect("start admin api");
+
+    let url = |path: &str| format!("http://127.0.0.1:{port}{path}");
+    let missing = http_client().get(url("/api/stats")).send().await.unwrap();
+    assert_eq!(missing.status(), 401);
+
+    let forbidden = http_client()
+        .post(url("/api/proxy/stop"))
+        .bearer_auth("ci-read-only-token-0001")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forbidden.status(), 403);
+
+    // 单元测试中没有 Tauri 应用，通过鉴权后返回未就绪
+    let allowed = http_client()
+        .get(url("/api/stats"))
+        .bearer_auth("ci-read-only-token-0001")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(allowed.status(), 503);
+
+    // 携带配置启动会改写已保存的配置，仅有启停权限的令牌不能这样做
+    let config = config_with(vec![upstream("a", "http://127.0.0.1:1", 0)], 0);
+    let with_config = http_client()
+        .post(url("/api/proxy/start"))
+        .bearer_auth("ops-control-only-token-01")
+        .json(&config)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(with_config.status(), 403);
+    let saved = http_client()
+        .post(url("/api/proxy/start"))
+        .bearer_auth("ops-control-only-token-01")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(saved.status(), 503);
+
+    // 修改钩子相当于在本机执行命令，仅有修改配置权限的令牌不能改动钩子
+    let mut with_hook = config.clone();
+    with_hook.hooks = Some(vec![crate::hooks::HookConfig {
+        command: Some("touch /tmp/owned".into()),
+        ..Default::default()
+    }]);
+    let hook_change = http_client()
+        .put(url("/api/settings"))
+        .bearer_auth("editor-write-config-token")
+        .json(&with_hook)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(hook_change.status(), 403);
+    let plain_change = http_client()
+        .put(url("/api/settings"))
+        .bearer_auth("editor-write-config-token")
+        .json(&config)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(plain_change.status(), 503);
+
+    configure(None, None).expect("stop admin api");
+}
+
+#[tokio::test]
+async fn reload_moves_listener_to_new_port_without_dropping_requests() {
+    use crate::{switch_listeners, ProxyState};
+
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"ok": true}))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&mock)
+        .await;
+
+    let (old_port, new_port) = {
+        let a = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let b = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        (a.local_addr().unwrap().port(), b.local_addr().unwrap().port())
+    };
+    let config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    let state = ProxyState::new();
+    let mut running = state.inner.lock().await;
+    switch_listeners(&state, &mut running, &ProxyConfig { listen_port: old_port, ..config.clone() })
+        .await
+        .expect("start listener");
+    drop(running);
+
+    let in_flight = tokio::spawn(
+        http_client()
+            .post(format!("http://127.0.0.1:{old_port}/v1/chat/completions"))
+            .json(&serde_json::json!({"model": "gpt-4o"}))
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut running = state.inner.lock().await;
+    switch_listeners(&state, &mut running, &ProxyConfig { listen_port: new_port, ..config })
+        .await
+        .expect("switch port");
+    assert_eq!(running.keys().copied().collect::<Vec<_>>(), [new_port]);
+    drop(running);
+
+    let resp = http_client()
+        .post(format!("http://127.0.0.1:{new_port}/v1/chat/completions"))
+        .json(&serde_json::json!({"model": "gpt-4o"}))
+        .send()
+        .await
+        .expect("new port");
+    assert_eq!(resp.status(), 200);
+    // 切换前已发出的请求在旧端口上正常完成，之后旧端口不再接受连接
+    assert_eq!(in_flight.await.unwrap().expect("old port").status(), 200);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(http_client()
+        .post(format!("http://127.0.0.1:{old_port}/v1/chat/completions"))
+        .send()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn failed_listener_switch_keeps_existing_ports_on_old_config() {
+    use crate::{switch_listeners, ListenerConfig, ProxyState};
+
+    let occupied = std::net::TcpListener::bind("0.0.0.0:0").expect("bind");
+    let busy_port = occupied.local_addr().unwrap().port();
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .expect("free port")
+        .port();
+    let config = ProxyConfig { listen_port: port, ..config_with(vec![upstream("a", "http://127.0.0.1:1", 1)], 0) };
+    let state = ProxyState::new();
+    let mut running = state.inner.lock().await;
+    switch_listeners(&state, &mut running, &config).await.expect("start listener");
+
+    let changed = ProxyConfig {
+        fallback_retries: 2,
+        listeners: Some(vec![ListenerConfig { listen_port: busy_port, services: Vec::new() }]),
+        ..config
+    };
+    assert!(switch_listeners(&state, &mut running, &changed).await.is_err());
+    assert_eq!(running[&port].config.load().fallback_retries, 0);
+    assert_eq!(running.keys().copied().collect::<Vec<_>>(), [port]);
+}
+
+#[tokio::test]
+async fn balance_poller_reads_provider_balance_and_flags_low_credit() {
+    use crate::balance::{self, BalanceConfig, BalanceProvider};
+    use wiremock::matchers::header;
+
+    let provider = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/user/balance"))
+        .and(header("authorization", "Bearer sk-balance"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "is_available": true,
+            "balance_infos": [{"currency": "CNY", "total_balance": "3.50"}],
+        })))
+        .expect(1)
+        .mount(&provider)
+        .await;
+
+    let mut entry = upstream("balance-up", &provider.uri(), 1);
+    entry.api_key = Some("sk-balance".into());
+    entry.balance = Some(BalanceConfig {
+        provider: BalanceProvider::DeepSeek,
+        url: Some(format!("{}/user/balance", provider.uri())),
+        interval_secs: None,
+        low_threshold: Some(5.0),
+    });
+    let config = config_with(vec![entry], 0);
+
+    let client = reqwest::Client::new();
+    balance::poll_due(&client, &config, false).await;
+    // 未到查询间隔时不会再次请求
+    balance::poll_due(&client, &config, false).await;
+
+    let polled = balance::snapshot()
+        .into_iter()
+        .find(|b| b.upstream_id == "balance-up")
+        .expect("balance recorded");
+    assert_eq!(polled.balance, Some(3.5));
+    assert_eq!(polled.currency.as_deref(), Some("CNY"));
+    assert!(polled.low);
+    assert!(polled.error.is_none());
+}
+
+#[tokio::test]
+async fn provider_status_poller_reports_ongoing_incidents_per_upstream() {
+    use crate::provider_status::{self, ProviderStatusConfig};
+
+    let status_page = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/summary.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "status": {"indicator": "major", "description": "Partial System Outage"},
+            "incidents": [
+                {"id": "inc-1", "name": "Elevated errors on chat completions", "status": "investigating",
+                 "impact": "major", "shortlink": "https://stspg.io/x", "started_at": "2024-06-07T10:00:00Z"},
+                {"id": "inc-0", "name": "Old incident", "status": "resolved", "impact": "minor"}
+            ],
+            "scheduled_maintenances": [{"id": "m-1", "name": "Planned", "status": "scheduled"}]
+        })))
+        .expect(1)
+        .mount(&status_page)
+        .await;
+
+    let mut acme = upstream("acme-up", "https://api.acme.example", 1);
+    acme.provider = Some("Acme".into());
+    let mut config = config_with(vec![acme, upstream("other-up", "https://example.com", 2)], 0);
+    config.provider_status = Some(ProviderStatusConfig {
+        interval_secs: None,
+        feeds: Some(std::collections::HashMap::from([(
+            "acme".to_string(),
+            format!("{}/api/v2/summary.json", status_page.uri()),
+        )])),
+    });
+
+    let client = reqwest::Client::new();
+    provider_status::poll_due(&client, &config, false).await;
+    // 未到轮询间隔时不会再次请求
+    provider_status::poll_due(&client, &config, false).await;
+
+    let statuses = provider_status::snapshot();
+    assert_eq!(statuses.len(), 1);
+    let acme = &statuses[0];
+    assert_eq!(acme.provider, "acme");
+    assert_eq!(acme.upstream_ids, ["acme-up"]);
+    assert_eq!(acme.indicator.as_deref(), Some("major"));
+    assert_eq!(acme.incidents.len(), 1);
+    assert_eq!(acme.incidents[0].name, "Elevated errors on chat completions");
+    assert!(acme.error.is_none());
+}
+
+#[tokio::test]
+async fn response_cache_serves_identical_requests_locally() {
+    use crate::response_cache::ResponseCacheConfig;
+
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"data":[0.1]}"#))
+        .expect(2)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].cache = Some(ResponseCacheConfig::default());
+    let proxy = spawn_proxy(config).await;
+
+    let client = http_client();
+    let send = |body: &'static str| {
+        client
+            .post(proxy.url("/v1/embeddings"))
+            .body(body)
+            .send()
+    };
+    let first = send(r#"{"input":"hello"}"#).await.expect("send");
+    assert!(first.headers().get("x-apiflow-cache").is_none());
+    assert_eq!(first.text().await.unwrap(), r#"{"data":[0.1]}"#);
+
+    let cached = send(r#"{"input":"hello"}"#).await.expect("send");
+    assert_eq!(cached.headers()["x-apiflow-cache"], "hit");
+    assert_eq!(cached.text().await.unwrap(), r#"{"data":[0.1]}"#);
+    let entry = proxy.wait_for_log(|e| e.cache_hit).await;
+    assert_eq!(entry.upstream_id.as_deref(), Some("a"));
+
+    // 请求体不同时不命中
+    let other = send(r#"{"input":"world"}"#).await.expect("send");
+    assert!(other.headers().get("x-apiflow-cache").is_none());
+}
+
+#[tokio::test]
+async fn identical_in_flight_requests_are_forwarded_once() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("shared")
+                .set_delay(Duration::from_millis(500)),
+        )
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].dedupe_in_flight = Some(true);
+    let proxy = spawn_proxy(config).await;
+
+    let client = http_client();
+    let send = || {
+        client
+            .post(proxy.url("/v1/chat/completions"))
+            .body(r#"{"model":"m","messages":[]}"#)
+            .send()
+    };
+    let (first, second) = tokio::join!(send(), send());
+    for resp in [first.expect("send"), second.expect("send")] {
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.text().await.unwrap(), "shared");
+    }
+
+    let joined = |e: &ProxyLogEntry| {
+        e.timeline
+            .iter()
+            .any(|t| t.detail.as_deref() == Some("与相同的并发请求合并"))
+    };
+    let follower = proxy.wait_for_log(|e| e.deduplicated && joined(e)).await;
+    let leader = proxy
+        .wait_for_log(|e| e.deduplicated && !joined(e) && e.status == Some(200))
+        .await;
+    assert_ne!(follower.id, leader.id);
+    assert_eq!(follower.upstream_id.as_deref(), Some("a"));
+}
+
+#[tokio::test]
+async fn default_model_is_injected_when_client_omits_it() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(serde_json::json!({"model": "fallback-model", "messages": []})))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].default_model = Some("fallback-model".into());
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"messages":[]}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert!(entry.default_model_applied);
+    assert_eq!(entry.model.as_deref(), Some("fallback-model"));
+}
+
+#[tokio::test]
+async fn mirror_copies_requests_to_shadow_upstream_and_logs_its_response() {
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("primary"))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let shadow = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(serde_json::json!({"model": "m"})))
+        .respond_with(ResponseTemplate::new(500).set_body_string("shadow failed"))
+        .expect(1)
+        .mount(&shadow)
+        .await;
+
+    // 影子上游优先级更高也不参与正常路由，它的失败不影响客户端
+    let mut config = config_with(
+        vec![upstream("shadow", &shadow.uri(), 1), upstream("a", &primary.uri(), 2)],
+        2,
+    );
+    config.services[0].mirror = Some(crate::mirror::MirrorConfig {
+        upstream_id: "shadow".into(),
+        percent: 100.0,
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"m"}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.text().await.unwrap(), "primary");
+
+    let mirrored = proxy.wait_for_log(|e| e.shadow).await;
+    assert_eq!(mirrored.upstream_id.as_deref(), Some("shadow"));
+    assert_eq!(mirrored.status, Some(500));
+    assert_eq!(mirrored.response_body.as_deref(), Some("shadow failed"));
+    assert!(mirrored.id.ends_with("-mirror"));
+    let original = proxy.wait_for_log(|e| !e.shadow && e.status == Some(200)).await;
+    assert_eq!(original.upstream_id.as_deref(), Some("a"));
+}
+
+#[tokio::test]
+async fn mirrored_requests_count_usage_and_cost_for_the_shadow_upstream() {
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("primary"))
+        .mount(&primary)
+        .await;
+    let shadow = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 1000, "total_tokens": 2000}
+        })))
+        .mount(&shadow)
+        .await;
+
+    let mut config = config_with(
+        vec![upstream("a", &primary.uri(), 1), upstream("shadow", &shadow.uri(), 2)],
+        0,
+    );
+    config.services[0].mirror = Some(crate::mirror::MirrorConfig {
+        upstream_id: "shadow".into(),
+        percent: 100.0,
+    });
+    config.pricing = Some(vec![crate::pricing::ModelPrice {
+        model: "m".into(),
+        input_per_1k: 1.0,
+        output_per_1k: 2.0,
+    }]);
+    let proxy = spawn_proxy(config).await;
+    http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"m"}"#)
+        .send()
+        .await
+        .expect("send");
+
+    let mirrored = proxy.wait_for_log(|e| e.shadow).await;
+    assert_eq!(mirrored.usage.map(|u| u.total_tokens), Some(2000));
+    assert_eq!(mirrored.cost, Some(3.0));
+    let stats = proxy.stats.snapshot();
+    let shadow_stats = stats.iter().find(|s| s.upstream_id == "shadow").expect("shadow stats");
+    assert_eq!(shadow_stats.total_requests, 1);
+    assert_eq!(shadow_stats.total_tokens, 2000);
+    assert_eq!(shadow_stats.total_cost, 3.0);
+}
+
+#[tokio::test]
+async fn capability_table_skips_text_only_upstreams_and_clamps_max_tokens() {
+    let text_only = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(serde_json::json!({"messages": [], "max_tokens": 100})))
+        .respond_with(ResponseTemplate::new(200).set_body_string("text"))
+        .expect(1)
+        .mount(&text_only)
+        .await;
+    let vision = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("vision"))
+        .expect(1)
+        .mount(&vision)
+        .await;
+
+    let mut first = upstream("a", &text_only.uri(), 1);
+    first.provider = Some("text".into());
+    let mut config = config_with(vec![first, upstream("b", &vision.uri(), 2)], 0);
+    config.provider_capabilities = Some(vec![crate::capabilities::ProviderCapabilities {
+        provider: "text".into(),
+        max_output_tokens: Some(100),
+        supports_tools: None,
+        supports_vision: Some(false),
+        supports_json_mode: None,
+    }]);
+    let proxy = spawn_proxy(config).await;
+
+    let client = http_client();
+    let image = client
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"messages":[{"role":"user","content":[{"type":"image_url","image_url":{"url":"data:"}}]}]}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(image.text().await.unwrap(), "vision");
+
+    let text = client
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"messages":[],"max_tokens":5000}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(text.text().await.unwrap(), "text");
+
+    let skipped = proxy.wait_for_log(|e| e.upstream_id.as_deref() == Some("b")).await;
+    assert!(skipped
+        .timeline
+        .iter()
+        .any(|t| t.detail.as_deref() == Some("跳过上游 a：不支持图片输入")));
+}
+
+#[tokio::test]
+async fn compare_mode_logs_secondary_response_and_json_diff() {
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":1,"choices":[{"text":"hi"}]}"#))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let secondary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":1,"choices":[{"text":"hello"}],"extra":true}"#))
+        .expect(1)
+        .mount(&secondary)
+        .await;
+
+    let mut config = config_with(
+        vec![upstream("b", &secondary.uri(), 1), upstream("a", &primary.uri(), 2)],
+        2,
+    );
+    config.services[0].compare = Some(crate::compare::CompareConfig {
+        primary_upstream_id: "a".into(),
+        secondary_upstream_id: "b".into(),
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"m","messages":[]}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.text().await.unwrap(), r#"{"id":1,"choices":[{"text":"hi"}]}"#);
+
+    let linked = proxy.wait_for_log(|e| e.compare.is_some()).await;
+    let report = linked.compare.unwrap();
+    assert_eq!(linked.upstream_id.as_deref(), Some("b"));
+    assert_eq!(report.primary_upstream_id, "a");
+    assert_eq!(report.secondary_status, Some(200));
+    let paths: Vec<&str> = report.differences.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, ["$.choices[0].text", "$.extra"]);
+    assert_eq!(linked.id, format!("{}-compare", report.primary_log_id));
+}
+
+#[tokio::test]
+async fn mock_upstream_fails_first_then_streams_fixture_chunks() {
+    let mut mock_upstream = upstream("mock", "", 1);
+    mock_upstream.mock = Some(crate::mock::MockUpstream {
+        stream_chunks: Some(vec![r#"{"model":"{{model}}"}"#.into(), "[DONE]".into()]),
+        chunk_delay_ms: Some(10),
+        fail_first: Some(1),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config_with(vec![mock_upstream], 2)).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"m","stream":true,"messages":[]}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    assert_eq!(
+        resp.text().await.unwrap(),
+        "data: {\"model\":\"m\"}\n\ndata: [DONE]\n\n"
+    );
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(entry.retry_action.as_deref(), Some("retry"));
+}
+
+#[tokio::test]
+async fn chaos_injects_errors_and_mid_stream_disconnects() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("0123456789"))
+        .mount(&upstream_server)
+        .await;
+
+    let mut failing = upstream("a", &upstream_server.uri(), 1);
+    failing.chaos = Some(crate::chaos::ChaosConfig {
+        error_percent: Some(100.0),
+        error_status: Some(429),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config_with(vec![failing], 0)).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 429);
+    assert_eq!(resp.headers().get("x-apiflow-chaos").unwrap(), "error");
+    assert_eq!(upstream_server.received_requests().await.unwrap().len(), 0);
+
+    let sse_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("data: 0123456789\n\n", "text/event-stream"))
+        .mount(&sse_server)
+        .await;
+    let mut cut = upstream("b", &sse_server.uri(), 1);
+    cut.chaos = Some(crate::chaos::ChaosConfig {
+        latency_ms: Some(50),
+        disconnect_percent: Some(100.0),
+        disconnect_after_bytes: Some(4),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config_with(vec![cut], 0)).await;
+    let started = Instant::now();
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.is_err());
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn failover_drill_short_circuits_the_upstream_and_falls_back() {
+    let primary = MockServer::start().await;
+    let backup = MockServer::start().await;
+    for server in [&primary, &backup] {
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(server)
+            .await;
+    }
+    // 演练状态是全局的，用唯一的上游 ID 避免影响其他测试
+    let drilled = format!("drill-{}", uuid::Uuid::new_v4());
+    crate::drill::start(&drilled, 5).expect("start drill");
+    let proxy = spawn_proxy(config_with(
+        vec![upstream(&drilled, &primary.uri(), 1), upstream("b", &backup.uri(), 2)],
+        1,
+    ))
+    .await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(primary.received_requests().await.unwrap().len(), 0);
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert!(entry.drill);
+    assert_eq!(entry.upstream_id.as_deref(), Some("b"));
+
+    assert!(crate::drill::stop(&drilled));
+    http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(primary.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn context_overflow_retries_once_with_reduced_max_tokens() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(serde_json::json!({"messages": [], "max_tokens": 1000})))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"error":{"code":"context_length_exceeded","message":"too long"}}"#,
+        ))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_json(serde_json::json!({"messages": [], "max_tokens": 500})))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].context_overflow = Some(crate::context_overflow::ContextOverflowRetry {
+        strategy: crate::context_overflow::OverflowStrategy::ReduceMaxTokens,
+        max_tokens_ratio: None,
+        trim_count: None,
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"messages":[],"max_tokens":1000}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert!(entry.timeline.iter().any(|e| e
+        .detail
+        .as_deref()
+        .is_some_and(|d| d.contains("max_tokens 由 1000 缩小为 500"))));
+}
+
+#[tokio::test]
+async fn content_filter_refusal_fails_over_to_policy_fallback_upstream() {
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"error":{"code":"content_policy_violation","message":"refused"}}"#,
+        ))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let backup = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&backup)
+        .await;
+    let local = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("local"))
+        .expect(1)
+        .mount(&local)
+        .await;
+
+    let mut config = config_with(
+        vec![
+            upstream("primary", &primary.uri(), 1),
+            upstream("backup", &backup.uri(), 2),
+            upstream("local", &local.uri(), 3),
+        ],
+        3,
+    );
+    config.services[0].policy_fallback_upstream_id = Some("local".into());
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "local");
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200)).await;
+    assert_eq!(entry.retry_action.as_deref(), Some("policy_fallback"));
+    assert_eq!(entry.upstream_id.as_deref(), Some("local"));
+    let refused = proxy.wait_for_log(|e| e.status == Some(400)).await;
+    assert_eq!(refused.retry_action.as_deref(), Some("policy_fallback"));
+}
+
+#[tokio::test]
+async fn service_headers_are_sent_with_upstream_overrides() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .and(header("openai-organization", "org-upstream"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut entry = upstream("a", &upstream_server.uri(), 1);
+    entry.headers = Some([("OpenAI-Organization".to_string(), "org-upstream".to_string())].into());
+    let mut config = config_with(vec![entry], 0);
+    config.services[0].headers = Some(
+        [
+            ("anthropic-version".to_string(), "2023-06-01".to_string()),
+            ("openai-organization".to_string(), "org-service".to_string()),
+        ]
+        .into(),
+    );
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("openai-organization", "org-client")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn blocked_headers_are_stripped_in_both_directions() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("set-cookie", "session=secret")
+                .insert_header("x-request-id", "abc")
+                .set_body_string("ok"),
+        )
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].blocked_request_headers = Some(vec!["X-Forwarded-For".into(), "x-internal-trace".into()]);
+    config.services[0].blocked_response_headers = Some(vec!["set-cookie".into()]);
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("x-forwarded-for", "10.0.0.1")
+        .header("x-internal-trace", "trace-1")
+        .header("x-keep", "yes")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("set-cookie").is_none());
+    assert_eq!(resp.headers().get("x-request-id").unwrap(), "abc");
+
+    let received = upstream_server.received_requests().await.unwrap();
+    let headers = &received[0].headers;
+    assert!(headers.get("x-forwarded-for").is_none());
+    assert!(headers.get("x-internal-trace").is_none());
+    assert_eq!(headers.get("x-keep").unwrap(), "yes");
+}
+
+#[tokio::test]
+async fn host_override_sets_host_header_and_connects_to_upstream_address() {
+    let upstream_server = MockServer::start().await;
+    let port = upstream_server.address().port();
+    Mock::given(method("POST"))
+        .and(header("host", format!("api.example.test:{port}").as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut entry = upstream("a", &format!("http://127.0.0.1:{port}"), 1);
+    entry.host_override = Some("api.example.test".into());
+    let proxy = spawn_proxy(config_with(vec![entry], 0)).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn cors_layer_answers_preflight_and_decorates_allowed_origins() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(2)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.global_key = Some("proxy-key".into());
+    config.cors = Some(crate::cors::CorsConfig {
+        allowed_origins: Some(vec!["http://localhost:5173".into()]),
+        allowed_headers: Some(vec!["authorization".into(), "content-type".into()]),
+        expose_headers: Some(vec!["x-request-id".into()]),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .request(reqwest::Method::OPTIONS, proxy.url("/v1/chat/completions"))
+        .header("origin", "http://localhost:5173")
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "authorization, content-type")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 204);
+    let headers = resp.headers();
+    assert_eq!(headers["access-control-allow-origin"], "http://localhost:5173");
+    assert_eq!(headers["access-control-allow-headers"], "authorization, content-type");
+    assert!(headers["access-control-allow-methods"].to_str().unwrap().contains("POST"));
+
+    let resp = http_client()
+        .request(reqwest::Method::OPTIONS, proxy.url("/v1/chat/completions"))
+        .header("origin", "http://evil.example")
+        .header("access-control-request-method", "POST")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 403);
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("origin", "http://localhost:5173")
+        .bearer_auth("proxy-key")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["access-control-allow-origin"], "http://localhost:5173");
+    assert_eq!(resp.headers()["access-control-expose-headers"], "x-request-id");
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .header("origin", "http://evil.example")
+        .bearer_auth("proxy-key")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn html_success_pages_are_treated_as_protocol_mismatch() {
+    let portal = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw("<html><body>Please sign in to Wi-Fi</body></html>", "text/html; charset=utf-8"),
+        )
+        .mount(&portal)
+        .await;
+    let good = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+        .expect(1)
+        .mount(&good)
+        .await;
+
+    let proxy = spawn_proxy(config_with(
+        vec![upstream("portal", &portal.uri(), 1), upstream("good", &good.uri(), 2)],
+        1,
+    ))
+    .await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    let failed = proxy.wait_for_log(|e| e.error_kind.is_some()).await;
+    assert_eq!(failed.error_kind, Some(crate::provider_error::ErrorKind::ProtocolMismatch));
+    assert_eq!(failed.retry_action.as_deref(), Some("fallback"));
+    assert!(failed.error.unwrap().contains("上游返回了网页"));
+
+    let proxy = spawn_proxy(config_with(vec![upstream("portal", &portal.uri(), 1)], 0)).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 502);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body: serde_json::Value = resp.json().await.expect("json");
+    assert!(body["error"].as_str().unwrap().contains("text/html"));
+    let entry = proxy.wait_for_log(|e| e.status == Some(502)).await;
+    assert_eq!(entry.error_kind, Some(crate::provider_error::ErrorKind::ProtocolMismatch));
+}
+
+#[tokio::test]
+async fn oversized_request_bodies_are_rejected_with_413() {
+    let upstream_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&upstream_server)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &upstream_server.uri(), 1)], 0);
+    config.services[0].max_request_bytes = Some(64);
+    let proxy = spawn_proxy(config).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("x".repeat(65))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 413);
+    let body: serde_json::Value = resp.json().await.expect("json");
+    assert!(body["error"].as_str().unwrap().contains("64"));
+
+    // 分块传输没有声明长度，读取时超过上限同样拒绝
+    let chunks = futures_util::stream::iter(
+        (0..4).map(|_| Ok::<_, std::io::Error>(bytes::Bytes::from_static(&[b'x'; 32]))),
+    );
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 413);
+
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(413)).await;
+    assert!(entry.error.unwrap().contains("大小上限"));
+}
+
+#[tokio::test]
+async fn json_responses_to_stream_requests_are_flagged_and_converted() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hello" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6 }
+        })))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].streaming = Some(crate::streaming::StreamingDetection {
+        convert_json: Some(true),
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"gpt-4o","stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let text = resp.text().await.unwrap();
+    let events: Vec<serde_json::Value> = text
+        .split("\n\n")
+        .filter_map(|e| e.strip_prefix("data: "))
+        .filter(|d| *d != "[DONE]")
+        .map(|d| serde_json::from_str(d).unwrap())
+        .collect();
+    assert_eq!(events[0]["object"], "chat.completion.chunk");
+    assert_eq!(events[0]["choices"][0]["delta"]["content"], "Hello");
+    assert_eq!(events[1]["usage"]["total_tokens"], 6);
+    assert!(text.ends_with("data: [DONE]\n\n"));
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200) && e.duration_ms > 0).await;
+    assert!(entry.stream_mismatch);
+    assert_eq!(entry.usage.map(|u| u.total_tokens), Some(6));
+
+    // 未开启转换时原样转发，只在日志中标记
+    let proxy = spawn_proxy(config_with(vec![upstream("a", &mock.uri(), 1)], 0)).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"stream":true}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let entry = proxy.wait_for_log(|e| e.status == Some(200) && e.stream_mismatch).await;
+    assert!(entry.stream_mismatch);
+}
+
+#[tokio::test]
+async fn client_timeout_header_bounds_the_request_and_is_not_forwarded() {
+    let slow = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/slow"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(3)))
+        .mount(&slow)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fast"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&slow)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &slow.uri(), 1)], 0);
+    config.max_client_timeout_ms = Some(300);
+    let proxy = spawn_proxy(config).await;
+
+    // 请求的超时超过上限时按上限处理
+    let started = Instant::now();
+    let resp = http_client()
+        .post(proxy.url("/slow"))
+        .header("x-apiflow-timeout-ms", "60000")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 504);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    let entry = proxy.wait_for_log(|e| e.status == Some(504)).await;
+    assert!(entry.error.unwrap().contains("300 ms"));
+
+    let resp = http_client()
+        .post(proxy.url("/fast"))
+        .header("x-apiflow-timeout-ms", "200")
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 200);
+    let received = slow.received_requests().await.unwrap();
+    assert!(received.iter().all(|r| r.headers.get("x-apiflow-timeout-ms").is_none()));
+}
+
+#[tokio::test]
+async fn failed_requests_on_queued_paths_are_parked_and_resubmitted() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/batch"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/batch"))
+        .and(body_json(serde_json::json!({ "n": 1 })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"done":true}"#))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.global_key = Some("proxy-secret".into());
+    config.redaction = Some(
+        serde_json::from_value(serde_json::json!({ "headerNames": ["x-tenant-secret"] })).unwrap(),
+    );
+    config.services[0].retry_queue = Some(crate::retry_queue::RetryQueueConfig {
+        paths: vec!["/v1/batch".into()],
+        ..Default::default()
+    });
+    let proxy = spawn_proxy(config.clone()).await;
+
+    let resp = http_client()
+        .post(proxy.url("/v1/batch"))
+        .header("x-proxy-key", "proxy-secret")
+        .header("x-tenant-secret", "tenant-1234")
+        .header("content-type", "application/json")
+        .body(r#"{"n":1}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 202);
+    let job_id = resp.headers()["x-apiflow-job-id"].to_str().unwrap().to_string();
+    let payload: serde_json::Value = resp.json().await.expect("json");
+    assert_eq!(payload["jobId"], job_id.as_str());
+    assert_eq!(payload["status"], "pending");
+    let entry = proxy.wait_for_log(|e| e.status == Some(202)).await;
+    assert!(entry.error.unwrap().contains(&job_id));
+
+    // 服务已不在运行中的配置里：记录原因并推迟，不消耗重新提交次数
+    let mut without_service = config.clone();
+    without_service.services[0].id = "other".into();
+    let job = crate::retry_queue::run_job(&http_client(), Some(&without_service), &job_id)
+        .await
+        .expect("job");
+    assert_eq!(job.status, crate::retry_queue::QueuedJobStatus::Pending);
+    assert_eq!(job.attempts, 0);
+    assert!(job.last_error.unwrap().contains("已不存在"));
+
+    // 重新提交到服务当前所在的端口，携带内部令牌，无需代理 key，也不会再次入队
+    let running = crate::ProxyConfig {
+        listen_port: proxy.addr.port(),
+        listeners: None,
+        ..config
+    };
+    let job = crate::retry_queue::run_job(&http_client(), Some(&running), &job_id)
+        .await
+        .expect("job");
+    assert_eq!(job.status, crate::retry_queue::QueuedJobStatus::Completed);
+    assert_eq!(job.attempts, 1);
+    let result = job.result.expect("result");
+    assert_eq!(result.status, 200);
+    assert_eq!(result.body, r#"{"done":true}"#);
+    let received = mock.received_requests().await.unwrap();
+    assert!(received
+        .iter()
+        .all(|r| r.headers.get("x-apiflow-queue-token").is_none() && r.headers.get("x-proxy-key").is_none()));
+    // 敏感请求头不随任务保存，重新提交的请求中不再携带
+    assert!(received[0].headers.get("x-tenant-secret").is_some());
+    assert!(received[1].headers.get("x-tenant-secret").is_none());
+
+    let resp = http_client()
+        .get(proxy.url(&format!("/_apiflow/jobs/{job_id}")))
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.status(), 401);
+    let polled: serde_json::Value = http_client()
+        .get(proxy.url(&format!("/_apiflow/jobs/{job_id}")))
+        .header("x-proxy-key", "proxy-secret")
+        .send()
+        .await
+        .expect("send")
+        .json()
+        .await
+        .expect("json");
+    assert_eq!(polled["status"], "completed");
+    assert_eq!(polled["result"]["status"], 200);
+}
+
+#[tokio::test]
+async fn model_filter_rejects_blocked_and_unlisted_models() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].model_filter = Some(crate::model_filter::ModelFilter {
+        allowed_models: Some(vec!["gpt-4o-mini*".into(), "gpt-4o".into()]),
+        blocked_models: Some(vec!["gpt-4o-mini-audio*".into()]),
+    });
+    let proxy = spawn_proxy(config).await;
+
+    let send = |model: &'static str| {
+        http_client()
+            .post(proxy.url("/v1/chat/completions"))
+            .body(format!(r#"{{"model":"{model}"}}"#))
+            .send()
+    };
+    assert_eq!(send("gpt-4o-mini-2024-07-18").await.expect("send").status(), 200);
+    assert_eq!(send("gpt-4o").await.expect("send").status(), 200);
+
+    let resp = send("o1-pro").await.expect("send");
+    assert_eq!(resp.status(), 403);
+    let payload: serde_json::Value = resp.json().await.expect("json");
+    assert_eq!(payload["type"], "model_not_allowed");
+    assert_eq!(payload["allowedModels"][1], "gpt-4o");
+    assert_eq!(send("gpt-4o-mini-audio-preview").await.expect("send").status(), 403);
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(403)).await;
+    assert!(entry.error.is_some());
+    assert_eq!(mock.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn hooks_post_event_json_and_run_commands() {
+    use crate::hooks::{deliver, HookConfig, HookEvent, HookPayload};
+
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_json(serde_json::json!({
+            "event": "proxy_started",
+            "timestamp": "2024-05-01T00:00:00Z",
+            "listenPorts": [8080],
+        })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock)
+        .await;
+
+    let payload = HookPayload {
+        timestamp: "2024-05-01T00:00:00Z".into(),
+        ..HookPayload::ports(HookEvent::ProxyStarted, vec![8080])
+    };
+    let hook = HookConfig {
+        url: Some(format!("{}/hook", mock.uri())),
+        ..Default::default()
+    };
+    deliver(&hook, &payload).await.expect("deliver");
+
+    let failing = HookConfig {
+        url: Some(format!("{}/missing", mock.uri())),
+        ..Default::default()
+    };
+    assert!(deliver(&failing, &payload).await.is_err());
+
+    if cfg!(unix) {
+        let out = std::env::temp_dir().join(format!("apiflow-hook-{}", uuid::Uuid::new_v4()));
+        let hook = HookConfig {
+            command: Some(format!("printf %s \"$APIFLOW_EVENT\" > '{}'", out.display())),
+            ..Default::default()
+        };
+        deliver(&hook, &payload).await.expect("command");
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "proxy_started");
+        let _ = std::fs::remove_file(out);
+    }
+}
+
+#[tokio::test]
+async fn services_can_opt_out_of_request_and_response_body_logging() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"secret":"answer"}"#))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].log_request_body = Some(false);
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"prompt":"customer data"}"#)
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.text().await.unwrap(), r#"{"secret":"answer"}"#);
+    let entry = proxy.wait_for_log(|e| e.status == Some(200) && e.duration_ms > 0).await;
+    assert!(entry.request_body.is_none());
+    assert!(entry.request_headers.is_some());
+    assert_eq!(entry.response_body.as_deref(), Some(r#"{"secret":"answer"}"#));
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].log_response_body = Some(false);
+    let proxy = spawn_proxy(config).await;
+    http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"prompt":"hi"}"#)
+        .send()
+        .await
+        .expect("send");
+    let entry = proxy.wait_for_log(|e| e.status == Some(200) && e.duration_ms > 0).await;
+    assert!(entry.request_body.is_some());
+    assert!(entry.response_body.is_none());
+}
+
+#[tokio::test]
+async fn error_body_cut_off_mid_read_counts_as_failed_attempt() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // 声明的响应体长度大于实际发送的字节数，发送后立即断开
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let broken = format!("http://{}", listener.local_addr().expect("addr"));
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let head = "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 100\r\n\r\n{\"error\":";
+            let _ = socket.write_all(head.as_bytes()).await;
+        }
+    });
+    let backup = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .expect(1)
+        .mount(&backup)
+        .await;
+
+    let config = config_with(
+        vec![upstream("broken", &broken, 1), upstream("backup", &backup.uri(), 2)],
+        1,
+    );
+    let proxy = spawn_proxy(config).await;
+    let resp = http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body("{}")
+        .send()
+        .await
+        .expect("send");
+    assert_eq!(resp.text().await.unwrap(), "ok");
+
+    let failed = proxy
+        .wait_for_log(|e| e.upstream_id.as_deref() == Some("broken") && e.status.is_some())
+        .await;
+    assert_eq!(failed.retry_action.as_deref(), Some("fallback"));
+    assert!(failed.error.unwrap().contains("读取上游错误响应失败"));
+}
+
+#[tokio::test]
+async fn pricing_reads_model_even_when_request_body_is_not_logged() {
+    let mock = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 1000, "total_tokens": 2000}
+        })))
+        .mount(&mock)
+        .await;
+
+    let mut config = config_with(vec![upstream("a", &mock.uri(), 1)], 0);
+    config.services[0].log_request_body = Some(false);
+    config.pricing = Some(vec![crate::pricing::ModelPrice {
+        model: "m".into(),
+        input_per_1k: 1.0,
+        output_per_1k: 2.0,
+    }]);
+    let proxy = spawn_proxy(config).await;
+    http_client()
+        .post(proxy.url("/v1/chat/completions"))
+        .body(r#"{"model":"m"}"#)
+        .send()
+        .await
+        .expect("send");
+
+    let entry = proxy.wait_for_log(|e| e.status == Some(200) && e.duration_ms > 0).await;
+    assert_eq!(entry.model.as_deref(), Some("m"));
+    assert_eq!(entry.cost, Some(3.0));
+    assert!(entry.request_body.is_none());
+}
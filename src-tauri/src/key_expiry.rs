@@ -0,0 +1,142 @@
+//! API key 到期提醒：上游可记录 key 的到期时间，后台任务在到期前若干天推送提醒，
+//! 到期后标记为已过期，并可按配置在路由时跳过这些上游。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use ts_rs::TS;
+
+use crate::persistence::cached_config;
+use crate::{events, timestamp, ProxyConfig, UpstreamEntry};
+
+const DEFAULT_REMIND_DAYS: u32 = 7;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/KeyExpiryConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct KeyExpiryConfig {
+    /// 到期前多少天开始提醒，默认 7 天
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub remind_days: Option<u32>,
+    /// 为 true 时路由跳过 key 已过期的上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub skip_expired: Option<bool>,
+}
+
+impl KeyExpiryConfig {
+    pub fn remind_days(&self) -> u32 {
+        self.remind_days.unwrap_or(DEFAULT_REMIND_DAYS)
+    }
+
+    pub fn skips_expired(&self) -> bool {
+        self.skip_expired.unwrap_or(false)
+    }
+}
+
+/// 即将到期或已过期的 key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/KeyExpiryStatus.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct KeyExpiryStatus {
+    pub service_name: String,
+    pub upstream_id: String,
+    pub upstream_label: Option<String>,
+    pub expires_at: String,
+    /// 距到期的整天数，已过期时为负
+    #[ts(type = "number")]
+    pub days_left: i64,
+    pub expired: bool,
+}
+
+/// 解析到期时间：完整时间戳，或只写日期（当天结束前仍有效）
+pub fn parse_expiry(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    timestamp::parse(value).or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()?
+            .succ_opt()?
+            .and_hms_opt(0, 0, 0)?
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+    })
+}
+
+pub fn is_expired(upstream: &UpstreamEntry, now: DateTime<Utc>) -> bool {
+    upstream
+        .key_expires_at
+        .as_deref()
+        .and_then(parse_expiry)
+        .is_some_and(|at| at <= now)
+}
+
+/// 列出所有服务中已过期或在提醒期内的 key，按到期时间排序
+pub fn statuses_at(config: &ProxyConfig, now: DateTime<Utc>) -> Vec<KeyExpiryStatus> {
+    let remind_days = config
+        .key_expiry
+        .as_ref()
+        .map(KeyExpiryConfig::remind_days)
+        .unwrap_or(DEFAULT_REMIND_DAYS);
+    let listeners = config.listeners.iter().flatten();
+    let mut statuses: Vec<KeyExpiryStatus> = config
+        .services
+        .iter()
+        .chain(listeners.flat_map(|l| l.services.iter()))
+        .flat_map(|service| {
+            service.upstreams.iter().filter_map(move |upstream| {
+                let at = upstream.key_expires_at.as_deref().and_then(parse_expiry)?;
+                let days_left = (at - now).num_days();
+                let expired = at <= now;
+                (expired || days_left < i64::from(remind_days)).then(|| KeyExpiryStatus {
+                    service_name: service.name.clone(),
+                    upstream_id: upstream.id.clone(),
+                    upstream_label: upstream.label.clone(),
+                    expires_at: timestamp::format_utc(at),
+                    days_left,
+                    expired,
+                })
+            })
+        })
+        .collect();
+    statuses.sort_by(|a, b| timestamp::compare(&a.expires_at, &b.expires_at));
+    statuses
+}
+
+/// 已推送过的提醒，同一个 key 即将到期和已过期各提醒一次
+fn alerted() -> &'static Mutex<HashSet<(String, String, bool)>> {
+    static ALERTED: OnceLock<Mutex<HashSet<(String, String, bool)>>> = OnceLock::new();
+    ALERTED.get_or_init(Default::default)
+}
+
+/// 每小时检查一次，新进入提醒期或刚过期的 key 推送提醒
+pub fn spawn_check_task(config: Arc<RwLock<Option<ProxyConfig>>>) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let current = config.read().await.clone();
+            let Some(current) = current.or_else(cached_config) else {
+                continue;
+            };
+            let fresh: Vec<KeyExpiryStatus> = {
+                let mut alerted = alerted().lock().unwrap_or_else(|e| e.into_inner());
+                statuses_at(&current, Utc::now())
+                    .into_iter()
+                    .filter(|s| {
+                        alerted.insert((s.upstream_id.clone(), s.expires_at.clone(), s.expired))
+                    })
+                    .collect()
+            };
+            if !fresh.is_empty() {
+                events::emit_key_expiry_alert(fresh);
+            }
+        }
+    });
+}
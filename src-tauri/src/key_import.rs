@@ -0,0 +1,122 @@
+//! 批量导入 API key：粘贴的文本每行一个 key，或 CSV 格式的 `标签,key[,上游地址]`，
+//! 每个 key 生成一个上游，未写地址时沿用服务中第一个上游的地址。
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{timestamp, ServiceConfig, UpstreamEntry};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedKey {
+    pub label: Option<String>,
+    pub api_key: String,
+    pub upstream_base: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/KeyImportSummary.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct KeyImportSummary {
+    pub added: u32,
+    /// 服务中已存在或在文本中重复出现而跳过的 key
+    pub duplicates: u32,
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field)
+        .trim()
+}
+
+/// 解析粘贴的文本，忽略空行、`#` 注释和 CSV 表头
+pub fn parse_keys(text: &str) -> Result<Vec<ParsedKey>, String> {
+    let mut keys = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(unquote).collect();
+        let parsed = match fields.as_slice() {
+            [key] => ParsedKey {
+                label: None,
+                api_key: key.to_string(),
+                upstream_base: None,
+            },
+            [label, key] | [label, key, ""] => ParsedKey {
+                label: Some(label.to_string()).filter(|l| !l.is_empty()),
+                api_key: key.to_string(),
+                upstream_base: None,
+            },
+            [label, key, base] => ParsedKey {
+                label: Some(label.to_string()).filter(|l| !l.is_empty()),
+                api_key: key.to_string(),
+                upstream_base: Some(base.trim_end_matches('/').to_string()),
+            },
+            _ => {
+                return Err(format!(
+                    "第 {} 行格式无效，应为 key 或 标签,key[,上游地址]",
+                    idx + 1
+                ))
+            }
+        };
+        if keys.is_empty() && parsed.api_key.eq_ignore_ascii_case("key") {
+            continue;
+        }
+        if parsed.api_key.is_empty() || parsed.api_key.chars().any(char::is_whitespace) {
+            return Err(format!("第 {} 行的 key 无效", idx + 1));
+        }
+        keys.push(parsed);
+    }
+    if keys.is_empty() {
+        return Err("没有找到可导入的 key".into());
+    }
+    Ok(keys)
+}
+
+/// 把解析出的 key 追加为服务的上游，优先级排在已有上游之后
+pub fn append_upstreams(
+    service: &mut ServiceConfig,
+    keys: Vec<ParsedKey>,
+) -> Result<KeyImportSummary, String> {
+    let default_base = service.upstreams.first().map(|u| u.upstream_base.clone());
+    let mut priority = service
+        .upstreams
+        .iter()
+        .map(|u| u.priority)
+        .max()
+        .unwrap_or(0);
+    let mut summary = KeyImportSummary::default();
+    let created_at = timestamp::now();
+    for key in keys {
+        if service
+            .upstreams
+            .iter()
+            .any(|u| u.api_key.as_deref() == Some(key.api_key.as_str()))
+        {
+            summary.duplicates += 1;
+            continue;
+        }
+        let upstream_base = key
+            .upstream_base
+            .or_else(|| default_base.clone())
+            .ok_or("服务还没有上游，导入时需要在每行写明上游地址")?;
+        priority += 1;
+        service.upstreams.push(UpstreamEntry {
+            id: Uuid::new_v4().to_string(),
+            label: key.label,
+            upstream_base,
+            api_key: Some(key.api_key),
+            priority,
+            enabled: true,
+            created_at: Some(created_at.clone()),
+            ..Default::default()
+        });
+        summary.added += 1;
+    }
+    Ok(summary)
+}
@@ -0,0 +1,130 @@
+//! 系统钥匙串：保存配置时把全局 key 与各上游的 API key 存入系统钥匙串
+//! （macOS 钥匙串、Windows 凭据管理器、Linux Secret Service），
+//! 配置文件中只保留 `keychain:<账户>` 引用，读取配置时再解析回实际的 key。
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Mutex;
+
+use crate::{env_vars, ProxyConfig};
+
+const SERVICE: &str = "com.apiflow.app";
+const PREFIX: &str = "keychain:";
+const GLOBAL_ACCOUNT: &str = "global-key";
+
+/// 最近一次读取失败的账户；读取失败时内存中的 key 已置空，保存配置时不能据此删除钥匙串条目
+static UNRESOLVED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+pub fn reference(account: &str) -> String {
+    format!("{PREFIX}{account}")
+}
+
+/// 钥匙串引用对应的账户，不是引用时返回 None
+pub fn account_of(value: &str) -> Option<&str> {
+    value.strip_prefix(PREFIX)
+}
+
+/// 配置中保存 key 的位置及其在钥匙串中的账户名
+fn secret_slots(config: &mut ProxyConfig) -> Vec<(String, &mut Option<String>)> {
+    let mut slots = vec![(GLOBAL_ACCOUNT.to_string(), &mut config.global_key)];
+    let services = config.services.iter_mut().chain(
+        config
+            .listeners
+            .iter_mut()
+            .flatten()
+            .flat_map(|l| l.services.iter_mut()),
+    );
+    for upstream in services.flat_map(|s| s.upstreams.iter_mut()) {
+        slots.push((format!("upstream:{}", upstream.id), &mut upstream.api_key));
+    }
+    slots
+}
+
+/// 把明文 key 交给 store 保存并替换为引用，环境变量占位符原样保留；保存失败的 key 保留明文，返回首个错误
+pub fn externalize_with(
+    config: &mut ProxyConfig,
+    mut store: impl FnMut(&str, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut first_err = None;
+    for (account, slot) in secret_slots(config) {
+        let Some(secret) = slot
+            .as_deref()
+            .filter(|s| !s.is_empty() && account_of(s).is_none() && !env_vars::has_placeholder(s))
+        else {
+            continue;
+        };
+        match store(&account, secret) {
+            Ok(()) => *slot = Some(reference(&account)),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    first_err.map_or(Ok(()), Err)
+}
+
+/// 用 load 把引用解析为实际的 key；解析失败的 key 置空，避免把引用当作 key 发给上游，
+/// 对应账户记为未解析，直到再次读取成功
+pub fn resolve_with(
+    config: &mut ProxyConfig,
+    mut load: impl FnMut(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    let mut first_err = None;
+    let mut unresolved = UNRESOLVED.lock().unwrap_or_else(|e| e.into_inner());
+    for (_, slot) in secret_slots(config) {
+        let Some(account) = slot.as_deref().and_then(account_of).map(str::to_string) else {
+            continue;
+        };
+        match load(&account) {
+            Ok(secret) => {
+                *slot = Some(secret);
+                unresolved.remove(&account);
+            }
+            Err(err) => {
+                *slot = None;
+                unresolved.insert(account);
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    first_err.map_or(Ok(()), Err)
+}
+
+/// 账户最近一次读取失败，其中的 key 可能仍然有效，不应被删除
+pub fn is_unresolved(account: &str) -> bool {
+    UNRESOLVED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(account)
+}
+
+/// 配置中引用的全部钥匙串账户
+pub fn referenced_accounts(config: &mut ProxyConfig) -> HashSet<String> {
+    secret_slots(config)
+        .into_iter()
+        .filter_map(|(_, slot)| slot.as_deref().and_then(account_of).map(str::to_string))
+        .collect()
+}
+
+fn entry(account: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, account).map_err(|e| format!("无法访问系统钥匙串: {e}"))
+}
+
+pub fn store(account: &str, secret: &str) -> Result<(), String> {
+    entry(account)?
+        .set_password(secret)
+        .map_err(|e| format!("写入系统钥匙串失败（{account}）: {e}"))
+}
+
+pub fn load(account: &str) -> Result<String, String> {
+    entry(account)?
+        .get_password()
+        .map_err(|e| format!("读取系统钥匙串失败（{account}）: {e}"))
+}
+
+/// 删除不再被配置引用的条目，条目不存在时忽略
+pub fn delete(account: &str) -> Result<(), String> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("删除系统钥匙串条目失败（{account}）: {e}")),
+    }
+}
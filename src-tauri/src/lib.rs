@@ -1,10 +1,11 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     net::SocketAddr,
     sync::Arc,
     time::{Duration, Instant},
 };
 
+use arc_swap::ArcSwap;
 use axum::{
     body::Body, extract::{ConnectInfo, State}, http::Request, http::StatusCode, response::Response, routing::any,
     Router,
@@ -13,33 +14,163 @@ use bytes::{Bytes, BytesMut};
 use chrono::Local;
 use futures_util::StreamExt;
 use http::header;
-use http_body_util::BodyExt;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use tauri::State as TauriState;
 use tokio::sync::{oneshot, Mutex, RwLock};
+use tracing::Instrument;
 use uuid::Uuid;
 
+mod access_schedule;
+mod admin_api;
+mod admin_auth;
+mod app_metrics;
+mod backoff;
+mod balance;
+mod budget;
+mod capabilities;
+mod chaos;
+mod checksum;
+pub mod cli;
+mod client_app;
+mod compare;
+mod config_transfer;
+mod context_overflow;
+mod cors;
+mod curl;
+mod daily_summary;
+mod dimension;
+mod drill;
+mod env_vars;
+mod events;
+mod gateway_import;
 mod helpers;
+mod hooks;
+mod host_override;
+mod images;
+mod inflight;
+mod key_expiry;
+mod key_import;
+mod keychain;
+mod linked;
 mod logging;
+mod mirror;
+mod mock;
+mod model_filter;
 mod network;
+mod path_rewrite;
 mod persistence;
+mod pii_mask;
+mod pricing;
+mod provider_error;
+mod provider_status;
+mod query_params;
+mod reconcile;
+mod redaction;
+mod request_limit;
+mod response_cache;
+mod retry_queue;
+mod retry_rules;
+pub mod rewrite;
+mod safe_mode;
+mod scheduler;
+mod schema;
+mod split;
+mod stats;
+mod status;
+mod storage;
+mod stream_bridge;
+mod stream_convert;
+mod streaming;
+mod structured_output;
+mod synthetic;
+mod tee;
+mod telemetry;
+mod timeline;
+mod timestamp;
+mod timeouts;
+mod transcript;
 mod tray;
+mod usage;
 
+#[cfg(test)]
+mod integration_tests;
 #[cfg(test)]
 mod tests;
 
-use crate::helpers::{
-    build_upstream_url, extract_proxy_key, format_headers, normalize_base_path, strip_base_path, truncate_body,
+use crate::access_schedule::AccessSchedule;
+use crate::admin_api::AdminApiConfig;
+use crate::admin_auth::{validate_admin_tokens, AdminToken};
+use crate::backoff::{random_unit, BackoffConfig};
+use crate::app_metrics::AppMetrics;
+use crate::balance::{BalanceConfig, UpstreamBalance};
+use crate::budget::{validate_budgets, BudgetRule, BudgetStatus};
+use crate::capabilities::{validate_capabilities, ProviderCapabilities, RequestNeeds};
+use crate::chaos::ChaosConfig;
+use crate::checksum::{sha256_hex, BodyHasher, ChecksumReport, CONTENT_DIGEST};
+use crate::compare::{CompareConfig, CompareReport};
+use crate::config_transfer::ConfigImport;
+use crate::context_overflow::ContextOverflowRetry;
+use crate::cors::CorsConfig;
+use crate::curl::{build_curl_command, CurlTarget};
+use crate::daily_summary::{DailySummary, DailySummaryConfig};
+use crate::dimension::CustomDimension;
+use crate::drill::FailoverDrill;
+use crate::gateway_import::GatewayImport;
+use crate::helpers::{extract_proxy_key, normalize_base_path, truncate_body};
+use crate::hooks::{validate_hooks, HookConfig, HookEvent, HookPayload};
+use crate::host_override::{HostOverride, OverrideResolver};
+use crate::images::ImageSummary;
+use crate::key_expiry::{KeyExpiryConfig, KeyExpiryStatus};
+use crate::key_import::KeyImportSummary;
+use crate::logging::{
+    apply_retention, finalize_inflight, paginate_logs, LogFilter, LogPage, RetentionConfig, DEFAULT_MAX_LOGS,
 };
-use crate::logging::{finalize_inflight, MAX_LOGS};
+use crate::mirror::MirrorConfig;
+use crate::mock::MockUpstream;
+use crate::model_filter::ModelFilter;
 use crate::network::NetworkInfo;
-use crate::persistence::{load_config, save_config};
-pub use tray::update_tray_status;
+use crate::persistence::{load_config, save_config, ConfigEncryption};
+use crate::pii_mask::PiiMaskConfig;
+use crate::path_rewrite::PathRewriteRule;
+use crate::pricing::{estimate_cost, validate_pricing, ModelPrice};
+use crate::provider_error::{classify_error, error_action, ErrorAction, ErrorKind};
+use crate::provider_status::{ProviderStatus, ProviderStatusConfig};
+use crate::query_params::QueryParamRule;
+use crate::reconcile::UsageReconciliation;
+use crate::redaction::RedactionConfig;
+use crate::request_limit::RequestRateLimit;
+use crate::response_cache::{CachedResponse, ResponseCacheConfig, CACHE_HEADER};
+use crate::retry_queue::{QueuedJob, RetryQueueConfig};
+use crate::retry_rules::{validate_retry_rules, RetryRule};
+use crate::rewrite::{
+    build_upstream_url, extract_model, format_upstream_headers, header_names, identity_headers, map_model,
+    matches_base_path, remove_headers, rewrite_upstream_headers, serialize_outbound, strip_base_path,
+};
+use crate::scheduler::{RateLimitConfig, SchedulerStats, SlotPermit};
+use crate::split::RoutingMode;
+use crate::stats::{GroupStats, SpendSummary, StatsDims, StatsGroupBy, StatsStore, UpstreamIdentity};
+use crate::status::ReservedRoute;
+use crate::storage::LogStorageConfig;
+use crate::stream_bridge::{SseFramer, StreamBridgeConfig};
+use crate::streaming::{StreamProbe, StreamingDetection};
+use crate::structured_output::StructuredOutputMode;
+use crate::synthetic::{validate_synthetic_endpoints, SyntheticEndpoint};
+use crate::tee::{TeeMessage, TeeSink};
+use crate::telemetry::TracingConfig;
+use crate::timeline::{TimelineEvent, TimelineEventKind};
+use crate::timeouts::TimeoutConfig;
+use crate::transcript::ExportFormat;
+use crate::usage::{parse_json_usage, TokenUsage, UsageScanner};
+use tray::update_tray_status;
 
 const MAX_FALLBACK_RETRIES: u32 = 10;
+/// 开启 trace_headers 时发给上游的请求标识头，便于与服务商侧日志对照
+const REQUEST_ID_HEADER: &str = "x-apiflow-request-id";
+const ATTEMPT_ID_HEADER: &str = "x-apiflow-attempt-id";
 
-#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../src/types/generated/ProxyConfig.ts")]
 #[serde(rename_all = "camelCase")]
 pub struct ProxyConfig {
@@ -49,6 +180,164 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub fallback_retries: u32,
     pub services: Vec<ServiceConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub redaction: Option<RedactionConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub retention: Option<RetentionConfig>,
+    /// 按错误类型覆盖默认的重试/切换策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "Partial<Record<ErrorKind, ErrorAction>>")]
+    pub error_actions: Option<HashMap<ErrorKind, ErrorAction>>,
+    /// 流式响应旁路输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub stream_tee: Option<TeeSink>,
+    /// 模型价格表，用于估算请求费用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub pricing: Option<Vec<ModelPrice>>,
+    /// 日志持久化后端，未设置时使用本地 SQLite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub log_storage: Option<LogStorageConfig>,
+    /// 管理接口令牌，每个令牌只能执行其权限范围内的操作
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub admin_tokens: Option<Vec<AdminToken>>,
+    /// 本机管理 REST API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub admin_api: Option<AdminApiConfig>,
+    /// 按天 / 按月的费用与 token 预算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub budgets: Option<Vec<BudgetRule>>,
+    /// OTLP 链路追踪导出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub tracing: Option<TracingConfig>,
+    /// 额外的监听端口，各自使用独立的服务配置，其余设置与主端口共享
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub listeners: Option<Vec<ListenerConfig>>,
+    /// 端到端校验请求体与响应体的 SHA-256，并与对端声明的 Content-Digest 比对
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub verify_checksums: Option<bool>,
+    /// 向上游附加 X-ApiFlow-Request-Id / X-ApiFlow-Attempt-Id 请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub trace_headers: Option<bool>,
+    /// 不经过上游、直接返回固定内容的接口，在路由匹配之前处理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub synthetic_endpoints: Option<Vec<SyntheticEndpoint>>,
+    /// 同一上游重试前的指数退避；未设置时立即重试，但仍遵守上游的 Retry-After
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub backoff: Option<BackoffConfig>,
+    /// 整个代理的请求速率上限，超出时直接返回 429
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub request_rate_limit: Option<RequestRateLimit>,
+    /// API key 到期提醒与过期后的路由处理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub key_expiry: Option<KeyExpiryConfig>,
+    /// 服务商能力表，上游通过 provider 字段引用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub provider_capabilities: Option<Vec<ProviderCapabilities>>,
+    /// 每日用量概览的推送设置，未配置时每天 22 点推送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub daily_summary: Option<DailySummaryConfig>,
+    /// 浏览器跨域访问的设置，未配置时不处理跨域请求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub cors: Option<CorsConfig>,
+    /// 客户端通过 `X-ApiFlow-Timeout-Ms` 请求头可指定的最长超时（毫秒），未配置时忽略该请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_client_timeout_ms: Option<u64>,
+    /// 代理启停、配置变更与上游健康状态变化时执行的钩子
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub hooks: Option<Vec<HookConfig>>,
+    /// 从请求中取出自定义统计维度（如项目）的规则，统计与费用汇总可按该维度分组
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub custom_dimension: Option<CustomDimension>,
+    /// 配置文件的加密方式，未配置时以明文 JSON 保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub config_encryption: Option<ConfigEncryption>,
+    /// 流式响应转发的缓冲与切分方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub stream_bridge: Option<StreamBridgeConfig>,
+    /// 轮询上游所属服务商的状态页，有进行中的故障时提醒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub provider_status: Option<ProviderStatusConfig>,
+}
+
+impl ProxyConfig {
+    /// 主端口在前的全部监听端口
+    fn listener_ports(&self) -> Vec<u16> {
+        std::iter::once(self.listen_port)
+            .chain(self.listeners.iter().flatten().map(|l| l.listen_port))
+            .collect()
+    }
+
+    /// 所有监听端口下的服务
+    fn all_services(&self) -> impl Iterator<Item = &ServiceConfig> {
+        self.services
+            .iter()
+            .chain(self.listeners.iter().flatten().flat_map(|l| &l.services))
+    }
+
+    /// 按 id 查找任一监听端口下的服务
+    fn service_mut(&mut self, service_id: &str) -> Option<&mut ServiceConfig> {
+        self.services
+            .iter_mut()
+            .chain(self.listeners.iter_mut().flatten().flat_map(|l| l.services.iter_mut()))
+            .find(|svc| svc.id == service_id)
+    }
+
+    /// 展开为每个监听端口各自的运行配置
+    fn per_listener(&self) -> Vec<ProxyConfig> {
+        let primary = ProxyConfig {
+            listeners: None,
+            ..self.clone()
+        };
+        let extra: Vec<ProxyConfig> = self
+            .listeners
+            .iter()
+            .flatten()
+            .map(|l| ProxyConfig {
+                listen_port: l.listen_port,
+                services: l.services.clone(),
+                ..primary.clone()
+            })
+            .collect();
+        let mut configs: Vec<ProxyConfig> = std::iter::once(primary).chain(extra).collect();
+        configs.iter_mut().for_each(env_vars::apply);
+        if safe_mode::enabled() {
+            configs.iter_mut().for_each(safe_mode::strip);
+        }
+        configs
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ListenerConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerConfig {
+    pub listen_port: u16,
+    pub services: Vec<ServiceConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -63,8 +352,13 @@ pub struct ProxyLogEntry {
     pub listen_port: u16,
     pub route_key: Option<String>,
     pub upstream_label: Option<String>,
+    #[serde(default)]
+    pub upstream_id: Option<String>,
     pub service_name: Option<String>,
     pub base_path: Option<String>,
+    /// 请求的模型名，从 JSON 请求体或 Gemini 风格路径中识别
+    #[serde(default)]
+    pub model: Option<String>,
     pub status: Option<u16>,
     #[ts(type = "number")]
     pub duration_ms: u128,
@@ -76,9 +370,70 @@ pub struct ProxyLogEntry {
     pub response_body: Option<String>,
     pub client_ip: Option<String>,
     pub is_streaming: bool,
+    /// 上游错误的归一化分类
+    #[serde(default)]
+    pub error_kind: Option<ErrorKind>,
+    /// 从响应中解析出的 token 用量
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// 按价格表估算的本次请求费用
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// chat 类请求所属的对话标识，可用于导出完整对话
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// 上游开启审计时，实际发出的完整请求报文（凭证已遮盖）
+    #[serde(default)]
+    pub outbound_request: Option<String>,
+    /// 请求处理过程的事件时间线
+    #[serde(default)]
+    pub timeline: Vec<TimelineEvent>,
+    /// 开启端到端校验时的请求体 / 响应体摘要及比对结果
+    #[serde(default)]
+    pub checksum: Option<ChecksumReport>,
+    /// 单调递增的日志序号，时间戳相同时用于排序
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub seq: u64,
+    /// 本次尝试的唯一标识：请求 ID 加尝试序号（从 1 开始）
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// 由响应缓存直接返回，未发往上游
+    #[serde(default)]
+    pub cache_hit: bool,
+    /// 与同时到达的相同请求合并，只向上游转发了一次
+    #[serde(default)]
+    pub deduplicated: bool,
+    /// 客户端未指定模型，已按服务配置填入默认模型
+    #[serde(default)]
+    pub default_model_applied: bool,
+    /// 影子流量的复制请求，响应未返回给客户端
+    #[serde(default)]
+    pub shadow: bool,
+    /// 对比模式下对比上游的响应与差异，只出现在关联的对比日志中
+    #[serde(default)]
+    pub compare: Option<CompareReport>,
+    /// 请求中的图片数量与大小，记录的请求体中以摘要代替 base64 内容
+    #[serde(default)]
+    pub images: Option<ImageSummary>,
+    /// 客户端请求流式响应，上游却返回了完整的 JSON（服务开启转换时已转为 SSE 返回）
+    #[serde(default)]
+    pub stream_mismatch: bool,
+    /// 转发前遮盖的敏感信息数量
+    #[serde(default)]
+    pub pii_masked: Option<usize>,
+    /// 按自定义维度规则取出的值
+    #[serde(default)]
+    pub dimension: Option<String>,
+    /// 有尝试因故障演练被模拟为上游不可用
+    #[serde(default)]
+    pub drill: bool,
+    /// 按 User-Agent 识别出的客户端工具
+    #[serde(default)]
+    pub client_app: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../src/types/generated/ServiceConfig.ts")]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceConfig {
@@ -87,9 +442,151 @@ pub struct ServiceConfig {
     pub base_path: String,
     pub enabled: bool,
     pub upstreams: Vec<UpstreamEntry>,
+    /// 是否记录请求/响应体，默认开启，是下面两个开关的默认值；关闭后请求体与响应体直接透传不做拷贝。
+    /// 两个开关同样决定对比报告与审计报文中是否包含请求/响应体，以及延迟重试任务是否落盘
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub capture_bodies: Option<bool>,
+    /// 单独控制是否记录请求体，未设置时跟随 captureBodies；关闭后日志只保留请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub log_request_body: Option<bool>,
+    /// 单独控制是否记录响应体，未设置时跟随 captureBodies；关闭后日志只保留响应头与状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub log_response_body: Option<bool>,
+    /// 维护模式：暂停期间该服务的请求不再转发，直接返回 503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub paused: Option<bool>,
+    /// 暂停期间返回的 JSON 响应体，未设置时返回默认提示
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub paused_response: Option<String>,
+    /// 该服务下所有上游的默认超时，上游可单独覆盖
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub timeouts: Option<TimeoutConfig>,
+    /// 在本地应答 OPTIONS（CORS 预检）与 HEAD 请求，不转发给上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub answer_locally: Option<bool>,
+    /// 流式响应的识别规则，未设置时使用默认规则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub streaming: Option<StreamingDetection>,
+    /// 自定义的重试 / 切换条件，按顺序匹配，命中时优先于按错误分类得出的处理方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub retry_rules: Option<Vec<RetryRule>>,
+    /// 相同请求在有效期内直接返回缓存的成功响应，默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub cache: Option<ResponseCacheConfig>,
+    /// 合并同时到达的相同非流式请求，只转发一次并共享响应，默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub dedupe_in_flight: Option<bool>,
+    /// 客户端请求体未指定 `model` 时填入的模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub default_model: Option<String>,
+    /// 影子流量：按比例把请求异步复制给影子上游，响应丢弃但记录日志
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub mirror: Option<MirrorConfig>,
+    /// 选择上游的方式，默认按优先级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub routing: Option<RoutingMode>,
+    /// 对比模式：同时请求两个上游并记录响应差异，仅用于调试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub compare: Option<CompareConfig>,
+    /// 单张内联图片解码后的大小上限（字节），超过时返回 413
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_image_bytes: Option<u64>,
+    /// 上游返回上下文超长错误时缩减请求并重试一次
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub context_overflow: Option<ContextOverflowRetry>,
+    /// 去掉 base path 后按顺序应用的路径改写规则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub path_rewrites: Option<Vec<PathRewriteRule>>,
+    /// 上游以内容策略拒绝时改用的备用上游 id（如本地模型），该上游不参与正常的重试与切换
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub policy_fallback_upstream_id: Option<String>,
+    /// 发往该服务所有上游时附加的固定请求头，上游配置的同名请求头优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub headers: Option<HashMap<String, String>>,
+    /// 转发前从客户端请求中删除的请求头，如 x-forwarded-for、内部追踪头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub blocked_request_headers: Option<Vec<String>>,
+    /// 返回客户端前从上游响应中删除的响应头，如 set-cookie
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub blocked_response_headers: Option<Vec<String>>,
+    /// 请求体大小上限（字节），超过时返回 413，未设置时不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_request_bytes: Option<u64>,
+    /// 指定路径在所有上游都失败时加入延迟重试队列，稍后自动重新提交
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub retry_queue: Option<RetryQueueConfig>,
+    /// 允许 / 禁止使用的模型，不符合的请求返回 403
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub model_filter: Option<ModelFilter>,
+    /// 转发前遮盖请求体中的敏感信息（邮箱、电话、API key 或自定义正则）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub pii_mask: Option<PiiMaskConfig>,
+    /// 禁用时段：按本地时间拒绝该服务的请求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub access_schedule: Option<AccessSchedule>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+impl ServiceConfig {
+    pub fn captures_bodies(&self) -> bool {
+        self.capture_bodies.unwrap_or(true)
+    }
+
+    pub fn logs_request_body(&self) -> bool {
+        self.log_request_body.unwrap_or_else(|| self.captures_bodies())
+    }
+
+    pub fn logs_response_body(&self) -> bool {
+        self.log_response_body.unwrap_or_else(|| self.captures_bodies())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.unwrap_or(false)
+    }
+
+    pub fn answers_locally(&self) -> bool {
+        self.answer_locally.unwrap_or(false)
+    }
+
+    pub fn dedupes_in_flight(&self) -> bool {
+        self.dedupe_in_flight.unwrap_or(false)
+    }
+
+    pub fn upstream_mut(&mut self, upstream_id: &str) -> Result<&mut UpstreamEntry, String> {
+        self.upstreams
+            .iter_mut()
+            .find(|u| u.id == upstream_id)
+            .ok_or_else(|| format!("未找到上游 {upstream_id}"))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../src/types/generated/UpstreamEntry.ts")]
 #[serde(rename_all = "camelCase")]
 pub struct UpstreamEntry {
@@ -99,6 +596,86 @@ pub struct UpstreamEntry {
     pub api_key: Option<String>,
     pub priority: u32,
     pub enabled: bool,
+    /// 每分钟请求数 / token 数上限，发送前按此排队
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// 发往该上游时使用的 User-Agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub user_agent: Option<String>,
+    /// 发往该上游时附加的固定请求头，覆盖客户端同名请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub headers: Option<HashMap<String, String>>,
+    /// 审计模式：在日志中保存实际发往该上游的完整请求报文（不截断、不脱敏，仅遮盖凭证）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub audit_outbound: Option<bool>,
+    /// 覆盖服务级别的超时设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub timeouts: Option<TimeoutConfig>,
+    /// 备注，例如该 key 所属的账号或付款卡
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub notes: Option<String>,
+    /// 界面中标记用的颜色，`#rgb` 或 `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub color: Option<String>,
+    /// 界面中用于筛选的标签
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub tags: Option<Vec<String>>,
+    /// 首次保存的时间，保存配置时自动填写
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub created_at: Option<String>,
+    /// 最近一次确认该上游可用（或轮换 key）的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub last_verified_at: Option<String>,
+    /// API key 的到期时间，可以只写日期（YYYY-MM-DD）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub key_expires_at: Option<String>,
+    /// 定期查询该上游账户的剩余额度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub balance: Option<BalanceConfig>,
+    /// 所属服务商，对应能力表中的名称；路由时跳过不支持该请求的上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub provider: Option<String>,
+    /// 按权重分流时的权重，默认 1；为 0 时只在其他上游失败后使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub weight: Option<u32>,
+    /// 模拟上游：不发起网络请求，直接返回配置的响应
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub mock: Option<MockUpstream>,
+    /// 故障注入：额外延迟、按比例返回错误或中途断开，用于测试客户端的重试逻辑
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub chaos: Option<ChaosConfig>,
+    /// 上游对 response_format 的支持程度，决定如何改写结构化输出请求，默认原样转发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub structured_output: Option<StructuredOutputMode>,
+    /// 发往该上游时按顺序应用的查询参数规则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub query_params: Option<Vec<QueryParamRule>>,
+    /// 发送时使用的 Host 与 TLS SNI，连接地址仍取自 upstream_base，用于直连 IP 或绕过 CDN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub host_override: Option<String>,
+    /// 发往该上游时改写请求体中的模型名，键为客户端请求的模型，值为上游实际的模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub model_map: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
@@ -115,33 +692,101 @@ pub struct UpstreamStats {
     pub error_count: u64,
     #[ts(type = "number")]
     pub total_duration_ms: u64,
+    #[ts(type = "number")]
+    pub min_duration_ms: u64,
+    #[ts(type = "number")]
+    pub max_duration_ms: u64,
+    /// 延迟分位数，基于对数分桶直方图估算
+    #[ts(type = "number")]
+    pub p50_ms: u64,
+    #[ts(type = "number")]
+    pub p95_ms: u64,
+    #[ts(type = "number")]
+    pub p99_ms: u64,
+    /// 最近 1 分钟 / 5 分钟 / 1 小时的滚动统计
+    pub windows: Vec<WindowStats>,
+    /// 配置了速率上限的上游的调度状态
+    pub scheduler: Option<SchedulerStats>,
+    #[ts(type = "number")]
+    pub prompt_tokens: u64,
+    #[ts(type = "number")]
+    pub completion_tokens: u64,
+    #[ts(type = "number")]
+    pub total_tokens: u64,
+    /// 按价格表估算的累计费用
+    pub total_cost: f64,
+    /// 上游已从配置中删除，统计仅作历史保留
+    #[serde(default)]
+    pub archived: bool,
+    /// 按权重分流时配置的权重占比（0.0 ~ 1.0）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub weight_share: Option<f64>,
+    /// 按权重分流时实际承接的请求占比（0.0 ~ 1.0）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub traffic_share: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/WindowStats.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct WindowStats {
+    #[ts(type = "number")]
+    pub window_secs: u64,
+    #[ts(type = "number")]
+    pub requests: u64,
+    #[ts(type = "number")]
+    pub errors: u64,
+    pub requests_per_minute: f64,
+    /// 0.0 ~ 1.0
+    pub error_rate: f64,
+    #[ts(type = "number")]
+    pub avg_duration_ms: u64,
+    #[ts(type = "number")]
+    pub max_duration_ms: u64,
 }
 
 #[derive(Clone)]
 struct SharedState {
-    config: Arc<RwLock<ProxyConfig>>,
-    client: Arc<RwLock<reqwest::Client>>,
+    config: Arc<ArcSwap<ProxyConfig>>,
+    client: Arc<ArcSwap<reqwest::Client>>,
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
+    stats: Arc<StatsStore>,
+    /// 监听开始的时间，用于状态接口中的运行时长
+    started_at: Instant,
 }
 
 struct RunningServer {
     shutdown: oneshot::Sender<()>,
     join: tauri::async_runtime::JoinHandle<()>,
-    config: Arc<RwLock<ProxyConfig>>,
+    config: Arc<ArcSwap<ProxyConfig>>,
 }
 
 struct ProxyState {
     inner: Mutex<HashMap<u16, RunningServer>>,
-    client: Arc<RwLock<reqwest::Client>>,
+    client: Arc<ArcSwap<reqwest::Client>>,
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
+    stats: Arc<StatsStore>,
     config: Arc<RwLock<Option<ProxyConfig>>>,
 }
 
-fn build_client(proxy_url: Option<&str>) -> Result<reqwest::Client, String> {
+fn build_client(
+    proxy_url: Option<&str>,
+    timeouts: &TimeoutConfig,
+    host_override: Option<&HostOverride>,
+) -> Result<reqwest::Client, String> {
     let mut builder = reqwest::Client::builder()
         .timeout(Duration::from_secs(600));
+    if let Some(host_override) = host_override {
+        builder = builder.dns_resolver(Arc::new(OverrideResolver(host_override.clone())));
+    }
+    if let Some(timeout) = timeouts.connect() {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = timeouts.read() {
+        builder = builder.read_timeout(timeout);
+    }
 
     if let Some(url) = proxy_url {
         if !url.trim().is_empty() {
@@ -156,53 +801,86 @@ fn build_client(proxy_url: Option<&str>) -> Result<reqwest::Client, String> {
 
 impl ProxyState {
     fn new() -> Self {
-        let client = build_client(None).expect("reqwest client");
+        let client = build_client(None, &TimeoutConfig::default(), None).expect("reqwest client");
 
         Self {
             inner: Mutex::new(HashMap::new()),
-            client: Arc::new(RwLock::new(client)),
-            logs: Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOGS))),
-            stats: Arc::new(Mutex::new(HashMap::new())),
+            client: Arc::new(ArcSwap::from_pointee(client)),
+            logs: Arc::new(Mutex::new(VecDeque::with_capacity(DEFAULT_MAX_LOGS))),
+            stats: Arc::new(StatsStore::default()),
             config: Arc::new(RwLock::new(None)),
         }
     }
 }
 
-#[tauri::command]
-async fn start_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) -> Result<(), String> {
-    // 拒绝空服务，后续校验以避免运行时 crash
-    if !(1..=65535).contains(&config.listen_port) {
-        return Err("listen_port 无效".into());
-    }
+/// 服务下各上游引用的服务商名称
+fn upstream_providers<'a>(services: impl Iterator<Item = &'a ServiceConfig>) -> impl Iterator<Item = &'a str> {
+    services
+        .flat_map(|svc| &svc.upstreams)
+        .filter_map(|u| u.provider.as_deref())
+        .filter(|p| !p.trim().is_empty())
+}
 
-    if config.services.is_empty() {
+/// 清洗前端提交的服务配置：去除空白、规范 base_path、丢弃无效上游并按优先级排序
+fn normalize_services(services: Vec<ServiceConfig>) -> Result<Vec<ServiceConfig>, String> {
+    if services.is_empty() {
         return Err("至少需要配置一个服务端".into());
     }
 
-    let mut services: Vec<ServiceConfig> = config
-        .services
+    let mut services: Vec<ServiceConfig> = services
         .into_iter()
         .map(|svc| ServiceConfig {
-            id: svc.id,
             name: svc.name.trim().to_string(),
             base_path: normalize_base_path(&svc.base_path),
-            enabled: svc.enabled,
             upstreams: svc
                 .upstreams
                 .into_iter()
                 .map(|u| UpstreamEntry {
-                    id: u.id,
                     label: u
                         .label
                         .map(|s| s.trim().to_string())
                         .filter(|s| !s.is_empty()),
                     upstream_base: u.upstream_base.trim().trim_end_matches('/').to_string(),
                     api_key: u.api_key.clone().filter(|s| !s.trim().is_empty()),
-                    priority: u.priority,
-                    enabled: u.enabled,
+                    user_agent: u
+                        .user_agent
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty()),
+                    notes: u.notes.filter(|s| !s.trim().is_empty()),
+                    color: u
+                        .color
+                        .map(|s| s.trim().to_ascii_lowercase())
+                        .filter(|s| !s.is_empty()),
+                    tags: u
+                        .tags
+                        .map(|tags| {
+                            let mut seen = HashSet::new();
+                            tags.into_iter()
+                                .map(|t| t.trim().to_string())
+                                .filter(|t| !t.is_empty() && seen.insert(t.clone()))
+                                .collect::<Vec<_>>()
+                        })
+                        .filter(|tags| !tags.is_empty()),
+                    created_at: Some(
+                        u.created_at
+                            .as_deref()
+                            .map(timestamp::normalize)
+                            .unwrap_or_else(timestamp::now),
+                    ),
+                    last_verified_at: u.last_verified_at.as_deref().map(timestamp::normalize),
+                    provider: u
+                        .provider
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty()),
+                    ..u
                 })
-                .filter(|u| !u.upstream_base.is_empty())
+                .filter(|u| !u.upstream_base.is_empty() || u.mock.is_some())
                 .collect(),
+            default_model: svc
+                .default_model
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            ..svc
         })
         .collect();
 
@@ -213,61 +891,215 @@ async fn start_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) ->
     }
 
     for svc in services.iter_mut() {
+        if let Some(body) = &svc.paused_response {
+            serde_json::from_str::<serde_json::Value>(body)
+                .map_err(|_| format!("服务 {} 的暂停响应不是有效的 JSON", svc.name))?;
+        }
+        if let Some(timeouts) = &svc.timeouts {
+            timeouts.validate()?;
+        }
+        if let Some(streaming) = &svc.streaming {
+            streaming.validate()?;
+        }
+        if let Some(rules) = &svc.retry_rules {
+            validate_retry_rules(rules)?;
+        }
+        if let Some(cache) = &svc.cache {
+            cache.validate()?;
+        }
+        if let Some(mirror) = &svc.mirror {
+            mirror.validate(&svc.upstreams)?;
+        }
+        split::validate_weights(svc)?;
+        if let Some(compare) = &svc.compare {
+            compare.validate(&svc.upstreams)?;
+            if !svc.logs_response_body() {
+                return Err(format!("服务 {} 未记录响应体，无法开启对比模式", svc.name));
+            }
+        }
+        if svc.max_image_bytes == Some(0) {
+            return Err(format!("服务 {} 的图片大小上限必须大于 0", svc.name));
+        }
+        if svc.max_request_bytes == Some(0) {
+            return Err(format!("服务 {} 的请求体大小上限必须大于 0", svc.name));
+        }
+        if let Some(queue) = &svc.retry_queue {
+            queue.validate()?;
+        }
+        if let Some(filter) = &svc.model_filter {
+            filter.validate()?;
+        }
+        if let Some(mask) = &svc.pii_mask {
+            mask.validate()?;
+        }
+        if let Some(schedule) = &svc.access_schedule {
+            schedule.validate()?;
+        }
+        if let Some(policy) = &svc.context_overflow {
+            policy.validate()?;
+        }
+        for rule in svc.path_rewrites.iter().flatten() {
+            rule.validate()?;
+        }
+        if let Some(id) = &svc.policy_fallback_upstream_id {
+            if !svc.upstreams.iter().any(|u| &u.id == id) {
+                return Err(format!("服务 {} 的内容策略备用上游不存在: {id}", svc.name));
+            }
+        }
+        identity_headers(None, svc.headers.as_ref())?;
+        header_names(svc.blocked_request_headers.as_deref())?;
+        header_names(svc.blocked_response_headers.as_deref())?;
         svc.upstreams.sort_by_key(|u| u.priority);
+        for upstream in &svc.upstreams {
+            if let Some(limit) = &upstream.rate_limit {
+                limit.validate()?;
+            }
+            identity_headers(upstream.user_agent.as_deref(), upstream.headers.as_ref())?;
+            if let Some(timeouts) = &upstream.timeouts {
+                timeouts.validate()?;
+            }
+            validate_upstream_metadata(upstream)?;
+            if let Some(mock) = &upstream.mock {
+                mock.validate()?;
+            }
+            if let Some(chaos) = &upstream.chaos {
+                chaos.validate()?;
+            }
+            for rule in upstream.query_params.iter().flatten() {
+                rule.validate()?;
+            }
+            if let Some(host) = &upstream.host_override {
+                host_override::validate(host)?;
+            }
+        }
     }
 
-    let proxy_url = config.proxy_url.clone().filter(|s| !s.trim().is_empty());
-    let fallback_retries = config.fallback_retries.min(MAX_FALLBACK_RETRIES);
-
-    let config = ProxyConfig {
-        listen_port: config.listen_port,
-        global_key: config.global_key.clone().filter(|s| !s.trim().is_empty()),
-        proxy_url: proxy_url.clone(),
-        fallback_retries,
-        services,
-    };
+    Ok(services)
+}
 
-    let new_client = build_client(proxy_url.as_deref())?;
-    {
-        let mut client_guard = state.client.write().await;
-        *client_guard = new_client;
+fn validate_upstream_metadata(upstream: &UpstreamEntry) -> Result<(), String> {
+    if let Some(color) = &upstream.color {
+        let hex = color.strip_prefix('#').unwrap_or("");
+        if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("上游 {} 的颜色无效: {color}", upstream.id));
+        }
     }
-    {
-        let mut cfg_guard = state.config.write().await;
-        *cfg_guard = Some(config.clone());
+    for (name, value) in [
+        ("创建时间", &upstream.created_at),
+        ("最近验证时间", &upstream.last_verified_at),
+    ] {
+        if value.as_deref().is_some_and(|t| timestamp::parse(t).is_none()) {
+            return Err(format!("上游 {} 的{name}无效", upstream.id));
+        }
     }
-
-    if let Err(err) = save_config(&config) {
-        eprintln!("配置持久化失败: {err}");
+    if let Some(balance) = &upstream.balance {
+        balance.validate()?;
     }
-
-    // Stop existing server if running
-    {
-        let mut guard = state.inner.lock().await;
-        if let Some(existing) = guard.remove(&config.listen_port) {
-            let _ = existing.shutdown.send(());
-            let _ = existing.join.await;
-            finalize_inflight(state.logs.clone(), Some(config.listen_port)).await;
+    if let Some(expires_at) = &upstream.key_expires_at {
+        if key_expiry::parse_expiry(expires_at).is_none() {
+            return Err(format!("上游 {} 的 key 到期时间无效: {expires_at}", upstream.id));
         }
     }
+    Ok(())
+}
 
-    let (shutdown_tx, shutdown_rx) = oneshot::channel();
-    let config_arc = Arc::new(RwLock::new(config.clone()));
-    let shared = SharedState {
-        config: config_arc.clone(),
-        client: state.client.clone(),
-        logs: state.logs.clone(),
-        stats: state.stats.clone(),
+/// 校验额外监听端口互不重复且不与主端口冲突，并清洗各自的服务配置
+fn normalize_listeners(
+    listen_port: u16,
+    listeners: Option<Vec<ListenerConfig>>,
+) -> Result<Option<Vec<ListenerConfig>>, String> {
+    let Some(listeners) = listeners.filter(|l| !l.is_empty()) else {
+        return Ok(None);
     };
+    let mut ports = HashSet::from([listen_port]);
+    listeners
+        .into_iter()
+        .map(|l| {
+            if l.listen_port == 0 {
+                return Err("listen_port 无效".into());
+            }
+            if !ports.insert(l.listen_port) {
+                return Err(format!("监听端口 {} 重复", l.listen_port));
+            }
+            let services =
+                normalize_services(l.services).map_err(|e| format!("端口 {}: {e}", l.listen_port))?;
+            Ok(ListenerConfig { services, ..l })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Some)
+}
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.listen_port));
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .map_err(|e| format!("监听端口失败: {e}"))?;
+/// 校验服务之外的各项全局配置；启动、保存与热重载共用，新增配置项只需在这里校验
+fn validate_proxy_config(config: &ProxyConfig) -> Result<(), String> {
+    if let Some(rules) = &config.redaction {
+        rules.validate()?;
+    }
+    if let Some(retention) = &config.retention {
+        retention.validate()?;
+    }
+    if let Some(sink) = &config.stream_tee {
+        sink.validate()?;
+    }
+    if let Some(pricing) = &config.pricing {
+        validate_pricing(pricing)?;
+    }
+    if let Some(storage) = &config.log_storage {
+        storage.validate()?;
+    }
+    if let Some(tokens) = &config.admin_tokens {
+        validate_admin_tokens(tokens)?;
+    }
+    if let Some(budgets) = &config.budgets {
+        validate_budgets(budgets)?;
+    }
+    if let Some(tracing) = &config.tracing {
+        tracing.validate()?;
+    }
+    if let Some(endpoints) = &config.synthetic_endpoints {
+        validate_synthetic_endpoints(endpoints)?;
+    }
+    if let Some(backoff) = &config.backoff {
+        backoff.validate()?;
+    }
+    if let Some(limit) = &config.request_rate_limit {
+        limit.validate()?;
+    }
+    if let Some(summary) = &config.daily_summary {
+        summary.validate()?;
+    }
+    if let Some(cors) = &config.cors {
+        cors.validate()?;
+    }
+    if config.max_client_timeout_ms == Some(0) {
+        return Err("客户端超时上限必须大于 0".into());
+    }
+    if let Some(hooks) = &config.hooks {
+        validate_hooks(hooks)?;
+    }
+    if let Some(dimension) = &config.custom_dimension {
+        dimension.validate()?;
+    }
+    if let Some(bridge) = &config.stream_bridge {
+        bridge.validate()?;
+    }
+    if let Some(status) = &config.provider_status {
+        status.validate()?;
+    }
+    if let Some(api) = &config.admin_api {
+        api.validate(&config.listener_ports(), config.admin_tokens.as_deref())?;
+    }
+    validate_capabilities(
+        config.provider_capabilities.as_deref().unwrap_or_default(),
+        upstream_providers(config.all_services()),
+    )?;
+    Ok(())
+}
 
-    let router = Router::new()
-        .fallback(any(proxy_handler))
-        .with_state(shared);
+/// 在已绑定的端口上启动代理服务
+fn spawn_server(listener: tokio::net::TcpListener, shared: SharedState) -> RunningServer {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let config = shared.config.clone();
+    let router = build_router(shared);
 
     let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).with_graceful_shutdown(async move {
         let _ = shutdown_rx.await;
@@ -279,14 +1111,148 @@ async fn start_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) ->
         }
     });
 
+    RunningServer {
+        shutdown: shutdown_tx,
+        join,
+        config,
+    }
+}
+
+/// 按配置调整运行中的监听端口：新增端口全部绑定成功后才切换，任一失败则保持原状；
+/// 不再使用的端口停止接受新连接，进行中的请求处理完后再退出
+async fn switch_listeners(
+    state: &ProxyState,
+    running: &mut HashMap<u16, RunningServer>,
+    config: &ProxyConfig,
+) -> Result<(), String> {
+    let mut bound = Vec::new();
+    let mut updated = Vec::new();
+    for listener_config in config.per_listener() {
+        let port = listener_config.listen_port;
+        if running.contains_key(&port) {
+            updated.push(listener_config);
+            continue;
+        }
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("监听端口 {port} 失败: {e}"))?;
+        bound.push((listener_config, listener));
+    }
+
+    // 全部绑定成功后才让已有端口使用新配置，避免绑定失败时部分端口已切换
+    for listener_config in updated {
+        if let Some(server) = running.get(&listener_config.listen_port) {
+            server.config.store(Arc::new(listener_config));
+        }
+    }
+
+    let started_at = Instant::now();
+    for (listener_config, listener) in bound {
+        let port = listener_config.listen_port;
+        let shared = SharedState {
+            config: Arc::new(ArcSwap::from_pointee(listener_config)),
+            client: state.client.clone(),
+            logs: state.logs.clone(),
+            stats: state.stats.clone(),
+            started_at,
+        };
+        running.insert(port, spawn_server(listener, shared));
+    }
+
+    let ports = config.listener_ports();
+    let retired: Vec<u16> = running.keys().copied().filter(|p| !ports.contains(p)).collect();
+    for port in retired {
+        if let Some(server) = running.remove(&port) {
+            let _ = server.shutdown.send(());
+            let logs = state.logs.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = server.join.await;
+                finalize_inflight(logs, Some(port)).await;
+            });
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) -> Result<(), String> {
+    // 拒绝空服务，后续校验以避免运行时 crash
+    if !(1..=65535).contains(&config.listen_port) {
+        return Err("listen_port 无效".into());
+    }
+
+    env_vars::check(&config)?;
+    let services = normalize_services(config.services)?;
+    let listeners = normalize_listeners(config.listen_port, config.listeners)?;
+
+    let proxy_url = config.proxy_url.clone().filter(|s| !s.trim().is_empty());
+    let fallback_retries = config.fallback_retries.min(MAX_FALLBACK_RETRIES);
+
+    let config = ProxyConfig {
+        global_key: config.global_key.clone().filter(|s| !s.trim().is_empty()),
+        proxy_url: proxy_url.clone(),
+        fallback_retries,
+        services,
+        listeners,
+        ..config
+    };
+    validate_proxy_config(&config)?;
+
+    let client_proxy = proxy_url.as_deref().map(env_vars::expand).transpose()?;
+    let new_client = build_client(client_proxy.as_deref(), &TimeoutConfig::default(), None)?;
+    state.client.store(Arc::new(new_client));
+    apply_retention(config.retention.as_ref());
+    storage::configure(config.log_storage.as_ref())?;
+    telemetry::configure(config.tracing.as_ref())?;
+    admin_api::configure(config.admin_api.as_ref(), config.admin_tokens.as_deref())?;
+    {
+        let mut cfg_guard = state.config.write().await;
+        *cfg_guard = Some(config.clone());
+    }
+
+    if let Err(err) = save_config(&config) {
+        eprintln!("配置持久化失败: {err}");
+    }
+
+    // 整组监听端口一起启动：先停止所有运行中的端口，任一端口绑定失败则全部不启动
     let mut guard = state.inner.lock().await;
-    guard.insert(
-        config.listen_port,
-        RunningServer {
-            shutdown: shutdown_tx,
-            join,
-            config: config_arc,
-        },
+    for (port, existing) in guard.drain() {
+        let _ = existing.shutdown.send(());
+        let _ = existing.join.await;
+        finalize_inflight(state.logs.clone(), Some(port)).await;
+    }
+
+    let mut bound = Vec::new();
+    for listener_config in config.per_listener() {
+        let port = listener_config.listen_port;
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => bound.push((listener_config, listener)),
+            Err(e) => {
+                events::emit_proxy_status(Vec::new());
+                return Err(format!("监听端口 {port} 失败: {e}"));
+            }
+        }
+    }
+
+    let started_at = Instant::now();
+    for (listener_config, listener) in bound {
+        let port = listener_config.listen_port;
+        let shared = SharedState {
+            config: Arc::new(ArcSwap::from_pointee(listener_config)),
+            client: state.client.clone(),
+            logs: state.logs.clone(),
+            stats: state.stats.clone(),
+            started_at,
+        };
+        guard.insert(port, spawn_server(listener, shared));
+    }
+    reconcile_stats(&state.stats, &config, &guard);
+    events::emit_proxy_status(guard.keys().copied().collect());
+    hooks::fire(
+        config.hooks.as_deref(),
+        HookPayload::ports(HookEvent::ProxyStarted, guard.keys().copied().collect()),
     );
 
     Ok(())
@@ -297,12 +1263,28 @@ async fn stop_proxy(
     listen_port: Option<u16>,
     state: TauriState<'_, ProxyState>,
 ) -> Result<(), String> {
+    // 指定端口属于当前配置时，停止该配置的整组监听端口
+    let ports = match listen_port {
+        Some(port) => Some(
+            state
+                .config
+                .read()
+                .await
+                .as_ref()
+                .map(ProxyConfig::listener_ports)
+                .filter(|ports| ports.contains(&port))
+                .unwrap_or_else(|| vec![port]),
+        ),
+        None => None,
+    };
     let mut guard = state.inner.lock().await;
-    if let Some(port) = listen_port {
-        if let Some(running) = guard.remove(&port) {
-            let _ = running.shutdown.send(());
-            let _ = running.join.await;
-            finalize_inflight(state.logs.clone(), Some(port)).await;
+    if let Some(ports) = ports {
+        for port in ports {
+            if let Some(running) = guard.remove(&port) {
+                let _ = running.shutdown.send(());
+                let _ = running.join.await;
+                finalize_inflight(state.logs.clone(), Some(port)).await;
+            }
         }
     } else {
         let servers: Vec<_> = guard.drain().collect();
@@ -312,6 +1294,12 @@ async fn stop_proxy(
         }
         finalize_inflight(state.logs.clone(), None).await;
     };
+    events::emit_proxy_status(guard.keys().copied().collect());
+    let hooks = state.config.read().await.as_ref().and_then(|c| c.hooks.clone());
+    hooks::fire(
+        hooks.as_deref(),
+        HookPayload::ports(HookEvent::ProxyStopped, guard.keys().copied().collect()),
+    );
     Ok(())
 }
 
@@ -319,10 +1307,15 @@ async fn stop_proxy(
 async fn get_logs(
     limit: Option<usize>,
     listen_port: Option<u16>,
+    filter: Option<LogFilter>,
+    before_id: Option<String>,
+    after_id: Option<String>,
     state: TauriState<'_, ProxyState>,
-) -> Result<Vec<ProxyLogEntry>, String> {
+) -> Result<LogPage, String> {
     let guard = state.logs.lock().await;
-    let max = limit.unwrap_or(MAX_LOGS).min(MAX_LOGS);
+    let max_logs = logging::max_entries();
+    let max = limit.unwrap_or(max_logs).min(max_logs);
+    let filter = filter.unwrap_or_default();
     let filtered: Vec<_> = guard
         .iter()
         .filter(|entry| {
@@ -330,33 +1323,306 @@ async fn get_logs(
                 .map(|lp| lp == entry.listen_port)
                 .unwrap_or(true)
         })
+        .filter(|entry| filter.matches(entry))
         .cloned()
         .collect();
-    let len = filtered.len();
-    let start = len.saturating_sub(max);
-    Ok(filtered.into_iter().skip(start).collect())
+    drop(guard);
+    paginate_logs(
+        filtered,
+        before_id.as_deref(),
+        after_id.as_deref(),
+        max,
+    )
+}
+
+/// 返回单条日志的完整内容（含事件时间线）
+#[tauri::command]
+async fn get_log_detail(log_id: String, state: TauriState<'_, ProxyState>) -> Result<ProxyLogEntry, String> {
+    let guard = state.logs.lock().await;
+    guard
+        .iter()
+        .find(|e| e.id == log_id)
+        .cloned()
+        .ok_or_else(|| "未找到对应的日志记录".to_string())
+}
+
+/// 导出日志中记录的完整对话，格式为 Markdown 或 JSON
+#[tauri::command]
+async fn export_conversation(
+    conversation_id: String,
+    format: ExportFormat,
+    state: TauriState<'_, ProxyState>,
+) -> Result<String, String> {
+    let conversation = {
+        let guard = state.logs.lock().await;
+        transcript::build_conversation(guard.iter(), &conversation_id)
+    }
+    .ok_or("未找到对应的对话记录")?;
+    transcript::export(&conversation, format)
+}
+
+/// 对账时从日志存储中最多读取的条数
+const RECONCILE_MAX_PERSISTED: usize = 200_000;
+
+/// 导入服务商导出的用量 CSV，按天与本地记录的用量和估算费用比对；
+/// 配置了日志存储时一并读取已持久化的日志
+#[tauri::command]
+async fn reconcile_usage(
+    csv: String,
+    upstream_ids: Option<Vec<String>>,
+    state: TauriState<'_, ProxyState>,
+) -> Result<UsageReconciliation, String> {
+    let storage_config = match state.config.read().await.as_ref() {
+        Some(config) => config.log_storage.clone(),
+        None => load_config()?.and_then(|c| c.log_storage),
+    };
+    let mut entries: HashMap<String, ProxyLogEntry> = tokio::task::spawn_blocking(move || {
+        storage::load_recent(storage_config.as_ref(), RECONCILE_MAX_PERSISTED)
+    })
+    .await
+    .map_err(|e| e.to_string())??
+    .into_iter()
+    .map(|e| (e.id.clone(), e))
+    .collect();
+    for entry in state.logs.lock().await.iter() {
+        entries.insert(entry.id.clone(), entry.clone());
+    }
+    reconcile::reconcile(&csv, entries.values(), upstream_ids.as_deref().unwrap_or_default())
+}
+
+#[tauri::command]
+async fn get_curl_command(
+    log_id: String,
+    target: CurlTarget,
+    reveal_key: Option<bool>,
+    state: TauriState<'_, ProxyState>,
+) -> Result<String, String> {
+    let entry = {
+        let guard = state.logs.lock().await;
+        guard.iter().find(|e| e.id == log_id).cloned()
+    }
+    .ok_or("未找到对应的日志记录")?;
+
+    let config = state.config.read().await.clone();
+    let key = match target {
+        CurlTarget::Proxy => config.and_then(|c| c.global_key),
+        // 日志中的凭证已脱敏，只能取配置中的 key；客户端透传 key 的上游不附带凭证
+        CurlTarget::Upstream => config.and_then(|c| {
+            let upstream_id = entry.upstream_id.as_deref()?;
+            let key = c
+                .all_services()
+                .flat_map(|svc| &svc.upstreams)
+                .find(|u| u.id == upstream_id)?
+                .api_key
+                .clone()?;
+            Some(env_vars::expand(&key).unwrap_or(key))
+        }),
+    };
+
+    Ok(build_curl_command(
+        &entry,
+        target,
+        key.as_deref(),
+        reveal_key.unwrap_or(false),
+    ))
 }
 
 #[tauri::command]
 async fn clear_logs(state: TauriState<'_, ProxyState>) -> Result<(), String> {
     let mut guard = state.logs.lock().await;
     guard.clear();
-    Ok(())
+    drop(guard);
+    tokio::task::spawn_blocking(storage::clear)
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
 async fn get_stats(state: TauriState<'_, ProxyState>) -> Result<Vec<UpstreamStats>, String> {
-    let guard = state.stats.lock().await;
-    Ok(guard.values().cloned().collect())
+    let mut stats = state.stats.snapshot();
+    if let Some(config) = state.config.read().await.as_ref() {
+        split::annotate_stats(&mut stats, config);
+    }
+    Ok(stats)
+}
+
+/// 按上游 / 服务 / 模型维度汇总统计
+#[tauri::command]
+async fn get_stats_breakdown(
+    group_by: StatsGroupBy,
+    state: TauriState<'_, ProxyState>,
+) -> Result<Vec<GroupStats>, String> {
+    Ok(state.stats.breakdown(group_by))
+}
+
+/// 按上游与服务汇总的累计估算费用
+#[tauri::command]
+async fn get_spend_summary(state: TauriState<'_, ProxyState>) -> Result<SpendSummary, String> {
+    Ok(state.stats.spend_summary())
+}
+
+/// 各预算规则在当前周期的用量
+#[tauri::command]
+async fn get_budget_status(state: TauriState<'_, ProxyState>) -> Result<Vec<BudgetStatus>, String> {
+    let config = state.config.read().await.clone();
+    let rules = config.and_then(|c| c.budgets).unwrap_or_default();
+    Ok(budget::tracker().status_at(&rules, Local::now()))
+}
+
+/// 各上游最近一次查询到的账户余额；refresh 为 true 时先立即查询一次
+#[tauri::command]
+async fn get_upstream_balances(
+    refresh: Option<bool>,
+    state: TauriState<'_, ProxyState>,
+) -> Result<Vec<UpstreamBalance>, String> {
+    if refresh.unwrap_or(false) {
+        let config = match state.config.read().await.clone() {
+            Some(config) => Some(config),
+            None => load_config()?,
+        };
+        if let Some(config) = config {
+            balance::poll_due(&state.client.load_full(), &config, true).await;
+        }
+    }
+    Ok(balance::snapshot())
+}
+
+/// 各服务商状态页上进行中的故障及受影响的上游；refresh 为 true 时先立即查询一次
+#[tauri::command]
+async fn get_provider_status(
+    refresh: Option<bool>,
+    state: TauriState<'_, ProxyState>,
+) -> Result<Vec<ProviderStatus>, String> {
+    if refresh.unwrap_or(false) {
+        let config = match state.config.read().await.clone() {
+            Some(config) => Some(config),
+            None => load_config()?,
+        };
+        if let Some(config) = config {
+            provider_status::poll_due(&state.client.load_full(), &config, true).await;
+        }
+    }
+    Ok(provider_status::snapshot())
+}
+
+/// 已过期或即将到期的 API key
+#[tauri::command]
+async fn get_key_expiry_status(state: TauriState<'_, ProxyState>) -> Result<Vec<KeyExpiryStatus>, String> {
+    let config = match state.config.read().await.clone() {
+        Some(config) => Some(config),
+        None => load_config()?,
+    };
+    Ok(config
+        .map(|c| key_expiry::statuses_at(&c, chrono::Utc::now()))
+        .unwrap_or_default())
+}
+
+/// 今天到目前为止的用量概览
+#[tauri::command]
+async fn get_daily_summary(state: TauriState<'_, ProxyState>) -> Result<DailySummary, String> {
+    Ok(daily_summary::current(&state.stats))
+}
+
+/// 应用自身的资源占用与超出阈值的提醒
+#[tauri::command]
+async fn get_app_metrics(state: TauriState<'_, ProxyState>) -> Result<AppMetrics, String> {
+    let storage_config = state.config.read().await.as_ref().and_then(|c| c.log_storage.clone());
+    let log_store_bytes =
+        tokio::task::spawn_blocking(move || storage::disk_usage(storage_config.as_ref()))
+            .await
+            .map_err(|e| e.to_string())?;
+    let logs = state.logs.lock().await;
+    Ok(app_metrics::collect(&logs, log_store_bytes))
+}
+
+/// 清空所有服务的响应缓存，返回清除的条数
+#[tauri::command]
+async fn clear_cache() -> Result<usize, String> {
+    Ok(response_cache::clear())
+}
+
+/// 是否以安全模式启动，界面据此提示扩展功能已停用
+#[tauri::command]
+fn get_safe_mode() -> bool {
+    safe_mode::enabled()
+}
+
+/// 开始故障演练：指定上游在 minutes 分钟内被模拟为不可用
+#[tauri::command]
+fn start_failover_drill(upstream_id: String, minutes: u64) -> Result<FailoverDrill, String> {
+    drill::start(&upstream_id, minutes)
+}
+
+/// 提前结束故障演练
+#[tauri::command]
+fn stop_failover_drill(upstream_id: String) -> bool {
+    drill::stop(&upstream_id)
+}
+
+/// 进行中的故障演练
+#[tauri::command]
+fn list_failover_drills() -> Vec<FailoverDrill> {
+    drill::active()
+}
+
+/// 延迟重试队列中的任务，最新的在前
+#[tauri::command]
+async fn get_retry_queue() -> Result<Vec<QueuedJob>, String> {
+    Ok(retry_queue::jobs())
+}
+
+/// 查询单个任务的状态与最终结果
+#[tauri::command]
+async fn get_queued_job(id: String) -> Result<QueuedJob, String> {
+    retry_queue::job(&id).ok_or_else(|| format!("任务不存在: {id}"))
+}
+
+/// 删除已完成或最终失败的任务，返回删除的数量
+#[tauri::command]
+async fn clear_finished_jobs() -> Result<usize, String> {
+    Ok(retry_queue::clear_finished())
 }
 
 #[tauri::command]
 async fn clear_stats(state: TauriState<'_, ProxyState>) -> Result<(), String> {
-    let mut guard = state.stats.lock().await;
-    guard.clear();
+    state.stats.clear();
     Ok(())
 }
 
+/// 删除已归档（上游已从配置中移除）的统计，返回删除数量
+#[tauri::command]
+async fn prune_archived_stats(state: TauriState<'_, ProxyState>) -> Result<usize, String> {
+    Ok(state.stats.prune_archived())
+}
+
+/// 把旧上游 id 的统计合并到新 id，用于无法按地址与名称自动匹配的重建上游
+#[tauri::command]
+async fn relink_upstream_stats(
+    from_id: String,
+    to_id: String,
+    state: TauriState<'_, ProxyState>,
+) -> Result<(), String> {
+    state.stats.relink_upstream(&from_id, &to_id)
+}
+
+/// 以当前配置与所有运行中端口的配置为准，找回重建上游的统计并归档已删除上游的统计
+fn reconcile_stats(stats: &StatsStore, config: &ProxyConfig, running: &HashMap<u16, RunningServer>) {
+    let running: Vec<Arc<ProxyConfig>> = running.values().map(|s| s.config.load_full()).collect();
+    let identities: Vec<UpstreamIdentity> = std::iter::once(config)
+        .chain(running.iter().map(|c| c.as_ref()))
+        .flat_map(|c| c.all_services())
+        .flat_map(|svc| &svc.upstreams)
+        .map(|u| UpstreamIdentity {
+            id: &u.id,
+            upstream_base: &u.upstream_base,
+            label: u.label.as_deref(),
+        })
+        .collect();
+    stats.match_identities(&identities);
+    let active: HashSet<&str> = identities.iter().map(|u| u.id).collect();
+    stats.reconcile_upstreams(&active);
+}
+
 #[tauri::command]
 async fn get_network_info() -> Result<NetworkInfo, String> {
     Ok(NetworkInfo {
@@ -376,18 +1642,49 @@ async fn load_settings() -> Result<Option<ProxyConfig>, String> {
     })
 }
 
+/// 把当前配置导出为 JSON 文件，include_secrets 为 false 时密钥替换为占位符
+#[tauri::command]
+async fn export_config(
+    path: String,
+    include_secrets: bool,
+    state: TauriState<'_, ProxyState>,
+) -> Result<(), String> {
+    let config = match state.config.read().await.clone() {
+        Some(config) => config,
+        None => load_config()?.ok_or("尚未保存任何配置")?,
+    };
+    config_transfer::export_to(path.as_ref(), &config, include_secrets)
+}
+
+/// 读取导出的配置文件，占位符沿用本机现有的密钥；返回的配置由界面确认后再保存
+#[tauri::command]
+async fn import_config(path: String, state: TauriState<'_, ProxyState>) -> Result<ConfigImport, String> {
+    let current = match state.config.read().await.clone() {
+        Some(config) => Some(config),
+        None => load_config()?,
+    };
+    config_transfer::import_from(path.as_ref(), current.as_ref())
+}
+
 #[tauri::command]
 async fn save_settings(config: ProxyConfig, state: TauriState<'_, ProxyState>) -> Result<(), String> {
     let mut config = config;
     config.fallback_retries = config.fallback_retries.min(MAX_FALLBACK_RETRIES);
+    validate_proxy_config(&config)?;
 
     save_config(&config)?;
-    
+    apply_retention(config.retention.as_ref());
+    storage::configure(config.log_storage.as_ref())?;
+    telemetry::configure(config.tracing.as_ref())?;
+    admin_api::configure(config.admin_api.as_ref(), config.admin_tokens.as_deref())?;
+
     let guard = state.inner.lock().await;
-    if let Some(server) = guard.get(&config.listen_port) {
-        let mut cfg_guard = server.config.write().await;
-        *cfg_guard = config.clone();
+    for listener_config in config.per_listener() {
+        if let Some(server) = guard.get(&listener_config.listen_port) {
+            server.config.store(Arc::new(listener_config));
+        }
     }
+    reconcile_stats(&state.stats, &config, &guard);
     {
         let mut cfg_guard = state.config.write().await;
         *cfg_guard = Some(config.clone());
@@ -395,81 +1692,153 @@ async fn save_settings(config: ProxyConfig, state: TauriState<'_, ProxyState>) -
     Ok(())
 }
 
-#[tauri::command]
-async fn reload_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) -> Result<(), String> {
-    // 不允许热切换端口
-    {
-        let guard = state.config.read().await;
-        if let Some(existing) = guard.as_ref() {
-            if existing.listen_port != config.listen_port {
-                return Err("监听端口已变更，请先停止服务后重启".into());
-            }
-        } else {
-            return Err("未找到运行中的配置，请先启动服务".into());
+/// 修改单个服务并持久化，运行中的监听端口立即生效，无需整体 reload
+async fn update_service(
+    state: &ProxyState,
+    service_id: &str,
+    update: impl FnOnce(&mut ServiceConfig) -> Result<(), String>,
+) -> Result<(), String> {
+    let running = state.inner.lock().await;
+    let mut current = state.config.write().await;
+    let mut config = match current.clone() {
+        Some(config) => config,
+        None => load_config()?.ok_or("尚未保存任何配置")?,
+    };
+    let service = config
+        .service_mut(service_id)
+        .ok_or_else(|| format!("未找到服务 {service_id}"))?;
+    update(service)?;
+
+    save_config(&config)?;
+    for listener_config in config.per_listener() {
+        if let Some(server) = running.get(&listener_config.listen_port) {
+            server.config.store(Arc::new(listener_config));
         }
     }
+    *current = Some(config);
+    Ok(())
+}
 
-    if config.services.is_empty() {
-        return Err("至少需要配置一个服务端".into());
-    }
+/// 切换服务的维护状态：保存配置并立即应用到运行中的端口，无需重载整个配置
+async fn set_service_paused(state: &ProxyState, service_id: &str, paused: bool) -> Result<(), String> {
+    update_service(state, service_id, |service| {
+        service.paused = paused.then_some(true);
+        Ok(())
+    })
+    .await
+}
 
-    let mut services: Vec<ServiceConfig> = config
-        .services
-        .into_iter()
-        .map(|svc| ServiceConfig {
-            id: svc.id,
-            name: svc.name.trim().to_string(),
-            base_path: normalize_base_path(&svc.base_path),
-            enabled: svc.enabled,
-            upstreams: svc
-                .upstreams
-                .into_iter()
-                .map(|u| UpstreamEntry {
-                    id: u.id,
-                    label: u
-                        .label
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty()),
-                    upstream_base: u.upstream_base.trim().trim_end_matches('/').to_string(),
-                    api_key: u.api_key.clone().filter(|s| !s.trim().is_empty()),
-                    priority: u.priority,
-                    enabled: u.enabled,
-                })
-                .filter(|u| !u.upstream_base.is_empty())
-                .collect(),
-        })
-        .collect();
+#[tauri::command]
+async fn pause_service(service_id: String, state: TauriState<'_, ProxyState>) -> Result<(), String> {
+    set_service_paused(&state, &service_id, true).await
+}
 
-    services.retain(|svc| !svc.upstreams.is_empty());
+#[tauri::command]
+async fn resume_service(service_id: String, state: TauriState<'_, ProxyState>) -> Result<(), String> {
+    set_service_paused(&state, &service_id, false).await
+}
 
-    if services.is_empty() {
-        return Err("有效的服务端配置为空".into());
-    }
+/// 临时停用 / 恢复单个上游，例如某个 key 持续报错时
+#[tauri::command]
+async fn set_upstream_enabled(
+    service_id: String,
+    upstream_id: String,
+    enabled: bool,
+    state: TauriState<'_, ProxyState>,
+) -> Result<(), String> {
+    update_service(&state, &service_id, |service| {
+        service.upstream_mut(&upstream_id)?.enabled = enabled;
+        Ok(())
+    })
+    .await
+}
 
-    for svc in services.iter_mut() {
-        svc.upstreams.sort_by_key(|u| u.priority);
-    }
+/// 记录上游已确认可用（或刚轮换过 key），更新最近验证时间
+#[tauri::command]
+async fn mark_upstream_verified(
+    service_id: String,
+    upstream_id: String,
+    state: TauriState<'_, ProxyState>,
+) -> Result<(), String> {
+    update_service(&state, &service_id, |service| {
+        service.upstream_mut(&upstream_id)?.last_verified_at = Some(timestamp::now());
+        Ok(())
+    })
+    .await
+}
 
-    let proxy_url = config.proxy_url.clone().filter(|s| !s.trim().is_empty());
-    let new_client = build_client(proxy_url.as_deref())?;
+/// 从粘贴的文本批量导入 key，每个 key 新建一个上游
+#[tauri::command]
+async fn import_keys(
+    service_id: String,
+    text: String,
+    state: TauriState<'_, ProxyState>,
+) -> Result<KeyImportSummary, String> {
+    let keys = key_import::parse_keys(&text)?;
+    let mut summary = KeyImportSummary::default();
+    update_service(&state, &service_id, |service| {
+        summary = key_import::append_upstreams(service, keys)?;
+        Ok(())
+    })
+    .await?;
+    Ok(summary)
+}
 
-    {
-        let mut client_guard = state.client.write().await;
-        *client_guard = new_client;
+/// 把 LiteLLM 的 config.yaml 或 one-api / new-api 的渠道导出转换为服务，由界面确认后合并
+#[tauri::command]
+fn import_gateway_config(text: String) -> Result<GatewayImport, String> {
+    gateway_import::import(&text)
+}
+
+#[tauri::command]
+async fn reload_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) -> Result<(), String> {
+    if state.config.read().await.is_none() {
+        return Err("未找到运行中的配置，请先启动服务".into());
     }
+    if !(1..=65535).contains(&config.listen_port) {
+        return Err("listen_port 无效".into());
+    }
+
+    env_vars::check(&config)?;
+    let services = normalize_services(config.services)?;
+    let listeners = normalize_listeners(config.listen_port, config.listeners)?;
 
+    let proxy_url = config.proxy_url.clone().filter(|s| !s.trim().is_empty());
     let new_cfg = ProxyConfig {
-        listen_port: config.listen_port,
         global_key: config.global_key.clone().filter(|s| !s.trim().is_empty()),
         proxy_url: proxy_url.clone(),
         fallback_retries: config.fallback_retries.min(MAX_FALLBACK_RETRIES),
         services,
+        listeners,
+        ..config
     };
+    validate_proxy_config(&new_cfg)?;
+    let client_proxy = proxy_url.as_deref().map(env_vars::expand).transpose()?;
+    let new_client = build_client(client_proxy.as_deref(), &TimeoutConfig::default(), None)?;
+
+    // 端口变更时热迁移：先监听新端口，再关闭旧端口，进行中的请求不受影响
+    let mut running = state.inner.lock().await;
+    if !running.is_empty() {
+        switch_listeners(&state, &mut running, &new_cfg).await?;
+    }
+    // 端口切换失败时保持原有客户端，与未变更的监听配置一致
+    state.client.store(Arc::new(new_client));
 
+    apply_retention(new_cfg.retention.as_ref());
+    storage::configure(new_cfg.log_storage.as_ref())?;
+    telemetry::configure(new_cfg.tracing.as_ref())?;
+    admin_api::configure(new_cfg.admin_api.as_ref(), new_cfg.admin_tokens.as_deref())?;
     {
         let mut guard = state.config.write().await;
         *guard = Some(new_cfg.clone());
     }
+    reconcile_stats(&state.stats, &new_cfg, &running);
+    events::emit_proxy_status(running.keys().copied().collect());
+    hooks::fire(
+        new_cfg.hooks.as_deref(),
+        HookPayload::ports(HookEvent::ConfigChanged, running.keys().copied().collect()),
+    );
+    drop(running);
 
     if let Err(err) = save_config(&new_cfg) {
         eprintln!("配置持久化失败: {err}");
@@ -478,28 +1847,82 @@ async fn reload_proxy(config: ProxyConfig, state: TauriState<'_, ProxyState>) ->
     Ok(())
 }
 
+fn build_router(shared: SharedState) -> Router {
+    Router::new()
+        .fallback(any(proxy_handler))
+        .layer(axum::middleware::from_fn_with_state(shared.config.clone(), cors::layer))
+        .with_state(shared)
+}
+
+#[tracing::instrument(
+    name = "proxy_request",
+    skip_all,
+    fields(
+        http.request.method = %req.method(),
+        url.path = %req.uri().path(),
+        apiflow.request_id = tracing::field::Empty,
+        apiflow.service = tracing::field::Empty,
+        apiflow.model = tracing::field::Empty,
+        http.response.status_code = tracing::field::Empty,
+    )
+)]
 async fn proxy_handler(
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
     State(shared): State<SharedState>,
     req: Request<Body>,
 ) -> Result<Response<Body>, StatusCode> {
     let started_at = Instant::now();
+    let _in_flight = app_metrics::InFlight::request();
     let request_id = Uuid::new_v4();
     let client_ip = client_addr.ip().to_string();
-    let (parts, body) = req.into_parts();
+    let span = tracing::Span::current();
+    span.record("apiflow.request_id", tracing::field::display(&request_id));
+    let (mut parts, body) = req.into_parts();
     let path = parts
         .uri
         .path_and_query()
         .map(|p| p.as_str())
         .unwrap_or("/");
 
-    let config = shared.config.read().await.clone();
+    let config = shared.config.load_full();
+
+    // 0. 内置接口与合成接口：健康检查无需鉴权，状态接口与代理请求使用同一鉴权
+    if let Some(route) = status::reserved_route(path) {
+        if route != ReservedRoute::Healthz {
+            if let Err((status, msg)) = check_auth(&config, &parts) {
+                return Ok(error_response(status, msg));
+            }
+        }
+        return Ok(reserved_response(route, path, &shared, &config).await);
+    }
+    let synthetic_endpoints = config.synthetic_endpoints.as_deref();
+    if let Some(endpoint) = synthetic::find(synthetic_endpoints, &parts.method, path) {
+        if !endpoint.is_public() {
+            if let Err((status, msg)) = check_auth(&config, &parts) {
+                return Ok(error_response(status, msg));
+            }
+        }
+        return Ok(synthetic_response(endpoint, &request_id, &parts.method, path));
+    }
+
+    // 本地应答 OPTIONS / HEAD，不占用上游的速率额度；预检请求不携带凭证，无需鉴权
+    let local_method = parts.method == http::Method::OPTIONS || parts.method == http::Method::HEAD;
+    if local_method && select_service(&config, path).is_some_and(ServiceConfig::answers_locally) {
+        if parts.method == http::Method::HEAD {
+            if let Err((status, msg)) = check_auth(&config, &parts) {
+                return Ok(error_response(status, msg));
+            }
+        }
+        return Ok(local_response(&parts));
+    }
 
-    // 1. Authentication
-    if let Err((status, msg)) = check_auth(&config, &parts) {
+    // 1. Authentication（延迟重试队列的重新提交在入队前已鉴权）
+    let resubmitted = retry_queue::is_resubmission(&parts.headers);
+    let auth = if resubmitted { Ok(()) } else { check_auth(&config, &parts) };
+    if let Err((status, msg)) = auth {
         let entry = ProxyLogEntry {
             id: request_id.to_string(),
-            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            timestamp: timestamp::now(),
             method: parts.method.to_string(),
             path: path.to_string(),
             upstream_url: "".to_string(),
@@ -509,8 +1932,10 @@ async fn proxy_handler(
             listen_port: config.listen_port,
             route_key: None,
             upstream_label: None,
+            upstream_id: None,
             service_name: None,
             base_path: None,
+            model: None,
             retry_action: None,
             request_headers: None,
             request_body: None,
@@ -518,6 +1943,29 @@ async fn proxy_handler(
             response_body: None,
             client_ip: Some(client_ip),
             is_streaming: false,
+            error_kind: None,
+            usage: None,
+            cost: None,
+            conversation_id: None,
+            outbound_request: None,
+            timeline: vec![
+                TimelineEvent::new(TimelineEventKind::Received, started_at),
+                TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg),
+            ],
+            checksum: None,
+            seq: timestamp::next_seq(),
+            trace_id: None,
+            cache_hit: false,
+            deduplicated: false,
+            default_model_applied: false,
+            shadow: false,
+            compare: None,
+            images: None,
+            stream_mismatch: false,
+            pii_masked: None,
+            dimension: None,
+            drill: false,
+            client_app: client_app::classify(&parts.headers),
         };
         logging::upsert_log(shared.logs.clone(), entry).await;
         return Ok(error_response(status, msg));
@@ -530,14 +1978,53 @@ async fn proxy_handler(
     };
 
     let RouteInfo {
+        service_id,
         service_name,
         service_base,
+        log_request_body,
+        log_response_body,
+        paused_response,
+        access_schedule,
+        streaming,
+        retry_rules: service_retry_rules,
+        cache: cache_config,
+        dedupe_in_flight,
+        default_model,
+        model_filter,
+        pii_mask,
+        compare_upstream,
+        mirror,
+        max_image_bytes,
+        max_request_bytes,
+        retry_queue,
+        context_overflow,
+        policy_fallback,
+        blocked_request_headers,
+        blocked_response_headers,
         upstreams,
     } = route;
+    remove_headers(&mut parts.headers, &blocked_request_headers);
+    span.record("apiflow.service", service_name.as_str());
+
+    // 设置为拒绝的预算用尽时：全局 / 服务预算直接拒绝请求，上游预算则跳过该上游
+    let budgets = config.budgets.as_deref();
+    let service_block = budget::blocked_by(budgets, Some(&service_name), None);
+    let mut upstream_block = None;
+    let mut upstreams: Vec<ResolvedUpstream> = upstreams
+        .into_iter()
+        .filter(|u| match budget::blocked_by(budgets, Some(&service_name), Some(&u.upstream_id)) {
+            Some(rule) => {
+                upstream_block.get_or_insert(rule);
+                false
+            }
+            None => true,
+        })
+        .collect();
+    let budget_block = service_block.or(upstream_block.filter(|_| upstreams.is_empty()));
 
     let mut entry = ProxyLogEntry {
         id: request_id.to_string(),
-        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        timestamp: timestamp::now(),
         method: parts.method.to_string(),
         path: path.to_string(),
         upstream_url: upstreams
@@ -554,8 +2041,10 @@ async fn proxy_handler(
         upstream_label: upstreams
             .first()
             .and_then(|u| u.upstream_label.clone()),
-        service_name: Some(service_name),
+        upstream_id: upstreams.first().map(|u| u.upstream_id.clone()),
+        service_name: Some(service_name.clone()),
         base_path: Some(service_base),
+        model: None,
         retry_action: None,
         request_headers: None, // 稍后在 prepare_upstream_request 后设置
         request_body: None,
@@ -563,84 +2052,816 @@ async fn proxy_handler(
         response_body: None,
         client_ip: Some(client_ip),
         is_streaming: false,
+        error_kind: None,
+        usage: None,
+        cost: None,
+        conversation_id: None,
+        outbound_request: None,
+        timeline: vec![TimelineEvent::new(TimelineEventKind::Received, started_at)],
+        checksum: None,
+        seq: timestamp::next_seq(),
+        trace_id: None,
+        cache_hit: false,
+        deduplicated: false,
+        default_model_applied: false,
+        shadow: false,
+        compare: None,
+        images: None,
+        stream_mismatch: false,
+        pii_masked: None,
+        dimension: config
+            .custom_dimension
+            .as_ref()
+            .and_then(|d| d.classify(&parts.headers, parts.uri.path(), parts.uri.query())),
+        drill: false,
+        client_app: client_app::classify(&parts.headers),
+    };
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Routed, started_at).detail(service_name.clone()));
+
+    if let Some(body) = paused_response {
+        let msg = format!("服务「{service_name}」已暂停");
+        span.record("http.response.status_code", StatusCode::SERVICE_UNAVAILABLE.as_u16());
+        entry.status = Some(StatusCode::SERVICE_UNAVAILABLE.as_u16());
+        entry.error = Some(msg.clone());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::RETRY_AFTER, "60")
+            .body(Body::from(body))
+            .unwrap_or_else(|_| error_response(StatusCode::SERVICE_UNAVAILABLE, "服务维护中")));
+    }
+
+    // 禁用时段按本地时间判断
+    let now = chrono::Local::now().naive_local();
+    if let Some(msg) = access_schedule.and_then(|s| s.rejection_at(&service_name, now)) {
+        span.record("http.response.status_code", StatusCode::FORBIDDEN.as_u16());
+        entry.status = Some(StatusCode::FORBIDDEN.as_u16());
+        entry.error = Some(msg.clone());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg.clone()));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(error_response(StatusCode::FORBIDDEN, &msg));
+    }
+
+    if let Some(rule) = budget_block {
+        let msg = format!("预算「{rule}」已用尽，请求已被拒绝");
+        span.record("http.response.status_code", StatusCode::TOO_MANY_REQUESTS.as_u16());
+        entry.status = Some(StatusCode::TOO_MANY_REQUESTS.as_u16());
+        entry.error = Some(msg.clone());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg.clone()));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(error_response(StatusCode::TOO_MANY_REQUESTS, &msg));
+    }
+
+    if let Some(Err(wait)) = config.request_rate_limit.as_ref().map(request_limit::try_acquire) {
+        let msg = "请求过于频繁，已超出代理的速率上限";
+        span.record("http.response.status_code", StatusCode::TOO_MANY_REQUESTS.as_u16());
+        entry.status = Some(StatusCode::TOO_MANY_REQUESTS.as_u16());
+        entry.error = Some(msg.to_string());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, msg);
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            header::HeaderValue::from(wait.as_secs_f64().ceil().max(1.0) as u64),
+        );
+        return Ok(response);
+    }
+
+    let allowed_retries = config.fallback_retries.min(MAX_FALLBACK_RETRIES);
+    let retries_per_upstream = allowed_retries.saturating_sub(1); // 0->no retry,1->no retry but allow fallback,2->retry once then fallback
+    let allow_fallback = allowed_retries >= 1;
+
+    // 不记录请求体、只会尝试一次且无需审计或校验时，请求体无需缓冲，直接以流的形式转发给上游
+    let audit_outbound = upstreams.iter().any(|u| u.audit_outbound);
+    let verify_checksums = config.verify_checksums.unwrap_or(false);
+    // 声明的长度已超过上限时不读取请求体
+    if let Some(max) = max_request_bytes {
+        let declared = parts
+            .headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > max) {
+            return Ok(reject_oversized_body(&shared, entry, started_at, max).await);
+        }
+    }
+    let (body_bytes, mut passthrough_body) = if !log_request_body
+        && allowed_retries == 0
+        && !audit_outbound
+        && !verify_checksums
+        && cache_config.is_none()
+        && !dedupe_in_flight
+        && default_model.is_none()
+        && model_filter.is_none()
+        && pii_mask.is_none()
+        && upstreams
+            .iter()
+            .all(|u| {
+                u.capabilities.is_none()
+                    && u.structured_output == StructuredOutputMode::Native
+                    && u.model_map.is_empty()
+            })
+        && compare_upstream.is_none()
+        && mirror.is_none()
+        && max_image_bytes.is_none()
+        && max_request_bytes.is_none()
+        && retry_queue.is_none()
+        && context_overflow.is_none()
+        && policy_fallback.is_none()
+        // 费用估算与预算需要从请求体中识别模型
+        && config.pricing.is_none()
+        && config.budgets.is_none()
+    {
+        (Bytes::new(), Some(body))
+    } else {
+        // 未声明长度（分块传输）时边读取边检查，超过上限立即停止读取
+        let limit = max_request_bytes.map_or(usize::MAX, |max| usize::try_from(max).unwrap_or(usize::MAX));
+        match Limited::new(body, limit).collect().await {
+            Ok(collected) => (collected.to_bytes(), None),
+            Err(err) if err.is::<LengthLimitError>() => {
+                let max = max_request_bytes.unwrap_or_default();
+                return Ok(reject_oversized_body(&shared, entry, started_at, max).await);
+            }
+            Err(err) => {
+                entry.error = Some(format!("读取请求体失败: {err}"));
+                entry
+                    .timeline
+                    .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail("读取请求体失败"));
+                entry.duration_ms = started_at.elapsed().as_millis();
+                logging::upsert_log(shared.logs.clone(), entry).await;
+                return Ok(error_response(StatusCode::BAD_REQUEST, "读取请求体失败"));
+            }
+        }
+    };
+
+    entry.model = extract_model(path, &body_bytes);
+    // 客户端未指定模型时填入服务的默认模型（路径中带模型的请求不受影响）
+    let body_bytes = match default_model
+        .filter(|_| entry.model.is_none())
+        .and_then(|model| rewrite::inject_default_model(&body_bytes, model))
+    {
+        Some(rewritten) => {
+            entry.model = default_model.map(str::to_string);
+            entry.default_model_applied = true;
+            Bytes::from(rewritten)
+        }
+        None => body_bytes,
     };
+    if let Some(model) = &entry.model {
+        span.record("apiflow.model", model.as_str());
+    }
+    if let Some((filter, Err(msg))) = model_filter.map(|f| (f, f.check(entry.model.as_deref()))) {
+        let body = filter.error_body(entry.model.as_deref(), &msg);
+        span.record("http.response.status_code", StatusCode::FORBIDDEN.as_u16());
+        entry.status = Some(StatusCode::FORBIDDEN.as_u16());
+        entry.error = Some(msg.clone());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap_or_else(|_| error_response(StatusCode::FORBIDDEN, "模型不可用")));
+    }
+
+    // 遮盖发往上游的敏感信息，记录的请求体同样是遮盖后的内容
+    let body_bytes = match pii_mask.and_then(|mask| mask.mask_body(&body_bytes)) {
+        Some((masked, count)) => {
+            entry.pii_masked = Some(count);
+            Bytes::from(masked)
+        }
+        None => body_bytes,
+    };
+
+    let image_scan = (log_request_body || max_image_bytes.is_some())
+        .then(|| images::scan(&body_bytes))
+        .flatten();
+    entry.images = image_scan.as_ref().map(|scan| scan.summary.clone());
+
+    let rules = redaction::rules(&config);
+    if log_request_body {
+        let logged_body = image_scan.as_ref().map_or(&body_bytes[..], |scan| &scan.redacted);
+        entry.request_body = truncate_body(logged_body, 8000).map(|b| rules.redact_body(b));
+        entry.conversation_id = transcript::request_messages(&body_bytes)
+            .and_then(|messages| transcript::conversation_id(&messages));
+    }
+
+    if let Some(Err(msg)) = max_image_bytes
+        .zip(entry.images.as_ref())
+        .map(|(max, images)| images.check_limit(max))
+    {
+        span.record("http.response.status_code", StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+        entry.status = Some(StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+        entry.error = Some(msg.clone());
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg.clone()));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(error_response(StatusCode::PAYLOAD_TOO_LARGE, &msg));
+    }
 
-    // Read Body
-    let body_bytes = match body.collect().await {
-        Ok(collected) => collected.to_bytes(),
-        Err(err) => {
-            entry.error = Some(format!("读取请求体失败: {err}"));
-            entry.duration_ms = started_at.elapsed().as_millis();
-            logging::upsert_log(shared.logs.clone(), entry).await;
-            return Ok(error_response(StatusCode::BAD_REQUEST, "读取请求体失败"));
+    // 命中响应缓存时直接返回，不经过上游
+    let cache_key = cache_config.and_then(|_| response_cache::key(&parts.method, path, &body_bytes));
+    if let Some(cached) = cache_config
+        .zip(cache_key.as_deref())
+        .and_then(|(cache, key)| response_cache::lookup(service_id, cache, key))
+    {
+        span.record("http.response.status_code", cached.status.as_u16());
+        entry.cache_hit = true;
+        let mut headers = cached.headers.clone();
+        headers.insert(CACHE_HEADER, header::HeaderValue::from_static("hit"));
+        remove_headers(&mut headers, &blocked_response_headers);
+        return replay_response(
+            &shared,
+            entry,
+            cached,
+            headers,
+            rules,
+            log_response_body,
+            started_at,
+            "命中响应缓存",
+        )
+        .await;
+    }
+
+    // 跳过服务商能力表中声明不支持该请求的上游
+    let needs = RequestNeeds::from_body(&body_bytes);
+    let mut skipped = Vec::new();
+    upstreams.retain(|u| match u.capabilities.as_ref().and_then(|c| c.missing(&needs)) {
+        Some(missing) => {
+            skipped.push(format!(
+                "跳过上游 {}：不支持{missing}",
+                u.upstream_label.as_deref().unwrap_or(&u.upstream_id)
+            ));
+            false
         }
-    };
+        None => true,
+    });
+    for detail in skipped.iter() {
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Routed, started_at).detail(detail.clone()));
+    }
+    if upstreams.is_empty() {
+        let msg = "没有支持该请求的上游";
+        span.record("http.response.status_code", StatusCode::BAD_REQUEST.as_u16());
+        entry.status = Some(StatusCode::BAD_REQUEST.as_u16());
+        entry.error = Some(format!("{msg}（{}）", skipped.join("；")));
+        entry
+            .timeline
+            .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg));
+        entry.duration_ms = started_at.elapsed().as_millis();
+        logging::upsert_log(shared.logs.clone(), entry).await;
+        return Ok(error_response(StatusCode::BAD_REQUEST, msg));
+    }
 
-    entry.request_body = truncate_body(&body_bytes, 8000);
+    let priority = scheduler::classify_request(path, &body_bytes);
+    let stream_probe = StreamProbe::new(streaming.as_ref(), path, &body_bytes);
+
+    // 已有相同的非流式请求在进行时等待它的响应；它未拿到响应就失败时照常转发
+    let mut dedupe_leader = None;
+    if dedupe_in_flight && !stream_probe.expects_stream() {
+        let key = format!("{service_id} {} {path} {}", parts.method, sha256_hex(&body_bytes));
+        match inflight::join(key) {
+            inflight::Joined::Leader(guard) => dedupe_leader = Some(guard),
+            inflight::Joined::Follower(rx) => {
+                if let Ok(shared_response) = rx.await {
+                    span.record("http.response.status_code", shared_response.status.as_u16());
+                    entry.deduplicated = true;
+                    let mut headers = shared_response.headers.clone();
+                    remove_headers(&mut headers, &blocked_response_headers);
+                    return replay_response(
+                        &shared,
+                        entry,
+                        shared_response,
+                        headers,
+                        rules,
+                        log_response_body,
+                        started_at,
+                        "与相同的并发请求合并",
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+    if verify_checksums {
+        entry.checksum = Some(ChecksumReport::for_request(&body_bytes, &parts.headers));
+    }
+    // 校验时把请求体摘要一并发给上游，便于对端核对
+    let request_digest = entry
+        .checksum
+        .as_ref()
+        .and_then(|c| checksum::digest_header(&c.request_sha256));
+
+    // 对比模式：同时把非流式请求发给对比上游，主上游的响应读完后生成对比日志
+    let mut compare_task = compare_upstream
+        .filter(|_| !stream_probe.expects_stream())
+        .map(|secondary| {
+            let client = timeouts::client_for(
+                &shared.client.load(),
+                config.proxy_url.as_deref(),
+                &secondary.timeouts,
+                secondary.host_override.as_ref(),
+            );
+            let (mut request, _) = prepare_upstream_request(
+                &client,
+                &parts.method,
+                &secondary.request_url,
+                &secondary.query_params,
+                &parts.headers,
+                secondary.api_key.as_deref(),
+                &secondary.identity_headers,
+                body_bytes.clone(),
+            );
+            if let Some(timeout) = secondary.timeouts.total(false) {
+                request = request.timeout(timeout);
+            }
+            (secondary, tokio::spawn(linked::send(request, "对比上游")))
+        });
+
+    // 影子流量：按比例复制请求发给影子上游，不等待其响应
+    if let Some((_, shadow)) = mirror.filter(|(m, _)| m.sampled()) {
+        let client = timeouts::client_for(
+            &shared.client.load(),
+            config.proxy_url.as_deref(),
+            &shadow.timeouts,
+            shadow.host_override.as_ref(),
+        );
+        let (mut request, _) = prepare_upstream_request(
+            &client,
+            &parts.method,
+            &shadow.request_url,
+            &shadow.query_params,
+            &parts.headers,
+            shadow.api_key.as_deref(),
+            &shadow.identity_headers,
+            body_bytes.clone(),
+        );
+        if let Some(timeout) = shadow.timeouts.total(stream_probe.expects_stream()) {
+            request = request.timeout(timeout);
+        }
+        mirror::spawn(
+            shared.logs.clone(),
+            shared.stats.clone(),
+            entry.clone(),
+            shadow,
+            request,
+            log_response_body,
+            config.clone(),
+            started_at,
+        );
+    }
 
-    let allowed_retries = config.fallback_retries.min(MAX_FALLBACK_RETRIES);
-    let retries_per_upstream = allowed_retries.saturating_sub(1); // 0->no retry,1->no retry but allow fallback,2->retry once then fallback
-    let allow_fallback = allowed_retries >= 1;
+    let trace_headers = config.trace_headers.unwrap_or(false);
+    // 客户端指定的超时覆盖整个请求（含重试与切换上游），每次尝试的总超时不超过剩余时间
+    let client_timeout = timeouts::client_timeout(&parts.headers, config.max_client_timeout_ms);
+    let client_deadline = client_timeout.map(|t| started_at + t);
+    // 上下文超长时的缩减重试整个请求只做一次
+    let mut context_retried = false;
+    // 内容策略拒绝后跳过剩余上游，只请求备用上游
+    let mut policy_fallback_triggered = false;
     let mut attempt_errors: Vec<String> = Vec::new();
+    let mut attempt_no: u32 = 0;
 
-    for (up_idx, upstream) in upstreams.iter().enumerate() {
-        for attempt in 0..=retries_per_upstream {
+    for (up_idx, upstream) in upstreams.iter().chain(policy_fallback.as_ref()).enumerate() {
+        let is_policy_fallback = up_idx >= upstreams.len();
+        if is_policy_fallback != policy_fallback_triggered {
+            if is_policy_fallback {
+                break;
+            }
+            continue;
+        }
+        // 按该上游服务商的最大输出 token 数补全或截断请求，并按其支持程度改写结构化输出要求
+        let upstream_body = upstream
+            .capabilities
+            .as_ref()
+            .and_then(|c| c.adjust_body(&body_bytes))
+            .map(Bytes::from)
+            .unwrap_or_else(|| body_bytes.clone());
+        let mut upstream_body = structured_output::normalize(&upstream_body, upstream.structured_output)
+            .map(Bytes::from)
+            .unwrap_or(upstream_body);
+        if let Some(mapped) = map_model(&upstream_body, &upstream.model_map) {
+            upstream_body = Bytes::from(mapped);
+        }
+        // 缩减重试不占用该上游的重试次数
+        let mut extra_attempts = 0;
+        for attempt in 0..=retries_per_upstream + 1 {
+            if attempt > retries_per_upstream + extra_attempts {
+                break;
+            }
+            if client_deadline.is_some_and(|d| Instant::now() >= d) {
+                let timeout = client_timeout.unwrap_or_default();
+                return Ok(client_timeout_exceeded(&shared, entry, started_at, timeout, &attempt_errors).await);
+            }
+            // 按上游并发与速率上限排队，等待时间不计入本次尝试的耗时
+            let permit = scheduler::acquire(
+                &upstream.upstream_id,
+                upstream.rate_limit.as_ref(),
+                priority,
+            )
+            .await;
             let attempt_started = Instant::now();
+            attempt_no += 1;
+            // 每次尝试一个子 span，流式响应体的转发也记在其下
+            let attempt_span = tracing::info_span!(
+                "upstream_attempt",
+                apiflow.attempt = attempt_no,
+                apiflow.upstream_id = %upstream.upstream_id,
+                url.full = %upstream.upstream_url,
+                http.response.status_code = tracing::field::Empty,
+                error.message = tracing::field::Empty,
+            );
 
             entry.upstream_url = upstream.upstream_url.clone();
             entry.route_key = upstream.upstream_label.clone();
             entry.upstream_label = upstream.upstream_label.clone();
+            entry.upstream_id = Some(upstream.upstream_id.clone());
+            let trace_id = format!("{request_id}-{attempt_no}");
+            entry.trace_id = Some(trace_id.clone());
 
             // 3. Prepare Request for this attempt
-            let client_guard = shared.client.read().await;
-            let (upstream_req, upstream_headers_str) = prepare_upstream_request(
-                &client_guard,
+            let attempt_body = match passthrough_body.take() {
+                Some(body) => reqwest::Body::wrap_stream(body.into_data_stream()),
+                None => reqwest::Body::from(upstream_body.clone()),
+            };
+            let mut identity = upstream.identity_headers.clone();
+            if let Some(digest) = &request_digest {
+                identity.insert(CONTENT_DIGEST, digest.clone());
+            }
+            if trace_headers {
+                if let (Ok(request), Ok(attempt)) = (
+                    header::HeaderValue::from_str(&request_id.to_string()),
+                    header::HeaderValue::from_str(&trace_id),
+                ) {
+                    identity.insert(REQUEST_ID_HEADER, request);
+                    identity.insert(ATTEMPT_ID_HEADER, attempt);
+                }
+            }
+            let client = timeouts::client_for(
+                &shared.client.load(),
+                config.proxy_url.as_deref(),
+                &upstream.timeouts,
+                upstream.host_override.as_ref(),
+            );
+            let (mut upstream_req, upstream_headers_str) = prepare_upstream_request(
+                &client,
                 &parts.method,
-                &upstream.upstream_url,
+                &upstream.request_url,
+                &upstream.query_params,
                 &parts.headers,
                 upstream.api_key.as_deref(),
-                body_bytes.clone(),
+                &identity,
+                attempt_body,
             );
-            drop(client_guard); // Release lock before await
+            drop(client);
+            let remaining = client_deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            if let Some(timeout) = upstream.timeouts.total(stream_probe.expects_stream()).into_iter().chain(remaining).min() {
+                upstream_req = upstream_req.timeout(timeout);
+            }
 
             // 记录发给上游的请求头（而不是客户端的原始请求头）
-            entry.request_headers = Some(upstream_headers_str);
+            entry.request_headers = Some(rules.redact_headers(&upstream_headers_str));
+            entry.outbound_request = upstream.audit_outbound.then(|| {
+                let headers = outbound_headers(&parts.headers, upstream.api_key.as_deref(), &identity);
+                // 查询参数规则与固定身份请求头中常带有上游 key，以掩码留档
+                let url = query_params::apply(&upstream.request_url, &query_params::masked(&upstream.query_params));
+                serialize_outbound(&parts.method, &url, &headers, &upstream_body, log_request_body, |name| {
+                    rules.is_sensitive_header(name)
+                })
+            });
+
+            entry.timeline.push(
+                TimelineEvent::new(TimelineEventKind::AttemptSent, started_at)
+                    .attempt(attempt_no)
+                    .upstream(&upstream.upstream_id),
+            );
 
             // 将“处理中”日志写入队列，便于前端立即展示/更新当前尝试的上游
             logging::upsert_log(shared.logs.clone(), entry.clone()).await;
 
             // 4. Execute & Handle Response
-            let upstream_resp = upstream_req.send().await;
+            // 故障注入：先等待额外延迟，再按比例直接返回错误或让响应体中途断开
+            let chaos = upstream.chaos.as_ref();
+            if let Some(delay) = chaos.and_then(ChaosConfig::latency) {
+                tokio::time::sleep(delay).await;
+            }
+            // 故障演练中的上游不实际请求，直接以 503 失败，按正常流程重试 / 切换
+            let simulated = drill::simulated_outage(&upstream.upstream_id)
+                .inspect(|_| entry.drill = true)
+                .or_else(|| chaos.and_then(ChaosConfig::injected_error));
+            let upstream_resp = match (&upstream.mock, simulated) {
+                (_, Some(injected)) => Ok(injected),
+                (Some(mock), None) => Ok(mock::respond(
+                    mock,
+                    &upstream.upstream_id,
+                    &request_id.to_string(),
+                    entry.model.as_deref(),
+                    stream_probe.expects_stream(),
+                )),
+                (None, None) => upstream_req
+                    .send()
+                    .instrument(attempt_span.clone())
+                    .await
+                    .map_err(|err| err.to_string()),
+            };
+            let upstream_resp = match chaos {
+                Some(chaos) => upstream_resp.map(|resp| chaos.maybe_disconnect(resp)),
+                None => upstream_resp,
+            };
+            let has_retry_left = attempt < retries_per_upstream + extra_attempts;
+            let can_shrink = context_overflow.is_some() && !context_retried;
+            let can_policy_fallback = policy_fallback.is_some() && !is_policy_fallback;
+            let has_next_upstream = allow_fallback && up_idx + 1 < upstreams.len();
+
+            // 流式响应先等到首个数据块再转发：上游卡住或在首块前断开时仍可重试 / 切换上游
+            let upstream_resp = match upstream_resp {
+                Ok(resp)
+                    if (has_retry_left || has_next_upstream)
+                        && stream_probe.is_streaming(resp.status(), response_content_type(&resp)) =>
+                {
+                    await_first_chunk(resp, upstream.timeouts.first_byte())
+                        .instrument(attempt_span.clone())
+                        .await
+                }
+                other => other,
+            };
 
-            match upstream_resp {
+            // 还有重试/切换机会时先读取错误体并分类，决定是否值得在同一个 key 上重试；
+            // 自定义条件列出的成功状态码也需读取（流式响应除外）。错误体读取中断时按本次尝试失败处理
+            let upstream_resp = match upstream_resp {
                 Ok(resp) => {
                     let status = resp.status();
-                    if should_retry_status(status) && attempt < retries_per_upstream {
+                    attempt_span.record("http.response.status_code", status.as_u16());
+                    span.record("http.response.status_code", status.as_u16());
+                    let inspect = status.is_client_error()
+                        || status.is_server_error()
+                        || (retry_rules::inspects_success(service_retry_rules, status.as_u16())
+                            && !stream_probe.is_streaming(status, response_content_type(&resp)));
+                    // 成功状态却返回网页（强制门户、CDN 拦截页等）时按协议不符处理，不把页面转发给客户端
+                    let mismatch_content_type = provider_error::is_protocol_mismatch(
+                        status.as_u16(),
+                        response_content_type(&resp),
+                    )
+                    .then(|| response_content_type(&resp).to_string());
+                    if mismatch_content_type.is_some() {
+                        Ok((resp, Bytes::new(), Some(ErrorKind::ProtocolMismatch), inspect, mismatch_content_type))
+                    } else if inspect && (has_retry_left || has_next_upstream || can_shrink || can_policy_fallback) {
+                        buffer_error_response(resp)
+                            .await
+                            .map(|(resp, body, kind)| (resp, body, kind, inspect, None))
+                    } else {
+                        Ok((resp, Bytes::new(), None, inspect, None))
+                    }
+                }
+                Err(err) => Err(err),
+            };
+
+            match upstream_resp {
+                Ok((resp, body, error_kind, inspect, mismatch_content_type)) => {
+                    let status = resp.status();
+                    entry.error_kind = error_kind;
+
+                    // 上下文超长：按服务策略缩减请求后在同一上游重试一次
+                    if let Some((shrunk, change)) = context_overflow
+                        .filter(|_| can_shrink && error_kind == Some(ErrorKind::ContextLengthExceeded))
+                        .and_then(|policy| policy.shrink(&upstream_body))
+                    {
+                        context_retried = true;
+                        extra_attempts = 1;
+                        upstream_body = Bytes::from(shrunk);
+                        let detail = format!("上下文超长，{change}后重试");
+                        entry.timeline.push(
+                            TimelineEvent::new(TimelineEventKind::Retried, started_at)
+                                .attempt(attempt_no)
+                                .upstream(&upstream.upstream_id)
+                                .detail(detail.clone()),
+                        );
                         let mut failed_entry = entry.clone();
                         failed_entry.id = format!("{}-{}-{}", entry.id, up_idx + 1, attempt + 1);
+                        failed_entry.seq = timestamp::next_seq();
                         failed_entry.status = Some(status.as_u16());
                         failed_entry.duration_ms = attempt_started.elapsed().as_millis();
-                        failed_entry.error = Some(format!("上游返回 {status}，已自动重试"));
+                        failed_entry.error = Some(detail.clone());
                         failed_entry.retry_action = Some("retry".into());
                         logging::upsert_log(shared.logs.clone(), failed_entry).await;
-                        logging::update_stats(
-                            shared.stats.clone(),
+                        shared.stats.record(
                             &upstream.upstream_id,
                             upstream.upstream_label.clone(),
+                            &StatsDims::of(&entry),
                             attempt_started.elapsed().as_millis() as u64,
                             false,
-                        )
-                        .await;
-                        attempt_errors.push(format!("上游返回 {status}"));
+                        );
+                        attempt_errors.push(detail);
+                        entry.error_kind = None;
                         continue;
                     }
 
-                    if up_idx > 0 {
+                    // 内容策略拒绝：不返回拒绝结果，改用服务指定的备用上游
+                    if can_policy_fallback && error_kind == Some(ErrorKind::ContentFilter) {
+                        policy_fallback_triggered = true;
+                        let target = policy_fallback.as_ref().map(|u| u.upstream_id.as_str()).unwrap_or_default();
+                        entry.timeline.push(
+                            TimelineEvent::new(TimelineEventKind::Fallback, started_at)
+                                .attempt(attempt_no)
+                                .upstream(&upstream.upstream_id)
+                                .detail(format!("上游以内容策略拒绝，切换到备用上游 {target}")),
+                        );
+                        let mut failed_entry = entry.clone();
+                        failed_entry.id = format!("{}-{}-{}", entry.id, up_idx + 1, attempt + 1);
+                        failed_entry.seq = timestamp::next_seq();
+                        failed_entry.status = Some(status.as_u16());
+                        failed_entry.duration_ms = attempt_started.elapsed().as_millis();
+                        failed_entry.error = Some(format!("上游以内容策略拒绝（{status}），已切换到备用上游"));
+                        failed_entry.retry_action = Some("policy_fallback".into());
+                        logging::upsert_log(shared.logs.clone(), failed_entry).await;
+                        shared.stats.record(
+                            &upstream.upstream_id,
+                            upstream.upstream_label.clone(),
+                            &StatsDims::of(&entry),
+                            attempt_started.elapsed().as_millis() as u64,
+                            false,
+                        );
+                        attempt_errors.push(format!("上游以内容策略拒绝（{status}）"));
+                        entry.error_kind = None;
+                        break;
+                    }
+
+                    // 先看服务的自定义条件，再按错误分类决定：在同一上游重试、切换到下一个上游，或直接返回给客户端
+                    let action = (inspect && (has_retry_left || has_next_upstream))
+                        .then(|| retry_rules::rule_action(service_retry_rules, status.as_u16(), &body))
+                        .flatten()
+                        .or_else(|| error_kind.map(|k| error_action(k, config.error_actions.as_ref())));
+                    // Retry-After 超过退避上限时不在同一上游等待，改为切换上游或直接返回
+                    let retry_wait = match action {
+                        Some(ErrorAction::Retry) if has_retry_left => backoff::retry_delay(
+                            config.backoff.as_ref(),
+                            attempt,
+                            Some(status),
+                            Some(resp.headers()),
+                        ),
+                        _ => None,
+                    };
+                    let next_step = match action {
+                        Some(ErrorAction::Retry) if retry_wait.is_some() => Some(TimelineEventKind::Retried),
+                        Some(ErrorAction::Retry | ErrorAction::Fallback) if has_next_upstream => {
+                            Some(TimelineEventKind::Fallback)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(step) = next_step {
+                        let retrying = step == TimelineEventKind::Retried;
+                        let wait = retry_wait.unwrap_or_default();
+                        let outcome = match &mismatch_content_type {
+                            Some(content_type) => format!("上游返回了网页（{content_type}）"),
+                            None => format!("上游返回 {status}"),
+                        };
+                        entry.timeline.push(
+                            TimelineEvent::new(step, started_at)
+                                .attempt(attempt_no)
+                                .upstream(&upstream.upstream_id)
+                                .detail(if retrying && !wait.is_zero() {
+                                    format!("{outcome}，{} ms 后重试", wait.as_millis())
+                                } else {
+                                    outcome.clone()
+                                }),
+                        );
+                        let mut failed_entry = entry.clone();
+                        failed_entry.id = format!("{}-{}-{}", entry.id, up_idx + 1, attempt + 1);
+                        failed_entry.seq = timestamp::next_seq();
+                        failed_entry.status = Some(status.as_u16());
+                        failed_entry.duration_ms = attempt_started.elapsed().as_millis();
+                        failed_entry.error = Some(if retrying {
+                            format!("{outcome}，已自动重试")
+                        } else {
+                            format!("{outcome}，已自动切换上游")
+                        });
+                        failed_entry.retry_action = Some(if retrying { "retry" } else { "fallback" }.into());
+                        logging::upsert_log(shared.logs.clone(), failed_entry).await;
+                        shared.stats.record(
+                            &upstream.upstream_id,
+                            upstream.upstream_label.clone(),
+                            &StatsDims::of(&entry),
+                            attempt_started.elapsed().as_millis() as u64,
+                            false,
+                        );
+                        attempt_errors.push(outcome);
+                        entry.error_kind = None;
+                        if retrying {
+                            drop(permit);
+                            tokio::time::sleep(wait).await;
+                            continue;
+                        }
+                        break;
+                    }
+
+                    let resp = match &mismatch_content_type {
+                        Some(content_type) => protocol_mismatch_response(content_type),
+                        None => resp,
+                    };
+                    let status = resp.status();
+
+                    // 开启延迟重试的路径：最后一个上游仍返回 5xx / 429 时入队，稍后重新提交
+                    if let Some(queue) = retry_queue.filter(|_| !resubmitted) {
+                        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                            shared.stats.record(
+                                &upstream.upstream_id,
+                                upstream.upstream_label.clone(),
+                                &StatsDims::of(&entry),
+                                attempt_started.elapsed().as_millis() as u64,
+                                false,
+                            );
+                            attempt_errors.push(format!("上游返回 {status}"));
+                            let reason = attempt_errors.join("; ");
+                            return Ok(
+                                park_request(&shared, &config, queue, service_id, entry, started_at, &parts, &body_bytes, log_request_body, &reason)
+                                    .await,
+                            );
+                        }
+                    }
+
+                    if is_policy_fallback {
+                        entry.retry_action = Some("policy_fallback".into());
+                    } else if up_idx > 0 {
                         entry.retry_action = Some("fallback".into());
                     } else if attempt > 0 {
                         entry.retry_action = Some("retry".into());
                     }
 
+                    // 客户端期望流式、上游却返回完整 JSON：在日志中标记，服务开启转换时改为等价的 SSE 返回
+                    let resp = if stream_probe.is_json_instead_of_stream(status, response_content_type(&resp)) {
+                        entry.stream_mismatch = true;
+                        if stream_probe.converts_json() {
+                            convert_to_sse(resp).await
+                        } else {
+                            resp
+                        }
+                    } else {
+                        resp
+                    };
+
+                    // 非流式响应读完后写入缓存（仅成功响应），并分发给合并进来的相同请求
+                    let cache_entry = cache_config.zip(cache_key.clone()).filter(|_| status.is_success());
+                    let resp = if (cache_entry.is_some() || dedupe_leader.is_some() || compare_task.is_some())
+                        && !stream_probe.is_streaming(status, response_content_type(&resp))
+                    {
+                        let (resp, body) = buffer_response(resp).await;
+                        if let Some(body) = body {
+                            if let Some((secondary, task)) = compare_task.take() {
+                                compare::spawn_report(
+                                    shared.logs.clone(),
+                                    shared.stats.clone(),
+                                    entry.clone(),
+                                    status.as_u16(),
+                                    body.clone(),
+                                    secondary,
+                                    task,
+                                    log_response_body,
+                                    config.clone(),
+                                    started_at,
+                                );
+                            }
+                            let complete = CachedResponse::new(
+                                status,
+                                resp.headers(),
+                                body,
+                                upstream.upstream_id.clone(),
+                                upstream.upstream_label.clone(),
+                            );
+                            if let Some(leader) = dedupe_leader.take() {
+                                entry.deduplicated = leader.complete(complete.clone()) > 0;
+                            }
+                            if let Some((cache, key)) = cache_entry {
+                                response_cache::store(service_id, cache, key, complete);
+                            }
+                        }
+                        resp
+                    } else {
+                        resp
+                    };
+
                     return handle_upstream_response(
                         resp,
                         entry,
@@ -650,25 +2871,49 @@ async fn proxy_handler(
                         shared.stats.clone(),
                         upstream.upstream_id.clone(),
                         upstream.upstream_label.clone(),
+                        log_response_body,
+                        &stream_probe,
+                        upstream.timeouts.stream_idle(),
+                        config.clone(),
+                        permit,
                     )
-                    .await;
+                    .instrument(attempt_span)
+                    .await
+                    .map(|mut resp| {
+                        remove_headers(resp.headers_mut(), &blocked_response_headers);
+                        resp
+                    });
                 }
                 Err(err) => {
-                logging::update_stats(
-                    shared.stats.clone(),
-                    &upstream.upstream_id,
-                    upstream.upstream_label.clone(),
-                    attempt_started.elapsed().as_millis() as u64,
-                    false,
-                )
-                .await;
+                    attempt_span.record("error.message", tracing::field::display(&err));
+                    shared.stats.record(
+                        &upstream.upstream_id,
+                        upstream.upstream_label.clone(),
+                        &StatsDims::of(&entry),
+                        attempt_started.elapsed().as_millis() as u64,
+                        false,
+                    );
 
                     let mut failed_entry = entry.clone();
                     failed_entry.id = format!("{}-{}-{}", entry.id, up_idx + 1, attempt + 1);
+                    failed_entry.seq = timestamp::next_seq();
                     failed_entry.status = Some(StatusCode::BAD_GATEWAY.as_u16());
                     failed_entry.duration_ms = attempt_started.elapsed().as_millis();
-                    let has_retry_left = attempt < retries_per_upstream;
-                    let has_next_upstream = allow_fallback && up_idx + 1 < upstreams.len();
+                    let next_kind = if has_retry_left {
+                        Some(TimelineEventKind::Retried)
+                    } else if has_next_upstream {
+                        Some(TimelineEventKind::Fallback)
+                    } else {
+                        None
+                    };
+                    if let Some(kind) = next_kind {
+                        entry.timeline.push(
+                            TimelineEvent::new(kind, started_at)
+                                .attempt(attempt_no)
+                                .upstream(&upstream.upstream_id)
+                                .detail(err.to_string()),
+                        );
+                    }
                     failed_entry.error = Some(match () {
                         _ if has_retry_left => format!("{}，已自动重试", err),
                         _ if has_next_upstream => format!("{}，已自动切换上游", err),
@@ -685,6 +2930,9 @@ async fn proxy_handler(
                 attempt_errors.push(err.to_string());
 
                 if has_retry_left {
+                    drop(permit);
+                    let wait = backoff::retry_delay(config.backoff.as_ref(), attempt, None, None);
+                    tokio::time::sleep(wait.unwrap_or_default()).await;
                     continue;
                 }
 
@@ -694,11 +2942,28 @@ async fn proxy_handler(
                 }
 
                     // No upstreams left
+                    if client_deadline.is_some_and(|d| Instant::now() >= d) {
+                        let timeout = client_timeout.unwrap_or_default();
+                        return Ok(client_timeout_exceeded(&shared, entry, started_at, timeout, &attempt_errors).await);
+                    }
+                    if let Some(queue) = retry_queue.filter(|_| !resubmitted) {
+                        let reason = attempt_errors.join("; ");
+                        return Ok(
+                            park_request(&shared, &config, queue, service_id, entry, started_at, &parts, &body_bytes, log_request_body, &reason).await,
+                        );
+                    }
                     entry.error = Some(format!(
                         "上游请求失败: {}",
                         attempt_errors.join("; ")
                     ));
                     entry.status = Some(StatusCode::BAD_GATEWAY.as_u16());
+                    span.record("http.response.status_code", StatusCode::BAD_GATEWAY.as_u16());
+                    entry.timeline.push(
+                        TimelineEvent::new(TimelineEventKind::Failed, started_at)
+                            .attempt(attempt_no)
+                            .upstream(&upstream.upstream_id)
+                            .detail(err.to_string()),
+                    );
                     entry.duration_ms = started_at.elapsed().as_millis();
                     logging::upsert_log(shared.logs.clone(), entry).await;
                     return Ok(error_response(
@@ -725,57 +2990,444 @@ fn check_auth(config: &ProxyConfig, parts: &http::request::Parts) -> Result<(),
     Ok(())
 }
 
-struct RouteInfo {
+struct RouteInfo<'a> {
+    service_id: &'a str,
     service_name: String,
     service_base: String,
+    log_request_body: bool,
+    log_response_body: bool,
+    /// 服务暂停时返回给客户端的响应体
+    paused_response: Option<String>,
+    access_schedule: Option<&'a AccessSchedule>,
+    streaming: Option<StreamingDetection>,
+    retry_rules: &'a [RetryRule],
+    cache: Option<&'a ResponseCacheConfig>,
+    dedupe_in_flight: bool,
+    default_model: Option<&'a str>,
+    model_filter: Option<&'a ModelFilter>,
+    pii_mask: Option<&'a PiiMaskConfig>,
+    /// 对比模式下只用于对比的上游
+    compare_upstream: Option<ResolvedUpstream>,
+    /// 影子流量的配置与影子上游
+    mirror: Option<(&'a MirrorConfig, ResolvedUpstream)>,
+    max_image_bytes: Option<u64>,
+    max_request_bytes: Option<u64>,
+    /// 请求路径开启了延迟重试时的队列配置
+    retry_queue: Option<&'a RetryQueueConfig>,
+    context_overflow: Option<&'a ContextOverflowRetry>,
+    /// 内容策略拒绝时改用的备用上游
+    policy_fallback: Option<ResolvedUpstream>,
+    blocked_request_headers: Vec<header::HeaderName>,
+    blocked_response_headers: Vec<header::HeaderName>,
     upstreams: Vec<ResolvedUpstream>,
 }
 
 #[derive(Clone)]
 struct ResolvedUpstream {
     upstream_url: String,
+    /// 实际发送的地址：配置了 Host 覆盖时主机名已替换
+    request_url: String,
+    host_override: Option<HostOverride>,
     upstream_id: String,
     upstream_label: Option<String>,
     api_key: Option<String>,
+    rate_limit: Option<RateLimitConfig>,
+    identity_headers: header::HeaderMap,
+    audit_outbound: bool,
+    timeouts: TimeoutConfig,
+    capabilities: Option<ProviderCapabilities>,
+    mock: Option<MockUpstream>,
+    chaos: Option<ChaosConfig>,
+    structured_output: StructuredOutputMode,
+    query_params: Vec<QueryParamRule>,
+    model_map: HashMap<String, String>,
 }
 
-fn enabled_upstreams_sorted<'a>(upstreams: &'a [UpstreamEntry]) -> Vec<&'a UpstreamEntry> {
+fn enabled_upstreams_sorted(upstreams: &[UpstreamEntry]) -> Vec<&UpstreamEntry> {
     let mut enabled: Vec<&UpstreamEntry> = upstreams.iter().filter(|u| u.enabled).collect();
     enabled.sort_by_key(|u| u.priority);
     enabled
 }
 
-fn resolve_route(config: &ProxyConfig, path: &str) -> Option<RouteInfo> {
+fn resolve_route<'a>(config: &'a ProxyConfig, path: &str) -> Option<RouteInfo<'a>> {
     let service = select_service(config, path)?;
-    let trimmed_path = strip_base_path(path, &service.base_path);
 
-    let enabled_upstreams = enabled_upstreams_sorted(&service.upstreams);
+    if service.is_paused() {
+        return Some(RouteInfo {
+            service_id: &service.id,
+            service_name: service.name.clone(),
+            service_base: service.base_path.clone(),
+            log_request_body: service.logs_request_body(),
+            log_response_body: service.logs_response_body(),
+            paused_response: Some(service.paused_response.clone().unwrap_or_else(|| {
+                serde_json::json!({ "error": format!("服务「{}」维护中，请稍后再试", service.name) })
+                    .to_string()
+            })),
+            access_schedule: None,
+            streaming: service.streaming.clone(),
+            retry_rules: &[],
+            cache: None,
+            dedupe_in_flight: false,
+            default_model: None,
+            model_filter: None,
+            pii_mask: None,
+            compare_upstream: None,
+            mirror: None,
+            max_image_bytes: None,
+            max_request_bytes: None,
+            retry_queue: None,
+            context_overflow: None,
+            policy_fallback: None,
+            blocked_request_headers: Vec::new(),
+            blocked_response_headers: Vec::new(),
+            upstreams: Vec::new(),
+        });
+    }
+
+    let mut enabled_upstreams = enabled_upstreams_sorted(&service.upstreams);
+    if config.key_expiry.as_ref().is_some_and(KeyExpiryConfig::skips_expired) {
+        let now = chrono::Utc::now();
+        enabled_upstreams.retain(|u| !key_expiry::is_expired(u, now));
+    }
+    if service.routing == Some(RoutingMode::Weighted) {
+        split::weighted_first(&mut enabled_upstreams, |u| split::weight_of(u), random_unit());
+    }
 
     if enabled_upstreams.is_empty() {
         return None;
     }
 
-    let upstreams: Vec<ResolvedUpstream> = enabled_upstreams
-        .into_iter()
-        .map(|u| ResolvedUpstream {
-            upstream_url: build_upstream_url(&u.upstream_base, &trimmed_path),
+    let upstream_path = path_rewrite::apply(
+        service.path_rewrites.as_deref().unwrap_or_default(),
+        strip_base_path(path, &service.base_path),
+    );
+    // 保存配置时已校验过，这里不会失败
+    let service_headers = identity_headers(None, service.headers.as_ref()).unwrap_or_default();
+    let resolve = |u: &UpstreamEntry| {
+        let upstream_url = build_upstream_url(&u.upstream_base, &upstream_path);
+        let (request_url, host_override) =
+            host_override::target(&upstream_url, u.host_override.as_deref());
+        ResolvedUpstream {
+            upstream_url,
+            request_url,
+            host_override,
             upstream_id: u.id.clone(),
             upstream_label: u.label.clone(),
             api_key: u.api_key.clone(),
-        })
+            rate_limit: u.rate_limit.clone(),
+            identity_headers: {
+                let mut headers = service_headers.clone();
+                headers.extend(identity_headers(u.user_agent.as_deref(), u.headers.as_ref()).unwrap_or_default());
+                headers
+            },
+            audit_outbound: u.audit_outbound.unwrap_or(false),
+            timeouts: TimeoutConfig::merge(u.timeouts.as_ref(), service.timeouts.as_ref()),
+            capabilities: capabilities::lookup(
+                config.provider_capabilities.as_deref(),
+                u.provider.as_deref(),
+            )
+            .cloned(),
+            mock: u.mock.clone(),
+            chaos: u.chaos.clone(),
+            structured_output: u.structured_output.unwrap_or_default(),
+            query_params: u.query_params.clone().unwrap_or_default(),
+            model_map: u.model_map.clone().unwrap_or_default(),
+        }
+    };
+    // 对比模式下主上游排在最前，对比上游不参与正常的重试与切换
+    let compare_upstream = service.compare.as_ref().and_then(|compare| {
+        if let Some(pos) = enabled_upstreams
+            .iter()
+            .position(|u| u.id == compare.primary_upstream_id)
+        {
+            enabled_upstreams[..=pos].rotate_right(1);
+        }
+        service
+            .upstreams
+            .iter()
+            .find(|u| u.id == compare.secondary_upstream_id)
+            .map(resolve)
+    });
+    let mirror = service.mirror.as_ref().and_then(|mirror| {
+        service
+            .upstreams
+            .iter()
+            .find(|u| u.id == mirror.upstream_id)
+            .map(|u| (mirror, resolve(u)))
+    });
+    let policy_fallback = service.policy_fallback_upstream_id.as_ref().and_then(|id| {
+        service
+            .upstreams
+            .iter()
+            .find(|u| &u.id == id && u.enabled)
+            .map(resolve)
+    });
+    let upstreams: Vec<ResolvedUpstream> = enabled_upstreams
+        .into_iter()
+        .filter(|u| Some(&u.id) != compare_upstream.as_ref().map(|c| &c.upstream_id))
+        .filter(|u| Some(&u.id) != service.mirror.as_ref().map(|m| &m.upstream_id))
+        .filter(|u| Some(&u.id) != service.policy_fallback_upstream_id.as_ref())
+        .map(resolve)
         .collect();
 
     Some(RouteInfo {
+        service_id: &service.id,
         service_name: service.name.clone(),
         service_base: service.base_path.clone(),
+        log_request_body: service.logs_request_body(),
+        log_response_body: service.logs_response_body(),
+        paused_response: None,
+        access_schedule: service.access_schedule.as_ref(),
+        streaming: service.streaming.clone(),
+        retry_rules: service.retry_rules.as_deref().unwrap_or_default(),
+        cache: service.cache.as_ref(),
+        dedupe_in_flight: service.dedupes_in_flight(),
+        default_model: service.default_model.as_deref(),
+        model_filter: service.model_filter.as_ref(),
+        pii_mask: service.pii_mask.as_ref(),
+        compare_upstream,
+        mirror,
+        max_image_bytes: service.max_image_bytes,
+        max_request_bytes: service.max_request_bytes,
+        retry_queue: service.retry_queue.as_ref().filter(|q| q.matches(path)),
+        context_overflow: service.context_overflow.as_ref(),
+        policy_fallback,
+        // 保存配置时已校验过，这里不会失败
+        blocked_request_headers: header_names(service.blocked_request_headers.as_deref()).unwrap_or_default(),
+        blocked_response_headers: header_names(service.blocked_response_headers.as_deref()).unwrap_or_default(),
         upstreams,
     })
 }
 
-fn should_retry_status(status: StatusCode) -> bool {
-    status.is_server_error()
-        || status == StatusCode::TOO_MANY_REQUESTS
-        || status == StatusCode::REQUEST_TIMEOUT
+/// 读取错误响应体用于分类和匹配重试条件，并重建一个等价的响应供后续流程转发；
+/// 响应体读取中断时返回错误，由调用方按本次尝试失败处理
+async fn buffer_error_response(
+    resp: reqwest::Response,
+) -> Result<(reqwest::Response, Bytes, Option<ErrorKind>), String> {
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("读取上游错误响应失败（{status}）: {e}"))?;
+    let kind = classify_error(status.as_u16(), &body);
+
+    let mut rebuilt = http::Response::new(body.clone());
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    Ok((reqwest::Response::from(rebuilt), body, kind))
+}
+
+/// 协议不符时代替上游网页返回给客户端的 502 错误
+fn protocol_mismatch_response(content_type: &str) -> reqwest::Response {
+    let payload = serde_json::json!({ "error": provider_error::protocol_mismatch_message(content_type) });
+    let mut rebuilt = http::Response::new(payload.to_string());
+    *rebuilt.status_mut() = StatusCode::BAD_GATEWAY;
+    rebuilt
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    reqwest::Response::from(rebuilt)
+}
+
+/// 读取完整响应体后重建等价的响应；读取失败时不返回响应体，并把错误留给后续转发
+async fn buffer_response(resp: reqwest::Response) -> (reqwest::Response, Option<Bytes>) {
+    let status = resp.status();
+    let version = resp.version();
+    let headers = resp.headers().clone();
+    let (body, bytes) = match resp.bytes().await {
+        Ok(bytes) => (reqwest::Body::from(bytes.clone()), Some(bytes)),
+        Err(err) => (
+            reqwest::Body::wrap_stream(futures_util::stream::once(async move { Err::<Bytes, _>(err) })),
+            None,
+        ),
+    };
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    (reqwest::Response::from(rebuilt), bytes)
+}
+
+/// 把完整的 JSON 响应转换为 SSE；无法识别的格式或读取失败时按原样转发
+async fn convert_to_sse(resp: reqwest::Response) -> reqwest::Response {
+    let (resp, body) = buffer_response(resp).await;
+    let Some(sse) = body.as_deref().and_then(stream_convert::json_to_sse) else {
+        return resp;
+    };
+    let mut headers = resp.headers().clone();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("text/event-stream"));
+    let mut rebuilt = http::Response::new(reqwest::Body::from(sse));
+    *rebuilt.status_mut() = resp.status();
+    *rebuilt.version_mut() = resp.version();
+    *rebuilt.headers_mut() = headers;
+    reqwest::Response::from(rebuilt)
+}
+
+/// 超过客户端通过请求头指定的超时：写入日志并返回 504
+async fn client_timeout_exceeded(
+    shared: &SharedState,
+    mut entry: ProxyLogEntry,
+    started_at: Instant,
+    timeout: Duration,
+    attempt_errors: &[String],
+) -> Response<Body> {
+    let msg = format!("已超过客户端指定的超时 {} ms", timeout.as_millis());
+    tracing::Span::current().record("http.response.status_code", StatusCode::GATEWAY_TIMEOUT.as_u16());
+    entry.status = Some(StatusCode::GATEWAY_TIMEOUT.as_u16());
+    entry.error = Some(if attempt_errors.is_empty() {
+        msg.clone()
+    } else {
+        format!("{msg}: {}", attempt_errors.join("; "))
+    });
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg.clone()));
+    entry.duration_ms = started_at.elapsed().as_millis();
+    logging::upsert_log(shared.logs.clone(), entry).await;
+    error_response(StatusCode::GATEWAY_TIMEOUT, &msg)
+}
+
+/// 请求体超过服务的大小上限：写入日志并返回 413
+async fn reject_oversized_body(
+    shared: &SharedState,
+    mut entry: ProxyLogEntry,
+    started_at: Instant,
+    max: u64,
+) -> Response<Body> {
+    let msg = format!("请求体超过服务的大小上限（{max} 字节）");
+    tracing::Span::current().record("http.response.status_code", StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+    entry.status = Some(StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+    entry.error = Some(msg.clone());
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg.clone()));
+    entry.duration_ms = started_at.elapsed().as_millis();
+    logging::upsert_log(shared.logs.clone(), entry).await;
+    error_response(StatusCode::PAYLOAD_TOO_LARGE, &msg)
+}
+
+/// 所有上游都失败时把请求加入延迟重试队列，返回 202 与任务 id；
+/// 请求体已按服务的遮盖规则处理，服务不记录请求体时任务不落盘
+#[allow(clippy::too_many_arguments)]
+async fn park_request(
+    shared: &SharedState,
+    config: &ProxyConfig,
+    queue: &RetryQueueConfig,
+    service_id: &str,
+    mut entry: ProxyLogEntry,
+    started_at: Instant,
+    parts: &http::request::Parts,
+    body: &[u8],
+    log_request_body: bool,
+    reason: &str,
+) -> Response<Body> {
+    let rules = redaction::rules(config);
+    let job = retry_queue::enqueue(
+        queue,
+        service_id,
+        entry.service_name.as_deref().unwrap_or_default(),
+        entry.base_path.as_deref().unwrap_or("/"),
+        parts,
+        body,
+        |name| rules.is_sensitive_header(name),
+        log_request_body,
+    );
+    let msg = format!("所有上游均失败，已加入延迟重试队列（任务 {}）", job.id);
+    tracing::Span::current().record("http.response.status_code", StatusCode::ACCEPTED.as_u16());
+    entry.status = Some(StatusCode::ACCEPTED.as_u16());
+    entry.error = Some(format!("{msg}: {reason}"));
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(msg));
+    entry.duration_ms = started_at.elapsed().as_millis();
+    logging::upsert_log(shared.logs.clone(), entry).await;
+    let payload = serde_json::json!({
+        "queued": true,
+        "jobId": job.id,
+        "status": job.status,
+        "pollUrl": format!("{}jobs/{}", status::RESERVED_PREFIX, job.id),
+    });
+    Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(retry_queue::JOB_ID_HEADER, job.id.as_str())
+        .body(Body::from(payload.to_string()))
+        .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "加入延迟重试队列失败"))
+}
+
+/// 用缓存或合并请求得到的完整响应直接应答客户端，并补全日志
+async fn replay_response(
+    shared: &SharedState,
+    mut entry: ProxyLogEntry,
+    cached: CachedResponse,
+    headers: header::HeaderMap,
+    rules: &RedactionConfig,
+    capture_bodies: bool,
+    started_at: Instant,
+    detail: &str,
+) -> Result<Response<Body>, StatusCode> {
+    entry.status = Some(cached.status.as_u16());
+    entry.upstream_id = Some(cached.upstream_id);
+    entry.upstream_label = cached.upstream_label;
+    entry.response_headers = Some(rules.response_headers(&cached.headers));
+    if capture_bodies {
+        entry.response_body = truncate_body(&cached.body, 8000).map(|b| rules.redact_body(b));
+    }
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Completed, started_at).detail(detail));
+    entry.duration_ms = started_at.elapsed().as_millis();
+    logging::upsert_log(shared.logs.clone(), entry).await;
+    build_response(cached.status, headers, Body::from(cached.body))
+}
+
+fn response_content_type(resp: &reqwest::Response) -> &str {
+    resp.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// 读取流式响应的首个数据块后重建等价的响应；超时或首块前出错时返回错误，由调用方重试或切换上游
+async fn await_first_chunk(
+    resp: reqwest::Response,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, String> {
+    let status = resp.status();
+    let version = resp.version();
+    let headers = resp.headers().clone();
+    let mut stream = resp.bytes_stream();
+    let first = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, stream.next())
+            .await
+            .map_err(|_| format!("等待上游首个数据块超时（{} 秒）", timeout.as_secs()))?,
+        None => stream.next().await,
+    };
+    let first = match first {
+        Some(Ok(chunk)) => Some(chunk),
+        Some(Err(err)) => return Err(format!("上游流在首个数据块前中断: {err}")),
+        None => None,
+    };
+    let body = futures_util::stream::iter(first.map(Ok::<_, reqwest::Error>)).chain(stream);
+    let mut rebuilt = http::Response::new(reqwest::Body::wrap_stream(body));
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(reqwest::Response::from(rebuilt))
+}
+
+/// 发往上游的最终请求头：改写凭证后再套用上游的固定身份请求头
+fn outbound_headers(
+    headers: &header::HeaderMap,
+    api_key: Option<&str>,
+    identity: &header::HeaderMap,
+) -> header::HeaderMap {
+    let mut upstream_headers = rewrite_upstream_headers(headers, api_key);
+    for (name, value) in identity {
+        upstream_headers.insert(name.clone(), value.clone());
+    }
+    upstream_headers
 }
 
 /// 返回 (RequestBuilder, 上游请求头字符串用于日志)
@@ -783,70 +3435,20 @@ fn prepare_upstream_request(
     client: &reqwest::Client,
     method: &http::Method,
     url: &str,
+    query_rules: &[QueryParamRule],
     headers: &header::HeaderMap,
     api_key: Option<&str>,
-    body: Bytes,
+    identity: &header::HeaderMap,
+    body: impl Into<reqwest::Body>,
 ) -> (reqwest::RequestBuilder, String) {
-    let mut builder = client.request(method.clone(), url);
-    let mut upstream_headers: Vec<(String, String)> = Vec::new();
-
-    // Detect which auth header the client is using to pass it through correctly or adapt it
-    let mut client_auth_header: Option<header::HeaderValue> = None;
-    let mut client_goog_key: Option<header::HeaderValue> = None;
-    let uses_goog_api_key = headers
-        .keys()
-        .any(|name| name.as_str().eq_ignore_ascii_case("x-goog-api-key"));
-
-    for (name, value) in headers.iter() {
-        if name == header::HOST || name == header::CONTENT_LENGTH {
-            continue;
-        }
-        // Auth header处理：记录原值，稍后根据配置决定覆盖或回填
-        if name == header::AUTHORIZATION {
-            client_auth_header = Some(value.clone());
-            continue;
-        }
-        if name.as_str().eq_ignore_ascii_case("x-goog-api-key") {
-            client_goog_key = Some(value.clone());
-            continue;
-        }
-        if name.as_str().eq_ignore_ascii_case("x-proxy-key") {
-            continue;
-        }
-        builder = builder.header(name.clone(), value.clone());
-        upstream_headers.push((name.to_string(), value.to_str().unwrap_or("<binary>").to_string()));
-    }
-
-    // 优先使用配置的上游 key，否则回填客户端提供的 auth
-    match api_key {
-        Some(key) => {
-            if uses_goog_api_key {
-                builder = builder.header("x-goog-api-key", key);
-                upstream_headers.push(("x-goog-api-key".to_string(), key.to_string()));
-            } else {
-                builder = builder.bearer_auth(key);
-                upstream_headers.push(("authorization".to_string(), format!("Bearer {}", key)));
-            }
-        }
-        None => {
-            if let Some(v) = client_goog_key.clone() {
-                builder = builder.header("x-goog-api-key", v.clone());
-                upstream_headers.push(("x-goog-api-key".to_string(), v.to_str().unwrap_or("<binary>").to_string()));
-            }
-            if let Some(v) = client_auth_header.clone() {
-                builder = builder.header(header::AUTHORIZATION, v.clone());
-                upstream_headers.push(("authorization".to_string(), v.to_str().unwrap_or("<binary>").to_string()));
-            }
-        }
-    }
-
-    let headers_str = upstream_headers
-        .iter()
-        .map(|(k, v)| format!("{}: {}", k, v))
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    (builder.body(body), headers_str)
+    let upstream_headers = outbound_headers(headers, api_key, identity);
+    let headers_str = format_upstream_headers(&upstream_headers);
+
+    let builder = client
+        .request(method.clone(), query_params::apply(url, query_rules))
+        .headers(upstream_headers)
+        .body(body);
+    (builder, headers_str)
 }
 
 async fn handle_upstream_response(
@@ -855,28 +3457,37 @@ async fn handle_upstream_response(
     request_started: Instant,
     attempt_started: Instant,
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
+    stats: Arc<StatsStore>,
     upstream_id: String,
     upstream_label: Option<String>,
+    capture_bodies: bool,
+    stream_probe: &StreamProbe,
+    stream_idle: Option<Duration>,
+    config: Arc<ProxyConfig>,
+    permit: Option<SlotPermit>,
 ) -> Result<Response<Body>, StatusCode> {
     let status = resp.status();
     entry.status = Some(status.as_u16());
+    entry.timeline.push(
+        TimelineEvent::new(TimelineEventKind::FirstByte, request_started)
+            .upstream(&upstream_id)
+            .detail(status.as_str()),
+    );
 
     let headers = resp.headers().clone();
-    entry.response_headers = Some(format_headers(&headers));
+    entry.response_headers = Some(redaction::rules(&config).response_headers(&headers));
 
     let content_type = headers
         .get(header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
-    let is_streaming = content_type.contains("text/event-stream")
-        || content_type.contains("application/x-ndjson")
-        || content_type.contains("text/plain");
+    let is_streaming = stream_probe.is_streaming(status, content_type);
 
     entry.is_streaming = is_streaming;
 
-    if is_streaming {
+    // 不记录响应体时普通响应也走流式桥接，避免先完整缓冲再转发
+    if is_streaming || !capture_bodies {
         handle_streaming_body(
             resp,
             entry,
@@ -888,9 +3499,13 @@ async fn handle_upstream_response(
             upstream_label,
             status,
             headers,
+            capture_bodies,
+            stream_idle.filter(|_| is_streaming),
+            config,
+            permit,
         )
     } else {
-        handle_regular_body(
+        let response = handle_regular_body(
             resp,
             entry,
             request_started,
@@ -901,8 +3516,12 @@ async fn handle_upstream_response(
             upstream_label,
             status,
             headers,
+            config,
         )
-        .await
+        .await;
+        // 普通响应读完即释放并发空位
+        drop(permit);
+        response
     }
 }
 
@@ -912,57 +3531,188 @@ fn handle_streaming_body(
     request_started: Instant,
     attempt_started: Instant,
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
+    stats: Arc<StatsStore>,
     upstream_id: String,
     upstream_label: Option<String>,
     status: StatusCode,
     headers: header::HeaderMap,
+    capture_bodies: bool,
+    idle_timeout: Option<Duration>,
+    config: Arc<ProxyConfig>,
+    permit: Option<SlotPermit>,
 ) -> Result<Response<Body>, StatusCode> {
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Result<Bytes, std::io::Error>>();
+    // 有界通道：客户端读取跟不上时暂停读取上游，而不是在内存中无限堆积
+    let bridge = config.stream_bridge.as_ref();
+    let (tx, rx) =
+        tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(stream_bridge::buffer_chunks(bridge));
+    let mut framer = (entry.is_streaming && stream_bridge::flush_per_event(bridge)).then(SseFramer::default);
     let mut byte_stream = resp.bytes_stream();
 
     let entry_clone = entry.clone();
-    
+
+    // 仅旁路真正的流式响应；未记录响应体时走桥接的普通响应不在此列
+    let tee = config
+        .stream_tee
+        .as_ref()
+        .filter(|_| entry.is_streaming)
+        .map(tee::handle_for);
+
+    // 开启校验时边转发边计算响应体摘要，结束后与上游声明比对
+    let mut hasher = entry
+        .checksum
+        .is_some()
+        .then(|| (BodyHasher::default(), headers.clone()));
+
+    let body_span = tracing::info_span!("response_body", apiflow.streaming = entry.is_streaming);
     tokio::spawn(async move {
+        // 流式响应转发结束后才释放并发空位
+        let _permit = permit;
+        let _stream = entry_clone.is_streaming.then(app_metrics::InFlight::stream);
         let mut collected = BytesMut::new();
+        let mut collected_bytes = app_metrics::CollectedBytes::default();
+        let mut stream_error: Option<String> = None;
+        let request_id = entry_clone.id.clone();
+        let mut seq: u64 = 0;
+        let mut usage_scanner = UsageScanner::new(entry_clone.is_streaming);
+
+        if let Some(tee) = &tee {
+            tee.send(TeeMessage::Start {
+                request_id: request_id.clone(),
+                path: entry_clone.path.clone(),
+                model: entry_clone.model.clone(),
+            });
+        }
 
-        while let Some(chunk) = byte_stream.next().await {
+        loop {
+            let next = match idle_timeout {
+                Some(idle) => match tokio::time::timeout(idle, byte_stream.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        let message = format!("上游流超过 {} 秒未发送数据，已中断", idle.as_secs());
+                        let _ = tx
+                            .send(Err(std::io::Error::new(
+                                std::io::ErrorKind::TimedOut,
+                                message.clone(),
+                            )))
+                            .await;
+                        stream_error = Some(message);
+                        break;
+                    }
+                },
+                None => byte_stream.next().await,
+            };
+            let Some(chunk) = next else {
+                break;
+            };
             match chunk {
                 Ok(bytes) => {
-                    collected.extend_from_slice(&bytes);
-                    if tx.send(Ok(bytes)).is_err() {
+                    if capture_bodies {
+                        collected.extend_from_slice(&bytes);
+                        collected_bytes.add(bytes.len());
+                    }
+                    usage_scanner.feed(&bytes);
+                    if let Some((hasher, _)) = hasher.as_mut() {
+                        hasher.update(&bytes);
+                    }
+                    if let Some(tee) = &tee {
+                        tee.send(TeeMessage::Chunk {
+                            request_id: request_id.clone(),
+                            seq,
+                            data: String::from_utf8_lossy(&bytes).into_owned(),
+                        });
+                        seq += 1;
+                    }
+                    let frames = match framer.as_mut() {
+                        Some(framer) => framer.push(&bytes),
+                        None => vec![bytes],
+                    };
+                    let mut client_gone = false;
+                    for frame in frames {
+                        if tx.send(Ok(frame)).await.is_err() {
+                            client_gone = true;
+                            break;
+                        }
+                    }
+                    if client_gone {
                         break;
                     }
                 }
                 Err(e) => {
-                    let _ = tx.send(Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())));
+                    stream_error = Some(e.to_string());
+                    let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
                     break;
                 }
             }
         }
+        if let Some(rest) = framer.and_then(SseFramer::finish).filter(|_| stream_error.is_none()) {
+            let _ = tx.send(Ok(rest)).await;
+        }
+
+        if let Some(tee) = &tee {
+            tee.send(TeeMessage::End {
+                request_id,
+                error: stream_error.clone(),
+            });
+        }
 
-        let response_body = if collected.is_empty() {
+        let response_body = if !capture_bodies {
+            None
+        } else if collected.is_empty() {
             Some("[流式响应]".to_string())
         } else {
-            truncate_body(&collected, 64000)
+            truncate_body(&collected, 64000).map(|b| redaction::rules(&config).redact_body(b))
         };
 
         let mut final_entry = entry_clone;
+        if final_entry.error_kind.is_none() && (status.is_client_error() || status.is_server_error()) {
+            final_entry.error_kind = classify_error(status.as_u16(), &collected);
+        }
         final_entry.response_body = response_body;
+        final_entry.usage = usage_scanner.finish();
+        final_entry.cost = estimate_cost(
+            config.pricing.as_deref(),
+            final_entry.model.as_deref(),
+            final_entry.usage.as_ref(),
+        );
         final_entry.duration_ms = request_started.elapsed().as_millis();
+        // 转发中断时响应体不完整，摘要没有比对意义
+        if let (Some(report), Some((hasher, upstream_headers))) = (final_entry.checksum.as_mut(), hasher) {
+            if stream_error.is_none() {
+                report.record_response(hasher.finish(), &upstream_headers);
+                if final_entry.error.is_none() {
+                    final_entry.error = report.mismatch_summary();
+                }
+            }
+        }
+        final_entry.timeline.push(match stream_error {
+            Some(err) => TimelineEvent::new(TimelineEventKind::Failed, request_started).detail(err),
+            None => TimelineEvent::new(TimelineEventKind::Completed, request_started),
+        });
 
+        let dims = StatsDims::of(&final_entry);
+        let (usage, cost) = (final_entry.usage, final_entry.cost);
         logging::upsert_log(logs, final_entry).await;
-        logging::update_stats(
-            stats,
+        stats.record(
             &upstream_id,
             upstream_label,
+            &dims,
             attempt_started.elapsed().as_millis() as u64,
             !status.is_client_error() && !status.is_server_error(),
-        )
-        .await;
-    });
+        );
+        if let Some(usage) = usage {
+            stats.record_usage(&upstream_id, &dims, &usage, cost);
+            scheduler::charge(&upstream_id, usage.total_tokens);
+            budget::record(
+                config.budgets.as_deref(),
+                dims.service_name.as_deref(),
+                &upstream_id,
+                &usage,
+                cost,
+            );
+        }
+    }.instrument(body_span));
 
-    let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(rx);
+    let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
     let body = Body::from_stream(stream);
     build_response(status, headers, body)
 }
@@ -973,16 +3723,21 @@ async fn handle_regular_body(
     request_started: Instant,
     attempt_started: Instant,
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
+    stats: Arc<StatsStore>,
     upstream_id: String,
     upstream_label: Option<String>,
     status: StatusCode,
-    headers: header::HeaderMap,
+    mut headers: header::HeaderMap,
+    config: Arc<ProxyConfig>,
 ) -> Result<Response<Body>, StatusCode> {
+    let rules = redaction::rules(&config);
     let body_bytes = match resp.bytes().await {
         Ok(bytes) => bytes,
         Err(err) => {
             entry.error = Some(format!("读取上游响应失败: {err}"));
+            entry
+                .timeline
+                .push(TimelineEvent::new(TimelineEventKind::Failed, request_started).detail(err.to_string()));
             entry.duration_ms = request_started.elapsed().as_millis();
             logging::upsert_log(logs, entry).await;
             return Ok(error_response(StatusCode::BAD_GATEWAY, "上游响应读取失败"));
@@ -990,22 +3745,58 @@ async fn handle_regular_body(
     };
 
     entry.duration_ms = request_started.elapsed().as_millis();
-    entry.response_body = truncate_body(&body_bytes, 8000);
+    entry
+        .timeline
+        .push(TimelineEvent::new(TimelineEventKind::Completed, request_started));
+    entry.response_body = truncate_body(&body_bytes, 8000).map(|b| rules.redact_body(b));
+    entry.usage = parse_json_usage(&body_bytes);
+    entry.cost = estimate_cost(
+        config.pricing.as_deref(),
+        entry.model.as_deref(),
+        entry.usage.as_ref(),
+    );
 
     if status.is_client_error() || status.is_server_error() {
+        entry.error_kind = entry
+            .error_kind
+            .or_else(|| classify_error(status.as_u16(), &body_bytes));
         let text = String::from_utf8_lossy(&body_bytes);
         let snippet: String = text.chars().take(2000).collect();
-        entry.error = Some(format!("上游返回 {status}: {snippet}"));
+        entry.error = Some(format!("上游返回 {status}: {}", rules.redact_text(&snippet)));
+    }
+    // 上游未声明摘要时由代理补上，客户端可据此校验代理到客户端这一段链路
+    if let Some(report) = entry.checksum.as_mut() {
+        let sha256 = sha256_hex(&body_bytes);
+        if !headers.contains_key(CONTENT_DIGEST) {
+            if let Some(digest) = checksum::digest_header(&sha256) {
+                headers.insert(CONTENT_DIGEST, digest);
+            }
+        }
+        report.record_response(sha256, &headers);
+        if entry.error.is_none() {
+            entry.error = report.mismatch_summary();
+        }
     }
 
-    logging::update_stats(
-        stats,
+    let dims = StatsDims::of(&entry);
+    stats.record(
         &upstream_id,
         upstream_label,
+        &dims,
         attempt_started.elapsed().as_millis() as u64,
         !status.is_client_error() && !status.is_server_error(),
-    )
-    .await;
+    );
+    if let Some(usage) = &entry.usage {
+        stats.record_usage(&upstream_id, &dims, usage, entry.cost);
+        scheduler::charge(&upstream_id, usage.total_tokens);
+        budget::record(
+            config.budgets.as_deref(),
+            dims.service_name.as_deref(),
+            &upstream_id,
+            usage,
+            entry.cost,
+        );
+    }
 
     logging::upsert_log(logs, entry).await;
 
@@ -1031,7 +3822,7 @@ fn build_response(
 }
 
 #[cfg(test)]
-fn select_upstream<'a>(upstreams: &'a [UpstreamEntry]) -> Option<&'a UpstreamEntry> {
+fn select_upstream(upstreams: &[UpstreamEntry]) -> Option<&UpstreamEntry> {
     let enabled = enabled_upstreams_sorted(upstreams);
     if enabled.is_empty() {
         return None;
@@ -1040,6 +3831,7 @@ fn select_upstream<'a>(upstreams: &'a [UpstreamEntry]) -> Option<&'a UpstreamEnt
     enabled.into_iter().next()
 }
 
+/// 暂停的服务仍参与匹配，由调用方返回维护响应，避免请求落到前缀更短的其他服务
 fn select_service<'a>(config: &'a ProxyConfig, path: &str) -> Option<&'a ServiceConfig> {
     let enabled: Vec<&ServiceConfig> = config.services.iter().filter(|s| s.enabled).collect();
     if enabled.is_empty() {
@@ -1048,12 +3840,7 @@ fn select_service<'a>(config: &'a ProxyConfig, path: &str) -> Option<&'a Service
 
     let mut candidates: Vec<&ServiceConfig> = enabled
         .into_iter()
-        .filter(|svc| {
-            if svc.base_path == "/" {
-                return true;
-            }
-            path.starts_with(&svc.base_path)
-        })
+        .filter(|svc| matches_base_path(path, &svc.base_path))
         .collect();
 
     candidates.sort_by_key(|svc| std::cmp::Reverse(svc.base_path.len()));
@@ -1062,6 +3849,83 @@ fn select_service<'a>(config: &'a ProxyConfig, path: &str) -> Option<&'a Service
 
 
 
+async fn reserved_response(
+    route: ReservedRoute,
+    path: &str,
+    shared: &SharedState,
+    config: &ProxyConfig,
+) -> Response<Body> {
+    let uptime = shared.started_at.elapsed();
+    let payload = match route {
+        ReservedRoute::Healthz => {
+            serde_json::json!({ "status": "ok", "uptimeSecs": uptime.as_secs() }).to_string()
+        }
+        ReservedRoute::Status => {
+            let stats = shared.stats.snapshot();
+            let logs = shared.logs.lock().await;
+            let status = status::build_status(config, uptime, &logs, &stats);
+            serde_json::to_string(&status).unwrap_or_default()
+        }
+        ReservedRoute::Job => match status::job_id(path).and_then(retry_queue::job) {
+            Some(job) => serde_json::to_string(&job).unwrap_or_default(),
+            None => return error_response(StatusCode::NOT_FOUND, "任务不存在"),
+        },
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(payload))
+        .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "生成状态失败"))
+}
+
+fn synthetic_response(
+    endpoint: &SyntheticEndpoint,
+    request_id: &Uuid,
+    method: &http::Method,
+    path: &str,
+) -> Response<Body> {
+    Response::builder()
+        .status(endpoint.status())
+        .header(header::CONTENT_TYPE, endpoint.content_type())
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(endpoint.render(&request_id.to_string(), method, path)))
+        .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "生成响应失败"))
+}
+
+/// OPTIONS 返回允许跨域的预检响应，HEAD 返回不带响应体的 200
+fn local_response(parts: &http::request::Parts) -> Response<Body> {
+    const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS, HEAD";
+    let mut builder = Response::builder();
+    if parts.method == http::Method::OPTIONS {
+        let origin = parts
+            .headers
+            .get(header::ORIGIN)
+            .cloned()
+            .unwrap_or(header::HeaderValue::from_static("*"));
+        let request_headers = parts
+            .headers
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned()
+            .unwrap_or(header::HeaderValue::from_static("*"));
+        builder = builder
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ALLOW, ALLOWED_METHODS)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, ALLOWED_METHODS)
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, request_headers)
+            .header(header::ACCESS_CONTROL_MAX_AGE, "86400")
+            .header(header::VARY, "Origin");
+    } else {
+        builder = builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json");
+    }
+    builder
+        .body(Body::empty())
+        .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR, "生成响应失败"))
+}
+
 fn error_response(status: StatusCode, msg: &str) -> Response<Body> {
     let payload = serde_json::json!({ "error": msg }).to_string();
     Response::builder()
@@ -1078,26 +3942,77 @@ fn error_response(status: StatusCode, msg: &str) -> Response<Body> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    safe_mode::init_from_env();
+    let proxy_state = ProxyState::new();
+    let stats = proxy_state.stats.clone();
+    let logs = proxy_state.logs.clone();
+    let config = proxy_state.config.clone();
+    let client = proxy_state.client.clone();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
-        .manage(ProxyState::new())
+        .manage(proxy_state)
         .invoke_handler(tauri::generate_handler![
             start_proxy,
             stop_proxy,
             get_logs,
+            get_log_detail,
+            export_conversation,
+            reconcile_usage,
+            get_curl_command,
             clear_logs,
             get_stats,
+            get_stats_breakdown,
+            get_spend_summary,
+            get_budget_status,
+            get_key_expiry_status,
+            get_upstream_balances,
+            get_provider_status,
+            get_daily_summary,
+            get_app_metrics,
+            clear_cache,
+            get_retry_queue,
+            get_safe_mode,
+            start_failover_drill,
+            stop_failover_drill,
+            list_failover_drills,
+            get_queued_job,
+            clear_finished_jobs,
             clear_stats,
+            prune_archived_stats,
+            relink_upstream_stats,
             load_settings,
+            export_config,
+            import_config,
             save_settings,
             reload_proxy,
+            pause_service,
+            set_upstream_enabled,
+            mark_upstream_verified,
+            import_keys,
+            import_gateway_config,
+            resume_service,
             update_tray_status,
             get_network_info
         ])
-        .setup(|app| {
+        .setup(move |app| {
             tray::setup_tray(app)?;
+            events::init(app.handle().clone(), stats.clone());
+            if let Err(err) = persistence::init() {
+                eprintln!("{err}");
+            }
+            logging::restore_persisted(logs.clone());
+            admin_api::init(app.handle().clone());
+            app_metrics::spawn_watch_task(logs.clone());
+            logging::spawn_retention_task(logs);
+            key_expiry::spawn_check_task(config.clone());
+            daily_summary::spawn_task(config.clone(), stats.clone());
+            hooks::spawn_health_task(config.clone(), stats);
+            balance::spawn_poll_task(client.clone(), config.clone());
+            provider_status::spawn_poll_task(client, config.clone());
+            retry_queue::init(config);
             Ok(())
         })
         .run(tauri::generate_context!())
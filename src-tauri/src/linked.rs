@@ -0,0 +1,129 @@
+//! 关联请求：影子流量与对比模式复制出的请求共用的发送与记录流程。
+//! 响应读完后按正常请求的方式解析用量与费用，计入上游统计与预算，
+//! 再生成一条挂在原请求日志下的关联日志。
+
+use std::time::Instant;
+
+use bytes::Bytes;
+use reqwest::header::CONTENT_TYPE;
+
+use crate::pricing::estimate_cost;
+use crate::stats::{StatsDims, StatsStore};
+use crate::timeline::{TimelineEvent, TimelineEventKind};
+use crate::usage::{TokenUsage, UsageScanner};
+use crate::{budget, scheduler, timestamp, ProxyConfig, ProxyLogEntry, ResolvedUpstream};
+
+/// 关联请求的结果
+pub struct Outcome {
+    pub result: Result<(u16, Bytes), String>,
+    pub usage: Option<TokenUsage>,
+    pub duration_ms: u128,
+}
+
+impl Outcome {
+    pub fn failed(error: String) -> Self {
+        Self {
+            result: Err(error),
+            usage: None,
+            duration_ms: 0,
+        }
+    }
+}
+
+/// 发送请求并读完响应体；`target` 用于错误信息，如“影子上游”
+pub async fn send(request: reqwest::RequestBuilder, target: &str) -> Outcome {
+    let started = Instant::now();
+    let (result, usage) = match request.send().await {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let is_sse = resp
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("text/event-stream"));
+            match resp.bytes().await {
+                Ok(body) => {
+                    let mut scanner = UsageScanner::new(is_sse);
+                    scanner.feed(&body);
+                    (Ok((status, body)), scanner.finish())
+                }
+                Err(e) => (Err(format!("读取{target}响应失败: {e}")), None),
+            }
+        }
+        Err(err) => (Err(format!("请求{target}失败: {err}")), None),
+    };
+    Outcome {
+        result,
+        usage,
+        duration_ms: started.elapsed().as_millis(),
+    }
+}
+
+/// 生成关联日志并把这次请求计入统计与预算；`response_body` 需已按日志开关与脱敏规则处理，
+/// 成功时时间线使用 `detail`，失败时使用错误原因
+#[allow(clippy::too_many_arguments)]
+pub fn record(
+    template: ProxyLogEntry,
+    id_suffix: &str,
+    upstream: &ResolvedUpstream,
+    outcome: &Outcome,
+    response_body: Option<String>,
+    detail: String,
+    started_at: Instant,
+    config: &ProxyConfig,
+    stats: &StatsStore,
+) -> ProxyLogEntry {
+    let (status, error) = match &outcome.result {
+        Ok((status, _)) => (Some(*status), None),
+        Err(err) => (None, Some(err.clone())),
+    };
+    let event = match &error {
+        Some(err) => TimelineEvent::new(TimelineEventKind::Failed, started_at).detail(err.clone()),
+        None => TimelineEvent::new(TimelineEventKind::Completed, started_at).detail(detail),
+    };
+    let cost = estimate_cost(
+        config.pricing.as_deref(),
+        template.model.as_deref(),
+        outcome.usage.as_ref(),
+    );
+    let entry = ProxyLogEntry {
+        id: format!("{}-{id_suffix}", template.id),
+        seq: timestamp::next_seq(),
+        upstream_url: upstream.upstream_url.clone(),
+        route_key: upstream.upstream_label.clone(),
+        upstream_label: upstream.upstream_label.clone(),
+        upstream_id: Some(upstream.upstream_id.clone()),
+        trace_id: None,
+        status,
+        error,
+        duration_ms: outcome.duration_ms,
+        response_headers: None,
+        response_body,
+        usage: outcome.usage,
+        cost,
+        retry_action: None,
+        timeline: vec![event.upstream(&upstream.upstream_id)],
+        ..template
+    };
+
+    let dims = StatsDims::of(&entry);
+    stats.record(
+        &upstream.upstream_id,
+        upstream.upstream_label.clone(),
+        &dims,
+        outcome.duration_ms as u64,
+        status.is_some_and(|s| s < 400),
+    );
+    if let Some(usage) = &entry.usage {
+        stats.record_usage(&upstream.upstream_id, &dims, usage, cost);
+        scheduler::charge(&upstream.upstream_id, usage.total_tokens);
+        budget::record(
+            config.budgets.as_deref(),
+            dims.service_name.as_deref(),
+            &upstream.upstream_id,
+            usage,
+            cost,
+        );
+    }
+    entry
+}
@@ -1,49 +1,327 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
+use ts_rs::TS;
 
-use crate::{ProxyLogEntry, UpstreamStats};
+use crate::persistence::load_config;
+use crate::{events, storage, timestamp, ProxyLogEntry};
 
-pub const MAX_LOGS: usize = 200;
+pub const DEFAULT_MAX_LOGS: usize = 200;
+/// 可配置的内存日志条数上限
+pub const MAX_LOGS_LIMIT: usize = 100_000;
+const RETENTION_INTERVAL: Duration = Duration::from_secs(30);
+
+static MAX_ENTRIES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LOGS);
+/// 0 表示不按时间淘汰
+static MAX_AGE_SECS: AtomicU64 = AtomicU64::new(0);
+/// 0 表示不限制持久化日志的磁盘占用
+static MAX_DISK_MB: AtomicU64 = AtomicU64::new(0);
+
+/// 日志保留策略，未设置的字段使用默认值（200 条、不按时间淘汰）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RetentionConfig.ts")]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionConfig {
+    /// 内存中最多保留的日志条数
+    #[ts(optional)]
+    pub max_entries: Option<usize>,
+    /// 日志最长保留时间（秒），处理中的请求不会因超时被淘汰
+    #[ts(optional, type = "number")]
+    pub max_age_secs: Option<u64>,
+    /// 持久化日志的磁盘占用上限（MB），超出后从最旧的日志开始删除；
+    /// 仅对 SQLite 与 JSONL 存储生效，JSONL 按天整体删除且保留当天文件
+    #[ts(optional, type = "number")]
+    pub max_disk_mb: Option<u64>,
+}
+
+impl RetentionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(n) = self.max_entries {
+            if !(1..=MAX_LOGS_LIMIT).contains(&n) {
+                return Err(format!("日志保留条数需在 1 到 {MAX_LOGS_LIMIT} 之间"));
+            }
+        }
+        if self.max_age_secs == Some(0) {
+            return Err("日志保留时间必须大于 0".into());
+        }
+        if self.max_disk_mb == Some(0) {
+            return Err("日志磁盘占用上限必须大于 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// 应用新的保留策略；传入 None 时恢复默认值
+pub fn apply_retention(config: Option<&RetentionConfig>) {
+    let max_entries = config
+        .and_then(|c| c.max_entries)
+        .unwrap_or(DEFAULT_MAX_LOGS)
+        .clamp(1, MAX_LOGS_LIMIT);
+    let max_age = config.and_then(|c| c.max_age_secs).unwrap_or(0);
+    let max_disk_mb = config.and_then(|c| c.max_disk_mb).unwrap_or(0);
+    MAX_ENTRIES.store(max_entries, Ordering::Relaxed);
+    MAX_AGE_SECS.store(max_age, Ordering::Relaxed);
+    MAX_DISK_MB.store(max_disk_mb, Ordering::Relaxed);
+}
+
+pub fn max_entries() -> usize {
+    MAX_ENTRIES.load(Ordering::Relaxed)
+}
+
+fn max_age() -> Option<Duration> {
+    match MAX_AGE_SECS.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+fn max_disk_bytes() -> Option<u64> {
+    match MAX_DISK_MB.load(Ordering::Relaxed) {
+        0 => None,
+        mb => Some(mb.saturating_mul(1024 * 1024)),
+    }
+}
+
+/// 按条数和时间淘汰最旧的日志，返回被移除的条数
+pub fn enforce_retention(
+    logs: &mut VecDeque<ProxyLogEntry>,
+    max_entries: usize,
+    max_age: Option<Duration>,
+    now: DateTime<Local>,
+) -> usize {
+    let before = logs.len();
+    if let Some(age) = max_age.and_then(|a| chrono::Duration::from_std(a).ok()) {
+        let cutoff = now - age;
+        logs.retain(|e| {
+            e.status.is_none() || timestamp::parse(&e.timestamp).is_none_or(|t| t >= cutoff)
+        });
+    }
+    while logs.len() > max_entries {
+        logs.pop_front();
+    }
+    before - logs.len()
+}
+
+/// 后台定期执行保留策略；条数上限在 upsert_log 中即时生效，这里主要负责按时间淘汰，
+/// 并让持久化存储按时间和磁盘占用清理
+pub fn spawn_retention_task(logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let mut guard = logs.lock().await;
+            enforce_retention(&mut guard, max_entries(), max_age(), Local::now());
+            drop(guard);
+            storage::prune(max_age(), max_disk_bytes());
+        }
+    });
+}
+
+/// 启动时按已保存的配置打开日志存储，并把最近的日志恢复到内存
+pub fn restore_persisted(logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>) {
+    let config = load_config().ok().flatten();
+    apply_retention(config.as_ref().and_then(|c| c.retention.as_ref()));
+    let storage_config = config.and_then(|c| c.log_storage);
+
+    tauri::async_runtime::spawn(async move {
+        let restored = tokio::task::spawn_blocking(move || {
+            storage::load_recent(storage_config.as_ref(), max_entries())
+                .and_then(|entries| storage::configure(storage_config.as_ref()).map(|_| entries))
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+        match restored {
+            Ok(entries) => {
+                if let Some(seq) = entries.iter().map(|e| e.seq).max() {
+                    timestamp::observe_seq(seq);
+                }
+                let mut guard = logs.lock().await;
+                for entry in entries.into_iter().rev() {
+                    if !guard.iter().any(|e| e.id == entry.id) {
+                        guard.push_front(entry);
+                    }
+                }
+                while guard.len() > max_entries() {
+                    guard.pop_front();
+                }
+            }
+            Err(err) => eprintln!("恢复持久化日志失败: {err}"),
+        }
+    });
+}
+
+/// get_logs 的服务端过滤条件，所有字段均为可选，未设置即不过滤
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/LogFilter.ts")]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFilter {
+    /// 状态码分类："2xx" / "3xx" / "4xx" / "5xx"，"pending" 表示处理中
+    #[ts(optional)]
+    pub status_class: Option<String>,
+    #[ts(optional)]
+    pub service_name: Option<String>,
+    #[ts(optional)]
+    pub upstream_label: Option<String>,
+    #[ts(optional)]
+    pub method: Option<String>,
+    /// 在路径、尝试标识、请求体、响应体中做不区分大小写的子串匹配
+    #[ts(optional)]
+    pub search: Option<String>,
+    /// 时间范围（含边界），RFC3339 或旧版本的本地时间格式
+    #[ts(optional)]
+    pub since: Option<String>,
+    #[ts(optional)]
+    pub until: Option<String>,
+    #[ts(optional)]
+    pub streaming_only: Option<bool>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &ProxyLogEntry) -> bool {
+        if let Some(class) = self.status_class.as_deref() {
+            let matched = match (class, entry.status) {
+                ("pending", status) => status.is_none(),
+                (_, None) => false,
+                (class, Some(status)) => class
+                    .strip_suffix("xx")
+                    .and_then(|d| d.parse::<u16>().ok())
+                    .map(|d| status / 100 == d)
+                    .unwrap_or(false),
+            };
+            if !matched {
+                return false;
+            }
+        }
+
+        if !eq_opt(self.service_name.as_deref(), entry.service_name.as_deref())
+            || !eq_opt(self.upstream_label.as_deref(), entry.upstream_label.as_deref())
+        {
+            return false;
+        }
+
+        if let Some(method) = self.method.as_deref() {
+            if !method.eq_ignore_ascii_case(&entry.method) {
+                return false;
+            }
+        }
+
+        if let Some(since) = self.since.as_deref() {
+            if timestamp::compare(&entry.timestamp, since).is_lt() {
+                return false;
+            }
+        }
+        if let Some(until) = self.until.as_deref() {
+            if timestamp::compare(&entry.timestamp, until).is_gt() {
+                return false;
+            }
+        }
+
+        if self.streaming_only.unwrap_or(false) && !entry.is_streaming {
+            return false;
+        }
+
+        if let Some(needle) = self.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            let needle = needle.to_lowercase();
+            let hit = [
+                Some(entry.path.as_str()),
+                entry.trace_id.as_deref(),
+                entry.request_body.as_deref(),
+                entry.response_body.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&needle));
+            if !hit {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// get_logs 的分页结果，entries 按时间正序排列
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/LogPage.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct LogPage {
+    pub entries: Vec<ProxyLogEntry>,
+    /// 满足过滤条件的日志总数（不受分页影响）
+    pub total: usize,
+    /// 沿翻页方向是否还有更多日志（before_id/默认为更早，after_id 为更新）
+    pub has_more: bool,
+}
+
+/// 基于日志 id 的游标分页：
+/// - before_id：返回游标之前（更早）的最多 `max` 条
+/// - after_id：返回游标之后（更新）的最多 `max` 条
+/// - 均未提供：返回最新的 `max` 条
+///
+/// 游标对应的日志已被淘汰（或不在筛选结果中）时返回错误，由调用方重新从最新一页加载
+pub fn paginate_logs(
+    filtered: Vec<ProxyLogEntry>,
+    before_id: Option<&str>,
+    after_id: Option<&str>,
+    max: usize,
+) -> Result<LogPage, String> {
+    let total = filtered.len();
+    let position = |id: &str| {
+        filtered
+            .iter()
+            .position(|e| e.id == id)
+            .ok_or_else(|| format!("日志 {id} 已不存在，可能已被淘汰，请重新加载"))
+    };
+
+    let (start, end, has_more) = if let Some(id) = after_id {
+        let start = position(id)? + 1;
+        let end = (start + max).min(total);
+        (start, end, end < total)
+    } else {
+        let end = match before_id {
+            Some(id) => position(id)?,
+            None => total,
+        };
+        let start = end.saturating_sub(max);
+        (start, end, start > 0)
+    };
+
+    let entries = filtered.into_iter().skip(start).take(end - start).collect();
+    Ok(LogPage {
+        entries,
+        total,
+        has_more,
+    })
+}
+
+fn eq_opt(expected: Option<&str>, actual: Option<&str>) -> bool {
+    match expected {
+        Some(want) => actual == Some(want),
+        None => true,
+    }
+}
 
 pub async fn upsert_log(logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>, entry: ProxyLogEntry) {
+    events::notify_log(&entry);
+    if entry.status.is_some() {
+        storage::persist(&entry);
+    }
     let mut guard = logs.lock().await;
     if let Some(pos) = guard.iter().position(|e| e.id == entry.id) {
         guard[pos] = entry;
     } else {
         guard.push_back(entry);
-        if guard.len() > MAX_LOGS {
+        let max = max_entries();
+        while guard.len() > max {
             guard.pop_front();
         }
     }
 }
 
-pub async fn update_stats(
-    stats: Arc<Mutex<HashMap<String, UpstreamStats>>>,
-    upstream_id: &str,
-    upstream_label: Option<String>,
-    duration_ms: u64,
-    success: bool,
-) {
-    let mut guard = stats.lock().await;
-    let entry = guard.entry(upstream_id.to_string()).or_insert_with(|| UpstreamStats {
-        upstream_id: upstream_id.to_string(),
-        upstream_label: upstream_label.clone(),
-        ..Default::default()
-    });
-    entry.total_requests += 1;
-    entry.total_duration_ms += duration_ms;
-    if success {
-        entry.success_count += 1;
-    } else {
-        entry.error_count += 1;
-    }
-    if entry.upstream_label.is_none() && upstream_label.is_some() {
-        entry.upstream_label = upstream_label;
-    }
-}
-
 /// Mark in-flight log entries (status == None) as terminated when the proxy stops.
 pub async fn finalize_inflight(
     logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
@@ -61,6 +339,8 @@ pub async fn finalize_inflight(
             if entry.duration_ms == 0 {
                 entry.duration_ms = 0;
             }
+            events::notify_log(entry);
+            storage::persist(entry);
         }
     }
 }
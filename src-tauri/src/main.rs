@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `apiflow logs` 等子命令通过管理接口与运行中的应用交互，不启动桌面界面
+    if let Some(code) = apiflow_lib::cli::run_from_args() {
+        std::process::exit(code);
+    }
     apiflow_lib::run()
 }
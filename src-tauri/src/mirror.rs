@@ -0,0 +1,84 @@
+//! 影子流量：按比例把服务收到的请求异步复制一份发给影子上游，客户端只收到正常路由的响应，
+//! 影子上游的响应读完后丢弃，状态码、耗时与响应体记录在一条关联日志中，
+//! 便于用真实流量评估新的服务商而不影响客户端。
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use ts_rs::TS;
+
+use crate::backoff::random_unit;
+use crate::helpers::truncate_body;
+use crate::stats::StatsStore;
+use crate::{
+    linked, logging, redaction, ProxyConfig, ProxyLogEntry, ResolvedUpstream, UpstreamEntry,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/MirrorConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorConfig {
+    /// 接收复制请求的影子上游，不参与正常的路由与切换
+    pub upstream_id: String,
+    /// 复制的请求比例（0 到 100）
+    pub percent: f64,
+}
+
+impl MirrorConfig {
+    pub fn validate(&self, upstreams: &[UpstreamEntry]) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.percent) {
+            return Err("影子流量的复制比例需在 0 到 100 之间".into());
+        }
+        if !upstreams.iter().any(|u| u.id == self.upstream_id) {
+            return Err(format!("影子流量引用了不存在的上游: {}", self.upstream_id));
+        }
+        Ok(())
+    }
+
+    /// 按比例决定本次请求是否复制
+    pub fn sampled(&self) -> bool {
+        random_unit() * 100.0 < self.percent
+    }
+}
+
+/// 发送复制的请求，读完响应后写入关联日志；不影响原请求的处理
+#[allow(clippy::too_many_arguments)]
+pub fn spawn(
+    logs: Arc<Mutex<VecDeque<ProxyLogEntry>>>,
+    stats: Arc<StatsStore>,
+    template: ProxyLogEntry,
+    shadow: ResolvedUpstream,
+    request: reqwest::RequestBuilder,
+    log_response_body: bool,
+    config: Arc<ProxyConfig>,
+    started_at: Instant,
+) {
+    tokio::spawn(async move {
+        let outcome = linked::send(request, "影子上游").await;
+        let rules = redaction::rules(&config);
+        let body = match &outcome.result {
+            Ok((_, body)) if log_response_body => {
+                truncate_body(body, 8000).map(|b| rules.redact_body(b))
+            }
+            _ => None,
+        };
+        let entry = ProxyLogEntry {
+            shadow: true,
+            ..linked::record(
+                template,
+                "mirror",
+                &shadow,
+                &outcome,
+                body,
+                "影子流量，响应未返回给客户端".to_string(),
+                started_at,
+                &config,
+                &stats,
+            )
+        };
+        logging::upsert_log(logs, entry).await;
+    });
+}
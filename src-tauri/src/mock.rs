@@ -0,0 +1,164 @@
+//! 模拟上游：不发起网络请求，直接返回配置的固定响应（静态 JSON 或按间隔逐块发送的 SSE 流），
+//! 便于离线开发客户端，或稳定地演示重试与切换上游的流程。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::{header, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// 未配置响应体时返回的 OpenAI 格式补全
+const DEFAULT_BODY: &str = r#"{"id":"mock-{{requestId}}","object":"chat.completion","created":{{unixTime}},"model":"{{model}}","choices":[{"index":0,"message":{"role":"assistant","content":"这是模拟上游的响应"},"finish_reason":"stop"}]}"#;
+const FAILURE_BODY: &str = r#"{"error":{"message":"模拟上游故障","type":"server_error"}}"#;
+/// 数据块间隔上限
+const MAX_CHUNK_DELAY_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/MockUpstream.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct MockUpstream {
+    /// 响应状态码，默认 200
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub status: Option<u16>,
+    /// 非流式响应体，支持 {{requestId}}、{{timestamp}}、{{unixTime}}、{{model}} 占位符；
+    /// 未设置时返回 OpenAI 格式的补全
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub body: Option<String>,
+    /// 非流式响应的 Content-Type，默认 application/json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub content_type: Option<String>,
+    /// 客户端请求流式响应时依次发送的 SSE 事件，支持相同的占位符；
+    /// 不以 `data:` / `event:` 开头的内容会包装成 `data:` 行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub stream_chunks: Option<Vec<String>>,
+    /// 发送每个数据块前的等待时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub chunk_delay_ms: Option<u64>,
+    /// 应用启动后的前 N 次请求返回 failure_status，用于演示重试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub fail_first: Option<u32>,
+    /// 模拟故障时的状态码，默认 503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub failure_status: Option<u16>,
+}
+
+impl MockUpstream {
+    pub fn validate(&self) -> Result<(), String> {
+        for status in [self.status, self.failure_status].into_iter().flatten() {
+            if !(200..=599).contains(&status) || StatusCode::from_u16(status).is_err() {
+                return Err(format!("模拟上游的状态码无效: {status}"));
+            }
+        }
+        if self
+            .content_type
+            .as_deref()
+            .is_some_and(|t| HeaderValue::from_str(t).is_err())
+        {
+            return Err("模拟上游的 Content-Type 无效".into());
+        }
+        if self.chunk_delay_ms.is_some_and(|d| d > MAX_CHUNK_DELAY_MS) {
+            return Err(format!(
+                "模拟上游的数据块间隔不能超过 {MAX_CHUNK_DELAY_MS} 毫秒"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 按上游 id 统计的请求次数，用于 fail_first
+fn request_counts() -> &'static Mutex<HashMap<String, u32>> {
+    static COUNTS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+    COUNTS.get_or_init(Default::default)
+}
+
+fn should_fail(mock: &MockUpstream, upstream_id: &str) -> bool {
+    let Some(limit) = mock.fail_first.filter(|n| *n > 0) else {
+        return false;
+    };
+    let mut counts = request_counts().lock().unwrap_or_else(|e| e.into_inner());
+    let count = counts.entry(upstream_id.to_string()).or_default();
+    *count = count.saturating_add(1);
+    *count <= limit
+}
+
+fn render(template: &str, request_id: &str, model: Option<&str>) -> String {
+    let now = chrono::Utc::now();
+    template
+        .replace("{{requestId}}", request_id)
+        .replace("{{timestamp}}", &crate::timestamp::format_utc(now))
+        .replace("{{unixTime}}", &now.timestamp().to_string())
+        .replace("{{model}}", model.unwrap_or("mock"))
+}
+
+fn sse_event(chunk: String) -> Bytes {
+    if chunk.starts_with("data:") || chunk.starts_with("event:") {
+        Bytes::from(format!("{chunk}\n\n"))
+    } else {
+        Bytes::from(format!("data: {chunk}\n\n"))
+    }
+}
+
+/// 生成模拟响应；客户端期望流式响应且配置了数据块时按 SSE 发送
+pub fn respond(
+    mock: &MockUpstream,
+    upstream_id: &str,
+    request_id: &str,
+    model: Option<&str>,
+    expects_stream: bool,
+) -> reqwest::Response {
+    let failing = should_fail(mock, upstream_id);
+    let status = if failing {
+        mock.failure_status.unwrap_or(503)
+    } else {
+        mock.status.unwrap_or(200)
+    };
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let delay = Duration::from_millis(mock.chunk_delay_ms.unwrap_or(0));
+
+    let (content_type, body) = match mock
+        .stream_chunks
+        .as_ref()
+        .filter(|_| expects_stream && !failing)
+    {
+        Some(chunks) => {
+            let events: Vec<Bytes> = chunks
+                .iter()
+                .map(|chunk| sse_event(render(chunk, request_id, model)))
+                .collect();
+            let stream = futures_util::stream::iter(events).then(move |event| async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                Ok::<_, std::io::Error>(event)
+            });
+            ("text/event-stream", reqwest::Body::wrap_stream(stream))
+        }
+        None if failing => ("application/json", reqwest::Body::from(FAILURE_BODY)),
+        None => (
+            mock.content_type.as_deref().unwrap_or("application/json"),
+            reqwest::Body::from(render(
+                mock.body.as_deref().unwrap_or(DEFAULT_BODY),
+                request_id,
+                model,
+            )),
+        ),
+    };
+
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    if let Ok(value) = HeaderValue::from_str(content_type) {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    reqwest::Response::from(response)
+}
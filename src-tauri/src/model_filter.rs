@@ -0,0 +1,84 @@
+//! 服务级的模型限制：按请求体（或路径）中解析出的模型检查允许 / 禁止列表，
+//! 不符合时直接返回 403，避免通过共享的入口误用昂贵的模型。
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ModelFilter.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ModelFilter {
+    /// 只允许这些模型，末尾的 `*` 表示前缀匹配，如 `gpt-4o-mini*`；未设置时不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub allowed_models: Option<Vec<String>>,
+    /// 禁止的模型，优先于允许列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub blocked_models: Option<Vec<String>>,
+}
+
+impl ModelFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        let patterns = self
+            .allowed_models
+            .iter()
+            .chain(&self.blocked_models)
+            .flatten();
+        for pattern in patterns {
+            let pattern = pattern.trim();
+            if pattern.is_empty()
+                || pattern == "*"
+                || pattern.strip_suffix('*').unwrap_or(pattern).contains('*')
+            {
+                return Err(format!(
+                    "模型限制只支持完整模型名或末尾带 * 的前缀: {pattern}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查模型，不允许时返回原因；请求未指定模型时不检查
+    pub fn check(&self, model: Option<&str>) -> Result<(), String> {
+        let Some(model) = model else {
+            return Ok(());
+        };
+        if self
+            .blocked_models
+            .iter()
+            .flatten()
+            .any(|p| model_matches(p, model))
+        {
+            return Err(format!("服务禁止使用模型 {model}"));
+        }
+        match &self.allowed_models {
+            Some(list) if !list.iter().any(|p| model_matches(p, model)) => Err(format!(
+                "服务不允许使用模型 {model}，请改用允许列表中的模型"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 返回给客户端的 403 响应体，附上允许的模型便于客户端改用
+    pub fn error_body(&self, model: Option<&str>, message: &str) -> String {
+        let mut payload = json!({
+            "error": message,
+            "type": "model_not_allowed",
+            "model": model,
+        });
+        if let Some(allowed) = &self.allowed_models {
+            payload["allowedModels"] = json!(allowed);
+        }
+        payload.to_string()
+    }
+}
+
+fn model_matches(pattern: &str, model: &str) -> bool {
+    let pattern = pattern.trim();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => model.starts_with(prefix),
+        None => model == pattern,
+    }
+}
@@ -0,0 +1,80 @@
+//! 按服务配置的路径改写规则：在去掉服务 base path 之后、拼接上游地址之前按顺序应用，
+//! 用于适配路径不标准的上游，例如把 `/openai/v1/*` 映射到 `/api/paas/v4/*`。
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/PathRewriteRule.ts")]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PathRewriteRule {
+    /// 正则匹配路径（不含查询参数），替换内容支持 `$1` / `${name}` 引用分组
+    Regex {
+        pattern: String,
+        replacement: String,
+        #[serde(skip)]
+        #[ts(skip)]
+        compiled: OnceLock<Option<Regex>>,
+    },
+    /// 路径以 `from` 开头时替换为 `to`
+    ReplacePrefix { from: String, to: String },
+    /// 在路径前添加前缀
+    AddPrefix { prefix: String },
+}
+
+impl PathRewriteRule {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            PathRewriteRule::Regex { pattern, .. } => {
+                Regex::new(pattern).map_err(|e| format!("路径改写正则无效 `{pattern}`: {e}"))?;
+            }
+            PathRewriteRule::ReplacePrefix { from, .. } if !from.starts_with('/') => {
+                return Err(format!("路径改写的前缀需以 / 开头: {from}"));
+            }
+            PathRewriteRule::AddPrefix { prefix } if !prefix.starts_with('/') => {
+                return Err(format!("路径改写的前缀需以 / 开头: {prefix}"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply(&self, path: &str) -> Option<String> {
+        match self {
+            PathRewriteRule::Regex {
+                pattern,
+                replacement,
+                compiled,
+            } => {
+                let regex = compiled.get_or_init(|| Regex::new(pattern).ok()).as_ref()?;
+                regex
+                    .is_match(path)
+                    .then(|| regex.replace(path, replacement.as_str()).into_owned())
+            }
+            PathRewriteRule::ReplacePrefix { from, to } => path
+                .strip_prefix(from.as_str())
+                .map(|rest| format!("{to}{rest}")),
+            PathRewriteRule::AddPrefix { prefix } => {
+                Some(format!("{}{path}", prefix.trim_end_matches('/')))
+            }
+        }
+    }
+}
+
+/// 按顺序应用所有规则，查询参数保持不变
+pub fn apply(rules: &[PathRewriteRule], path_and_query: &str) -> String {
+    if rules.is_empty() {
+        return path_and_query.to_string();
+    }
+    let (path, query) = match path_and_query.find('?') {
+        Some(idx) => path_and_query.split_at(idx),
+        None => (path_and_query, ""),
+    };
+    let path = rules.iter().fold(path.to_string(), |path, rule| {
+        rule.apply(&path).unwrap_or(path)
+    });
+    format!("{path}{query}")
+}
@@ -1,7 +1,48 @@
-use crate::ProxyConfig;
+use crate::{env_vars, keychain, ProxyConfig};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use directories::ProjectDirs;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use ts_rs::TS;
+
+/// 加密口令所在的环境变量
+pub const PASSPHRASE_ENV: &str = "APIFLOW_CONFIG_PASSPHRASE";
+const ENVELOPE_VERSION: u32 = 1;
+const KDF_ITERATIONS: u32 = 210_000;
+const SALT_LEN: usize = 16;
+
+/// 最近一次读取或保存的配置；外层 None 表示还没读过文件
+static SAVED: Mutex<Option<Option<ProxyConfig>>> = Mutex::new(None);
+
+/// 配置文件的加密方式，适用于没有系统钥匙串的环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ConfigEncryption.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigEncryption {
+    /// 由本机标识（系统机器 id、主机名与用户名）派生密钥，换机或改主机名后无法解密；
+    /// 读取不到系统机器 id 时不可用
+    Machine,
+    /// 由 `APIFLOW_CONFIG_PASSPHRASE` 环境变量中的口令派生密钥
+    Passphrase,
+}
+
+/// 加密后写入 config.json 的内容
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedConfig {
+    apiflow_encrypted: u32,
+    key_source: ConfigEncryption,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
 
 pub fn config_file_path() -> Result<PathBuf, String> {
     let proj = ProjectDirs::from("com", "apiflow", "app").ok_or("无法定位配置目录")?;
@@ -11,18 +52,220 @@ pub fn config_file_path() -> Result<PathBuf, String> {
     Ok(path)
 }
 
+/// 保存配置；key 存入系统钥匙串，文件中只保留引用。钥匙串不可用时退回明文保存，
+/// 配置了 config_encryption 时整个文件加密后写入
 pub fn save_config(config: &ProxyConfig) -> Result<(), String> {
+    // 持有缓存锁直到写完，并发保存不会互相覆盖临时文件
+    let mut saved = SAVED.lock().unwrap_or_else(|e| e.into_inner());
     let path = config_file_path()?;
-    let json = serde_json::to_string_pretty(config).map_err(|e| format!("序列化失败: {e}"))?;
-    fs::write(path, json).map_err(|e| format!("写入配置失败: {e}"))
+    let mut previous = read_config_file(&path).ok().flatten();
+    let mut stored = config.clone();
+    if let Err(err) = keychain::externalize_with(&mut stored, keychain::store) {
+        eprintln!("{err}，相关 key 仍以明文保存");
+    }
+    let json = serde_json::to_string_pretty(&stored).map_err(|e| format!("序列化失败: {e}"))?;
+    let data = match config.config_encryption {
+        Some(source) => encrypt_config(&json, source, &key_secret(source)?)?,
+        None => json,
+    };
+    write_atomic(&path, &data)?;
+    *saved = Some(Some(config.clone()));
+
+    if let Some(previous) = previous.as_mut() {
+        let current = keychain::referenced_accounts(&mut stored);
+        // 读取失败的账户在内存中已置空，不能视为用户删除了 key
+        let previous = keychain::referenced_accounts(previous);
+        let removed = previous
+            .difference(&current)
+            .filter(|account| !keychain::is_unresolved(account));
+        for account in removed {
+            if let Err(err) = keychain::delete(account) {
+                eprintln!("{err}");
+            }
+        }
+    }
+    Ok(())
 }
 
+/// 先写入临时文件再改名，读取方不会读到写了一半的配置
+fn write_atomic(path: &Path, data: &str) -> Result<(), String> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, data).map_err(|e| format!("写入配置失败: {e}"))?;
+    fs::rename(&tmp, path).map_err(|e| format!("写入配置失败: {e}"))
+}
+
+/// 读取已保存的配置；首次读取后缓存在内存中，由 save_config 更新，不重复解密与访问钥匙串
 pub fn load_config() -> Result<Option<ProxyConfig>, String> {
+    let mut saved = SAVED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cfg) = saved.as_ref() {
+        return Ok(cfg.clone());
+    }
     let path = config_file_path()?;
+    let cfg = match read_config_file(&path)? {
+        Some(mut cfg) => {
+            if let Err(err) = keychain::resolve_with(&mut cfg, keychain::load) {
+                eprintln!("{err}");
+            }
+            let unresolved = env_vars::unresolved(&cfg);
+            if !unresolved.is_empty() {
+                eprintln!("配置引用的环境变量未设置: {}", unresolved.join(", "));
+            }
+            Some(cfg)
+        }
+        None => None,
+    };
+    *saved = Some(cfg.clone());
+    Ok(cfg)
+}
+
+/// 内存中已保存的配置，不读取磁盘；供后台定时任务在代理未启动时使用
+pub fn cached_config() -> Option<ProxyConfig> {
+    SAVED.lock().unwrap_or_else(|e| e.into_inner()).clone().flatten()
+}
+
+/// 启动时读取配置到内存，并把开启加密前保存的明文配置迁移为加密文件
+pub fn init() -> Result<(), String> {
+    let Some(cfg) = load_config()? else {
+        return Ok(());
+    };
+    let data = fs::read_to_string(config_file_path()?).map_err(|e| format!("读取配置失败: {e}"))?;
+    if cfg.config_encryption.is_some() && serde_json::from_str::<EncryptedConfig>(&data).is_err() {
+        save_config(&cfg)?;
+    }
+    Ok(())
+}
+
+/// 读取配置文件，加密的文件先解密
+fn read_config_file(path: &Path) -> Result<Option<ProxyConfig>, String> {
     if !path.exists() {
         return Ok(None);
     }
-    let data = fs::read_to_string(&path).map_err(|e| format!("读取配置失败: {e}"))?;
-    let cfg: ProxyConfig = serde_json::from_str(&data).map_err(|e| format!("解析配置失败: {e}"))?;
+    let data = fs::read_to_string(path).map_err(|e| format!("读取配置失败: {e}"))?;
+    let json = decrypt_config(&data, key_secret)?.unwrap_or(data);
+    let cfg: ProxyConfig = serde_json::from_str(&json).map_err(|e| format!("解析配置失败: {e}"))?;
     Ok(Some(cfg))
 }
+
+fn key_secret(source: ConfigEncryption) -> Result<Vec<u8>, String> {
+    match source {
+        ConfigEncryption::Passphrase => std::env::var(PASSPHRASE_ENV)
+            .ok()
+            .filter(|p| !p.is_empty())
+            .map(String::into_bytes)
+            .ok_or_else(|| format!("配置文件使用口令加密，需要通过 {PASSPHRASE_ENV} 环境变量提供口令")),
+        ConfigEncryption::Machine => {
+            // 主机名与用户名容易猜到，没有系统机器 id 时拒绝使用本机密钥；
+            // 读取机器 id 可能需要启动子进程，只读一次
+            static MACHINE_ID: OnceLock<Option<String>> = OnceLock::new();
+            let machine_id = MACHINE_ID
+                .get_or_init(|| machine_id().filter(|id| !id.is_empty()))
+                .as_deref()
+                .ok_or("无法获取本机标识，请改用口令加密")?;
+            let host = hostname::get()
+                .map(|h| h.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default();
+            Ok(format!("apiflow:{machine_id}:{host}:{user}").into_bytes())
+        }
+    }
+}
+
+/// 系统机器 id：Linux 的 machine-id
+#[cfg(not(any(target_os = "macos", windows)))]
+fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|p| fs::read_to_string(p).ok())
+        .map(|id| id.trim().to_string())
+}
+
+/// 系统机器 id：macOS 的 IOPlatformUUID
+#[cfg(target_os = "macos")]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    // 形如 `"IOPlatformUUID" = "XXXXXXXX-XXXX-..."`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))?
+        .split('"')
+        .nth(3)
+        .map(str::to_string)
+}
+
+/// 系统机器 id：Windows 注册表中的 MachineGuid
+#[cfg(windows)]
+fn machine_id() -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    // 形如 `    MachineGuid    REG_SZ    xxxxxxxx-xxxx-...`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.trim_start().starts_with("MachineGuid"))?
+        .split_whitespace()
+        .nth(2)
+        .map(str::to_string)
+}
+
+fn derive_key(secret: &[u8], salt: &[u8]) -> Result<LessSafeKey, String> {
+    let mut key = [0u8; 32];
+    let iterations = NonZeroU32::new(KDF_ITERATIONS).ok_or("密钥派生参数无效")?;
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, secret, &mut key);
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "密钥无效".to_string())?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// 用 AES-256-GCM 加密配置 JSON，密钥由 secret 经 PBKDF2 派生
+pub fn encrypt_config(json: &str, source: ConfigEncryption, secret: &[u8]) -> Result<String, String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt).map_err(|_| "生成随机数失败".to_string())?;
+    rng.fill(&mut nonce).map_err(|_| "生成随机数失败".to_string())?;
+    let mut in_out = json.as_bytes().to_vec();
+    derive_key(secret, &salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| "加密配置失败".to_string())?;
+    let envelope = EncryptedConfig {
+        apiflow_encrypted: ENVELOPE_VERSION,
+        key_source: source,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(in_out),
+    };
+    serde_json::to_string_pretty(&envelope).map_err(|e| format!("序列化失败: {e}"))
+}
+
+/// 文件是加密配置时解密出 JSON，明文配置返回 None
+pub fn decrypt_config(
+    data: &str,
+    secret: impl FnOnce(ConfigEncryption) -> Result<Vec<u8>, String>,
+) -> Result<Option<String>, String> {
+    let Ok(envelope) = serde_json::from_str::<EncryptedConfig>(data) else {
+        return Ok(None);
+    };
+    if envelope.apiflow_encrypted != ENVELOPE_VERSION {
+        return Err(format!("不支持的配置加密版本: {}", envelope.apiflow_encrypted));
+    }
+    let decode = |s: &str| BASE64.decode(s).map_err(|_| "加密配置已损坏".to_string());
+    let salt = decode(&envelope.salt)?;
+    let nonce: [u8; NONCE_LEN] = decode(&envelope.nonce)?
+        .try_into()
+        .map_err(|_| "加密配置已损坏".to_string())?;
+    let mut in_out = decode(&envelope.ciphertext)?;
+    let plain = derive_key(&secret(envelope.key_source)?, &salt)?
+        .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| "解密配置失败：口令错误或本机标识已变化".to_string())?;
+    String::from_utf8(plain.to_vec())
+        .map(Some)
+        .map_err(|_| "加密配置已损坏".to_string())
+}
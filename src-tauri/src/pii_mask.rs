@@ -0,0 +1,119 @@
+//! 转发前的敏感信息遮盖：按服务配置，用内置规则（邮箱、电话、API key）与自定义正则
+//! 替换发往上游的请求体中的匹配内容，避免把客户数据泄露给第三方模型。
+//! JSON 请求体只处理字符串值，保证改写后仍是合法的 JSON。
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+const MASKED: &str = "[MASKED]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/PiiPattern.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum PiiPattern {
+    Email,
+    /// 带分隔符的电话号码与中国大陆手机号
+    Phone,
+    /// 常见服务商的 API key 格式（OpenAI、Anthropic、Google、GitHub、AWS、Slack）
+    ApiKey,
+}
+
+impl PiiPattern {
+    fn regex(self) -> &'static str {
+        match self {
+            PiiPattern::Email => {
+                r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}"
+            }
+            PiiPattern::Phone => {
+                r"(?:\+\d{1,3}[\s-]?)?(?:\(\d{2,4}\)[\s-]?|\b\d{2,4}[\s-])?\b\d{3,4}[\s-]\d{4}\b|\b1[3-9]\d{9}\b"
+            }
+            PiiPattern::ApiKey => {
+                r"\b(?:sk-(?:ant-|proj-)?[A-Za-z0-9_-]{16,}|AIza[0-9A-Za-z_-]{35}|gh[pousr]_[A-Za-z0-9]{36}|AKIA[0-9A-Z]{16}|xox[abpr]-[A-Za-z0-9-]{10,})"
+            }
+        }
+    }
+
+    fn replacement(self) -> &'static str {
+        match self {
+            PiiPattern::Email => "[EMAIL]",
+            PiiPattern::Phone => "[PHONE]",
+            PiiPattern::ApiKey => "[API_KEY]",
+        }
+    }
+}
+
+/// 服务的遮盖规则
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/PiiMaskConfig.ts")]
+#[serde(rename_all = "camelCase", default)]
+pub struct PiiMaskConfig {
+    /// 启用的内置规则，匹配内容替换为 `[EMAIL]` / `[PHONE]` / `[API_KEY]`
+    pub builtins: Vec<PiiPattern>,
+    /// 自定义正则，匹配内容替换为 [MASKED]
+    pub patterns: Vec<String>,
+    #[serde(skip)]
+    #[ts(skip)]
+    compiled: OnceLock<Vec<(Regex, &'static str)>>,
+}
+
+impl PiiMaskConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.builtins.is_empty() && self.patterns.is_empty() {
+            return Err("敏感信息遮盖至少需要启用一条规则".into());
+        }
+        for pattern in &self.patterns {
+            Regex::new(pattern).map_err(|e| format!("遮盖正则无效 `{pattern}`: {e}"))?;
+        }
+        Ok(())
+    }
+
+    fn rules(&self) -> &[(Regex, &'static str)] {
+        self.compiled.get_or_init(|| {
+            let builtins = self
+                .builtins
+                .iter()
+                .filter_map(|p| Some((Regex::new(p.regex()).ok()?, p.replacement())));
+            let custom = self
+                .patterns
+                .iter()
+                .filter_map(|p| Some((Regex::new(p).ok()?, MASKED)));
+            builtins.chain(custom).collect()
+        })
+    }
+
+    fn mask_text(&self, text: &mut String) -> usize {
+        let mut count = 0;
+        for (re, replacement) in self.rules() {
+            let found = re.find_iter(text).count();
+            if found > 0 {
+                *text = re.replace_all(text, *replacement).into_owned();
+                count += found;
+            }
+        }
+        count
+    }
+
+    /// 返回遮盖后的请求体与替换次数；没有匹配或请求体不是文本时返回 None
+    pub fn mask_body(&self, body: &[u8]) -> Option<(Vec<u8>, usize)> {
+        if let Ok(mut value) = serde_json::from_slice::<Value>(body) {
+            let count = self.mask_json(&mut value);
+            return (count > 0).then(|| (value.to_string().into_bytes(), count));
+        }
+        let mut text = std::str::from_utf8(body).ok()?.to_string();
+        let count = self.mask_text(&mut text);
+        (count > 0).then(|| (text.into_bytes(), count))
+    }
+
+    fn mask_json(&self, value: &mut Value) -> usize {
+        match value {
+            Value::String(text) => self.mask_text(text),
+            Value::Array(items) => items.iter_mut().map(|v| self.mask_json(v)).sum(),
+            Value::Object(map) => map.values_mut().map(|v| self.mask_json(v)).sum(),
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::usage::TokenUsage;
+
+/// 单个模型的价格，单位为每 1K token 的金额（币种由用户自行约定）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ModelPrice.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    /// 模型名；以 `*` 结尾时按前缀匹配，如 `gpt-4o*`
+    pub model: String,
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+pub fn validate_pricing(table: &[ModelPrice]) -> Result<(), String> {
+    for price in table {
+        if price.model.trim().is_empty() {
+            return Err("价格表中的模型名不能为空".into());
+        }
+        let valid = |v: f64| v.is_finite() && v >= 0.0;
+        if !valid(price.input_per_1k) || !valid(price.output_per_1k) {
+            return Err(format!("模型 {} 的价格必须为非负数", price.model));
+        }
+    }
+    Ok(())
+}
+
+/// 精确匹配优先，其次取最长的前缀匹配
+pub fn find_price<'a>(table: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+    table.iter().find(|p| p.model == model).or_else(|| {
+        table
+            .iter()
+            .filter_map(|p| Some((p, p.model.strip_suffix('*')?)))
+            .filter(|(_, prefix)| model.starts_with(prefix))
+            .max_by_key(|(_, prefix)| prefix.len())
+            .map(|(p, _)| p)
+    })
+}
+
+/// 按价格表估算单次请求费用；模型未知、未配置价格或没有 usage 时返回 None
+pub fn estimate_cost(
+    table: Option<&[ModelPrice]>,
+    model: Option<&str>,
+    usage: Option<&TokenUsage>,
+) -> Option<f64> {
+    let price = find_price(table?, model?)?;
+    let usage = usage?;
+    Some(
+        usage.prompt_tokens as f64 / 1000.0 * price.input_per_1k
+            + usage.completion_tokens as f64 / 1000.0 * price.output_per_1k,
+    )
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+/// 归一化后的上游错误类型，覆盖 OpenAI / Anthropic / Gemini 的错误格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ErrorKind.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidApiKey,
+    PermissionDenied,
+    InsufficientQuota,
+    RateLimited,
+    ContentFilter,
+    ContextLengthExceeded,
+    InvalidRequest,
+    NotFound,
+    Overloaded,
+    Timeout,
+    ServerError,
+    /// 上游以成功状态返回了网页等非 API 响应，常见于强制门户、CDN 拦截页或填错的上游地址
+    ProtocolMismatch,
+    Unknown,
+}
+
+/// 上游返回某类错误后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ErrorAction.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorAction {
+    /// 在同一上游重试，重试次数用尽后切换到下一个上游
+    Retry,
+    /// 跳过重试，直接切换到下一个上游
+    Fallback,
+    /// 直接把错误返回给客户端
+    Fail,
+}
+
+impl ErrorKind {
+    /// 默认策略：临时性错误重试，与 key 相关的错误换上游，
+    /// 请求本身的问题（格式错误、内容审核、上下文超长）在任何上游都会同样失败，直接返回
+    pub fn default_action(self) -> ErrorAction {
+        match self {
+            ErrorKind::RateLimited
+            | ErrorKind::Overloaded
+            | ErrorKind::Timeout
+            | ErrorKind::ServerError
+            | ErrorKind::Unknown => ErrorAction::Retry,
+            ErrorKind::InvalidApiKey
+            | ErrorKind::PermissionDenied
+            | ErrorKind::InsufficientQuota
+            | ErrorKind::NotFound
+            | ErrorKind::ProtocolMismatch => ErrorAction::Fallback,
+            ErrorKind::ContentFilter | ErrorKind::ContextLengthExceeded | ErrorKind::InvalidRequest => {
+                ErrorAction::Fail
+            }
+        }
+    }
+}
+
+/// 配置中的覆盖优先，其次为默认策略
+pub fn error_action(kind: ErrorKind, overrides: Option<&HashMap<ErrorKind, ErrorAction>>) -> ErrorAction {
+    overrides
+        .and_then(|o| o.get(&kind).copied())
+        .unwrap_or_else(|| kind.default_action())
+}
+
+fn from_code(code: &str) -> Option<ErrorKind> {
+    let code = code.to_ascii_lowercase();
+    let kind = match code.as_str() {
+        "invalid_api_key" | "authentication_error" | "unauthenticated" | "api_key_invalid" => {
+            ErrorKind::InvalidApiKey
+        }
+        "permission_error" | "permission_denied" => ErrorKind::PermissionDenied,
+        "insufficient_quota" | "billing_hard_limit_reached" => ErrorKind::InsufficientQuota,
+        "rate_limit_exceeded" | "rate_limit_error" | "resource_exhausted" => ErrorKind::RateLimited,
+        "content_filter" | "content_policy_violation" | "safety" => ErrorKind::ContentFilter,
+        "context_length_exceeded" | "request_too_large" => ErrorKind::ContextLengthExceeded,
+        "invalid_request_error" | "invalid_argument" | "failed_precondition" => ErrorKind::InvalidRequest,
+        "not_found_error" | "model_not_found" | "not_found" => ErrorKind::NotFound,
+        "overloaded_error" | "unavailable" => ErrorKind::Overloaded,
+        "deadline_exceeded" => ErrorKind::Timeout,
+        "server_error" | "api_error" | "internal" => ErrorKind::ServerError,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn from_status(status: u16) -> ErrorKind {
+    match status {
+        401 => ErrorKind::InvalidApiKey,
+        402 => ErrorKind::InsufficientQuota,
+        403 => ErrorKind::PermissionDenied,
+        404 => ErrorKind::NotFound,
+        408 | 504 => ErrorKind::Timeout,
+        413 => ErrorKind::ContextLengthExceeded,
+        429 => ErrorKind::RateLimited,
+        400..=499 => ErrorKind::InvalidRequest,
+        503 | 529 => ErrorKind::Overloaded,
+        500..=599 => ErrorKind::ServerError,
+        _ => ErrorKind::Unknown,
+    }
+}
+
+/// 成功状态的响应却是 HTML 页面时视为协议不符，不应把页面转发给 API 客户端
+pub fn is_protocol_mismatch(status: u16, content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    (200..300).contains(&status) && matches!(mime.as_str(), "text/html" | "application/xhtml+xml")
+}
+
+pub fn protocol_mismatch_message(content_type: &str) -> String {
+    format!(
+        "上游返回了网页（{content_type}）而不是 API 响应，可能被强制门户、代理或 CDN 拦截，请检查上游地址与网络"
+    )
+}
+
+/// 按从具体到笼统的顺序收集错误码：OpenAI `error.code`、Gemini `details[].reason`、
+/// OpenAI / Anthropic `error.type`、Gemini `error.status`
+fn codes(body: &Value) -> Vec<&str> {
+    let error = &body["error"];
+    let mut codes = Vec::new();
+    if let Some(code) = error["code"].as_str() {
+        codes.push(code);
+    }
+    if let Some(details) = error["details"].as_array() {
+        codes.extend(details.iter().filter_map(|d| d["reason"].as_str()));
+    }
+    codes.extend(error["type"].as_str());
+    codes.extend(error["status"].as_str());
+    codes
+}
+
+/// 解析上游错误响应体；无法识别时按状态码归类。非错误状态返回 None。
+pub fn classify_error(status: u16, body: &[u8]) -> Option<ErrorKind> {
+    if status < 400 {
+        return None;
+    }
+    let parsed = serde_json::from_slice::<Value>(body).ok();
+    let from_body = parsed
+        .as_ref()
+        .and_then(|v| codes(v).into_iter().find_map(from_code));
+
+    // 部分 OpenAI 兼容服务只在 message 中说明上下文超长
+    let from_message = || {
+        let message = parsed.as_ref()?["error"]["message"].as_str()?.to_ascii_lowercase();
+        if message.contains("context length") || message.contains("maximum context") {
+            Some(ErrorKind::ContextLengthExceeded)
+        } else if message.contains("api key not valid") {
+            Some(ErrorKind::InvalidApiKey)
+        } else {
+            None
+        }
+    };
+
+    Some(
+        from_body
+            .filter(|k| *k != ErrorKind::InvalidRequest)
+            .or_else(from_message)
+            .or(from_body)
+            .unwrap_or_else(|| from_status(status)),
+    )
+}
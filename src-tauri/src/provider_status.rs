@@ -0,0 +1,318 @@
+//! 服务商状态页轮询：定期读取上游所属服务商的状态页（Statuspage 格式的 `summary.json`），
+//! 有进行中的故障时推送提醒并在上游旁展示，便于区分是自己的 key 出了问题还是服务商整体故障。
+//! 内置 OpenAI 与 Anthropic 的状态页，其他服务商可在配置中填写地址。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use ts_rs::TS;
+
+use crate::persistence::cached_config;
+use crate::{events, timestamp, ProxyConfig, UpstreamEntry};
+
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+const MIN_INTERVAL_SECS: u64 = 60;
+const TICK: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 内置的状态页：服务商名称、上游地址中的域名、状态页地址
+const BUILTIN_FEEDS: &[(&str, &str, &str)] = &[
+    (
+        "openai",
+        "openai.com",
+        "https://status.openai.com/api/v2/summary.json",
+    ),
+    (
+        "anthropic",
+        "anthropic.com",
+        "https://status.anthropic.com/api/v2/summary.json",
+    ),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ProviderStatusConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatusConfig {
+    /// 轮询间隔，默认 300 秒，最少 60 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub interval_secs: Option<u64>,
+    /// 服务商名称（对应上游的 provider）到状态页 `summary.json` 地址，可覆盖内置地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub feeds: Option<HashMap<String, String>>,
+}
+
+impl ProviderStatusConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs.is_some_and(|s| s < MIN_INTERVAL_SECS) {
+            return Err(format!("状态页轮询间隔不能小于 {MIN_INTERVAL_SECS} 秒"));
+        }
+        for (provider, url) in self.feeds.iter().flatten() {
+            if provider.trim().is_empty() {
+                return Err("状态页需要填写服务商名称".into());
+            }
+            reqwest::Url::parse(url).map_err(|e| format!("状态页地址无效 `{url}`: {e}"))?;
+        }
+        Ok(())
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS))
+    }
+
+    fn feed_url(&self, provider: &str) -> Option<String> {
+        self.feeds
+            .iter()
+            .flatten()
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(provider))
+            .map(|(_, url)| url.clone())
+            .or_else(|| {
+                BUILTIN_FEEDS
+                    .iter()
+                    .find(|(name, _, _)| *name == provider)
+                    .map(|(_, _, url)| url.to_string())
+            })
+    }
+}
+
+/// 状态页上进行中的故障或维护
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ProviderIncident.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderIncident {
+    pub id: String,
+    pub name: String,
+    /// investigating / identified / monitoring 等
+    pub status: String,
+    /// none / minor / major / critical
+    pub impact: Option<String>,
+    pub url: Option<String>,
+    pub started_at: Option<String>,
+}
+
+/// 服务商最近一次查询到的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ProviderStatus.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatus {
+    pub provider: String,
+    /// 整体状态：none / minor / major / critical / maintenance
+    pub indicator: Option<String>,
+    pub description: Option<String>,
+    pub incidents: Vec<ProviderIncident>,
+    /// 使用该服务商的上游
+    pub upstream_ids: Vec<String>,
+    pub checked_at: String,
+    pub error: Option<String>,
+}
+
+/// 上游所属的服务商：优先取配置的 provider，否则按上游地址的域名识别内置服务商
+pub fn provider_of(upstream: &UpstreamEntry) -> Option<String> {
+    if let Some(provider) = upstream.provider.as_deref().map(str::trim) {
+        if !provider.is_empty() {
+            return Some(provider.to_ascii_lowercase());
+        }
+    }
+    let url = reqwest::Url::parse(&upstream.upstream_base).ok()?;
+    let host = url.host_str()?;
+    BUILTIN_FEEDS
+        .iter()
+        .find(|(_, domain, _)| host == *domain || host.ends_with(&format!(".{domain}")))
+        .map(|(name, _, _)| name.to_string())
+}
+
+/// 配置中有状态页的服务商及其上游，按服务商名称排序
+pub fn providers_in(
+    config: &ProxyConfig,
+    status: &ProviderStatusConfig,
+) -> BTreeMap<String, (String, Vec<String>)> {
+    let mut providers: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for upstream in config.all_services().flat_map(|s| &s.upstreams) {
+        if !upstream.enabled || !seen.insert(upstream.id.clone()) {
+            continue;
+        }
+        let Some(provider) = provider_of(upstream) else {
+            continue;
+        };
+        let Some(url) = status.feed_url(&provider) else {
+            continue;
+        };
+        providers
+            .entry(provider)
+            .or_insert_with(|| (url, Vec::new()))
+            .1
+            .push(upstream.id.clone());
+    }
+    providers
+}
+
+/// 解析 Statuspage 的 summary.json，返回整体状态、描述与未解决的故障
+pub fn parse_summary(body: &Value) -> (Option<String>, Option<String>, Vec<ProviderIncident>) {
+    let text = |v: &Value| v.as_str().map(str::to_string);
+    let incidents = body["incidents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .chain(
+            body["scheduled_maintenances"]
+                .as_array()
+                .into_iter()
+                .flatten(),
+        )
+        // 已结束的故障与尚未开始的计划维护不算进行中
+        .filter(|i| {
+            !matches!(
+                i["status"].as_str(),
+                Some("resolved" | "postmortem" | "completed" | "scheduled")
+            )
+        })
+        .filter_map(|i| {
+            Some(ProviderIncident {
+                id: text(&i["id"])?,
+                name: text(&i["name"]).unwrap_or_default(),
+                status: text(&i["status"]).unwrap_or_default(),
+                impact: text(&i["impact"]),
+                url: text(&i["shortlink"]),
+                started_at: text(&i["started_at"]).or_else(|| text(&i["created_at"])),
+            })
+        })
+        .collect();
+    (
+        text(&body["status"]["indicator"]),
+        text(&body["status"]["description"]),
+        incidents,
+    )
+}
+
+#[derive(Default)]
+struct Poller {
+    statuses: HashMap<String, ProviderStatus>,
+    last_polled: HashMap<String, Instant>,
+}
+
+fn poller() -> &'static Mutex<Poller> {
+    static POLLER: OnceLock<Mutex<Poller>> = OnceLock::new();
+    POLLER.get_or_init(Default::default)
+}
+
+/// 各服务商最近一次查询结果，按服务商名称排序
+pub fn snapshot() -> Vec<ProviderStatus> {
+    let guard = poller().lock().unwrap_or_else(|e| e.into_inner());
+    let mut statuses: Vec<ProviderStatus> = guard.statuses.values().cloned().collect();
+    statuses.sort_by(|a, b| a.provider.cmp(&b.provider));
+    statuses
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Value, String> {
+    let resp = client
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("查询状态页失败: {e}"))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("查询状态页失败: HTTP {}", status.as_u16()));
+    }
+    resp.json()
+        .await
+        .map_err(|e| format!("状态页响应不是有效的 JSON: {e}"))
+}
+
+async fn poll_provider(
+    client: &reqwest::Client,
+    provider: String,
+    url: &str,
+    upstream_ids: Vec<String>,
+) {
+    let result = fetch(client, url).await.map(|body| parse_summary(&body));
+    let mut guard = poller().lock().unwrap_or_else(|e| e.into_inner());
+    let previous = guard.statuses.get(&provider);
+    let known: HashSet<String> = previous
+        .iter()
+        .flat_map(|p| &p.incidents)
+        .map(|i| i.id.clone())
+        .collect();
+    let entry = match result {
+        Ok((indicator, description, incidents)) => ProviderStatus {
+            provider: provider.clone(),
+            indicator,
+            description,
+            incidents,
+            upstream_ids,
+            checked_at: timestamp::now(),
+            error: None,
+        },
+        // 查询失败时保留已知的状态，避免恢复后把同一故障再提醒一次
+        Err(err) => ProviderStatus {
+            provider: provider.clone(),
+            indicator: previous.and_then(|p| p.indicator.clone()),
+            description: previous.and_then(|p| p.description.clone()),
+            incidents: previous.map(|p| p.incidents.clone()).unwrap_or_default(),
+            upstream_ids,
+            checked_at: timestamp::now(),
+            error: Some(err),
+        },
+    };
+    guard.statuses.insert(provider.clone(), entry.clone());
+    drop(guard);
+    // 只在出现新的故障时提醒
+    if entry.incidents.iter().any(|i| !known.contains(&i.id)) {
+        eprintln!("服务商 {provider} 状态页有进行中的故障");
+        events::emit_provider_incident(entry);
+    }
+}
+
+/// 查询所有到期需要刷新的服务商；force 为 true 时忽略查询间隔
+pub async fn poll_due(client: &reqwest::Client, config: &ProxyConfig, force: bool) {
+    let Some(status_config) = config.provider_status.as_ref() else {
+        return;
+    };
+    let now = Instant::now();
+    let providers = providers_in(config, status_config);
+    let due: Vec<(String, String, Vec<String>)> = {
+        let mut guard = poller().lock().unwrap_or_else(|e| e.into_inner());
+        // 移除不再使用的服务商
+        guard.statuses.retain(|p, _| providers.contains_key(p));
+        guard.last_polled.retain(|p, _| providers.contains_key(p));
+        let mut due = Vec::new();
+        for (provider, (url, upstream_ids)) in providers {
+            let fresh = guard
+                .last_polled
+                .get(&provider)
+                .is_some_and(|at| now.duration_since(*at) < status_config.interval());
+            if force || !fresh {
+                guard.last_polled.insert(provider.clone(), now);
+                due.push((provider, url, upstream_ids));
+            }
+        }
+        due
+    };
+    for (provider, url, upstream_ids) in due {
+        poll_provider(client, provider, &url, upstream_ids).await;
+    }
+}
+
+/// 后台定期查询状态页，使用与代理请求相同的出站代理设置
+pub fn spawn_poll_task(
+    client: Arc<ArcSwap<reqwest::Client>>,
+    config: Arc<RwLock<Option<ProxyConfig>>>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            ticker.tick().await;
+            let current = config.read().await.clone();
+            if let Some(current) = current.or_else(cached_config) {
+                poll_due(&client.load_full(), &current, false).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,215 @@
+//! 用量对账：导入服务商后台导出的用量 CSV，按天与本地日志记录的 token 与估算费用比对，
+//! 生成差异报告，用于确认或校准本地的费用估算。日期统一按 UTC 计算。
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{timestamp, ProxyLogEntry};
+
+/// CSV 表头中可识别的列名（不区分大小写，按包含关系匹配）
+const DATE_COLUMNS: [&str; 4] = ["date", "day", "time", "日期"];
+const TOTAL_TOKEN_COLUMNS: [&str; 2] = ["total_tokens", "total tokens"];
+const INPUT_TOKEN_COLUMNS: [&str; 3] = ["input", "prompt", "输入"];
+const OUTPUT_TOKEN_COLUMNS: [&str; 3] = ["output", "completion", "输出"];
+const TOKEN_COLUMNS: [&str; 2] = ["tokens", "token"];
+const COST_COLUMNS: [&str; 6] = ["cost", "amount", "spend", "usd", "费用", "金额"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/DailyUsageVariance.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsageVariance {
+    /// UTC 日期，YYYY-MM-DD
+    pub date: String,
+    #[ts(type = "number | null")]
+    pub provider_tokens: Option<u64>,
+    #[ts(type = "number")]
+    pub local_tokens: u64,
+    pub provider_cost: Option<f64>,
+    pub local_cost: f64,
+    /// 本地减去服务商的 token 数
+    #[ts(type = "number | null")]
+    pub token_variance: Option<i64>,
+    /// 本地估算费用相对服务商账单的偏差比例，如 -0.05 表示少估 5%
+    pub cost_variance_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/UsageReconciliation.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReconciliation {
+    pub days: Vec<DailyUsageVariance>,
+    #[ts(type = "number")]
+    pub provider_tokens: u64,
+    #[ts(type = "number")]
+    pub local_tokens: u64,
+    pub provider_cost: f64,
+    pub local_cost: f64,
+    /// 服务商费用与本地估算之比，可作为本地价格表的校准系数
+    pub calibration_factor: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct DayTotals {
+    tokens: Option<u64>,
+    cost: Option<f64>,
+}
+
+/// 按逗号拆分一行 CSV，支持双引号包裹（含逗号或转义引号）的字段
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|h| names.iter().any(|n| h.contains(n)))
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | '-'))
+        .collect();
+    cleaned.parse().ok()
+}
+
+/// 取日期部分：完整时间戳换算为 UTC，其余按开头的 YYYY-MM-DD 截取
+fn parse_date(value: &str) -> Option<String> {
+    if let Some(time) = timestamp::parse(value) {
+        return Some(time.format("%Y-%m-%d").to_string());
+    }
+    let date = value.get(..10)?;
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.to_string())
+}
+
+/// 解析服务商导出的 CSV，按天汇总 token 与费用
+fn parse_provider_csv(csv: &str) -> Result<BTreeMap<String, DayTotals>, String> {
+    let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+    let headers: Vec<String> = split_csv_line(lines.next().ok_or("CSV 内容为空")?)
+        .into_iter()
+        .map(|h| h.trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    let date_col = find_column(&headers, &DATE_COLUMNS).ok_or("CSV 中没有找到日期列")?;
+    let total_col = find_column(&headers, &TOTAL_TOKEN_COLUMNS);
+    let input_col = find_column(&headers, &INPUT_TOKEN_COLUMNS);
+    let output_col = find_column(&headers, &OUTPUT_TOKEN_COLUMNS);
+    let tokens_col = total_col.or_else(|| {
+        // 有输入 / 输出两列时相加，否则取任一含 token 的列
+        (input_col.is_none() || output_col.is_none())
+            .then(|| find_column(&headers, &TOKEN_COLUMNS))
+            .flatten()
+    });
+    let cost_col = find_column(&headers, &COST_COLUMNS);
+    if tokens_col.is_none() && (input_col.is_none() || output_col.is_none()) && cost_col.is_none() {
+        return Err("CSV 中没有找到 token 或费用列".into());
+    }
+
+    let mut days: BTreeMap<String, DayTotals> = BTreeMap::new();
+    for (idx, line) in lines.enumerate() {
+        let fields = split_csv_line(line);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .and_then(|v| parse_number(v))
+        };
+        let date = fields
+            .get(date_col)
+            .and_then(|v| parse_date(v))
+            .ok_or_else(|| format!("第 {} 行的日期无效", idx + 2))?;
+        let tokens = match tokens_col {
+            Some(col) => field(Some(col)),
+            None => field(input_col)
+                .zip(field(output_col))
+                .map(|(input, output)| input + output),
+        };
+        let day = days.entry(date).or_default();
+        if let Some(tokens) = tokens {
+            *day.tokens.get_or_insert(0) += tokens.max(0.0) as u64;
+        }
+        if let Some(cost) = field(cost_col) {
+            *day.cost.get_or_insert(0.0) += cost;
+        }
+    }
+    Ok(days)
+}
+
+/// 按天汇总本地日志中的 token 用量与估算费用；upstream_ids 非空时只统计这些上游
+fn local_totals<'a>(
+    entries: impl IntoIterator<Item = &'a ProxyLogEntry>,
+    upstream_ids: &[String],
+) -> BTreeMap<String, (u64, f64)> {
+    let mut days: BTreeMap<String, (u64, f64)> = BTreeMap::new();
+    for entry in entries {
+        let matches_upstream = upstream_ids.is_empty()
+            || entry
+                .upstream_id
+                .as_ref()
+                .is_some_and(|id| upstream_ids.contains(id));
+        if !matches_upstream || (entry.usage.is_none() && entry.cost.is_none()) {
+            continue;
+        }
+        let Some(date) = parse_date(&entry.timestamp) else {
+            continue;
+        };
+        let day = days.entry(date).or_default();
+        day.0 += entry.usage.as_ref().map_or(0, |u| u.total_tokens);
+        day.1 += entry.cost.unwrap_or(0.0);
+    }
+    days
+}
+
+/// 只比对 CSV 覆盖的日期，本地有而 CSV 没有的日期不计入
+pub fn reconcile<'a>(
+    csv: &str,
+    entries: impl IntoIterator<Item = &'a ProxyLogEntry>,
+    upstream_ids: &[String],
+) -> Result<UsageReconciliation, String> {
+    let provider = parse_provider_csv(csv)?;
+    let local = local_totals(entries, upstream_ids);
+    let mut report = UsageReconciliation::default();
+    let mut cost_compared = (0.0, 0.0);
+    for (date, totals) in provider {
+        let (local_tokens, local_cost) = local.get(&date).copied().unwrap_or_default();
+        report.provider_tokens += totals.tokens.unwrap_or(0);
+        report.provider_cost += totals.cost.unwrap_or(0.0);
+        report.local_tokens += local_tokens;
+        report.local_cost += local_cost;
+        if let Some(cost) = totals.cost {
+            cost_compared.0 += cost;
+            cost_compared.1 += local_cost;
+        }
+        report.days.push(DailyUsageVariance {
+            date,
+            provider_tokens: totals.tokens,
+            local_tokens,
+            provider_cost: totals.cost,
+            local_cost,
+            token_variance: totals.tokens.map(|t| local_tokens as i64 - t as i64),
+            cost_variance_ratio: totals
+                .cost
+                .filter(|c| *c > 0.0)
+                .map(|c| (local_cost - c) / c),
+        });
+    }
+    report.calibration_factor = (cost_compared.1 > 0.0).then(|| cost_compared.0 / cost_compared.1);
+    Ok(report)
+}
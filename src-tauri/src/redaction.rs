@@ -0,0 +1,172 @@
+use std::sync::OnceLock;
+
+use http::HeaderMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::helpers::format_headers;
+use crate::ProxyConfig;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 无论是否配置都会脱敏的请求/响应头
+const BUILTIN_HEADERS: [&str; 6] = [
+    "authorization",
+    "x-goog-api-key",
+    "x-proxy-key",
+    "x-api-key",
+    "api-key",
+    "proxy-authorization",
+];
+
+/// 日志脱敏规则，在请求头/请求体/响应写入 ProxyLogEntry 之前生效
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RedactionConfig.ts")]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionConfig {
+    /// 额外需要脱敏的 header 名（不区分大小写）
+    pub header_names: Vec<String>,
+    /// JSON 体中的字段路径，以 `.` 分隔，`*` 匹配任意对象键或数组下标，如 `messages.*.content`
+    pub json_paths: Vec<String>,
+    /// 正则表达式，匹配到的文本会被替换为 [REDACTED]
+    pub patterns: Vec<String>,
+    /// 非空时日志只记录这些上游响应头，以 `*` 结尾表示前缀匹配，如 `x-ratelimit-*`
+    pub response_header_allowlist: Vec<String>,
+    /// 日志中不记录的上游响应头，格式同上，如 `set-cookie`、`cf-*`
+    pub response_header_denylist: Vec<String>,
+    #[serde(skip)]
+    #[ts(skip)]
+    compiled: OnceLock<Vec<Regex>>,
+}
+
+impl RedactionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in &self.patterns {
+            Regex::new(pattern).map_err(|e| format!("脱敏正则无效 `{pattern}`: {e}"))?;
+        }
+        Ok(())
+    }
+
+    fn regexes(&self) -> &[Regex] {
+        self.compiled.get_or_init(|| {
+            self.patterns
+                .iter()
+                .filter_map(|p| Regex::new(p).ok())
+                .collect()
+        })
+    }
+
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        BUILTIN_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h))
+            || self.header_names.iter().any(|h| name.eq_ignore_ascii_case(h.trim()))
+    }
+
+    fn logs_response_header(&self, name: &str) -> bool {
+        (self.response_header_allowlist.is_empty()
+            || self.response_header_allowlist.iter().any(|p| header_matches(p, name)))
+            && !self.response_header_denylist.iter().any(|p| header_matches(p, name))
+    }
+
+    /// 按允许/排除列表筛选上游响应头后格式化并脱敏，用于写入日志
+    pub fn response_headers(&self, headers: &HeaderMap) -> String {
+        let kept: HeaderMap = headers
+            .iter()
+            .filter(|(name, _)| self.logs_response_header(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        self.redact_headers(&format_headers(&kept))
+    }
+
+    /// 处理 `name: value` 逐行格式的 header 文本
+    pub fn redact_headers(&self, raw: &str) -> String {
+        raw.lines()
+            .map(|line| match line.split_once(':') {
+                Some((name, _)) if self.is_sensitive_header(name.trim()) => {
+                    format!("{}: {}", name.trim(), REDACTED)
+                }
+                _ => self.redact_text(line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 先按 JSON 路径替换字段，再对整体文本应用正则
+    pub fn redact_body(&self, raw: String) -> String {
+        let raw = if self.json_paths.is_empty() {
+            raw
+        } else {
+            match serde_json::from_str::<Value>(&raw) {
+                Ok(mut value) => {
+                    for path in &self.json_paths {
+                        let segments: Vec<&str> =
+                            path.split('.').filter(|s| !s.is_empty()).collect();
+                        redact_json_path(&mut value, &segments);
+                    }
+                    value.to_string()
+                }
+                Err(_) => raw,
+            }
+        };
+        self.redact_text(&raw)
+    }
+
+    pub fn redact_text(&self, raw: &str) -> String {
+        let mut text = raw.to_string();
+        for re in self.regexes() {
+            if re.is_match(&text) {
+                text = re.replace_all(&text, REDACTED).into_owned();
+            }
+        }
+        text
+    }
+}
+
+fn header_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.trim();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name
+            .get(..prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(prefix)),
+        None => name.eq_ignore_ascii_case(pattern),
+    }
+}
+
+fn redact_json_path(value: &mut Value, segments: &[&str]) {
+    let Some((head, rest)) = segments.split_first() else {
+        *value = Value::String(REDACTED.to_string());
+        return;
+    };
+
+    match value {
+        Value::Object(map) => {
+            if *head == "*" {
+                for child in map.values_mut() {
+                    redact_json_path(child, rest);
+                }
+            } else if let Some(child) = map.get_mut(*head) {
+                redact_json_path(child, rest);
+            }
+        }
+        Value::Array(items) => {
+            if *head == "*" {
+                for child in items.iter_mut() {
+                    redact_json_path(child, rest);
+                }
+            } else if let Some(child) = head.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                redact_json_path(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 当前配置的脱敏规则；未配置时仅脱敏内置的认证头
+pub fn rules(config: &ProxyConfig) -> &RedactionConfig {
+    static EMPTY: OnceLock<RedactionConfig> = OnceLock::new();
+    config
+        .redaction
+        .as_ref()
+        .unwrap_or_else(|| EMPTY.get_or_init(RedactionConfig::default))
+}
@@ -0,0 +1,84 @@
+//! 整个代理的请求速率上限（令牌桶）：超出时直接返回 429 而不排队，
+//! 防止失控的本地脚本在短时间内耗尽服务商额度。所有监听端口共用同一个桶。
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RequestRateLimit.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRateLimit {
+    /// 每秒补充的请求数，可以是小数，如 0.5 表示每两秒一个
+    pub requests_per_second: f64,
+    /// 桶容量，即允许的突发请求数，默认取每秒请求数（至少为 1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub burst: Option<u32>,
+}
+
+impl RequestRateLimit {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.requests_per_second.is_finite() || self.requests_per_second <= 0.0 {
+            return Err("每秒请求数必须大于 0".into());
+        }
+        if self.burst == Some(0) {
+            return Err("突发请求数必须大于 0".into());
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> f64 {
+        self.burst
+            .map(f64::from)
+            .unwrap_or(self.requests_per_second.floor().max(1.0))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Bucket {
+    limit: RequestRateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    pub(crate) fn new(limit: RequestRateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.capacity(),
+            limit,
+            updated: now,
+        }
+    }
+
+    /// 取一个请求额度；不足时返回还需等待多久
+    pub(crate) fn acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let rate = self.limit.requests_per_second;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.limit.capacity());
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+fn bucket() -> &'static Mutex<Option<Bucket>> {
+    static BUCKET: OnceLock<Mutex<Option<Bucket>>> = OnceLock::new();
+    BUCKET.get_or_init(Default::default)
+}
+
+/// 按当前配置取一个请求额度，配置变化后桶重新装满
+pub fn try_acquire(limit: &RequestRateLimit) -> Result<(), Duration> {
+    let now = Instant::now();
+    let mut guard = bucket().lock().unwrap_or_else(|e| e.into_inner());
+    match guard.as_mut() {
+        Some(bucket) if bucket.limit == *limit => bucket.acquire(now),
+        _ => guard.insert(Bucket::new(limit.clone(), now)).acquire(now),
+    }
+}
@@ -0,0 +1,163 @@
+//! 按服务开启的响应缓存：以方法、路径（含查询参数）与请求体摘要为键，
+//! 在有效期内直接返回上一次的成功响应，适合模型列表、相同文本的 embeddings、重复的评测请求等。
+//! 每个服务各自按最近使用顺序淘汰，流式响应不缓存。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::checksum::sha256_hex;
+
+const DEFAULT_TTL_SECS: u64 = 300;
+const DEFAULT_MAX_ENTRIES: u32 = 256;
+const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// 命中缓存的响应附带该响应头
+pub const CACHE_HEADER: &str = "x-apiflow-cache";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ResponseCacheConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheConfig {
+    /// 缓存有效期，默认 300 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub ttl_secs: Option<u64>,
+    /// 每个服务最多缓存的响应数，默认 256
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_entries: Option<u32>,
+    /// 超过该大小的响应体不缓存，默认 1 MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_body_bytes: Option<u64>,
+}
+
+impl ResponseCacheConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ttl_secs == Some(0) || self.max_entries == Some(0) || self.max_body_bytes == Some(0)
+        {
+            return Err("响应缓存的有效期、条数与大小上限必须大于 0".into());
+        }
+        Ok(())
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs.unwrap_or(DEFAULT_TTL_SECS))
+    }
+
+    fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES) as usize
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES) as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub upstream_id: String,
+    pub upstream_label: Option<String>,
+    stored_at: Instant,
+}
+
+impl CachedResponse {
+    pub fn new(
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Bytes,
+        upstream_id: String,
+        upstream_label: Option<String>,
+    ) -> Self {
+        Self {
+            status,
+            headers: headers.clone(),
+            body,
+            upstream_id,
+            upstream_label,
+            stored_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ServiceCache {
+    entries: HashMap<String, CachedResponse>,
+    /// 最近使用的键排在末尾
+    order: VecDeque<String>,
+}
+
+impl ServiceCache {
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(key.to_string());
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        self.order.retain(|k| k != key);
+    }
+}
+
+fn caches() -> &'static Mutex<HashMap<String, ServiceCache>> {
+    static CACHES: OnceLock<Mutex<HashMap<String, ServiceCache>>> = OnceLock::new();
+    CACHES.get_or_init(Default::default)
+}
+
+/// 只缓存 GET 与 POST 请求，返回 None 表示该请求不参与缓存
+pub fn key(method: &Method, path: &str, body: &[u8]) -> Option<String> {
+    (method == Method::GET || method == Method::POST)
+        .then(|| format!("{method} {path} {}", sha256_hex(body)))
+}
+
+pub fn lookup(service_id: &str, config: &ResponseCacheConfig, key: &str) -> Option<CachedResponse> {
+    let mut guard = caches().lock().unwrap_or_else(|e| e.into_inner());
+    let cache = guard.get_mut(service_id)?;
+    let cached = cache.entries.get(key)?.clone();
+    if cached.stored_at.elapsed() >= config.ttl() {
+        cache.remove(key);
+        return None;
+    }
+    cache.touch(key);
+    Some(cached)
+}
+
+pub fn store(
+    service_id: &str,
+    config: &ResponseCacheConfig,
+    key: String,
+    response: CachedResponse,
+) {
+    if response.body.len() > config.max_body_bytes() {
+        return;
+    }
+    let mut guard = caches().lock().unwrap_or_else(|e| e.into_inner());
+    let cache = guard.entry(service_id.to_string()).or_default();
+    cache.touch(&key);
+    cache.entries.insert(key, response);
+    while cache.entries.len() > config.max_entries() {
+        let Some(oldest) = cache.order.pop_front() else {
+            break;
+        };
+        cache.entries.remove(&oldest);
+    }
+}
+
+/// 清空全部缓存，返回清除的条数
+pub fn clear() -> usize {
+    let mut guard = caches().lock().unwrap_or_else(|e| e.into_inner());
+    let count = guard.values().map(|c| c.entries.len()).sum();
+    guard.clear();
+    count
+}
@@ -0,0 +1,437 @@
+//! 延迟重试队列：服务为指定路径开启后，所有上游都失败的请求不直接报错，而是存入持久化队列并返回 202，
+//! 之后由后台任务按间隔重新提交给本机代理中该服务当前所在的端口。最终结果推送到回调地址，
+//! 也可通过 `/_apiflow/jobs/{id}` 或应用内的队列列表查询，适合服务商故障期间的非交互批处理任务。
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use directories::ProjectDirs;
+use http::header::{self, HeaderMap};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::helpers::strip_base_path;
+use crate::timeouts::CLIENT_TIMEOUT_HEADER;
+use crate::{timestamp, ProxyConfig};
+
+/// 重新提交时携带的内部令牌，持有者免代理鉴权且失败后不再入队；转发给上游前删除
+pub const QUEUE_TOKEN_HEADER: &str = "x-apiflow-queue-token";
+/// 入队时返回给客户端的任务 id
+pub const JOB_ID_HEADER: &str = "x-apiflow-job-id";
+const DEFAULT_DELAY_SECS: u64 = 60;
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+const GOOG_API_KEY: &str = "x-goog-api-key";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 保存的结果响应体上限
+const MAX_RESULT_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RetryQueueConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct RetryQueueConfig {
+    /// 开启延迟重试的请求路径前缀（完整路径，不含查询参数），如 `/v1/batch`
+    pub paths: Vec<String>,
+    /// 两次重新提交的间隔，默认 60 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub delay_secs: Option<u64>,
+    /// 最多重新提交的次数，默认 10 次，用尽后任务标记为失败
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_attempts: Option<u32>,
+    /// 任务完成或最终失败时以 POST 推送结果的地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub callback_url: Option<String>,
+}
+
+impl RetryQueueConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.paths.is_empty() || self.paths.iter().any(|p| !p.starts_with('/')) {
+            return Err("延迟重试的路径不能为空，且需以 / 开头".into());
+        }
+        if self.delay_secs == Some(0) || self.max_attempts == Some(0) {
+            return Err("延迟重试的间隔与次数必须大于 0".into());
+        }
+        if let Some(url) = &self.callback_url {
+            reqwest::Url::parse(url).map_err(|e| format!("延迟重试的回调地址无效: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = path.split('?').next().unwrap_or(path);
+        self.paths.iter().any(|p| path.starts_with(p.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/QueuedJobStatus.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum QueuedJobStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// 重新提交得到的最终响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/QueuedJobResult.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedJobResult {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+    pub completed_at: String,
+}
+
+/// 队列中的任务，不含请求头与请求体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/QueuedJob.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedJob {
+    pub id: String,
+    pub created_at: String,
+    pub service_name: String,
+    pub method: String,
+    pub path: String,
+    pub status: QueuedJobStatus,
+    /// 已重新提交的次数
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// 下次重新提交的时间（毫秒时间戳）
+    #[ts(type = "number")]
+    pub next_attempt_ms: i64,
+    pub last_error: Option<String>,
+    pub result: Option<QueuedJobResult>,
+}
+
+/// 持久化的完整任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueueEntry {
+    #[serde(flatten)]
+    job: QueuedJob,
+    /// 重新提交时按服务查找当前的监听端口，热重载换了端口的服务仍能收到
+    service_id: String,
+    /// 入队时服务的 base_path，服务改了 base_path 时据此替换请求路径
+    base_path: String,
+    headers: Vec<(String, String)>,
+    body_base64: String,
+    delay_secs: u64,
+    callback_url: Option<String>,
+    /// 服务不记录请求体时任务只保存在内存中，重启后丢失
+    #[serde(skip)]
+    memory_only: bool,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// 未初始化（如单元测试）时只保存在内存中
+    path: Option<PathBuf>,
+    entries: Vec<QueueEntry>,
+}
+
+fn state() -> &'static Mutex<QueueState> {
+    static STATE: OnceLock<Mutex<QueueState>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+fn token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| Uuid::new_v4().to_string())
+}
+
+/// 请求来自队列的重新提交
+pub fn is_resubmission(headers: &HeaderMap) -> bool {
+    headers
+        .get(QUEUE_TOKEN_HEADER)
+        .is_some_and(|v| v.as_bytes() == token().as_bytes())
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn save(state: &QueueState) {
+    let Some(path) = &state.path else {
+        return;
+    };
+    let persisted: Vec<&QueueEntry> = state.entries.iter().filter(|e| !e.memory_only).collect();
+    let result = serde_json::to_string(&persisted)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+    if let Err(err) = result {
+        eprintln!("保存延迟重试队列失败: {err}");
+    }
+}
+
+/// 加入队列；`sensitive` 判定的凭证头与内部请求头不保存，重新提交时由代理注入上游 key。
+/// `persist` 为 false 时（服务不记录请求体）任务不写入磁盘
+pub fn enqueue(
+    config: &RetryQueueConfig,
+    service_id: &str,
+    service_name: &str,
+    base_path: &str,
+    parts: &http::request::Parts,
+    body: &[u8],
+    sensitive: impl Fn(&str) -> bool,
+    persist: bool,
+) -> QueuedJob {
+    let headers = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            *name != header::HOST
+                && *name != header::CONTENT_LENGTH
+                && !matches!(name.as_str(), QUEUE_TOKEN_HEADER | CLIENT_TIMEOUT_HEADER)
+                && (!sensitive(name.as_str()) || name.as_str() == GOOG_API_KEY)
+        })
+        .filter_map(|(name, value)| {
+            // 只保留 x-goog-api-key 的名称，代理据此按原格式注入上游 key
+            let value = match name.as_str() {
+                GOOG_API_KEY => String::new(),
+                _ => value.to_str().ok()?.to_string(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect();
+    let delay_secs = config.delay_secs.unwrap_or(DEFAULT_DELAY_SECS);
+    let job = QueuedJob {
+        id: Uuid::new_v4().to_string(),
+        created_at: timestamp::now(),
+        service_name: service_name.to_string(),
+        method: parts.method.to_string(),
+        path: parts
+            .uri
+            .path_and_query()
+            .map_or("/", |p| p.as_str())
+            .to_string(),
+        status: QueuedJobStatus::Pending,
+        attempts: 0,
+        max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+        next_attempt_ms: now_ms() + (delay_secs * 1000) as i64,
+        last_error: None,
+        result: None,
+    };
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    state.entries.push(QueueEntry {
+        job: job.clone(),
+        service_id: service_id.to_string(),
+        base_path: base_path.to_string(),
+        headers,
+        body_base64: STANDARD.encode(body),
+        delay_secs,
+        callback_url: config.callback_url.clone(),
+        memory_only: !persist,
+    });
+    save(&state);
+    job
+}
+
+pub fn jobs() -> Vec<QueuedJob> {
+    let state = state().lock().unwrap_or_else(|e| e.into_inner());
+    state.entries.iter().rev().map(|e| e.job.clone()).collect()
+}
+
+pub fn job(id: &str) -> Option<QueuedJob> {
+    let state = state().lock().unwrap_or_else(|e| e.into_inner());
+    state
+        .entries
+        .iter()
+        .find(|e| e.job.id == id)
+        .map(|e| e.job.clone())
+}
+
+/// 删除已结束的任务，返回删除的数量
+pub fn clear_finished() -> usize {
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    let before = state.entries.len();
+    state
+        .entries
+        .retain(|e| e.job.status == QueuedJobStatus::Pending);
+    save(&state);
+    before - state.entries.len()
+}
+
+/// 5xx 与 429 视为仍未恢复，其余响应（含 4xx）作为最终结果
+fn is_final(status: u16) -> bool {
+    status < 500 && status != 429
+}
+
+/// 按当前配置找到服务所在的端口与请求路径
+fn resolve_target(config: &ProxyConfig, entry: &QueueEntry) -> Option<(u16, String)> {
+    config.per_listener().into_iter().find_map(|listener| {
+        let service = listener
+            .services
+            .iter()
+            .find(|s| s.id == entry.service_id)?;
+        let rest = strip_base_path(&entry.job.path, &entry.base_path);
+        let path = match service.base_path.as_str() {
+            "/" => rest.to_string(),
+            base => format!("{base}{rest}"),
+        };
+        Some((listener.listen_port, path))
+    })
+}
+
+/// 立即重新提交一个待处理的任务，返回更新后的任务；`config` 为运行中的代理配置。
+/// 代理未运行或服务已删除时记录原因并推迟，不计入重新提交次数
+pub async fn run_job(
+    client: &reqwest::Client,
+    config: Option<&ProxyConfig>,
+    id: &str,
+) -> Option<QueuedJob> {
+    let entry = {
+        let state = state().lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .entries
+            .iter()
+            .find(|e| e.job.id == id && e.job.status == QueuedJobStatus::Pending)
+            .cloned()?
+    };
+
+    let target = match config {
+        Some(config) => resolve_target(config, &entry)
+            .ok_or_else(|| format!("服务 {} 已不存在", entry.job.service_name)),
+        None => Err("代理未运行".to_string()),
+    };
+    let (port, path) = match target {
+        Ok(target) => target,
+        Err(err) => {
+            let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+            let stored = state.entries.iter_mut().find(|e| e.job.id == id)?;
+            stored.job.last_error = Some(err);
+            stored.job.next_attempt_ms = now_ms() + (stored.delay_secs * 1000) as i64;
+            let updated = stored.job.clone();
+            save(&state);
+            return Some(updated);
+        }
+    };
+
+    let method = reqwest::Method::from_bytes(entry.job.method.as_bytes()).ok()?;
+    let url = format!("http://127.0.0.1:{port}{path}");
+    let mut request = client
+        .request(method, url)
+        .header(QUEUE_TOKEN_HEADER, token())
+        .body(STANDARD.decode(&entry.body_base64).unwrap_or_default());
+    for (name, value) in &entry.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let outcome = match request.send().await {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let content_type = resp
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            match resp.bytes().await {
+                Ok(body) if is_final(status) => Ok(QueuedJobResult {
+                    status,
+                    content_type,
+                    body: String::from_utf8_lossy(&body[..body.len().min(MAX_RESULT_BYTES)])
+                        .into_owned(),
+                    completed_at: timestamp::now(),
+                }),
+                Ok(_) => Err(format!("重新提交后仍返回 {status}")),
+                Err(err) => Err(format!("读取响应失败: {err}")),
+            }
+        }
+        Err(err) => Err(format!("重新提交失败: {err}")),
+    };
+
+    let updated = {
+        let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+        let stored = state.entries.iter_mut().find(|e| e.job.id == id)?;
+        let job = &mut stored.job;
+        job.attempts += 1;
+        match outcome {
+            Ok(result) => {
+                job.status = QueuedJobStatus::Completed;
+                job.result = Some(result);
+            }
+            Err(err) => {
+                job.last_error = Some(err);
+                if job.attempts >= job.max_attempts {
+                    job.status = QueuedJobStatus::Failed;
+                } else {
+                    job.next_attempt_ms = now_ms() + (stored.delay_secs * 1000) as i64;
+                }
+            }
+        }
+        let updated = stored.job.clone();
+        save(&state);
+        updated
+    };
+
+    if updated.status != QueuedJobStatus::Pending {
+        if let Some(url) = &entry.callback_url {
+            if let Err(err) = client.post(url).json(&updated).send().await {
+                eprintln!("推送延迟重试结果失败: {err}");
+            }
+        }
+    }
+    Some(updated)
+}
+
+/// 读取持久化的队列并启动后台任务，按间隔把到期的任务重新提交给运行中的代理
+pub fn init(config: Arc<RwLock<Option<ProxyConfig>>>) {
+    let path = ProjectDirs::from("com", "apiflow", "app").map(|proj| {
+        let dir = proj.data_dir().to_path_buf();
+        let _ = std::fs::create_dir_all(&dir);
+        dir.join("retry_queue.json")
+    });
+    {
+        let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(data) = path.as_ref().and_then(|p| std::fs::read_to_string(p).ok()) {
+            match serde_json::from_str::<Vec<QueueEntry>>(&data) {
+                Ok(entries) => state.entries = entries,
+                Err(err) => eprintln!("读取延迟重试队列失败: {err}"),
+            }
+        }
+        state.path = path;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .no_proxy()
+            .timeout(Duration::from_secs(600))
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                eprintln!("创建延迟重试客户端失败: {err}");
+                return;
+            }
+        };
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let due: Vec<String> = {
+                let state = state().lock().unwrap_or_else(|e| e.into_inner());
+                let now = now_ms();
+                state
+                    .entries
+                    .iter()
+                    .filter(|e| {
+                        e.job.status == QueuedJobStatus::Pending && e.job.next_attempt_ms <= now
+                    })
+                    .map(|e| e.job.id.clone())
+                    .collect()
+            };
+            if due.is_empty() {
+                continue;
+            }
+            let current = config.read().await.clone();
+            for id in due {
+                run_job(&client, current.as_ref(), &id).await;
+            }
+        }
+    });
+}
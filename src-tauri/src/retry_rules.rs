@@ -0,0 +1,134 @@
+//! 按服务配置的重试 / 切换条件：按状态码与响应体（正则或 JSON 路径）匹配，
+//! 命中时覆盖按错误分类得出的默认处理方式，例如把 400 + insufficient_quota 视为需要换上游。
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::provider_error::ErrorAction;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RetryRule.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct RetryRule {
+    /// 匹配的状态码；未设置时匹配所有 4xx / 5xx。列出 2xx 时会缓冲该状态的非流式响应体用于匹配
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub statuses: Option<Vec<u16>>,
+    /// 响应体需匹配的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub body_pattern: Option<String>,
+    /// 以 `.` 分隔的 JSON 路径，如 `error.code`；未设置 json_equals 时只要求该字段存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub json_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub json_equals: Option<String>,
+    pub action: ErrorAction,
+    #[serde(skip)]
+    #[ts(skip)]
+    compiled: OnceLock<Option<Regex>>,
+}
+
+impl RetryRule {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(pattern) = &self.body_pattern {
+            Regex::new(pattern).map_err(|e| format!("重试条件正则无效 `{pattern}`: {e}"))?;
+        }
+        if self.json_equals.is_some() && self.json_path.is_none() {
+            return Err("重试条件设置了 jsonEquals 时必须同时设置 jsonPath".into());
+        }
+        if self
+            .json_path
+            .as_deref()
+            .is_some_and(|p| p.trim().is_empty())
+        {
+            return Err("重试条件的 JSON 路径不能为空".into());
+        }
+        if self
+            .statuses
+            .iter()
+            .flatten()
+            .any(|s| !(100..=599).contains(s))
+        {
+            return Err("重试条件的状态码无效".into());
+        }
+        Ok(())
+    }
+
+    fn matches_status(&self, status: u16) -> bool {
+        match &self.statuses {
+            Some(statuses) => statuses.contains(&status),
+            None => status >= 400,
+        }
+    }
+
+    fn matches(&self, status: u16, body: &[u8]) -> bool {
+        if !self.matches_status(status) {
+            return false;
+        }
+        if self.body_pattern.is_some() {
+            let regex = self.compiled.get_or_init(|| {
+                self.body_pattern
+                    .as_deref()
+                    .and_then(|p| Regex::new(p).ok())
+            });
+            let matched = regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(&String::from_utf8_lossy(body)));
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(path) = &self.json_path {
+            let Ok(json) = serde_json::from_slice::<Value>(body) else {
+                return false;
+            };
+            let value = path
+                .split('.')
+                .try_fold(&json, |v, key| match v {
+                    Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                    _ => v.get(key),
+                })
+                .filter(|v| !v.is_null());
+            let matched = match (value, &self.json_equals) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(Value::String(s)), Some(expected)) => s == expected,
+                // 非字符串字段按 JSON 字面量比较，如 "429"、"true"
+                (Some(v), Some(expected)) => {
+                    serde_json::from_str::<Value>(expected).is_ok_and(|e| e == *v)
+                }
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub fn validate_retry_rules(rules: &[RetryRule]) -> Result<(), String> {
+    rules.iter().try_for_each(RetryRule::validate)
+}
+
+/// 第一条命中的规则给出的处理方式
+pub fn rule_action(rules: &[RetryRule], status: u16, body: &[u8]) -> Option<ErrorAction> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(status, body))
+        .map(|rule| rule.action)
+}
+
+/// 是否有规则明确列出了该非错误状态码，需要读取响应体判断
+pub fn inspects_success(rules: &[RetryRule], status: u16) -> bool {
+    status < 400
+        && rules
+            .iter()
+            .any(|r| r.statuses.as_ref().is_some_and(|s| s.contains(&status)))
+}
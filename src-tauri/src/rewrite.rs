@@ -0,0 +1,216 @@
+//! 请求改写的纯函数：请求头改写、base path 处理，以及后续的协议转换。
+//! 不依赖运行中的代理与网络，可直接被单元测试和 `fuzz/` 下的 cargo-fuzz 目标调用。
+
+use std::collections::HashMap;
+
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+
+use crate::curl::mask_secret;
+use crate::retry_queue::QUEUE_TOKEN_HEADER;
+use crate::timeouts::CLIENT_TIMEOUT_HEADER;
+pub use crate::helpers::{build_upstream_url, normalize_base_path, strip_base_path};
+
+const GOOG_API_KEY: &str = "x-goog-api-key";
+const PROXY_KEY: &str = "x-proxy-key";
+
+/// 根据客户端请求头生成发往上游的请求头：
+/// 去掉 host / content-length / x-proxy-key / x-apiflow-timeout-ms / x-apiflow-queue-token；配置了上游 key 时按客户端使用的认证方式
+/// （`x-goog-api-key` 或 Bearer）替换凭证，否则回填客户端自带的认证头。
+/// 配置的 key 不是合法的 header 值时不发送任何认证头，避免把客户端凭证泄露给上游。
+pub fn rewrite_upstream_headers(headers: &HeaderMap, api_key: Option<&str>) -> HeaderMap {
+    let mut out = HeaderMap::with_capacity(headers.len() + 1);
+    let uses_goog_api_key = headers.contains_key(GOOG_API_KEY);
+
+    for (name, value) in headers.iter() {
+        if name == header::HOST
+            || name == header::CONTENT_LENGTH
+            || name == header::AUTHORIZATION
+            || name.as_str() == GOOG_API_KEY
+            || name.as_str() == PROXY_KEY
+            || name.as_str() == CLIENT_TIMEOUT_HEADER
+            || name.as_str() == QUEUE_TOKEN_HEADER
+        {
+            continue;
+        }
+        out.append(name.clone(), value.clone());
+    }
+
+    match api_key {
+        Some(key) => {
+            let (name, raw) = if uses_goog_api_key {
+                (HeaderName::from_static(GOOG_API_KEY), key.to_string())
+            } else {
+                (header::AUTHORIZATION, format!("Bearer {key}"))
+            };
+            if let Ok(mut value) = HeaderValue::from_str(&raw) {
+                value.set_sensitive(true);
+                out.insert(name, value);
+            }
+        }
+        None => {
+            if let Some(v) = headers.get(GOOG_API_KEY) {
+                out.insert(HeaderName::from_static(GOOG_API_KEY), v.clone());
+            }
+            if let Some(v) = headers.get(header::AUTHORIZATION) {
+                out.insert(header::AUTHORIZATION, v.clone());
+            }
+        }
+    }
+
+    out
+}
+
+/// 上游固定的身份请求头：`User-Agent` 及自定义请求头（如网关要求的合作方标识），
+/// 发送时覆盖客户端的同名请求头。名称或值不合法时返回错误，用于保存配置时校验。
+pub fn identity_headers(
+    user_agent: Option<&str>,
+    extra: Option<&HashMap<String, String>>,
+) -> Result<HeaderMap, String> {
+    let mut out = HeaderMap::new();
+    for (name, value) in extra.into_iter().flatten() {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| format!("无效的请求头名称: {name}"))?;
+        if name == header::HOST || name == header::CONTENT_LENGTH {
+            return Err(format!("不允许自定义请求头 {name}"));
+        }
+        let value =
+            HeaderValue::from_str(value.trim()).map_err(|_| format!("请求头 {name} 的值无效"))?;
+        out.insert(name, value);
+    }
+    if let Some(ua) = user_agent {
+        let value =
+            HeaderValue::from_str(ua.trim()).map_err(|_| "User-Agent 的值无效".to_string())?;
+        out.insert(header::USER_AGENT, value);
+    }
+    Ok(out)
+}
+
+/// 解析请求头黑名单，名称不合法时返回错误，用于保存配置时校验
+pub fn header_names(names: Option<&[String]>) -> Result<Vec<HeaderName>, String> {
+    names
+        .into_iter()
+        .flatten()
+        .map(|name| {
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| format!("无效的请求头名称: {name}"))
+        })
+        .collect()
+}
+
+/// 删除黑名单中的请求头 / 响应头
+pub fn remove_headers(headers: &mut HeaderMap, names: &[HeaderName]) {
+    for name in names {
+        headers.remove(name);
+    }
+}
+
+/// 按 HTTP/1.1 报文格式还原发往上游的请求，用于审计留档：
+/// 凭证头与 `sensitive` 判定为敏感的请求头以掩码输出，其余请求头与请求体保持原样，不应用脱敏规则；
+/// `include_body` 为 false 时只保留 content-length，不输出请求体。
+pub fn serialize_outbound(
+    method: &http::Method,
+    url: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    include_body: bool,
+    sensitive: impl Fn(&str) -> bool,
+) -> String {
+    let uri = url.parse::<http::Uri>().ok();
+    let target = uri
+        .as_ref()
+        .and_then(|u| u.path_and_query())
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let mut out = format!("{method} {target} HTTP/1.1\r\n");
+    if let Some(host) = uri.as_ref().and_then(|u| u.authority()) {
+        out.push_str(&format!("host: {host}\r\n"));
+    }
+    for (name, value) in headers {
+        let value = value.to_str().unwrap_or("<binary>");
+        let value = if name == header::AUTHORIZATION {
+            match value.split_once(' ') {
+                Some((scheme, secret)) => format!("{scheme} {}", mask_secret(secret)),
+                None => mask_secret(value),
+            }
+        } else if name.as_str() == GOOG_API_KEY || sensitive(name.as_str()) {
+            mask_secret(value)
+        } else {
+            value.to_string()
+        };
+        out.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if !body.is_empty() {
+        out.push_str(&format!("content-length: {}\r\n", body.len()));
+    }
+    out.push_str("\r\n");
+    if include_body {
+        out.push_str(&String::from_utf8_lossy(body));
+    }
+    out
+}
+
+/// 以 `name: value` 逐行格式输出，用于写入日志
+pub fn format_upstream_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value.to_str().unwrap_or("<binary>")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 服务的 base path 是否覆盖该请求路径
+pub fn matches_base_path(path: &str, base: &str) -> bool {
+    base == "/" || path.starts_with(base)
+}
+
+/// 去掉服务 base path 后拼接到上游地址，得到最终转发的 URL
+pub fn rewrite_path(path_and_query: &str, service_base: &str, upstream_base: &str) -> String {
+    build_upstream_url(upstream_base, strip_base_path(path_and_query, service_base))
+}
+
+/// 请求体是未指定 `model` 的 JSON 对象时写入默认模型，返回改写后的请求体；
+/// 其他情况（非 JSON、已有模型）返回 None
+pub fn inject_default_model(body: &[u8], model: &str) -> Option<Vec<u8>> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let object = value.as_object_mut()?;
+    let missing = object
+        .get("model")
+        .is_none_or(|m| m.is_null() || m.as_str().is_some_and(|s| s.trim().is_empty()));
+    if !missing {
+        return None;
+    }
+    object.insert("model".into(), model.into());
+    serde_json::to_vec(&value).ok()
+}
+
+/// 请求体的 `model` 在映射表中时替换为上游实际的模型名，返回改写后的请求体
+pub fn map_model(body: &[u8], map: &HashMap<String, String>) -> Option<Vec<u8>> {
+    if map.is_empty() {
+        return None;
+    }
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let object = value.as_object_mut()?;
+    let mapped = map.get(object.get("model")?.as_str()?)?;
+    object.insert("model".into(), mapped.as_str().into());
+    serde_json::to_vec(&value).ok()
+}
+
+/// 识别请求的模型名：优先取 JSON 请求体的 `model` 字段，
+/// 其次取 Gemini 风格路径 `/models/{model}:generateContent` 中的模型
+pub fn extract_model(path: &str, body: &[u8]) -> Option<String> {
+    let from_body = body
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .filter(|b| **b == b'{')
+        .and_then(|_| serde_json::from_slice::<serde_json::Value>(body).ok())
+        .and_then(|v| v.get("model")?.as_str().map(str::to_string));
+
+    from_body
+        .or_else(|| {
+            let path = path.split('?').next().unwrap_or(path);
+            let rest = &path[path.find("/models/")? + "/models/".len()..];
+            let model = rest.split([':', '/']).next()?;
+            Some(model.to_string())
+        })
+        .filter(|m| !m.trim().is_empty())
+}
@@ -0,0 +1,60 @@
+//! 安全模式：以 `--safe-mode` 启动（或设置 `APIFLOW_SAFE_MODE=1`）时，运行中的代理忽略
+//! 缓存、合并、改写、模型映射、结构化输出转换、流式转换、遮盖、对比、模拟上游、模拟端点、
+//! 能力表、加权路由与钩子等扩展功能，只按优先级转发并保留失败切换，便于判断问题出在核心转发还是某个扩展。
+//! 只影响运行时配置，保存的配置保持原样，正常启动后即恢复。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ProxyConfig;
+
+const FLAG: &str = "--safe-mode";
+const ENV: &str = "APIFLOW_SAFE_MODE";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 按启动参数与环境变量决定是否进入安全模式
+pub fn init_from_env() {
+    let by_flag = std::env::args().skip(1).any(|a| a == FLAG);
+    let by_env = std::env::var(ENV).is_ok_and(|v| matches!(v.trim(), "1" | "true"));
+    if by_flag || by_env {
+        ENABLED.store(true, Ordering::Relaxed);
+        eprintln!("已以安全模式启动：扩展功能全部停用");
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 去掉运行配置中的扩展功能，只保留上游、鉴权、超时与重试切换等核心设置
+pub fn strip(config: &mut ProxyConfig) {
+    config.synthetic_endpoints = None;
+    config.stream_tee = None;
+    config.hooks = None;
+    config.custom_dimension = None;
+    config.verify_checksums = None;
+    config.provider_capabilities = None;
+    for service in &mut config.services {
+        service.cache = None;
+        service.dedupe_in_flight = None;
+        service.default_model = None;
+        service.compare = None;
+        service.mirror = None;
+        service.context_overflow = None;
+        service.path_rewrites = None;
+        service.policy_fallback_upstream_id = None;
+        service.retry_queue = None;
+        service.model_filter = None;
+        service.pii_mask = None;
+        service.answer_locally = None;
+        service.routing = None;
+        service.streaming = None;
+        for upstream in &mut service.upstreams {
+            upstream.chaos = None;
+            upstream.structured_output = None;
+            upstream.query_params = None;
+            upstream.mock = None;
+            upstream.model_map = None;
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! 按上游的令牌桶调度：发往上游前预约一次请求额度，并检查 token 额度是否已透支，
+//! 不足时稍作等待再发送，而不是等上游返回 429 后再重试。
+//! token 用量在响应结束后按实际值扣减，透支部分由后续请求等待补足。
+//! 配置了并发上限时，占满后排队的交互式请求优先于批量请求获得空位。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+const DEFAULT_MAX_DELAY_MS: u64 = 10_000;
+/// 非流式请求声明的最大输出长度达到该值时视为批量请求
+const LARGE_MAX_TOKENS: u64 = 4096;
+
+/// 上游的速率上限，通常按服务商账户等级给出的 RPM / TPM 填写
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RateLimitConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub tokens_per_minute: Option<u64>,
+    /// 单次请求最多等待的毫秒数，超过后不再等待直接发送，默认 10 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub max_delay_ms: Option<u64>,
+    /// 同时发往该上游的最大请求数（流式请求直到响应结束才释放）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub max_concurrency: Option<u32>,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.requests_per_minute == Some(0)
+            || self.tokens_per_minute == Some(0)
+            || self.max_concurrency == Some(0)
+        {
+            return Err("速率上限必须大于 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// 上游调度器的当前状态，随统计一起展示
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/SchedulerStats.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerStats {
+    pub requests_per_minute: Option<u32>,
+    #[ts(type = "number | null")]
+    pub tokens_per_minute: Option<u64>,
+    /// 当前可立即使用的额度，为负表示已有请求在排队等待
+    pub available_requests: Option<f64>,
+    pub available_tokens: Option<f64>,
+    /// 因限流被推迟发送的请求数
+    #[ts(type = "number")]
+    pub delayed_requests: u64,
+    #[ts(type = "number")]
+    pub total_delay_ms: u64,
+    pub max_concurrency: Option<u32>,
+    pub in_flight: u32,
+    /// 等待并发空位的交互式 / 批量请求数
+    pub queued_interactive: u32,
+    pub queued_batch: u32,
+}
+
+/// 请求类别，并发占满时交互式请求优先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RequestPriority.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum RequestPriority {
+    /// 流式对话等需要即时响应的请求
+    Interactive,
+    /// embeddings、批处理以及声明了较大输出长度的非流式请求
+    Batch,
+}
+
+pub fn classify_request(path: &str, body: &[u8]) -> RequestPriority {
+    let path = path.split('?').next().unwrap_or(path);
+    if path.ends_with("/embeddings")
+        || path.contains("/batches")
+        || path.ends_with(":embedContent")
+        || path.ends_with(":batchEmbedContents")
+    {
+        return RequestPriority::Batch;
+    }
+    if path.ends_with(":streamGenerateContent") {
+        return RequestPriority::Interactive;
+    }
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return RequestPriority::Interactive;
+    };
+    if value["stream"].as_bool() == Some(true) {
+        return RequestPriority::Interactive;
+    }
+    let max_tokens = ["max_tokens", "max_completion_tokens", "max_output_tokens"]
+        .iter()
+        .find_map(|key| value[key].as_u64())
+        .or_else(|| value["generationConfig"]["maxOutputTokens"].as_u64());
+    if max_tokens.is_some_and(|m| m >= LARGE_MAX_TOKENS) {
+        RequestPriority::Batch
+    } else {
+        RequestPriority::Interactive
+    }
+}
+
+/// 按每分钟额度匀速补充的令牌桶；预约时直接扣减，余额为负代表后续请求需要排队
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: f64, now: Instant) -> Self {
+        Self {
+            capacity: limit,
+            available: limit,
+            per_sec: limit / 60.0,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// 余额补足到 0 所需的时长
+    fn wait(&self) -> Duration {
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / self.per_sec)
+        }
+    }
+
+    /// 扣减 amount 并返回需要等待的时长
+    fn reserve(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        self.available -= amount.min(self.capacity);
+        self.wait()
+    }
+
+    fn adjust(&mut self, delta: f64, now: Instant) {
+        self.refill(now);
+        self.available = (self.available + delta).min(self.capacity);
+    }
+
+    fn resize(&mut self, limit: f64, now: Instant) {
+        self.refill(now);
+        self.capacity = limit;
+        self.per_sec = limit / 60.0;
+        self.available = self.available.min(limit);
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+fn sync_bucket(bucket: &mut Option<TokenBucket>, limit: Option<f64>, now: Instant) {
+    match (bucket.as_mut(), limit) {
+        (Some(b), Some(limit)) if b.capacity != limit => b.resize(limit, now),
+        (Some(_), Some(_)) => {}
+        (None, Some(limit)) => *bucket = Some(TokenBucket::per_minute(limit, now)),
+        (_, None) => *bucket = None,
+    }
+}
+
+/// 并发空位；释放时直接交给排队中的下一个请求，交互式队列优先
+#[derive(Default)]
+struct Slots {
+    max: Option<u32>,
+    in_flight: u32,
+    interactive: VecDeque<oneshot::Sender<()>>,
+    batch: VecDeque<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct UpstreamLimiter {
+    buckets: Mutex<Buckets>,
+    slots: Mutex<Slots>,
+    delayed_requests: AtomicU64,
+    total_delay_ms: AtomicU64,
+}
+
+impl UpstreamLimiter {
+    fn release(&self) {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(next) = slots
+            .interactive
+            .pop_front()
+            .or_else(|| slots.batch.pop_front())
+        {
+            // 等待方已取消时接收端关闭，继续交给下一个
+            if next.send(()).is_ok() {
+                return;
+            }
+        }
+        slots.in_flight = slots.in_flight.saturating_sub(1);
+    }
+}
+
+/// 持有期间占用上游的一个并发空位，drop 时释放
+pub struct SlotPermit {
+    limiter: Arc<UpstreamLimiter>,
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+/// 排队中的等待；若空位已转交但等待方在取走前被取消，drop 时归还
+struct Waiting {
+    rx: Option<oneshot::Receiver<()>>,
+    limiter: Arc<UpstreamLimiter>,
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.limiter.release();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    limiters: Mutex<HashMap<String, Arc<UpstreamLimiter>>>,
+}
+
+impl Scheduler {
+    fn limiter(&self, upstream_id: &str) -> Arc<UpstreamLimiter> {
+        let mut guard = self.limiters.lock().unwrap_or_else(|e| e.into_inner());
+        guard.entry(upstream_id.to_string()).or_default().clone()
+    }
+
+    /// 申请一个并发空位，占满时按优先级排队；未配置并发上限时返回 None
+    pub async fn acquire_slot(
+        &self,
+        upstream_id: &str,
+        max_concurrency: Option<u32>,
+        priority: RequestPriority,
+    ) -> Option<SlotPermit> {
+        let max = max_concurrency?;
+        let limiter = self.limiter(upstream_id);
+        let rx = {
+            let mut slots = limiter.slots.lock().unwrap_or_else(|e| e.into_inner());
+            slots.max = Some(max);
+            if slots.in_flight < max {
+                slots.in_flight += 1;
+                drop(slots);
+                return Some(SlotPermit { limiter });
+            }
+            let (tx, rx) = oneshot::channel();
+            match priority {
+                RequestPriority::Interactive => slots.interactive.push_back(tx),
+                RequestPriority::Batch => slots.batch.push_back(tx),
+            }
+            rx
+        };
+
+        let mut waiting = Waiting {
+            rx: Some(rx),
+            limiter: limiter.clone(),
+        };
+        let granted = match waiting.rx.as_mut() {
+            Some(rx) => rx.await.is_ok(),
+            None => false,
+        };
+        waiting.rx = None;
+        granted.then_some(SlotPermit { limiter })
+    }
+
+    /// 为一次发送预约额度，返回应等待的时长（不超过 max_delay_ms）
+    pub fn reserve_at(
+        &self,
+        upstream_id: &str,
+        limits: &RateLimitConfig,
+        now: Instant,
+    ) -> Duration {
+        let limiter = self.limiter(upstream_id);
+        let delay = {
+            let mut buckets = limiter.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            sync_bucket(
+                &mut buckets.requests,
+                limits.requests_per_minute.map(f64::from),
+                now,
+            );
+            sync_bucket(
+                &mut buckets.tokens,
+                limits.tokens_per_minute.map(|t| t as f64),
+                now,
+            );
+            let by_requests = buckets
+                .requests
+                .as_mut()
+                .map(|b| b.reserve(1.0, now))
+                .unwrap_or_default();
+            let by_tokens = buckets
+                .tokens
+                .as_mut()
+                .map(|b| {
+                    b.refill(now);
+                    b.wait()
+                })
+                .unwrap_or_default();
+            by_requests.max(by_tokens)
+        };
+
+        let max_delay = Duration::from_millis(limits.max_delay_ms.unwrap_or(DEFAULT_MAX_DELAY_MS));
+        let delay = delay.min(max_delay);
+        if !delay.is_zero() {
+            limiter.delayed_requests.fetch_add(1, Ordering::Relaxed);
+            limiter
+                .total_delay_ms
+                .fetch_add(delay.as_millis() as u64, Ordering::Relaxed);
+        }
+        delay
+    }
+
+    /// 响应结束后按实际用量扣减 token 额度；未配置 TPM 的上游不做处理
+    pub fn charge_at(&self, upstream_id: &str, tokens: u64, now: Instant) {
+        let limiter = {
+            let guard = self.limiters.lock().unwrap_or_else(|e| e.into_inner());
+            match guard.get(upstream_id) {
+                Some(limiter) => limiter.clone(),
+                None => return,
+            }
+        };
+        let mut buckets = limiter.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = buckets.tokens.as_mut() {
+            bucket.adjust(-(tokens as f64), now);
+        }
+    }
+
+    pub fn stats_at(&self, upstream_id: &str, now: Instant) -> Option<SchedulerStats> {
+        let limiter = {
+            let guard = self.limiters.lock().unwrap_or_else(|e| e.into_inner());
+            guard.get(upstream_id).cloned()?
+        };
+        let mut buckets = limiter.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot = |bucket: &mut Option<TokenBucket>| {
+            bucket.as_mut().map(|b| {
+                b.refill(now);
+                (b.capacity, b.available)
+            })
+        };
+        let requests = snapshot(&mut buckets.requests);
+        let tokens = snapshot(&mut buckets.tokens);
+        let mut slots = limiter.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.interactive.retain(|tx| !tx.is_closed());
+        slots.batch.retain(|tx| !tx.is_closed());
+        Some(SchedulerStats {
+            requests_per_minute: requests.map(|(cap, _)| cap as u32),
+            tokens_per_minute: tokens.map(|(cap, _)| cap as u64),
+            available_requests: requests.map(|(_, avail)| avail),
+            available_tokens: tokens.map(|(_, avail)| avail),
+            delayed_requests: limiter.delayed_requests.load(Ordering::Relaxed),
+            total_delay_ms: limiter.total_delay_ms.load(Ordering::Relaxed),
+            max_concurrency: slots.max,
+            in_flight: slots.in_flight,
+            queued_interactive: slots.interactive.len() as u32,
+            queued_batch: slots.batch.len() as u32,
+        })
+    }
+}
+
+pub fn scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    SCHEDULER.get_or_init(Scheduler::default)
+}
+
+/// 发送前先取得并发空位，再按速率上限等待；未配置限额时立即返回
+pub async fn acquire(
+    upstream_id: &str,
+    limits: Option<&RateLimitConfig>,
+    priority: RequestPriority,
+) -> Option<SlotPermit> {
+    let limits = limits?;
+    let permit = scheduler()
+        .acquire_slot(upstream_id, limits.max_concurrency, priority)
+        .await;
+    let delay = scheduler().reserve_at(upstream_id, limits, Instant::now());
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    permit
+}
+
+pub fn charge(upstream_id: &str, tokens: u64) {
+    scheduler().charge_at(upstream_id, tokens, Instant::now());
+}
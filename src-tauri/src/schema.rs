@@ -0,0 +1,43 @@
+//! 持久化日志的结构版本与前向迁移。日志以 JSON 保存，打开存储时若数据版本较旧，
+//! 按顺序执行迁移后再写回，使 ProxyLogEntry 字段调整后升级前的历史仍能加载。
+
+use serde_json::{Map, Value};
+
+/// 当前日志结构版本。修改 ProxyLogEntry 导致旧数据无法直接反序列化时递增，
+/// 并在 MIGRATIONS 末尾追加对应的迁移
+pub const LOG_SCHEMA_VERSION: u32 = 2;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// MIGRATIONS[i] 把版本 i 的日志迁移到版本 i + 1
+const MIGRATIONS: [Migration; LOG_SCHEMA_VERSION as usize] = [v0_to_v1, v1_to_v2];
+
+/// 版本 0 为引入版本号之前写入的数据，字段与版本 1 兼容，只需标记版本
+fn v0_to_v1(_entry: &mut Map<String, Value>) {}
+
+/// 版本 2 起时间戳改为毫秒精度的 RFC3339 UTC，旧的本地时间字符串按本机时区转换
+fn v1_to_v2(entry: &mut Map<String, Value>) {
+    if let Some(Value::String(ts)) = entry.get_mut("timestamp") {
+        *ts = crate::timestamp::normalize(ts);
+    }
+}
+
+/// 数据版本高于当前应用时拒绝打开，避免旧版本应用写入新版本无法识别的数据
+pub fn ensure_supported(version: u32) -> Result<(), String> {
+    if version > LOG_SCHEMA_VERSION {
+        return Err(format!(
+            "日志数据版本 {version} 高于当前支持的版本 {LOG_SCHEMA_VERSION}，请升级 ApiFlow 或更换日志存储位置"
+        ));
+    }
+    Ok(())
+}
+
+/// 把版本 from 的单条日志 JSON 迁移到当前版本；无法解析时返回 None，由调用方保留原文
+pub fn migrate_json(json: &str, from: u32) -> Option<String> {
+    let mut value: Value = serde_json::from_str(json).ok()?;
+    let entry = value.as_object_mut()?;
+    for migration in MIGRATIONS.iter().skip(from as usize) {
+        migration(entry);
+    }
+    serde_json::to_string(&value).ok()
+}
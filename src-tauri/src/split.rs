@@ -0,0 +1,85 @@
+//! 按权重分流：服务使用 weighted 模式时，每个请求按上游权重随机选出首选上游（如 90/10 灰度新服务商），
+//! 其余上游仍按优先级排在后面，用于失败时切换。
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{ProxyConfig, ServiceConfig, UpstreamEntry, UpstreamStats};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/RoutingMode.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum RoutingMode {
+    /// 按优先级依次尝试
+    #[default]
+    Priority,
+    /// 按权重随机选择首选上游
+    Weighted,
+}
+
+/// 未设置权重的上游按 1 计
+pub fn weight_of(upstream: &UpstreamEntry) -> u32 {
+    upstream.weight.unwrap_or(1)
+}
+
+pub fn validate_weights(service: &ServiceConfig) -> Result<(), String> {
+    if service.routing == Some(RoutingMode::Weighted)
+        && service.upstreams.iter().all(|u| weight_of(u) == 0)
+    {
+        return Err(format!(
+            "服务 {} 按权重分流时至少需要一个权重大于 0 的上游",
+            service.name
+        ));
+    }
+    Ok(())
+}
+
+/// 把按权重随机选中的元素移到最前，其余元素保持原有顺序；unit 为 [0, 1) 的随机数。
+/// 权重全为 0 时不调整
+pub fn weighted_first<T>(items: &mut [T], weight: impl Fn(&T) -> u32, unit: f64) {
+    let total: u64 = items.iter().map(|item| u64::from(weight(item))).sum();
+    if total == 0 {
+        return;
+    }
+    let mut target = ((unit * total as f64) as u64).min(total - 1);
+    for i in 0..items.len() {
+        let w = u64::from(weight(&items[i]));
+        if target < w {
+            items[..=i].rotate_right(1);
+            return;
+        }
+        target -= w;
+    }
+}
+
+/// 为按权重分流的服务下的上游填写配置的权重占比与实际请求占比
+pub fn annotate_stats(stats: &mut [UpstreamStats], config: &ProxyConfig) {
+    for service in config.all_services() {
+        if service.routing != Some(RoutingMode::Weighted) {
+            continue;
+        }
+        let weights: HashMap<&str, u32> = service
+            .upstreams
+            .iter()
+            .filter(|u| u.enabled)
+            .map(|u| (u.id.as_str(), weight_of(u)))
+            .collect();
+        let total_weight: u32 = weights.values().sum();
+        let total_requests: u64 = stats
+            .iter()
+            .filter(|s| weights.contains_key(s.upstream_id.as_str()))
+            .map(|s| s.total_requests)
+            .sum();
+        for upstream in stats.iter_mut() {
+            let Some(weight) = weights.get(upstream.upstream_id.as_str()) else {
+                continue;
+            };
+            upstream.weight_share =
+                (total_weight > 0).then(|| f64::from(*weight) / f64::from(total_weight));
+            upstream.traffic_share = (total_requests > 0)
+                .then(|| upstream.total_requests as f64 / total_requests as f64);
+        }
+    }
+}
@@ -0,0 +1,706 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::scheduler::scheduler;
+use crate::usage::TokenUsage;
+use crate::{events, ProxyLogEntry, UpstreamStats, WindowStats};
+
+const STATS_SHARDS: usize = 16;
+
+/// 直方图桶按 1.2 倍指数增长，第 i 个桶上界为 1.2^i 毫秒，
+/// 80 个桶可覆盖约 35 分钟，分位数相对误差不超过 20%
+const HISTOGRAM_GROWTH: f64 = 1.2;
+const HISTOGRAM_BUCKETS: usize = 80;
+
+const COST_SCALE: f64 = 1_000_000.0;
+
+/// 已删除上游的统计归档超过 30 天后，在下次对账时清除
+const ARCHIVE_RETENTION_SECS: u64 = 30 * 24 * 3600;
+
+/// 无锁的对数分桶延迟直方图
+struct LatencyHistogram {
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS],
+    min_ms: AtomicU64,
+    max_ms: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            min_ms: AtomicU64::new(u64::MAX),
+            max_ms: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn bucket_index(ms: u64) -> usize {
+        if ms <= 1 {
+            return 0;
+        }
+        let idx = ((ms as f64).ln() / HISTOGRAM_GROWTH.ln()).ceil() as usize;
+        idx.min(HISTOGRAM_BUCKETS - 1)
+    }
+
+    fn bucket_upper_bound(idx: usize) -> u64 {
+        HISTOGRAM_GROWTH.powi(idx as i32).ceil() as u64
+    }
+
+    fn record(&self, ms: u64) {
+        self.buckets[Self::bucket_index(ms)].fetch_add(1, Ordering::Relaxed);
+        self.min_ms.fetch_min(ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(ms, Ordering::Relaxed);
+    }
+
+    /// 返回 (min, max, p50, p95, p99)，没有样本时全部为 0
+    fn summary(&self) -> (u64, u64, u64, u64, u64) {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return (0, 0, 0, 0, 0);
+        }
+        let min = self.min_ms.load(Ordering::Relaxed);
+        let max = self.max_ms.load(Ordering::Relaxed);
+
+        let percentile = |q: f64| {
+            let rank = ((total as f64) * q).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (idx, count) in counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return Self::bucket_upper_bound(idx).clamp(min, max);
+                }
+            }
+            max
+        };
+
+        (min, max, percentile(0.50), percentile(0.95), percentile(0.99))
+    }
+}
+
+/// 滚动窗口按 10 秒分槽，保留最近 1 小时
+const WINDOW_SLOT_SECS: u64 = 10;
+const WINDOW_SLOTS: usize = 360;
+/// get_stats 返回的时间窗口：1 分钟 / 5 分钟 / 1 小时
+pub const STATS_WINDOWS_SECS: [u64; 3] = [60, 300, 3600];
+
+#[derive(Default, Clone, Copy)]
+struct WindowSlot {
+    /// 该槽对应的时间序号（unix 秒 / 槽宽），用于判断槽是否过期
+    epoch: u64,
+    requests: u64,
+    errors: u64,
+    total_duration_ms: u64,
+    max_duration_ms: u64,
+}
+
+/// 环形时间槽，槽被复用时按 epoch 判断并清零
+struct RollingWindow {
+    slots: Vec<WindowSlot>,
+}
+
+impl Default for RollingWindow {
+    fn default() -> Self {
+        Self {
+            slots: vec![WindowSlot::default(); WINDOW_SLOTS],
+        }
+    }
+}
+
+impl RollingWindow {
+    fn record(&mut self, now_secs: u64, duration_ms: u64, success: bool) {
+        let epoch = now_secs / WINDOW_SLOT_SECS;
+        let slot = &mut self.slots[(epoch as usize) % WINDOW_SLOTS];
+        if slot.epoch > epoch {
+            // 比环内已有数据还早一整圈的记录直接丢弃
+            return;
+        }
+        if slot.epoch != epoch {
+            *slot = WindowSlot {
+                epoch,
+                ..WindowSlot::default()
+            };
+        }
+        slot.requests += 1;
+        if !success {
+            slot.errors += 1;
+        }
+        slot.total_duration_ms += duration_ms;
+        slot.max_duration_ms = slot.max_duration_ms.max(duration_ms);
+    }
+
+    /// 汇总最近 `window_secs` 秒（按槽宽对齐，包含当前未结束的槽）
+    fn summary(&self, now_secs: u64, window_secs: u64) -> WindowStats {
+        let current = now_secs / WINDOW_SLOT_SECS;
+        let span = window_secs.div_ceil(WINDOW_SLOT_SECS).min(WINDOW_SLOTS as u64);
+        let oldest = current.saturating_sub(span.saturating_sub(1));
+
+        let mut stats = WindowStats {
+            window_secs,
+            ..WindowStats::default()
+        };
+        let mut total_duration_ms = 0;
+        for slot in self.slots.iter().filter(|s| s.epoch >= oldest && s.epoch <= current) {
+            stats.requests += slot.requests;
+            stats.errors += slot.errors;
+            total_duration_ms += slot.total_duration_ms;
+            stats.max_duration_ms = stats.max_duration_ms.max(slot.max_duration_ms);
+        }
+        if stats.requests > 0 {
+            stats.requests_per_minute = stats.requests as f64 * 60.0 / window_secs as f64;
+            stats.error_rate = stats.errors as f64 / stats.requests as f64;
+            stats.avg_duration_ms = total_duration_ms / stats.requests;
+        }
+        stats
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/StatsGroupBy.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum StatsGroupBy {
+    Upstream,
+    Service,
+    Model,
+    /// 配置中的自定义维度，如项目
+    Dimension,
+    /// 按 User-Agent 识别出的客户端工具
+    ClientApp,
+}
+
+/// get_stats_breakdown 的单行结果
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/GroupStats.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStats {
+    pub key: String,
+    /// 仅按上游分组时有值
+    pub label: Option<String>,
+    #[ts(type = "number")]
+    pub total_requests: u64,
+    #[ts(type = "number")]
+    pub success_count: u64,
+    #[ts(type = "number")]
+    pub error_count: u64,
+    #[ts(type = "number")]
+    pub total_duration_ms: u64,
+    #[ts(type = "number")]
+    pub p50_ms: u64,
+    #[ts(type = "number")]
+    pub p95_ms: u64,
+    #[ts(type = "number")]
+    pub p99_ms: u64,
+    pub windows: Vec<WindowStats>,
+    #[ts(type = "number")]
+    pub prompt_tokens: u64,
+    #[ts(type = "number")]
+    pub completion_tokens: u64,
+    #[ts(type = "number")]
+    pub total_tokens: u64,
+    pub total_cost: f64,
+}
+
+impl From<UpstreamStats> for GroupStats {
+    fn from(stats: UpstreamStats) -> Self {
+        Self {
+            key: stats.upstream_id,
+            label: stats.upstream_label,
+            total_requests: stats.total_requests,
+            success_count: stats.success_count,
+            error_count: stats.error_count,
+            total_duration_ms: stats.total_duration_ms,
+            p50_ms: stats.p50_ms,
+            p95_ms: stats.p95_ms,
+            p99_ms: stats.p99_ms,
+            windows: stats.windows,
+            prompt_tokens: stats.prompt_tokens,
+            completion_tokens: stats.completion_tokens,
+            total_tokens: stats.total_tokens,
+            total_cost: stats.total_cost,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/SpendItem.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct SpendItem {
+    pub key: String,
+    pub label: Option<String>,
+    #[ts(type = "number")]
+    pub total_requests: u64,
+    #[ts(type = "number")]
+    pub total_tokens: u64,
+    pub total_cost: f64,
+}
+
+/// get_spend_summary 的结果，各列表按费用从高到低排序
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/SpendSummary.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct SpendSummary {
+    pub total_cost: f64,
+    pub by_upstream: Vec<SpendItem>,
+    pub by_service: Vec<SpendItem>,
+    pub by_dimension: Vec<SpendItem>,
+}
+
+fn unix_secs(now: SystemTime) -> u64 {
+    now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// 单个统计 key 的计数器，累计值只做原子加法；滚动窗口使用该 key 独占的锁
+#[derive(Default)]
+struct UpstreamCounters {
+    upstream_label: RwLock<Option<String>>,
+    total_requests: AtomicU64,
+    success_count: AtomicU64,
+    error_count: AtomicU64,
+    total_duration_ms: AtomicU64,
+    latency: LatencyHistogram,
+    window: Mutex<RollingWindow>,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    total_tokens: AtomicU64,
+    /// 累计费用，以百万分之一为单位存储以便原子累加
+    cost_micros: AtomicU64,
+    /// 归档时间（unix 秒），0 表示仍在配置中
+    archived_at: AtomicU64,
+}
+
+impl UpstreamCounters {
+    fn record(&self, now_secs: u64, duration_ms: u64, success: bool) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        self.total_duration_ms.fetch_add(duration_ms, Ordering::Relaxed);
+        self.latency.record(duration_ms);
+        self.window
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(now_secs, duration_ms, success);
+        if success {
+            self.success_count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn record_usage(&self, usage: &TokenUsage, cost: Option<f64>) {
+        self.prompt_tokens.fetch_add(usage.prompt_tokens, Ordering::Relaxed);
+        self.completion_tokens.fetch_add(usage.completion_tokens, Ordering::Relaxed);
+        self.total_tokens.fetch_add(usage.total_tokens, Ordering::Relaxed);
+        if let Some(cost) = cost {
+            self.cost_micros
+                .fetch_add((cost * COST_SCALE).round() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self, upstream_id: &str, now_secs: u64) -> UpstreamStats {
+        let (min_duration_ms, max_duration_ms, p50_ms, p95_ms, p99_ms) = self.latency.summary();
+        let windows = {
+            let window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+            STATS_WINDOWS_SECS
+                .iter()
+                .map(|secs| window.summary(now_secs, *secs))
+                .collect()
+        };
+        UpstreamStats {
+            upstream_id: upstream_id.to_string(),
+            upstream_label: self.upstream_label.read().map(|l| l.clone()).unwrap_or(None),
+            total_requests: self.total_requests.load(Ordering::Relaxed),
+            success_count: self.success_count.load(Ordering::Relaxed),
+            error_count: self.error_count.load(Ordering::Relaxed),
+            total_duration_ms: self.total_duration_ms.load(Ordering::Relaxed),
+            min_duration_ms,
+            max_duration_ms,
+            p50_ms,
+            p95_ms,
+            p99_ms,
+            windows,
+            scheduler: None,
+            prompt_tokens: self.prompt_tokens.load(Ordering::Relaxed),
+            completion_tokens: self.completion_tokens.load(Ordering::Relaxed),
+            total_tokens: self.total_tokens.load(Ordering::Relaxed),
+            total_cost: self.cost_micros.load(Ordering::Relaxed) as f64 / COST_SCALE,
+            archived: self.archived_at.load(Ordering::Relaxed) != 0,
+            weight_share: None,
+            traffic_share: None,
+        }
+    }
+}
+
+type Shard = RwLock<HashMap<String, Arc<UpstreamCounters>>>;
+
+/// 按 key 分片的计数器表：读多写少的 map 查找走分片读锁，
+/// 计数更新全部为原子操作，避免并发流式请求在同一把锁上排队。
+struct CounterMap {
+    shards: Vec<Shard>,
+}
+
+impl Default for CounterMap {
+    fn default() -> Self {
+        Self {
+            shards: (0..STATS_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+        }
+    }
+}
+
+impl CounterMap {
+    fn shard(&self, key: &str) -> &Shard {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[(hasher.finish() as usize) % self.shards.len()]
+    }
+
+    fn counters(&self, key: &str) -> Arc<UpstreamCounters> {
+        let shard = self.shard(key);
+        if let Some(existing) = shard.read().ok().and_then(|g| g.get(key).cloned()) {
+            return existing;
+        }
+        let mut guard = shard.write().unwrap_or_else(|e| e.into_inner());
+        guard.entry(key.to_string()).or_default().clone()
+    }
+
+    fn snapshot(&self, now_secs: u64) -> Vec<UpstreamStats> {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                let guard = shard.read().unwrap_or_else(|e| e.into_inner());
+                guard
+                    .iter()
+                    .map(|(id, counters)| counters.snapshot(id, now_secs))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> Option<Arc<UpstreamCounters>> {
+        let guard = self.shard(key).read().unwrap_or_else(|e| e.into_inner());
+        guard.get(key).cloned()
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// 把 from 的计数器移到 to 名下；from 不存在或 to 已有计数器时返回 false
+    fn rename(&self, from: &str, to: &str) -> bool {
+        if self.contains(to) {
+            return false;
+        }
+        let Some(counters) = self
+            .shard(from)
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(from)
+        else {
+            return false;
+        };
+        self.shard(to)
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(to.to_string(), counters);
+        true
+    }
+
+    /// 删除 keep 返回 false 的计数器，返回删除数量
+    fn retain(&self, mut keep: impl FnMut(&str, &UpstreamCounters) -> bool) -> usize {
+        let mut removed = 0;
+        for shard in &self.shards {
+            let mut guard = shard.write().unwrap_or_else(|e| e.into_inner());
+            let before = guard.len();
+            guard.retain(|key, counters| keep(key, counters));
+            removed += before - guard.len();
+        }
+        removed
+    }
+
+    fn clear(&self) {
+        for shard in &self.shards {
+            shard.write().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+/// 除上游外的统计维度，取自请求日志
+#[derive(Debug, Clone, Default)]
+pub struct StatsDims {
+    pub service_name: Option<String>,
+    pub model: Option<String>,
+    pub dimension: Option<String>,
+    pub client_app: Option<String>,
+}
+
+impl StatsDims {
+    pub fn of(entry: &ProxyLogEntry) -> Self {
+        Self {
+            service_name: entry.service_name.clone(),
+            model: entry.model.clone(),
+            dimension: entry.dimension.clone(),
+            client_app: entry.client_app.clone(),
+        }
+    }
+}
+
+/// 上游在配置中的身份；id 变化（删除后重建）时按 base URL + 名称找回历史统计
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamIdentity<'a> {
+    pub id: &'a str,
+    pub upstream_base: &'a str,
+    pub label: Option<&'a str>,
+}
+
+/// 统计存储：按上游、服务、模型、自定义维度与客户端工具分别累计
+#[derive(Default)]
+pub struct StatsStore {
+    upstreams: CounterMap,
+    services: CounterMap,
+    models: CounterMap,
+    dimensions: CounterMap,
+    client_apps: CounterMap,
+    /// 上游 id 最近一次在配置中的 (base URL, 名称)
+    identities: Mutex<HashMap<String, (String, Option<String>)>>,
+}
+
+impl StatsStore {
+    pub fn record(
+        &self,
+        upstream_id: &str,
+        upstream_label: Option<String>,
+        dims: &StatsDims,
+        duration_ms: u64,
+        success: bool,
+    ) {
+        self.record_at(upstream_id, upstream_label, dims, duration_ms, success, SystemTime::now());
+    }
+
+    pub fn record_at(
+        &self,
+        upstream_id: &str,
+        upstream_label: Option<String>,
+        dims: &StatsDims,
+        duration_ms: u64,
+        success: bool,
+        now: SystemTime,
+    ) {
+        let now_secs = unix_secs(now);
+        let counters = self.upstreams.counters(upstream_id);
+        counters.record(now_secs, duration_ms, success);
+        if upstream_label.is_some() {
+            let missing = counters
+                .upstream_label
+                .read()
+                .map(|l| l.is_none())
+                .unwrap_or(false);
+            if missing {
+                if let Ok(mut label) = counters.upstream_label.write() {
+                    if label.is_none() {
+                        *label = upstream_label;
+                    }
+                }
+            }
+        }
+        if let Some(service) = dims.service_name.as_deref() {
+            self.services.counters(service).record(now_secs, duration_ms, success);
+        }
+        if let Some(model) = dims.model.as_deref() {
+            self.models.counters(model).record(now_secs, duration_ms, success);
+        }
+        if let Some(dimension) = dims.dimension.as_deref() {
+            self.dimensions.counters(dimension).record(now_secs, duration_ms, success);
+        }
+        if let Some(app) = dims.client_app.as_deref() {
+            self.client_apps.counters(app).record(now_secs, duration_ms, success);
+        }
+        events::notify_stats();
+    }
+
+    /// 响应结束后累计 token 用量与估算费用，维度与 record 相同
+    pub fn record_usage(&self, upstream_id: &str, dims: &StatsDims, usage: &TokenUsage, cost: Option<f64>) {
+        self.upstreams.counters(upstream_id).record_usage(usage, cost);
+        if let Some(service) = dims.service_name.as_deref() {
+            self.services.counters(service).record_usage(usage, cost);
+        }
+        if let Some(model) = dims.model.as_deref() {
+            self.models.counters(model).record_usage(usage, cost);
+        }
+        if let Some(dimension) = dims.dimension.as_deref() {
+            self.dimensions.counters(dimension).record_usage(usage, cost);
+        }
+        if let Some(app) = dims.client_app.as_deref() {
+            self.client_apps.counters(app).record_usage(usage, cost);
+        }
+        events::notify_stats();
+    }
+
+    pub fn snapshot(&self) -> Vec<UpstreamStats> {
+        self.snapshot_at(SystemTime::now())
+    }
+
+    pub fn snapshot_at(&self, now: SystemTime) -> Vec<UpstreamStats> {
+        let scheduler_now = Instant::now();
+        let mut stats = self.upstreams.snapshot(unix_secs(now));
+        for upstream in stats.iter_mut() {
+            upstream.scheduler = scheduler().stats_at(&upstream.upstream_id, scheduler_now);
+        }
+        stats
+    }
+
+    /// 按指定维度汇总，key 为上游 id / 服务名 / 模型名 / 自定义维度的取值 / 客户端工具
+    pub fn breakdown(&self, group_by: StatsGroupBy) -> Vec<GroupStats> {
+        let map = match group_by {
+            StatsGroupBy::Upstream => &self.upstreams,
+            StatsGroupBy::Service => &self.services,
+            StatsGroupBy::Model => &self.models,
+            StatsGroupBy::Dimension => &self.dimensions,
+            StatsGroupBy::ClientApp => &self.client_apps,
+        };
+        let mut groups: Vec<GroupStats> = map
+            .snapshot(unix_secs(SystemTime::now()))
+            .into_iter()
+            .map(GroupStats::from)
+            .collect();
+        groups.sort_by(|a, b| b.total_requests.cmp(&a.total_requests).then_with(|| a.key.cmp(&b.key)));
+        groups
+    }
+
+    /// 按上游、服务与自定义维度汇总的累计费用
+    pub fn spend_summary(&self) -> SpendSummary {
+        let items = |group_by| {
+            let mut items: Vec<SpendItem> = self
+                .breakdown(group_by)
+                .into_iter()
+                .map(|g| SpendItem {
+                    key: g.key,
+                    label: g.label,
+                    total_requests: g.total_requests,
+                    total_tokens: g.total_tokens,
+                    total_cost: g.total_cost,
+                })
+                .collect();
+            items.sort_by(|a, b| b.total_cost.total_cmp(&a.total_cost).then_with(|| a.key.cmp(&b.key)));
+            items
+        };
+        let by_upstream = items(StatsGroupBy::Upstream);
+        SpendSummary {
+            total_cost: by_upstream.iter().map(|i| i.total_cost).sum(),
+            by_upstream,
+            by_service: items(StatsGroupBy::Service),
+            by_dimension: items(StatsGroupBy::Dimension),
+        }
+    }
+
+    /// 配置中新出现且尚无统计的上游，若 base URL 与名称和某个已移除的上游相同，
+    /// 则把后者的统计（含滚动窗口）转到新 id 下，并同步最新名称。返回找回的数量
+    pub fn match_identities(&self, active: &[UpstreamIdentity]) -> usize {
+        let active_ids: HashSet<&str> = active.iter().map(|u| u.id).collect();
+        let mut identities = self.identities.lock().unwrap_or_else(|e| e.into_inner());
+        let mut relinked = 0;
+        for upstream in active {
+            let identity = (upstream.upstream_base.to_string(), upstream.label.map(str::to_string));
+            if !self.upstreams.contains(upstream.id) {
+                let previous = identities
+                    .iter()
+                    .find(|(id, known)| {
+                        !active_ids.contains(id.as_str()) && **known == identity && self.upstreams.contains(id)
+                    })
+                    .map(|(id, _)| id.clone());
+                if let Some(previous) = previous {
+                    if self.upstreams.rename(&previous, upstream.id) {
+                        identities.remove(&previous);
+                        relinked += 1;
+                    }
+                }
+            }
+            if let (Some(counters), Some(_)) = (self.upstreams.get(upstream.id), upstream.label) {
+                if let Ok(mut label) = counters.upstream_label.write() {
+                    *label = identity.1.clone();
+                }
+            }
+            identities.insert(upstream.id.to_string(), identity);
+        }
+        identities.retain(|id, _| active_ids.contains(id.as_str()) || self.upstreams.contains(id));
+        if relinked > 0 {
+            events::notify_stats();
+        }
+        relinked
+    }
+
+    /// 手动把 from 的统计转给 to，用于身份无法自动匹配（如同时改了地址和名称）的情况
+    pub fn relink_upstream(&self, from: &str, to: &str) -> Result<(), String> {
+        if from == to {
+            return Ok(());
+        }
+        if !self.upstreams.contains(from) {
+            return Err(format!("未找到上游 {from} 的统计"));
+        }
+        if !self.upstreams.rename(from, to) {
+            return Err(format!("上游 {to} 已有统计，无法合并"));
+        }
+        if let Some(counters) = self.upstreams.get(to) {
+            counters.archived_at.store(0, Ordering::Relaxed);
+        }
+        let mut identities = self.identities.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(identity) = identities.remove(from) {
+            identities.entry(to.to_string()).or_insert(identity);
+        }
+        events::notify_stats();
+        Ok(())
+    }
+
+    /// 配置变更后对账：不在配置中的上游统计标记为归档以保留历史，重新加入时恢复；
+    /// 归档超过 30 天的直接删除。返回本次新归档的数量
+    pub fn reconcile_upstreams(&self, active: &HashSet<&str>) -> usize {
+        self.reconcile_upstreams_at(active, SystemTime::now())
+    }
+
+    pub fn reconcile_upstreams_at(&self, active: &HashSet<&str>, now: SystemTime) -> usize {
+        let now_secs = unix_secs(now).max(1);
+        let mut archived = 0;
+        let removed = self.upstreams.retain(|id, counters| {
+            if active.contains(id) {
+                counters.archived_at.store(0, Ordering::Relaxed);
+                return true;
+            }
+            match counters.archived_at.load(Ordering::Relaxed) {
+                0 => {
+                    counters.archived_at.store(now_secs, Ordering::Relaxed);
+                    archived += 1;
+                    true
+                }
+                since => now_secs.saturating_sub(since) < ARCHIVE_RETENTION_SECS,
+            }
+        });
+        if archived > 0 || removed > 0 {
+            events::notify_stats();
+        }
+        archived
+    }
+
+    /// 立即删除所有已归档的上游统计，返回删除数量
+    pub fn prune_archived(&self) -> usize {
+        let removed = self
+            .upstreams
+            .retain(|_, counters| counters.archived_at.load(Ordering::Relaxed) == 0);
+        if removed > 0 {
+            events::notify_stats();
+        }
+        removed
+    }
+
+    pub fn clear(&self) {
+        self.upstreams.clear();
+        self.services.clear();
+        self.models.clear();
+        self.dimensions.clear();
+        self.client_apps.clear();
+        events::notify_stats();
+    }
+}
@@ -0,0 +1,131 @@
+//! 代理内置的 `/_apiflow/*` 接口，在路由匹配之前处理，供脚本和监控直接查询代理状态。
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::{ProxyConfig, ProxyLogEntry, UpstreamStats};
+
+pub(crate) const RESERVED_PREFIX: &str = "/_apiflow/";
+/// 按最近 5 分钟的错误率判断上游健康状态
+const HEALTH_WINDOW_SECS: u64 = 300;
+const UNHEALTHY_ERROR_RATE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservedRoute {
+    /// 存活检查，无需鉴权
+    Healthz,
+    /// 运行状态，与代理请求使用同一鉴权
+    Status,
+    /// 延迟重试队列中任务的状态与结果（`jobs/{id}`），与代理请求使用同一鉴权
+    Job,
+}
+
+/// 识别保留路径；`/_apiflow/` 下的未知路径返回 None，仍按普通请求路由
+pub fn reserved_route(path: &str) -> Option<ReservedRoute> {
+    let path = path.split('?').next().unwrap_or(path);
+    match path.strip_prefix(RESERVED_PREFIX)? {
+        "healthz" => Some(ReservedRoute::Healthz),
+        "status" => Some(ReservedRoute::Status),
+        rest if job_id(rest).is_some() => Some(ReservedRoute::Job),
+        _ => None,
+    }
+}
+
+/// 从 `jobs/{id}` 或完整路径中取出任务 id
+pub fn job_id(path: &str) -> Option<&str> {
+    let path = path.split('?').next().unwrap_or(path);
+    let rest = path.strip_prefix(RESERVED_PREFIX).unwrap_or(path);
+    rest.strip_prefix("jobs/").filter(|id| !id.is_empty() && !id.contains('/'))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyStatus {
+    pub listen_port: u16,
+    pub uptime_secs: u64,
+    /// 本端口尚未完成的请求数
+    pub in_flight: usize,
+    pub services: Vec<ServiceStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStatus {
+    pub name: String,
+    pub base_path: String,
+    pub enabled: bool,
+    pub paused: bool,
+    pub upstreams: Vec<UpstreamHealth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamHealth {
+    pub id: String,
+    pub label: Option<String>,
+    pub enabled: bool,
+    /// 最近 5 分钟没有请求或错误率低于 50% 时视为健康
+    pub healthy: bool,
+    pub total_requests: u64,
+    pub recent_error_rate: Option<f64>,
+    pub in_flight: usize,
+}
+
+pub fn build_status(
+    config: &ProxyConfig,
+    uptime: Duration,
+    logs: &VecDeque<ProxyLogEntry>,
+    stats: &[UpstreamStats],
+) -> ProxyStatus {
+    let in_flight: Vec<&ProxyLogEntry> = logs
+        .iter()
+        .filter(|e| e.listen_port == config.listen_port && e.status.is_none())
+        .collect();
+
+    let services = config
+        .services
+        .iter()
+        .map(|svc| ServiceStatus {
+            name: svc.name.clone(),
+            base_path: svc.base_path.clone(),
+            enabled: svc.enabled,
+            paused: svc.is_paused(),
+            upstreams: svc
+                .upstreams
+                .iter()
+                .map(|u| {
+                    let stats = stats.iter().find(|s| s.upstream_id == u.id);
+                    let recent_error_rate = stats
+                        .and_then(|s| {
+                            s.windows
+                                .iter()
+                                .find(|w| w.window_secs == HEALTH_WINDOW_SECS)
+                        })
+                        .filter(|w| w.requests > 0)
+                        .map(|w| w.error_rate);
+                    UpstreamHealth {
+                        id: u.id.clone(),
+                        label: u.label.clone(),
+                        enabled: u.enabled,
+                        healthy: recent_error_rate.is_none_or(|r| r < UNHEALTHY_ERROR_RATE),
+                        total_requests: stats.map(|s| s.total_requests).unwrap_or(0),
+                        recent_error_rate,
+                        in_flight: in_flight
+                            .iter()
+                            .filter(|e| e.upstream_id.as_deref() == Some(u.id.as_str()))
+                            .count(),
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    ProxyStatus {
+        listen_port: config.listen_port,
+        uptime_secs: uptime.as_secs(),
+        in_flight: in_flight.len(),
+        services,
+    }
+}
@@ -0,0 +1,633 @@
+//! 日志持久化：已完成的日志经有界队列交给后台线程批量写入所选后端，
+//! 写入失败或队列已满时只丢弃持久化副本，不影响内存中的日志和代理本身。
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::schema::{self, LOG_SCHEMA_VERSION};
+use crate::{timestamp, ProxyLogEntry};
+
+const STORAGE_QUEUE_SIZE: usize = 4096;
+const MAX_BATCH: usize = 256;
+/// JSONL 目录中记录日志结构版本的文件
+const JSONL_VERSION_FILE: &str = "schema-version";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/LogStorageConfig.ts")]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LogStorageConfig {
+    /// 仅保存在内存中，重启后丢失
+    Memory,
+    /// 本地 SQLite 数据库，默认位于数据目录下的 logs.db
+    Sqlite {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        path: Option<String>,
+    },
+    /// 按天滚动的 JSON Lines 文件，默认位于数据目录下的 logs/
+    Jsonl {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        dir: Option<String>,
+    },
+    /// 通过 HTTP 接口以 JSONEachRow 格式写入 ClickHouse
+    #[serde(rename = "clickhouse", rename_all = "camelCase")]
+    ClickHouse {
+        url: String,
+        table: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        user: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        password: Option<String>,
+    },
+}
+
+impl Default for LogStorageConfig {
+    fn default() -> Self {
+        LogStorageConfig::Sqlite { path: None }
+    }
+}
+
+impl LogStorageConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            LogStorageConfig::ClickHouse { url, .. }
+                if !url.starts_with("http://") && !url.starts_with("https://") =>
+            {
+                Err("ClickHouse 地址需以 http:// 或 https:// 开头".into())
+            }
+            LogStorageConfig::ClickHouse { table, .. }
+                if table.is_empty()
+                    || !table
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') =>
+            {
+                Err("ClickHouse 表名只能包含字母、数字、下划线和点".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 日志存储后端。方法在后台写入线程中同步调用。
+pub trait LogStore: Send {
+    fn write_batch(&mut self, entries: &[ProxyLogEntry]) -> Result<(), String>;
+
+    /// 读取最近的日志（按时间正序），用于启动时恢复；只写不读的后端返回空
+    fn load_recent(&mut self, _limit: usize) -> Result<Vec<ProxyLogEntry>, String> {
+        Ok(Vec::new())
+    }
+
+    /// 删除所有已持久化的日志；远端存储不由本应用管理，默认不做任何事
+    fn clear(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// 删除早于 cutoff 的日志
+    fn prune_before(&mut self, _cutoff: DateTime<Utc>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// 删除最旧的一部分日志并释放磁盘空间，没有可删除的内容时返回 false
+    fn drop_oldest(&mut self) -> Result<bool, String> {
+        Ok(false)
+    }
+}
+
+fn data_dir() -> Result<PathBuf, String> {
+    let proj = ProjectDirs::from("com", "apiflow", "app").ok_or("无法定位数据目录")?;
+    let path = proj.data_dir().to_path_buf();
+    fs::create_dir_all(&path).map_err(|e| format!("创建数据目录失败: {e}"))?;
+    Ok(path)
+}
+
+pub struct SqliteStore {
+    conn: rusqlite::Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: &std::path::Path) -> Result<Self, String> {
+        let conn =
+            rusqlite::Connection::open(path).map_err(|e| format!("打开日志数据库失败: {e}"))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS logs (
+                 id TEXT PRIMARY KEY,
+                 timestamp TEXT NOT NULL,
+                 entry TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS logs_timestamp ON logs (timestamp);",
+        )
+        .map_err(|e| format!("初始化日志数据库失败: {e}"))?;
+        let mut store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// 结构版本记录在 user_version 中，旧版本数据在同一事务内迁移并更新版本号
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: u32 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("读取日志数据库版本失败: {e}"))?;
+        schema::ensure_supported(version)?;
+        if version == LOG_SCHEMA_VERSION {
+            return Ok(());
+        }
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("迁移日志数据库失败: {e}"))?;
+        {
+            let rows: Vec<(String, String)> = tx
+                .prepare("SELECT id, entry FROM logs")
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect()
+                })
+                .map_err(|e| format!("迁移日志数据库失败: {e}"))?;
+            let mut update = tx
+                .prepare(
+                    "UPDATE logs SET entry = ?2, timestamp = COALESCE(?3, timestamp) WHERE id = ?1",
+                )
+                .map_err(|e| format!("迁移日志数据库失败: {e}"))?;
+            for (id, json) in rows {
+                if let Some(migrated) = schema::migrate_json(&json, version) {
+                    // 读取时按 timestamp 列排序，需与迁移后的日志保持一致
+                    let timestamp = serde_json::from_str::<serde_json::Value>(&migrated)
+                        .ok()
+                        .and_then(|v| v.get("timestamp")?.as_str().map(str::to_string));
+                    update
+                        .execute((&id, &migrated, &timestamp))
+                        .map_err(|e| format!("迁移日志数据库失败: {e}"))?;
+                }
+            }
+        }
+        tx.pragma_update(None, "user_version", LOG_SCHEMA_VERSION)
+            .map_err(|e| format!("迁移日志数据库失败: {e}"))?;
+        tx.commit().map_err(|e| format!("迁移日志数据库失败: {e}"))
+    }
+}
+
+impl LogStore for SqliteStore {
+    fn write_batch(&mut self, entries: &[ProxyLogEntry]) -> Result<(), String> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("写入日志数据库失败: {e}"))?;
+        {
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT OR REPLACE INTO logs (id, timestamp, entry) VALUES (?1, ?2, ?3)",
+                )
+                .map_err(|e| format!("写入日志数据库失败: {e}"))?;
+            for entry in entries {
+                let json =
+                    serde_json::to_string(entry).map_err(|e| format!("序列化日志失败: {e}"))?;
+                stmt.execute((&entry.id, &entry.timestamp, &json))
+                    .map_err(|e| format!("写入日志数据库失败: {e}"))?;
+            }
+        }
+        tx.commit().map_err(|e| format!("写入日志数据库失败: {e}"))
+    }
+
+    fn load_recent(&mut self, limit: usize) -> Result<Vec<ProxyLogEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT entry FROM logs ORDER BY timestamp DESC, rowid DESC LIMIT ?1")
+            .map_err(|e| format!("读取日志数据库失败: {e}"))?;
+        let rows = stmt
+            .query_map([limit as i64], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("读取日志数据库失败: {e}"))?;
+        let mut entries: Vec<ProxyLogEntry> = rows
+            .filter_map(|row| row.ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM logs", [])
+            .map_err(|e| format!("清空日志数据库失败: {e}"))?;
+        Ok(())
+    }
+
+    fn prune_before(&mut self, cutoff: DateTime<Utc>) -> Result<(), String> {
+        // 迁移后 timestamp 列均为 UTC RFC3339 格式，可直接按字符串比较
+        self.conn
+            .execute(
+                "DELETE FROM logs WHERE timestamp < ?1",
+                [timestamp::format_utc(cutoff)],
+            )
+            .map_err(|e| format!("清理日志数据库失败: {e}"))?;
+        Ok(())
+    }
+
+    fn drop_oldest(&mut self) -> Result<bool, String> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM logs WHERE id IN (
+                     SELECT id FROM logs ORDER BY timestamp, rowid
+                     LIMIT MAX(1, (SELECT COUNT(*) FROM logs) / 10)
+                 )",
+                [],
+            )
+            .map_err(|e| format!("清理日志数据库失败: {e}"))?;
+        // 删除行只会留下空闲页，需要 VACUUM 并截断 WAL 才能真正缩小文件
+        self.conn
+            .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| format!("压缩日志数据库失败: {e}"))?;
+        Ok(removed > 0)
+    }
+}
+
+pub struct JsonlStore {
+    dir: PathBuf,
+}
+
+impl JsonlStore {
+    pub fn open(dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&dir).map_err(|e| format!("创建日志目录失败: {e}"))?;
+        let store = Self { dir };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// 没有版本文件的目录视为版本 0；逐个文件迁移后整体替换，最后写入版本号
+    fn migrate(&self) -> Result<(), String> {
+        let version_path = self.dir.join(JSONL_VERSION_FILE);
+        let version = match fs::read_to_string(&version_path) {
+            Ok(v) => v
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| "日志目录版本文件无效".to_string())?,
+            Err(_) => 0,
+        };
+        schema::ensure_supported(version)?;
+        if version == LOG_SCHEMA_VERSION {
+            return Ok(());
+        }
+        for path in self.files_newest_first() {
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("读取日志文件失败: {e}"))?;
+            let mut migrated = String::with_capacity(content.len());
+            for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                match schema::migrate_json(line, version) {
+                    Some(json) => migrated.push_str(&json),
+                    None => migrated.push_str(line),
+                }
+                migrated.push('\n');
+            }
+            let tmp = path.with_extension("jsonl.tmp");
+            fs::write(&tmp, migrated)
+                .and_then(|_| fs::rename(&tmp, &path))
+                .map_err(|e| format!("迁移日志文件失败: {e}"))?;
+        }
+        fs::write(&version_path, LOG_SCHEMA_VERSION.to_string())
+            .map_err(|e| format!("写入日志目录版本失败: {e}"))
+    }
+
+    fn files_newest_first(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map(|rd| rd.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.retain(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("logs-") && n.ends_with(".jsonl"))
+        });
+        files.sort();
+        files.reverse();
+        files
+    }
+}
+
+impl LogStore for JsonlStore {
+    fn write_batch(&mut self, entries: &[ProxyLogEntry]) -> Result<(), String> {
+        let path = self
+            .dir
+            .join(format!("logs-{}.jsonl", Local::now().format("%Y-%m-%d")));
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("打开日志文件失败: {e}"))?;
+        let mut buf = String::new();
+        for entry in entries {
+            buf.push_str(
+                &serde_json::to_string(entry).map_err(|e| format!("序列化日志失败: {e}"))?,
+            );
+            buf.push('\n');
+        }
+        file.write_all(buf.as_bytes())
+            .map_err(|e| format!("写入日志文件失败: {e}"))
+    }
+
+    fn load_recent(&mut self, limit: usize) -> Result<Vec<ProxyLogEntry>, String> {
+        let mut entries: Vec<ProxyLogEntry> = Vec::new();
+        for path in self.files_newest_first() {
+            if entries.len() >= limit {
+                break;
+            }
+            let Ok(file) = fs::File::open(&path) else {
+                continue;
+            };
+            let mut day: Vec<ProxyLogEntry> = BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect();
+            day.append(&mut entries);
+            entries = day;
+        }
+        let skip = entries.len().saturating_sub(limit);
+        Ok(entries.split_off(skip))
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        for path in self.files_newest_first() {
+            fs::remove_file(&path).map_err(|e| format!("删除日志文件失败: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// 按天滚动的文件只能整体删除：仅删除日期早于 cutoff 当天的文件
+    fn prune_before(&mut self, cutoff: DateTime<Utc>) -> Result<(), String> {
+        let cutoff_day = cutoff.with_timezone(&Local).date_naive();
+        for path in self.files_newest_first() {
+            let day = path
+                .file_stem()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("logs-"))
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            if day.is_some_and(|day| day < cutoff_day) {
+                fs::remove_file(&path).map_err(|e| format!("删除日志文件失败: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除最旧的一天，始终保留正在写入的最新文件
+    fn drop_oldest(&mut self) -> Result<bool, String> {
+        let files = self.files_newest_first();
+        match files.last() {
+            Some(oldest) if files.len() > 1 => {
+                fs::remove_file(oldest).map_err(|e| format!("删除日志文件失败: {e}"))?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// ClickHouse 写入；表结构需与日志字段对应，未知字段由 ClickHouse 端决定是否忽略
+pub struct ClickHouseStore {
+    client: reqwest::Client,
+    runtime: tokio::runtime::Handle,
+    url: String,
+    table: String,
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl LogStore for ClickHouseStore {
+    fn write_batch(&mut self, entries: &[ProxyLogEntry]) -> Result<(), String> {
+        let mut body = String::new();
+        for entry in entries {
+            body.push_str(
+                &serde_json::to_string(entry).map_err(|e| format!("序列化日志失败: {e}"))?,
+            );
+            body.push('\n');
+        }
+        let mut req = self
+            .client
+            .post(&self.url)
+            .query(&[(
+                "query",
+                format!("INSERT INTO {} FORMAT JSONEachRow", self.table),
+            )])
+            .body(body);
+        if let Some(user) = &self.user {
+            req = req.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.password {
+            req = req.header("X-ClickHouse-Key", password);
+        }
+        let resp = self
+            .runtime
+            .block_on(req.send())
+            .map_err(|e| format!("写入 ClickHouse 失败: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("写入 ClickHouse 失败: 状态码 {}", resp.status()));
+        }
+        Ok(())
+    }
+}
+
+/// 根据配置打开存储后端；Memory 返回 None
+pub fn open_store(config: &LogStorageConfig) -> Result<Option<Box<dyn LogStore>>, String> {
+    let store: Box<dyn LogStore> = match config {
+        LogStorageConfig::Memory => return Ok(None),
+        LogStorageConfig::Sqlite { path } => {
+            let path = match path {
+                Some(p) => PathBuf::from(p),
+                None => data_dir()?.join("logs.db"),
+            };
+            Box::new(SqliteStore::open(&path)?)
+        }
+        LogStorageConfig::Jsonl { dir } => {
+            let dir = match dir {
+                Some(d) => PathBuf::from(d),
+                None => data_dir()?.join("logs"),
+            };
+            Box::new(JsonlStore::open(dir)?)
+        }
+        LogStorageConfig::ClickHouse {
+            url,
+            table,
+            user,
+            password,
+        } => Box::new(ClickHouseStore {
+            client: reqwest::Client::new(),
+            runtime: tokio::runtime::Handle::try_current()
+                .map_err(|_| "ClickHouse 写入需要运行时")?,
+            url: url.clone(),
+            table: table.clone(),
+            user: user.clone(),
+            password: password.clone(),
+        }),
+    };
+    Ok(Some(store))
+}
+
+/// 发给写入线程的任务；清空也经由写入线程执行，保证排在已入队的日志之后
+enum Job {
+    Write(Box<ProxyLogEntry>),
+    Clear(mpsc::Sender<Result<(), String>>),
+    Prune {
+        max_age: Option<Duration>,
+        max_bytes: Option<u64>,
+    },
+}
+
+static ACTIVE: Mutex<Option<(LogStorageConfig, SyncSender<Job>)>> = Mutex::new(None);
+
+/// 切换到新的存储配置；配置未变时不做任何事，旧的写入线程在发送端释放后写完剩余日志自行退出
+pub fn configure(config: Option<&LogStorageConfig>) -> Result<(), String> {
+    let config = config.cloned().unwrap_or_default();
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if active
+        .as_ref()
+        .is_some_and(|(current, _)| *current == config)
+    {
+        return Ok(());
+    }
+    *active = match open_store(&config)? {
+        Some(store) => {
+            let (tx, rx) = mpsc::sync_channel(STORAGE_QUEUE_SIZE);
+            let writer_config = config.clone();
+            std::thread::spawn(move || run_writer(store, writer_config, rx));
+            Some((config, tx))
+        }
+        None => None,
+    };
+    Ok(())
+}
+
+/// 把已完成的日志交给后台写入；未配置存储或队列已满时直接跳过
+pub fn persist(entry: &ProxyLogEntry) {
+    let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, tx)) = active.as_ref() {
+        let _ = tx.try_send(Job::Write(Box::new(entry.clone())));
+    }
+}
+
+/// 清空当前存储中的日志，等待写入线程执行完毕；未配置存储时直接返回
+pub fn clear() -> Result<(), String> {
+    let tx = {
+        let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+        match active.as_ref() {
+            Some((_, tx)) => tx.clone(),
+            None => return Ok(()),
+        }
+    };
+    let (reply_tx, reply_rx) = mpsc::channel();
+    tx.send(Job::Clear(reply_tx))
+        .map_err(|_| "日志写入线程已退出".to_string())?;
+    reply_rx
+        .recv()
+        .map_err(|_| "日志写入线程已退出".to_string())?
+}
+
+/// 按保留策略清理存储：先删除过期日志，再删除最旧的日志直到磁盘占用低于上限
+pub fn prune(max_age: Option<Duration>, max_bytes: Option<u64>) {
+    if max_age.is_none() && max_bytes.is_none() {
+        return;
+    }
+    let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, tx)) = active.as_ref() {
+        let _ = tx.try_send(Job::Prune { max_age, max_bytes });
+    }
+}
+
+fn prune_store(
+    store: &mut dyn LogStore,
+    config: &LogStorageConfig,
+    max_age: Option<Duration>,
+    max_bytes: Option<u64>,
+) -> Result<(), String> {
+    if let Some(age) = max_age.and_then(|a| chrono::Duration::from_std(a).ok()) {
+        store.prune_before(Utc::now() - age)?;
+    }
+    if let Some(max_bytes) = max_bytes {
+        while disk_usage(Some(config)).is_some_and(|used| used > max_bytes) {
+            if !store.drop_oldest()? {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_writer(mut store: Box<dyn LogStore>, config: LogStorageConfig, rx: Receiver<Job>) {
+    let mut pending = None;
+    loop {
+        let Some(first) = pending.take().or_else(|| rx.recv().ok()) else {
+            break;
+        };
+        let mut batch = match first {
+            Job::Write(entry) => vec![*entry],
+            Job::Clear(reply) => {
+                let _ = reply.send(store.clear());
+                continue;
+            }
+            Job::Prune { max_age, max_bytes } => {
+                if let Err(err) = prune_store(store.as_mut(), &config, max_age, max_bytes) {
+                    eprintln!("清理持久化日志失败: {err}");
+                }
+                continue;
+            }
+        };
+        while batch.len() < MAX_BATCH {
+            match rx.try_recv() {
+                Ok(Job::Write(entry)) => batch.push(*entry),
+                Ok(job) => {
+                    pending = Some(job);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        if let Err(err) = store.write_batch(&batch) {
+            eprintln!("持久化日志失败: {err}");
+        }
+    }
+}
+
+/// 本地存储占用的磁盘空间：SQLite 数据库（含 WAL 文件）或 JSONL 目录下的日志文件；
+/// 内存与 ClickHouse 存储返回 None
+pub fn disk_usage(config: Option<&LogStorageConfig>) -> Option<u64> {
+    let file_size = |path: &std::path::Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    match config.cloned().unwrap_or_default() {
+        LogStorageConfig::Sqlite { path } => {
+            let path = path.map(PathBuf::from).or_else(|| Some(data_dir().ok()?.join("logs.db")))?;
+            let wal = path.with_extension("db-wal");
+            Some(file_size(&path) + file_size(&wal))
+        }
+        LogStorageConfig::Jsonl { dir } => {
+            let dir = dir.map(PathBuf::from).or_else(|| Some(data_dir().ok()?.join("logs")))?;
+            let total = fs::read_dir(dir)
+                .ok()?
+                .flatten()
+                .map(|entry| file_size(&entry.path()))
+                .sum();
+            Some(total)
+        }
+        LogStorageConfig::Memory | LogStorageConfig::ClickHouse { .. } => None,
+    }
+}
+
+/// 启动时从存储中读取最近的日志
+pub fn load_recent(
+    config: Option<&LogStorageConfig>,
+    limit: usize,
+) -> Result<Vec<ProxyLogEntry>, String> {
+    match open_store(&config.cloned().unwrap_or_default())? {
+        Some(mut store) => store.load_recent(limit),
+        None => Ok(Vec::new()),
+    }
+}
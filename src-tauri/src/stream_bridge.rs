@@ -0,0 +1,78 @@
+//! 流式响应桥接的调优参数：上游数据经有界通道转发给客户端，客户端读取较慢时
+//! 通道写满后暂停读取上游（背压），避免缓冲无限增长；可选按 SSE 事件切分后逐个发送。
+
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// 默认最多缓冲的数据块数
+const DEFAULT_BUFFER_CHUNKS: usize = 64;
+const MAX_BUFFER_CHUNKS: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/StreamBridgeConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct StreamBridgeConfig {
+    /// 转发通道最多缓冲的数据块数，写满后暂停读取上游，默认 64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub buffer_chunks: Option<usize>,
+    /// 流式响应按 SSE 事件切分，每个完整事件单独发送，默认按上游的数据块原样转发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub flush_per_event: Option<bool>,
+}
+
+impl StreamBridgeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.buffer_chunks {
+            Some(n) if n == 0 || n > MAX_BUFFER_CHUNKS => Err(format!(
+                "流式转发缓冲块数必须在 1 到 {MAX_BUFFER_CHUNKS} 之间"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn buffer_chunks(config: Option<&StreamBridgeConfig>) -> usize {
+    config
+        .and_then(|c| c.buffer_chunks)
+        .unwrap_or(DEFAULT_BUFFER_CHUNKS)
+}
+
+pub fn flush_per_event(config: Option<&StreamBridgeConfig>) -> bool {
+    config.and_then(|c| c.flush_per_event).unwrap_or(false)
+}
+
+/// 把任意切分的字节流重新组合为完整的 SSE 事件（以空行结尾）
+#[derive(Default)]
+pub struct SseFramer {
+    pending: BytesMut,
+}
+
+impl SseFramer {
+    /// 追加数据，返回其中已完整的事件
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Bytes> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(end) = event_end(&self.pending) {
+            events.push(self.pending.split_to(end).freeze());
+        }
+        events
+    }
+
+    /// 上游结束时剩余的不完整数据
+    pub fn finish(self) -> Option<Bytes> {
+        (!self.pending.is_empty()).then(|| self.pending.freeze())
+    }
+}
+
+/// 第一个事件分隔符（`\n\n` 或 `\r\n\r\n`）之后的位置
+fn event_end(buf: &[u8]) -> Option<usize> {
+    let lf = buf.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    let crlf = buf.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
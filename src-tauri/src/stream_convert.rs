@@ -0,0 +1,150 @@
+//! 客户端请求了流式响应、上游却返回完整 JSON 时（部分网关在高负载下会这样），
+//! 把 JSON 转换为等价的 SSE 事件序列，避免流式客户端解析失败。
+//! 支持 OpenAI Chat Completions、Anthropic Messages 与 Gemini 的响应格式。
+
+use serde_json::{json, Map, Value};
+
+/// 按响应格式转换为 SSE；无法识别的格式返回 None，由调用方原样转发
+pub fn json_to_sse(body: &[u8]) -> Option<Vec<u8>> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let openai = value["object"] == "chat.completion";
+    let events = if openai {
+        openai_chat(&value)
+    } else if value["type"] == "message" {
+        anthropic_message(&value)
+    } else if value["candidates"].is_array() {
+        vec![(None, value)]
+    } else {
+        return None;
+    };
+
+    let mut out = String::new();
+    for (event, data) in events {
+        if let Some(event) = event {
+            out.push_str(&format!("event: {event}\n"));
+        }
+        out.push_str(&format!("data: {data}\n\n"));
+    }
+    if openai {
+        out.push_str("data: [DONE]\n\n");
+    }
+    Some(out.into_bytes())
+}
+
+/// 每个 choice 一个 chunk，message 整体放进 delta；有用量时再追加一个只含 usage 的 chunk
+fn openai_chat(value: &Value) -> Vec<(Option<&'static str>, Value)> {
+    let chunk = |choices: Vec<Value>| {
+        let mut chunk = Map::new();
+        for key in ["id", "created", "model", "system_fingerprint"] {
+            if let Some(v) = value.get(key) {
+                chunk.insert(key.into(), v.clone());
+            }
+        }
+        chunk.insert("object".into(), json!("chat.completion.chunk"));
+        chunk.insert("choices".into(), Value::Array(choices));
+        Value::Object(chunk)
+    };
+
+    let mut events: Vec<(Option<&'static str>, Value)> = value["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|choice| {
+            let mut delta = choice["message"].clone();
+            if let Some(calls) = delta.get_mut("tool_calls").and_then(Value::as_array_mut) {
+                for (i, call) in calls.iter_mut().enumerate() {
+                    call["index"] = json!(i);
+                }
+            }
+            (
+                None,
+                chunk(vec![json!({
+                    "index": choice["index"],
+                    "delta": delta,
+                    "finish_reason": choice["finish_reason"],
+                })]),
+            )
+        })
+        .collect();
+    if let Some(usage) = value.get("usage").filter(|u| !u.is_null()) {
+        let mut last = chunk(Vec::new());
+        last["usage"] = usage.clone();
+        events.push((None, last));
+    }
+    events
+}
+
+/// 按 Messages 流式协议拆成 message_start、各内容块的 start / delta / stop、message_delta 与 message_stop
+fn anthropic_message(value: &Value) -> Vec<(Option<&'static str>, Value)> {
+    let mut start = value.clone();
+    start["content"] = json!([]);
+    start["stop_reason"] = Value::Null;
+    start["stop_sequence"] = Value::Null;
+    let output_tokens = value["usage"]["output_tokens"].clone();
+    if start["usage"].is_object() {
+        start["usage"]["output_tokens"] = json!(0);
+    }
+
+    let mut events = vec![(
+        Some("message_start"),
+        json!({ "type": "message_start", "message": start }),
+    )];
+    for (index, block) in value["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        let (empty, delta) = match block["type"].as_str() {
+            Some("text") => (
+                json!({ "type": "text", "text": "" }),
+                json!({ "type": "text_delta", "text": block["text"] }),
+            ),
+            Some("thinking") => (
+                json!({ "type": "thinking", "thinking": "" }),
+                json!({ "type": "thinking_delta", "thinking": block["thinking"] }),
+            ),
+            Some("tool_use") => {
+                let mut empty = block.clone();
+                empty["input"] = json!({});
+                (
+                    empty,
+                    json!({ "type": "input_json_delta", "partial_json": block["input"].to_string() }),
+                )
+            }
+            _ => {
+                events.push((
+                    Some("content_block_start"),
+                    json!({ "type": "content_block_start", "index": index, "content_block": block }),
+                ));
+                events.push((
+                    Some("content_block_stop"),
+                    json!({ "type": "content_block_stop", "index": index }),
+                ));
+                continue;
+            }
+        };
+        events.push((
+            Some("content_block_start"),
+            json!({ "type": "content_block_start", "index": index, "content_block": empty }),
+        ));
+        events.push((
+            Some("content_block_delta"),
+            json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+        ));
+        events.push((
+            Some("content_block_stop"),
+            json!({ "type": "content_block_stop", "index": index }),
+        ));
+    }
+    events.push((
+        Some("message_delta"),
+        json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": value["stop_reason"], "stop_sequence": value["stop_sequence"] },
+            "usage": { "output_tokens": output_tokens },
+        }),
+    ));
+    events.push((Some("message_stop"), json!({ "type": "message_stop" })));
+    events
+}
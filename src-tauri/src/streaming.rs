@@ -0,0 +1,111 @@
+//! 判断上游响应是否按流式处理（边收边转发、完成后再收尾日志）。
+//! 默认只认 SSE / NDJSON 响应，以及声明了 `"stream": true` 或使用流式接口路径的成功响应，
+//! 避免把 text/plain 的小错误体当作流。
+
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+const DEFAULT_CONTENT_TYPES: [&str; 2] = ["text/event-stream", "application/x-ndjson"];
+const DEFAULT_PATH_SUFFIXES: [&str; 1] = [":streamGenerateContent"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/StreamingDetection.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingDetection {
+    /// 视为流式的响应 Content-Type，默认 text/event-stream 与 application/x-ndjson
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub content_types: Option<Vec<String>>,
+    /// 请求路径（不含查询参数）以其结尾时，成功响应按流式处理，默认 `:streamGenerateContent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub path_suffixes: Option<Vec<String>>,
+    /// 请求体声明 `"stream": true` 时，成功响应按流式处理，默认开启
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub body_flag: Option<bool>,
+    /// 客户端期望流式而上游返回完整 JSON 时，转换为 SSE 再返回，默认关闭（只在日志中标记）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub convert_json: Option<bool>,
+}
+
+impl StreamingDetection {
+    pub fn validate(&self) -> Result<(), String> {
+        let empty =
+            |items: &Option<Vec<String>>| items.iter().flatten().any(|s| s.trim().is_empty());
+        if empty(&self.content_types) || empty(&self.path_suffixes) {
+            return Err("流式识别规则不能包含空字符串".into());
+        }
+        Ok(())
+    }
+}
+
+/// 在发送请求前根据请求本身得出的判断依据，收到响应后再结合响应头确定
+#[derive(Debug, Clone)]
+pub struct StreamProbe {
+    content_types: Vec<String>,
+    /// 请求路径或请求体表明客户端期望流式响应
+    expects_stream: bool,
+    /// 请求体声明了 `"stream": true`；按路径识别的流式接口（如 Gemini）本身可能返回 JSON 数组流
+    body_flag: bool,
+    convert_json: bool,
+}
+
+impl StreamProbe {
+    pub fn new(config: Option<&StreamingDetection>, path: &str, body: &[u8]) -> Self {
+        let path = path.split('?').next().unwrap_or(path);
+        let content_types = match config.and_then(|c| c.content_types.as_ref()) {
+            Some(types) => types
+                .iter()
+                .map(|t| t.trim().to_ascii_lowercase())
+                .collect(),
+            None => DEFAULT_CONTENT_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+        };
+        let path_match = match config.and_then(|c| c.path_suffixes.as_ref()) {
+            Some(suffixes) => suffixes.iter().any(|s| path.ends_with(s.as_str())),
+            None => DEFAULT_PATH_SUFFIXES.iter().any(|s| path.ends_with(s)),
+        };
+        let body_flag = config.and_then(|c| c.body_flag).unwrap_or(true)
+            && serde_json::from_slice::<Value>(body)
+                .is_ok_and(|v| v["stream"].as_bool() == Some(true));
+        Self {
+            content_types,
+            expects_stream: path_match || body_flag,
+            body_flag,
+            convert_json: config.and_then(|c| c.convert_json).unwrap_or(false),
+        }
+    }
+
+    /// 请求本身表明期望流式响应，用于在发送前选择超时
+    pub fn expects_stream(&self) -> bool {
+        self.expects_stream
+    }
+
+    /// 上游未按流式返回时是否转换为 SSE
+    pub fn converts_json(&self) -> bool {
+        self.convert_json
+    }
+
+    /// 请求体声明了流式，上游却以成功状态返回了完整的 JSON
+    pub fn is_json_instead_of_stream(&self, status: StatusCode, content_type: &str) -> bool {
+        let content_type = content_type.to_ascii_lowercase();
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        self.body_flag
+            && status.is_success()
+            && !self.content_types.iter().any(|t| mime == t.as_str())
+            && (mime == "application/json" || mime.ends_with("+json"))
+    }
+
+    pub fn is_streaming(&self, status: StatusCode, content_type: &str) -> bool {
+        let content_type = content_type.to_ascii_lowercase();
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        self.content_types.iter().any(|t| mime == t.as_str())
+            || (self.expects_stream && status.is_success())
+    }
+}
@@ -0,0 +1,75 @@
+//! 结构化输出归一化：客户端按 OpenAI 格式发送 `response_format`（json_schema / json_object）时，
+//! 按上游支持的程度改写请求——原样转发、降级为 json_object，或去掉该字段并在系统消息中写入 schema 要求。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/StructuredOutputMode.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum StructuredOutputMode {
+    /// 上游支持 json_schema，原样转发
+    #[default]
+    Native,
+    /// 上游只支持 json_object：降级并在系统消息中写入 schema
+    JsonObject,
+    /// 上游不支持 response_format：去掉该字段，只在系统消息中写入要求
+    Prompt,
+}
+
+/// 按模式改写请求体，无需改动时返回 None
+pub fn normalize(body: &[u8], mode: StructuredOutputMode) -> Option<Vec<u8>> {
+    if mode == StructuredOutputMode::Native {
+        return None;
+    }
+    let mut value: Value = serde_json::from_slice(body).ok()?;
+    let object = value.as_object_mut()?;
+    let format = object.get("response_format")?;
+    let instruction = match format.get("type").and_then(Value::as_str)? {
+        "json_schema" => {
+            let schema = format.pointer("/json_schema/schema")?;
+            format!(
+                "Respond only with a JSON value that conforms to the following JSON Schema, without any other text:\n{schema}"
+            )
+        }
+        "json_object" if mode == StructuredOutputMode::Prompt => {
+            "Respond only with a valid JSON object, without any other text.".to_string()
+        }
+        _ => return None,
+    };
+
+    match mode {
+        StructuredOutputMode::JsonObject => {
+            object.insert(
+                "response_format".into(),
+                serde_json::json!({ "type": "json_object" }),
+            );
+        }
+        _ => {
+            object.remove("response_format");
+        }
+    }
+    add_system_instruction(object, &instruction)?;
+    serde_json::to_vec(&value).ok()
+}
+
+/// 追加到第一条文本系统消息之后，没有时在开头插入一条
+fn add_system_instruction(object: &mut Map<String, Value>, instruction: &str) -> Option<()> {
+    let messages = object.get_mut("messages")?.as_array_mut()?;
+    let existing = messages.first_mut().filter(|m| {
+        m.get("role").and_then(Value::as_str) == Some("system")
+            && m.get("content").is_some_and(Value::is_string)
+    });
+    match existing.and_then(|m| m.get_mut("content")) {
+        Some(Value::String(content)) => {
+            content.push_str("\n\n");
+            content.push_str(instruction);
+        }
+        _ => messages.insert(
+            0,
+            serde_json::json!({ "role": "system", "content": instruction }),
+        ),
+    }
+    Some(())
+}
@@ -0,0 +1,117 @@
+//! 配置中定义的合成接口：按路径直接返回固定内容，不经过上游，
+//! 例如用精选列表替代 `/v1/models`，或为其他工具提供 `/healthz`。
+
+use http::{HeaderValue, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::status::RESERVED_PREFIX;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/SyntheticEndpoint.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct SyntheticEndpoint {
+    /// 完整匹配的请求路径（不含查询参数）
+    pub path: String,
+    /// 仅匹配该方法，未设置时匹配任意方法
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub method: Option<String>,
+    /// 响应状态码，默认 200
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub status: Option<u16>,
+    /// 响应体，支持 {{requestId}}、{{timestamp}}、{{unixTime}}、{{method}}、{{path}} 占位符
+    pub body: String,
+    /// 默认 application/json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub content_type: Option<String>,
+    /// 为 true 时无需代理鉴权即可访问
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub public: Option<bool>,
+}
+
+impl SyntheticEndpoint {
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        self.path == path
+            && self
+                .method
+                .as_deref()
+                .is_none_or(|m| m.eq_ignore_ascii_case(method.as_str()))
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.public.unwrap_or(false)
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+            .and_then(|s| StatusCode::from_u16(s).ok())
+            .unwrap_or(StatusCode::OK)
+    }
+
+    pub fn content_type(&self) -> &str {
+        self.content_type.as_deref().unwrap_or("application/json")
+    }
+
+    /// 填充占位符后的响应体
+    pub fn render(&self, request_id: &str, method: &Method, path: &str) -> String {
+        let now = chrono::Utc::now();
+        self.body
+            .replace("{{requestId}}", request_id)
+            .replace("{{timestamp}}", &crate::timestamp::format_utc(now))
+            .replace("{{unixTime}}", &now.timestamp().to_string())
+            .replace("{{method}}", method.as_str())
+            .replace("{{path}}", path)
+    }
+}
+
+/// 查找与请求匹配的合成接口，path 可以带查询参数
+pub fn find<'a>(
+    endpoints: Option<&'a [SyntheticEndpoint]>,
+    method: &Method,
+    path: &str,
+) -> Option<&'a SyntheticEndpoint> {
+    let path = path.split('?').next().unwrap_or(path);
+    endpoints?.iter().find(|e| e.matches(method, path))
+}
+
+pub fn validate_synthetic_endpoints(endpoints: &[SyntheticEndpoint]) -> Result<(), String> {
+    for (idx, endpoint) in endpoints.iter().enumerate() {
+        let path = endpoint.path.as_str();
+        if !path.starts_with('/') || path.contains('?') {
+            return Err(format!("合成接口路径必须以 / 开头且不含查询参数: {path}"));
+        }
+        if path.starts_with(RESERVED_PREFIX) {
+            return Err(format!(
+                "合成接口路径不能使用保留前缀 {RESERVED_PREFIX}: {path}"
+            ));
+        }
+        if let Some(method) = &endpoint.method {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                return Err(format!("合成接口 {path} 的请求方法无效: {method}"));
+            }
+        }
+        if endpoints[..idx].iter().any(|e| {
+            e.path == endpoint.path
+                && (e.method.is_none()
+                    || endpoint.method.is_none()
+                    || e.method.as_deref().map(str::to_ascii_uppercase)
+                        == endpoint.method.as_deref().map(str::to_ascii_uppercase))
+        }) {
+            return Err(format!("合成接口重复: {path}"));
+        }
+        if endpoint
+            .status
+            .is_some_and(|s| !(200..=599).contains(&s) || StatusCode::from_u16(s).is_err())
+        {
+            return Err(format!("合成接口 {path} 的状态码无效"));
+        }
+        if HeaderValue::from_str(endpoint.content_type()).is_err() {
+            return Err(format!("合成接口 {path} 的 Content-Type 无效"));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! 流式响应旁路：把每个 SSE chunk 实时转发到本地文件或 WebSocket，
+//! 便于外部实时查看 token 输出或留存完整对话记录，无需改动客户端。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::SinkExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use ts_rs::TS;
+
+/// 旁路队列满时直接丢弃，绝不阻塞发往客户端的数据
+const TEE_QUEUE_SIZE: usize = 1024;
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TeeSink.ts")]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TeeSink {
+    /// 以 JSON Lines 追加写入本地文件
+    File { path: String },
+    /// 以文本帧发送到 WebSocket 服务（仅支持 ws://）
+    #[serde(rename = "websocket")]
+    WebSocket { url: String },
+}
+
+impl TeeSink {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            TeeSink::File { path } if path.trim().is_empty() => Err("旁路文件路径不能为空".into()),
+            TeeSink::WebSocket { url } if !url.starts_with("ws://") => {
+                Err("旁路 WebSocket 地址需以 ws:// 开头".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 写入旁路的消息，每条序列化为一行 JSON / 一个文本帧
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum TeeMessage {
+    #[serde(rename_all = "camelCase")]
+    Start {
+        request_id: String,
+        path: String,
+        model: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Chunk { request_id: String, seq: u64, data: String },
+    #[serde(rename_all = "camelCase")]
+    End { request_id: String, error: Option<String> },
+}
+
+#[derive(Clone)]
+pub struct TeeHandle {
+    tx: mpsc::Sender<TeeMessage>,
+}
+
+impl TeeHandle {
+    pub fn send(&self, message: TeeMessage) {
+        let _ = self.tx.try_send(message);
+    }
+}
+
+static ACTIVE: Mutex<Option<(TeeSink, TeeHandle)>> = Mutex::new(None);
+
+/// 取得当前配置对应的旁路；配置变化时启动新的写入任务，旧任务在发送端全部释放后自行退出
+pub fn handle_for(sink: &TeeSink) -> TeeHandle {
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((current, handle)) = active.as_ref() {
+        if current == sink {
+            return handle.clone();
+        }
+    }
+
+    let (tx, rx) = mpsc::channel(TEE_QUEUE_SIZE);
+    let handle = TeeHandle { tx };
+    match sink.clone() {
+        TeeSink::File { path } => {
+            tokio::spawn(run_file_sink(path, rx));
+        }
+        TeeSink::WebSocket { url } => {
+            tokio::spawn(run_websocket_sink(url, rx));
+        }
+    }
+    *active = Some((sink.clone(), handle.clone()));
+    handle
+}
+
+async fn run_file_sink(path: String, mut rx: mpsc::Receiver<TeeMessage>) {
+    let mut file = match tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+    {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("打开旁路文件失败 {path}: {err}");
+            return;
+        }
+    };
+
+    while let Some(message) = rx.recv().await {
+        let Ok(mut line) = serde_json::to_string(&message) else {
+            continue;
+        };
+        line.push('\n');
+        if let Err(err) = file.write_all(line.as_bytes()).await {
+            eprintln!("写入旁路文件失败: {err}");
+        }
+    }
+}
+
+async fn run_websocket_sink(url: String, mut rx: mpsc::Receiver<TeeMessage>) {
+    use tokio_tungstenite::tungstenite::Message;
+
+    let mut socket = None;
+    let mut last_attempt: Option<Instant> = None;
+
+    while let Some(message) = rx.recv().await {
+        if socket.is_none() && last_attempt.is_none_or(|t| t.elapsed() >= RECONNECT_INTERVAL) {
+            last_attempt = Some(Instant::now());
+            match tokio_tungstenite::connect_async(url.as_str()).await {
+                Ok((stream, _)) => socket = Some(stream),
+                Err(err) => eprintln!("连接旁路 WebSocket 失败 {url}: {err}"),
+            }
+        }
+        // 未连接期间的消息直接丢弃
+        let Some(stream) = socket.as_mut() else {
+            continue;
+        };
+        let Ok(text) = serde_json::to_string(&message) else {
+            continue;
+        };
+        if let Err(err) = stream.send(Message::Text(text)).await {
+            eprintln!("发送旁路消息失败: {err}");
+            socket = None;
+        }
+    }
+}
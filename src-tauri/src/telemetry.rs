@@ -0,0 +1,115 @@
+//! 基于 tracing 的请求链路追踪。配置 OTLP 端点后，代理请求及每次上游尝试以 span 形式
+//! 导出到 Jaeger / Tempo 等后端；未配置时 span 不会被记录。
+
+use std::sync::{Mutex, OnceLock};
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use serde::{Deserialize, Serialize};
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+use ts_rs::TS;
+
+const DEFAULT_SERVICE_NAME: &str = "apiflow";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TracingConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct TracingConfig {
+    /// OTLP/HTTP traces 端点，如 `http://localhost:4318/v1/traces`
+    pub otlp_endpoint: String,
+    /// 上报的服务名，默认 `apiflow`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub service_name: Option<String>,
+    /// 采样比例（0~1），默认全部采样
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub sample_ratio: Option<f64>,
+}
+
+impl TracingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let endpoint = self.otlp_endpoint.trim();
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err("OTLP 端点必须以 http:// 或 https:// 开头".into());
+        }
+        if self.sample_ratio.is_some_and(|r| !(0.0..=1.0).contains(&r)) {
+            return Err("链路追踪采样比例必须在 0 到 1 之间".into());
+        }
+        Ok(())
+    }
+
+    fn build_provider(&self) -> Result<SdkTracerProvider, String> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(self.otlp_endpoint.trim())
+            .build()
+            .map_err(|e| format!("创建 OTLP 导出器失败: {e}"))?;
+        let service_name = self
+            .service_name
+            .clone()
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string());
+        Ok(SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                self.sample_ratio.unwrap_or(1.0),
+            ))))
+            .with_resource(Resource::builder().with_service_name(service_name).build())
+            .build())
+    }
+}
+
+type OtelLayer = Option<OpenTelemetryLayer<Registry, SdkTracer>>;
+
+/// 全局 subscriber 只能设置一次，导出层通过 reload 句柄随配置替换
+fn layer_handle() -> &'static reload::Handle<OtelLayer, Registry> {
+    static HANDLE: OnceLock<reload::Handle<OtelLayer, Registry>> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        let (layer, handle) = reload::Layer::new(None);
+        if tracing_subscriber::registry()
+            .with(layer)
+            .try_init()
+            .is_err()
+        {
+            eprintln!("已存在全局 tracing subscriber，链路追踪导出不会生效");
+        }
+        handle
+    })
+}
+
+static ACTIVE: Mutex<Option<(TracingConfig, SdkTracerProvider)>> = Mutex::new(None);
+
+/// 按配置启用、替换或关闭 OTLP 导出；配置未变化时保持现有导出器
+pub fn configure(config: Option<&TracingConfig>) -> Result<(), String> {
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if active.as_ref().map(|(cfg, _)| cfg) == config {
+        return Ok(());
+    }
+
+    let provider = config.map(TracingConfig::build_provider).transpose()?;
+    let layer = provider
+        .as_ref()
+        .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer(DEFAULT_SERVICE_NAME)));
+    if layer.is_some() || active.is_some() {
+        layer_handle()
+            .reload(layer)
+            .map_err(|e| format!("更新链路追踪配置失败: {e}"))?;
+    }
+
+    let previous = std::mem::replace(&mut *active, config.cloned().zip(provider));
+    if let Some((_, provider)) = previous {
+        // shutdown 会同步导出剩余 span，放到后台线程避免阻塞调用方
+        std::thread::spawn(move || {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("关闭链路追踪导出器失败: {err}");
+            }
+        });
+    }
+    Ok(())
+}
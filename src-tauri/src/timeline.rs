@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TimelineEventKind.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineEventKind {
+    Received,
+    Routed,
+    AttemptSent,
+    /// 收到上游响应头
+    FirstByte,
+    Retried,
+    Fallback,
+    Completed,
+    Failed,
+}
+
+/// 单个请求处理过程中的一个节点，供前端绘制瀑布图
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TimelineEvent.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    /// 相对请求接收时刻（即日志 timestamp）的毫秒偏移
+    #[ts(type = "number")]
+    pub offset_ms: u64,
+    /// 第几次发往上游的尝试（从 1 开始，跨上游累计）
+    pub attempt: Option<u32>,
+    pub upstream_id: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl TimelineEvent {
+    pub fn new(kind: TimelineEventKind, request_started: Instant) -> Self {
+        Self {
+            kind,
+            offset_ms: request_started.elapsed().as_millis() as u64,
+            attempt: None,
+            upstream_id: None,
+            detail: None,
+        }
+    }
+
+    pub fn attempt(mut self, attempt: u32) -> Self {
+        self.attempt = Some(attempt);
+        self
+    }
+
+    pub fn upstream(mut self, upstream_id: &str) -> Self {
+        self.upstream_id = Some(upstream_id.to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
@@ -0,0 +1,161 @@
+//! 服务 / 上游级别的超时覆盖。总超时按请求设置；连接与读取超时只能在客户端上设置，
+//! 因此按取值缓存对应的客户端实例，未覆盖时沿用共享客户端。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::host_override::HostOverride;
+
+/// 单项超时的上限，避免误填毫秒数导致请求长时间挂起
+const MAX_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+/// 客户端为单个请求指定的超时（毫秒），只在代理内生效，转发前删除
+pub const CLIENT_TIMEOUT_HEADER: &str = "x-apiflow-timeout-ms";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TimeoutConfig.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutConfig {
+    /// 建立连接（含 TLS 握手）的超时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub connect_secs: Option<u64>,
+    /// 两次读取之间允许的最长间隔
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub read_secs: Option<u64>,
+    /// 非流式请求（含响应体）的总超时，未设置时为 600 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub total_secs: Option<u64>,
+    /// 流式响应等待首个数据块的超时，超时后切换到下一个上游或重试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub first_byte_secs: Option<u64>,
+    /// 期望流式响应的请求的总超时，未设置时为 600 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub stream_total_secs: Option<u64>,
+    /// 流式响应两个数据块之间允许的最长间隔，超过后中断转发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub stream_idle_secs: Option<u64>,
+}
+
+impl TimeoutConfig {
+    /// 上游的设置优先，未设置的项沿用服务的设置
+    pub fn merge(upstream: Option<&Self>, service: Option<&Self>) -> Self {
+        let upstream = upstream.copied().unwrap_or_default();
+        let service = service.copied().unwrap_or_default();
+        Self {
+            connect_secs: upstream.connect_secs.or(service.connect_secs),
+            read_secs: upstream.read_secs.or(service.read_secs),
+            total_secs: upstream.total_secs.or(service.total_secs),
+            first_byte_secs: upstream.first_byte_secs.or(service.first_byte_secs),
+            stream_total_secs: upstream.stream_total_secs.or(service.stream_total_secs),
+            stream_idle_secs: upstream.stream_idle_secs.or(service.stream_idle_secs),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("连接超时", self.connect_secs),
+            ("读取超时", self.read_secs),
+            ("总超时", self.total_secs),
+            ("首个数据块超时", self.first_byte_secs),
+            ("流式总超时", self.stream_total_secs),
+            ("流式空闲超时", self.stream_idle_secs),
+        ] {
+            if value.is_some_and(|v| v == 0 || v > MAX_TIMEOUT_SECS) {
+                return Err(format!("{name}需在 1 到 {MAX_TIMEOUT_SECS} 秒之间"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect_secs.map(Duration::from_secs)
+    }
+
+    pub fn read(&self) -> Option<Duration> {
+        self.read_secs.map(Duration::from_secs)
+    }
+
+    /// 按请求是否期望流式响应选择总超时
+    pub fn total(&self, streaming: bool) -> Option<Duration> {
+        if streaming {
+            self.stream_total_secs
+        } else {
+            self.total_secs
+        }
+        .map(Duration::from_secs)
+    }
+
+    pub fn first_byte(&self) -> Option<Duration> {
+        self.first_byte_secs.map(Duration::from_secs)
+    }
+
+    pub fn stream_idle(&self) -> Option<Duration> {
+        self.stream_idle_secs.map(Duration::from_secs)
+    }
+}
+
+/// 读取客户端通过请求头指定的超时，不超过配置的上限；未配置上限时忽略该请求头
+pub fn client_timeout(headers: &http::HeaderMap, max_ms: Option<u64>) -> Option<Duration> {
+    let max_ms = max_ms?;
+    let requested = headers
+        .get(CLIENT_TIMEOUT_HEADER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|ms| *ms > 0)?;
+    Some(Duration::from_millis(requested.min(max_ms)))
+}
+
+type ClientKey = (Option<u64>, Option<u64>, Option<HostOverride>);
+/// 代理地址与该地址下按 (连接超时, 读取超时, Host 覆盖) 缓存的客户端
+type ClientCache = (Option<String>, HashMap<ClientKey, reqwest::Client>);
+
+/// 代理地址变化时整体清空
+fn clients() -> &'static Mutex<ClientCache> {
+    static CLIENTS: OnceLock<Mutex<ClientCache>> = OnceLock::new();
+    CLIENTS.get_or_init(Default::default)
+}
+
+/// 选择发送请求使用的客户端：需要覆盖连接或读取超时、或覆盖 Host 时使用专用客户端，
+/// 创建失败时退回共享客户端
+pub fn client_for(
+    shared: &reqwest::Client,
+    proxy_url: Option<&str>,
+    timeouts: &TimeoutConfig,
+    host_override: Option<&HostOverride>,
+) -> reqwest::Client {
+    if timeouts.connect_secs.is_none() && timeouts.read_secs.is_none() && host_override.is_none() {
+        return shared.clone();
+    }
+    let mut guard = clients().lock().unwrap_or_else(|e| e.into_inner());
+    let (cached_proxy, cache) = &mut *guard;
+    if cached_proxy.as_deref() != proxy_url {
+        *cached_proxy = proxy_url.map(str::to_string);
+        cache.clear();
+    }
+    let key = (timeouts.connect_secs, timeouts.read_secs, host_override.cloned());
+    if let Some(client) = cache.get(&key) {
+        return client.clone();
+    }
+    match crate::build_client(proxy_url, timeouts, host_override) {
+        Ok(client) => {
+            cache.insert(key, client.clone());
+            client
+        }
+        Err(err) => {
+            eprintln!("创建专用客户端失败，使用默认客户端: {err}");
+            shared.clone()
+        }
+    }
+}
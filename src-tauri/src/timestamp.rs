@@ -0,0 +1,57 @@
+//! 日志时间戳：统一为毫秒精度的 RFC3339 UTC 字符串，并附带进程内单调递增的序号，
+//! 使同一毫秒内的重试也能稳定排序。旧版本写入的本地时间字符串仍可解析和比较。
+
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, Utc};
+
+/// 旧版本日志使用的本地时间格式
+const LEGACY_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+pub fn now() -> String {
+    format_utc(Utc::now())
+}
+
+pub fn format_utc(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn next_seq() -> u64 {
+    SEQ.fetch_add(1, AtomicOrdering::Relaxed) + 1
+}
+
+/// 恢复持久化日志后调用，保证新日志的序号大于已有日志
+pub fn observe_seq(seq: u64) {
+    SEQ.fetch_max(seq, AtomicOrdering::Relaxed);
+}
+
+pub fn parse(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(timestamp, LEGACY_FORMAT)
+                .ok()?
+                .and_local_timezone(Local)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc))
+        })
+}
+
+/// 把旧格式转换为当前格式，无法解析时原样返回
+pub fn normalize(timestamp: &str) -> String {
+    parse(timestamp)
+        .map(format_utc)
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// 按时间先后比较，任一方无法解析时退回字符串比较
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a), parse(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
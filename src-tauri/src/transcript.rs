@@ -0,0 +1,200 @@
+//! 从 chat 类请求/响应中还原对话，并导出为 Markdown 或 JSON。
+//! 同一对话的后续请求会携带完整历史，因此以「系统提示 + 首条用户消息」作为对话标识，
+//! 导出时取该对话中消息最多的一次请求，再拼上它的助手回复。
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::{timestamp, ProxyLogEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/ExportFormat.ts")]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: String,
+    pub model: Option<String>,
+    pub log_ids: Vec<String>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// 文本内容可能是字符串，也可能是 `[{type: "text", text}]` / `[{text}]` 形式的分块
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+/// 解析请求体中的消息列表，兼容 OpenAI `messages`、Anthropic `system` + `messages`
+/// 以及 Gemini `systemInstruction` + `contents`
+pub fn request_messages(body: &[u8]) -> Option<Vec<ChatMessage>> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let mut messages = Vec::new();
+
+    if let Some(system) = value
+        .get("system")
+        .or_else(|| value.get("systemInstruction"))
+    {
+        let text = match system.get("parts") {
+            Some(parts) => content_text(parts),
+            None => content_text(system),
+        };
+        if !text.is_empty() {
+            messages.push(ChatMessage {
+                role: "system".into(),
+                content: text,
+            });
+        }
+    }
+
+    if let Some(items) = value.get("messages").and_then(Value::as_array) {
+        messages.extend(items.iter().map(|m| ChatMessage {
+            role: m["role"].as_str().unwrap_or("user").to_string(),
+            content: content_text(&m["content"]),
+        }));
+    } else if let Some(items) = value.get("contents").and_then(Value::as_array) {
+        messages.extend(items.iter().map(|m| ChatMessage {
+            role: match m["role"].as_str() {
+                Some("model") => "assistant".to_string(),
+                Some(role) => role.to_string(),
+                None => "user".to_string(),
+            },
+            content: content_text(&m["parts"]),
+        }));
+    } else {
+        return None;
+    }
+
+    messages
+        .iter()
+        .any(|m| m.role != "system")
+        .then_some(messages)
+}
+
+/// 对话标识：系统提示与首条用户消息的哈希
+pub fn conversation_id(messages: &[ChatMessage]) -> Option<String> {
+    let first_user = messages.iter().find(|m| m.role == "user")?;
+    let mut hasher = DefaultHasher::new();
+    for m in messages.iter().filter(|m| m.role == "system") {
+        m.content.hash(&mut hasher);
+    }
+    first_user.content.hash(&mut hasher);
+    Some(format!("{:016x}", hasher.finish()))
+}
+
+/// 单个 JSON 响应（或 SSE 中的一个事件）里的助手文本
+fn reply_text(value: &Value) -> Option<String> {
+    let choice = &value["choices"][0];
+    if let Some(text) = choice["message"]["content"]
+        .as_str()
+        .or(choice["delta"]["content"].as_str())
+    {
+        return Some(text.to_string());
+    }
+    if let Some(text) = value["delta"]["text"].as_str() {
+        return Some(text.to_string());
+    }
+    if let Some(blocks) = value.get("content").filter(|c| c.is_array()) {
+        return Some(content_text(blocks));
+    }
+    let parts = &value["candidates"][0]["content"]["parts"];
+    parts.is_array().then(|| content_text(parts))
+}
+
+/// 还原助手回复；流式响应逐个拼接 `data:` 事件中的增量文本
+pub fn assistant_reply(response_body: &str, is_streaming: bool) -> Option<String> {
+    if !is_streaming {
+        let value: Value = serde_json::from_str(response_body).ok()?;
+        return reply_text(&value).filter(|t| !t.is_empty());
+    }
+
+    let text: String = response_body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .filter_map(|v| reply_text(&v))
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+pub fn build_conversation<'a>(
+    logs: impl IntoIterator<Item = &'a ProxyLogEntry>,
+    id: &str,
+) -> Option<Conversation> {
+    let entries: Vec<&ProxyLogEntry> = logs
+        .into_iter()
+        .filter(|e| e.conversation_id.as_deref() == Some(id))
+        .collect();
+
+    let (latest, mut messages) = entries
+        .iter()
+        .filter_map(|e| Some((*e, request_messages(e.request_body.as_deref()?.as_bytes())?)))
+        .max_by(|(a, ma), (b, mb)| {
+            ma.len()
+                .cmp(&mb.len())
+                .then_with(|| timestamp::compare(&a.timestamp, &b.timestamp))
+                .then_with(|| a.seq.cmp(&b.seq))
+        })?;
+
+    if let Some(reply) = latest
+        .response_body
+        .as_deref()
+        .and_then(|body| assistant_reply(body, latest.is_streaming))
+    {
+        messages.push(ChatMessage {
+            role: "assistant".into(),
+            content: reply,
+        });
+    }
+
+    Some(Conversation {
+        id: id.to_string(),
+        model: latest.model.clone(),
+        log_ids: entries.iter().map(|e| e.id.clone()).collect(),
+        messages,
+    })
+}
+
+pub fn export(conversation: &Conversation, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(conversation).map_err(|e| format!("序列化对话失败: {e}"))
+        }
+        ExportFormat::Markdown => {
+            let mut out = format!("# 对话 {}\n", conversation.id);
+            if let Some(model) = &conversation.model {
+                out.push_str(&format!("\n模型：{model}\n"));
+            }
+            for message in &conversation.messages {
+                out.push_str(&format!(
+                    "\n## {}\n\n{}\n",
+                    message.role,
+                    message.content.trim_end()
+                ));
+            }
+            Ok(out)
+        }
+    }
+}
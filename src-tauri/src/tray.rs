@@ -8,9 +8,13 @@ use tauri::{
 pub fn setup_tray(app: &tauri::App) -> tauri::Result<()> {
     let status_item = MenuItem::with_id(app, "status", "状态: 已停止", false, None::<&str>)?;
     let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
+    let summary_item = MenuItem::with_id(app, "daily_summary", "今日用量概览", true, None::<&str>)?;
     let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
     let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&status_item, &separator, &show_item, &quit_item])?;
+    let menu = Menu::with_items(
+        app,
+        &[&status_item, &separator, &summary_item, &show_item, &quit_item],
+    )?;
 
     let icon = Image::from_bytes(include_bytes!("../icons/tray-iconTemplate@2x.png"))?;
 
@@ -26,6 +30,10 @@ pub fn setup_tray(app: &tauri::App) -> tauri::Result<()> {
                     let _ = window.set_focus();
                 }
             }
+            "daily_summary" => {
+                let state = app.state::<crate::ProxyState>();
+                crate::events::emit_daily_summary(crate::daily_summary::current(&state.stats));
+            }
             "quit" => {
                 app.exit(0);
             }
@@ -50,13 +58,33 @@ pub fn setup_tray(app: &tauri::App) -> tauri::Result<()> {
     Ok(())
 }
 
+/// 按当前运行中的监听端口刷新托盘状态
 #[tauri::command]
-pub fn update_tray_status(
+pub(crate) async fn update_tray_status(
     app: tauri::AppHandle,
-    running: bool,
-    port: u16,
+    state: tauri::State<'_, crate::ProxyState>,
     processing_count: Option<u32>,
 ) -> Result<(), String> {
+    let mut ports: Vec<u16> = state.inner.lock().await.keys().copied().collect();
+    ports.sort_unstable();
+    let running = !ports.is_empty();
+    let port = ports
+        .iter()
+        .map(u16::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let expiring = match state.config.read().await.as_ref() {
+        Some(config) => crate::key_expiry::statuses_at(config, chrono::Utc::now()),
+        None => Vec::new(),
+    };
+    let expired = expiring.iter().filter(|s| s.expired).count();
+    let expiry_suffix = if expired > 0 {
+        format!(" · ⚠ {expired} 个 key 已过期")
+    } else if !expiring.is_empty() {
+        format!(" · ⚠ {} 个 key 即将到期", expiring.len())
+    } else {
+        "".to_string()
+    };
     let active_processing = processing_count.unwrap_or(0);
     let processing_suffix = if active_processing > 0 {
         format!(" · 处理中 {}", active_processing)
@@ -66,9 +94,9 @@ pub fn update_tray_status(
 
     if let Some(tray) = app.tray_by_id("main") {
         let tooltip = if running {
-            format!("ApiFlow - 运行中 ({}){}", port, processing_suffix)
+            format!("ApiFlow - 运行中 ({}){}{}", port, processing_suffix, expiry_suffix)
         } else {
-            "ApiFlow - 已停止".to_string()
+            format!("ApiFlow - 已停止{}", expiry_suffix)
         };
         tray.set_tooltip(Some(&tooltip)).map_err(|e| e.to_string())?;
 
@@ -80,18 +108,24 @@ pub fn update_tray_status(
             }
         } else {
             "○ 已停止".to_string()
-        };
+        } + &expiry_suffix;
 
         let status_item = MenuItem::with_id(&app, "status", &status_text, false, None::<&str>)
             .map_err(|e| e.to_string())?;
         let separator = tauri::menu::PredefinedMenuItem::separator(&app)
             .map_err(|e| e.to_string())?;
+        let summary_item =
+            MenuItem::with_id(&app, "daily_summary", "今日用量概览", true, None::<&str>)
+                .map_err(|e| e.to_string())?;
         let show_item = MenuItem::with_id(&app, "show", "显示窗口", true, None::<&str>)
             .map_err(|e| e.to_string())?;
         let quit_item = MenuItem::with_id(&app, "quit", "退出", true, None::<&str>)
             .map_err(|e| e.to_string())?;
-        let menu = Menu::with_items(&app, &[&status_item, &separator, &show_item, &quit_item])
-            .map_err(|e| e.to_string())?;
+        let menu = Menu::with_items(
+            &app,
+            &[&status_item, &separator, &summary_item, &show_item, &quit_item],
+        )
+        .map_err(|e| e.to_string())?;
 
         tray.set_menu(Some(menu)).map_err(|e| e.to_string())?;
     }
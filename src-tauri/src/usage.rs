@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+/// 非流式响应最多缓冲这么多字节用于解析 usage
+const MAX_BUFFERED_BODY: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/types/generated/TokenUsage.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    #[ts(type = "number")]
+    pub prompt_tokens: u64,
+    #[ts(type = "number")]
+    pub completion_tokens: u64,
+    #[ts(type = "number")]
+    pub total_tokens: u64,
+}
+
+fn field(obj: &Value, key: &str) -> Option<u64> {
+    obj.get(key).and_then(Value::as_u64)
+}
+
+/// 从单个 JSON 对象中读取 usage，兼容 OpenAI `usage`、
+/// Anthropic `usage` / `message.usage`（input/output_tokens）以及 Gemini `usageMetadata`
+fn parse_value(value: &Value) -> Option<TokenUsage> {
+    if let Some(meta) = value.get("usageMetadata") {
+        let prompt = field(meta, "promptTokenCount").unwrap_or(0);
+        let completion = field(meta, "candidatesTokenCount").unwrap_or(0);
+        return Some(TokenUsage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: field(meta, "totalTokenCount").unwrap_or(prompt + completion),
+        });
+    }
+
+    let usage = value
+        .get("usage")
+        .or_else(|| value.get("message").and_then(|m| m.get("usage")))
+        .filter(|u| u.is_object())?;
+    let prompt = field(usage, "prompt_tokens").or_else(|| field(usage, "input_tokens"));
+    let completion = field(usage, "completion_tokens").or_else(|| field(usage, "output_tokens"));
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+    let (prompt, completion) = (prompt.unwrap_or(0), completion.unwrap_or(0));
+    Some(TokenUsage {
+        prompt_tokens: prompt,
+        completion_tokens: completion,
+        total_tokens: field(usage, "total_tokens").unwrap_or(prompt + completion),
+    })
+}
+
+pub fn parse_json_usage(body: &[u8]) -> Option<TokenUsage> {
+    serde_json::from_slice::<Value>(body).ok().as_ref().and_then(parse_value)
+}
+
+/// 逐 chunk 扫描响应，提取 usage。
+/// SSE 只解析包含 usage 的 `data:` 行，无需保留整个响应体；
+/// Anthropic 的输入/输出 token 分散在不同事件中，按字段取最大值合并。
+#[derive(Default)]
+pub struct UsageScanner {
+    is_sse: bool,
+    pending: Vec<u8>,
+    usage: Option<TokenUsage>,
+}
+
+impl UsageScanner {
+    pub fn new(is_sse: bool) -> Self {
+        Self {
+            is_sse,
+            ..Self::default()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if !self.is_sse {
+            if self.pending.len() + chunk.len() <= MAX_BUFFERED_BODY {
+                self.pending.extend_from_slice(chunk);
+            }
+            return;
+        }
+
+        self.pending.extend_from_slice(chunk);
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            self.scan_line(&line);
+        }
+        // 单行异常长时放弃，避免无界增长
+        if self.pending.len() > MAX_BUFFERED_BODY {
+            self.pending.clear();
+        }
+    }
+
+    fn scan_line(&mut self, line: &[u8]) {
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return;
+        };
+        let text = String::from_utf8_lossy(data);
+        if !text.contains("usage") {
+            return;
+        }
+        if let Some(found) = serde_json::from_str::<Value>(text.trim()).ok().as_ref().and_then(parse_value) {
+            self.merge(found);
+        }
+    }
+
+    fn merge(&mut self, found: TokenUsage) {
+        let merged = match self.usage {
+            Some(prev) => {
+                let prompt = prev.prompt_tokens.max(found.prompt_tokens);
+                let completion = prev.completion_tokens.max(found.completion_tokens);
+                TokenUsage {
+                    prompt_tokens: prompt,
+                    completion_tokens: completion,
+                    total_tokens: found.total_tokens.max(prompt + completion),
+                }
+            }
+            None => found,
+        };
+        self.usage = Some(merged);
+    }
+
+    pub fn finish(mut self) -> Option<TokenUsage> {
+        if self.is_sse {
+            let rest = std::mem::take(&mut self.pending);
+            self.scan_line(&rest);
+            self.usage
+        } else {
+            parse_json_usage(&self.pending)
+        }
+    }
+}
@@ -8,11 +8,14 @@ import {
   Play,
   Square,
   RefreshCw,
-  Loader2
+  Loader2,
+  ShieldAlert
 } from "lucide-react";
+import { useEffect, useState } from "react";
 import { Button } from "@/components/ui/button";
 import { TabKey } from "@/types";
 import { useMonitoring } from "@/context/MonitoringContext";
+import { getSafeMode } from "@/lib/proxy";
 
 interface SidebarProps {
   activeTab: TabKey;
@@ -38,6 +41,12 @@ export function Sidebar({
   listenPort
 }: SidebarProps) {
   const { processingCount } = useMonitoring();
+  const [safeMode, setSafeMode] = useState(false);
+
+  useEffect(() => {
+    getSafeMode().then(setSafeMode).catch(() => setSafeMode(false));
+  }, []);
+
   const navItems: { id: TabKey; label: string; icon: React.ElementType }[] = [
     { id: "config", label: "服务管理", icon: Server },
     { id: "providers", label: "提供商", icon: Building2 },
@@ -74,6 +83,13 @@ export function Sidebar({
         ))}
       </div>
 
+      {safeMode && (
+        <div className="mx-4 mb-2 flex items-center gap-2 rounded-lg bg-amber-50 px-3 py-2 text-xs font-medium text-amber-700 ring-1 ring-amber-100 dark:bg-amber-900/30 dark:text-amber-100 dark:ring-amber-800">
+          <ShieldAlert className="h-3.5 w-3.5" />
+          安全模式：扩展功能已停用
+        </div>
+      )}
+
       {isRunning && processingCount > 0 && (
         <div className="mx-4 mb-2 flex items-center gap-2 rounded-lg bg-emerald-50 px-3 py-2 text-xs font-medium text-emerald-700 ring-1 ring-emerald-100 dark:bg-emerald-900/30 dark:text-emerald-100 dark:ring-emerald-800">
           <Loader2 className="h-3.5 w-3.5 animate-spin" />
@@ -25,6 +25,7 @@ import {
 } from "@/components/ui/tooltip";
 import { useMonitoring } from "@/context/MonitoringContext";
 import { LogEntry } from "@/types";
+import { formatTimestamp } from "@/lib/utils";
 
 const BODY_PREVIEW_LIMIT = 2000;
 
@@ -142,6 +143,46 @@ const LogItem = memo(function LogItem({
                   流式
                 </Badge>
               )}
+              {log.cacheHit && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-emerald-600 border-emerald-200 dark:text-emerald-400 dark:border-emerald-800">
+                  缓存
+                </Badge>
+              )}
+              {log.deduplicated && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-sky-600 border-sky-200 dark:text-sky-400 dark:border-sky-800">
+                  合并
+                </Badge>
+              )}
+              {log.defaultModelApplied && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-amber-600 border-amber-200 dark:text-amber-400 dark:border-amber-800">
+                  默认模型
+                </Badge>
+              )}
+              {log.compare && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-indigo-600 border-indigo-200 dark:text-indigo-400 dark:border-indigo-800">
+                  对比 · {log.compare.differences.length} 处差异
+                </Badge>
+              )}
+              {log.shadow && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-slate-600 border-slate-200 dark:text-slate-400 dark:border-slate-800">
+                  影子流量
+                </Badge>
+              )}
+              {log.images && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-teal-600 border-teal-200 dark:text-teal-400 dark:border-teal-800">
+                  图片 × {log.images.inlineCount + log.images.urlCount}
+                </Badge>
+              )}
+              {log.streamMismatch && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-orange-600 border-orange-200 dark:text-orange-400 dark:border-orange-800">
+                  非流式响应
+                </Badge>
+              )}
+              {log.piiMasked != null && (
+                <Badge variant="outline" className="text-[10px] px-1.5 py-0 h-4 text-violet-600 border-violet-200 dark:text-violet-400 dark:border-violet-800">
+                  已遮盖 {log.piiMasked} 处
+                </Badge>
+              )}
           </div>
           <div className="flex flex-wrap items-center gap-x-4 gap-y-1 text-xs text-slate-500 dark:text-slate-400">
             <Tooltip>
@@ -170,7 +211,7 @@ const LogItem = memo(function LogItem({
               </span>
             )}
             <span className="text-slate-400 dark:text-slate-600 ml-auto text-[10px]">
-               {log.timestamp}
+               {formatTimestamp(log.timestamp)}
             </span>
           </div>
           {log.error && (
@@ -239,7 +280,7 @@ const LogItem = memo(function LogItem({
               </div>
               <div>
                 <span className="text-[10px] font-medium text-slate-400 uppercase block">时间</span>
-                <span className="text-xs font-mono text-slate-700 dark:text-slate-300">{log.timestamp}</span>
+                <span className="text-xs font-mono text-slate-700 dark:text-slate-300">{formatTimestamp(log.timestamp)}</span>
               </div>
               <div className="col-span-2">
                 <span className="text-[10px] font-medium text-slate-400 uppercase block">请求路径</span>
@@ -465,8 +506,10 @@ export function LogsView() {
   // 按时间降序排列
   const sortedLogs = useMemo(() => {
     return [...logs].sort((a, b) => {
-      // 按 timestamp 降序
-      return b.timestamp.localeCompare(a.timestamp);
+      // 按 timestamp 降序，同一毫秒内按序号
+      const diff = new Date(b.timestamp).getTime() - new Date(a.timestamp).getTime();
+      if (diff) return diff;
+      return b.seq - a.seq || b.timestamp.localeCompare(a.timestamp);
     });
   }, [logs]);
 
@@ -113,16 +113,7 @@ export function SettingsView() {
                     <h3 className="font-semibold text-slate-900 dark:text-slate-100">网络配置</h3>
                 </div>
                 
-                <div className={`relative ${isRunning ? "opacity-60" : ""}`}>
-                    {isRunning && (
-                        <>
-                            <div className="pointer-events-auto absolute inset-0 z-10" />
-                            <div className="absolute right-3 top-3 z-20 inline-flex items-center gap-1 rounded-full bg-amber-100 px-2 py-1 text-xs font-medium text-amber-700 dark:bg-amber-900/60 dark:text-amber-200">
-                                <Lock className="h-3.5 w-3.5" />
-                                运行中已锁定
-                            </div>
-                        </>
-                    )}
+                <div>
                     <div className="grid gap-6 p-6 rounded-xl border border-slate-200 dark:border-slate-800 bg-white dark:bg-slate-950">
                     <div className="space-y-3">
                         <Label className="text-base">监听端口</Label>
@@ -142,14 +133,13 @@ export function SettingsView() {
                                     const val = Number(e.target.value);
                                     setListenPort(Math.min(65535, Math.max(1, val || 23333)));
                                 }}
-                                disabled={isRunning}
                                 placeholder="23333"
                                 className="w-40 font-mono"
                             />
                             <span className="text-sm text-slate-500">默认: 23333</span>
                         </div>
                         <p className="text-sm text-slate-500">
-                            本地代理服务将在此端口接收请求。请确保端口未被占用。
+                            本地代理服务将在此端口接收请求。请确保端口未被占用。运行中修改端口后点击热更新即可迁移，进行中的请求不受影响。
                         </p>
                     </div>
                     </div>
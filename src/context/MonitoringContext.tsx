@@ -1,5 +1,7 @@
 import { createContext, useContext, useState, useEffect, ReactNode, useCallback } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { LogEntry } from "@/types";
+import type { DailySummary, KeyExpiryStatus, ProviderStatus, UpstreamBalance } from "@/types/backend";
 import {
   clearLogs as clearLogsCommand,
   getLogs as fetchLogs,
@@ -26,6 +28,18 @@ export function useMonitoring() {
   return context;
 }
 
+function showNotification(title: string, body: string) {
+  if (typeof Notification === "undefined") {
+    return;
+  }
+  const show = () => new Notification(title, { body });
+  if (Notification.permission === "granted") {
+    show();
+  } else if (Notification.permission !== "denied") {
+    Notification.requestPermission().then((p) => p === "granted" && show());
+  }
+}
+
 export function MonitoringProvider({ children }: { children: ReactNode }) {
   const { listenPort, isRunning } = useProxyStore();
   const [logs, setLogs] = useState<LogEntry[]>([]);
@@ -54,19 +68,99 @@ export function MonitoringProvider({ children }: { children: ReactNode }) {
     if (autoRefreshEnabled) {
       loadLogs();
     }
-    const timer = setInterval(() => {
-      if (isRunning && autoRefreshEnabled) {
-        loadLogs();
+  }, [listenPort, loadLogs, autoRefreshEnabled]);
+
+  // 后端通过 log:upsert 事件批量推送日志变更，按 id 合并到本地列表
+  useEffect(() => {
+    if (!autoRefreshEnabled) {
+      return;
+    }
+    const unlisten = listen<LogEntry[]>("log:upsert", (event) => {
+      const batch = event.payload.filter((entry) => entry.listenPort === listenPort);
+      if (batch.length === 0) {
+        return;
       }
-    }, 3000);
-    return () => clearInterval(timer);
-  }, [listenPort, isRunning, loadLogs, autoRefreshEnabled]);
+      setLogs((prev) => {
+        const next = [...prev];
+        for (const entry of batch) {
+          const idx = next.findIndex((e) => e.id === entry.id);
+          if (idx >= 0) {
+            next[idx] = entry;
+          } else {
+            next.push(entry);
+          }
+        }
+        return next.slice(-180);
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [listenPort, autoRefreshEnabled]);
 
   useEffect(() => {
     if (isRunning) {
-      updateTrayStatus(true, listenPort, processingCount).catch(() => {});
+      updateTrayStatus(processingCount).catch(() => {});
     }
-  }, [processingCount, isRunning, listenPort]);
+  }, [processingCount, isRunning]);
+
+  // 后台任务发现 key 即将到期或已过期时推送 key:expiry，刷新托盘并弹出系统通知
+  useEffect(() => {
+    const unlisten = listen<KeyExpiryStatus[]>("key:expiry", (event) => {
+      updateTrayStatus().catch(() => {});
+      const body = event.payload
+        .map((s) => {
+          const name = `${s.serviceName} / ${s.upstreamLabel ?? s.upstreamId}`;
+          return s.expired ? `${name} 的 key 已过期` : `${name} 的 key 将在 ${s.daysLeft} 天内到期`;
+        })
+        .join("\n");
+      showNotification("API key 到期提醒", body);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 余额轮询发现上游余额低于阈值时推送 balance:low
+  useEffect(() => {
+    const unlisten = listen<UpstreamBalance>("balance:low", (event) => {
+      const b = event.payload;
+      const amount = `${b.balance ?? "-"} ${b.currency ?? ""}`.trim();
+      showNotification("余额不足", `${b.upstreamLabel ?? b.upstreamId} 剩余 ${amount}`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 状态页轮询发现服务商有新的进行中故障时推送 provider:incident
+  useEffect(() => {
+    const unlisten = listen<ProviderStatus>("provider:incident", (event) => {
+      const s = event.payload;
+      const body = s.incidents.map((i) => `${i.name}（${i.status}）`).join("\n");
+      showNotification(`${s.provider} 服务商故障`, `${body}\n受影响的上游: ${s.upstreamIds.join(", ")}`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 每日用量概览：后台定时推送，或在托盘菜单中手动查看
+  useEffect(() => {
+    const unlisten = listen<DailySummary>("summary:daily", (event) => {
+      const s = event.payload;
+      const lines = [
+        `请求 ${s.requests} 次 · 错误率 ${(s.errorRate * 100).toFixed(1)}% · ${s.tokens} tokens · $${s.cost.toFixed(2)}`,
+        ...s.services.map(
+          (svc) => `${svc.serviceName}: ${svc.requests} 次 · 错误 ${svc.errors} · ${svc.tokens} tokens · $${svc.cost.toFixed(2)}`
+        ),
+      ];
+      showNotification(`今日用量（${s.date}）`, lines.join("\n"));
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
 
   return (
     <MonitoringContext.Provider
@@ -288,11 +288,11 @@ export function ProxyStoreProvider({ children }: { children: ReactNode }) {
     try {
       await saveSettingsCmd(cfg);
       await startProxy(cfg);
-      await updateTrayStatus(true, listenPort, 0);
+      await updateTrayStatus(0);
       setIsRunning(true);
     } catch (err) {
       setIsRunning(false);
-      await updateTrayStatus(false, listenPort).catch(() => {});
+      await updateTrayStatus().catch(() => {});
       console.error(`启动失败：${String(err)}`);
     } finally {
       setGlobalBusy(false);
@@ -317,7 +317,7 @@ export function ProxyStoreProvider({ children }: { children: ReactNode }) {
     try {
       await saveSettingsCmd(cfg);
       await reloadProxy(cfg);
-      await updateTrayStatus(true, listenPort, 0);
+      await updateTrayStatus(0);
       setIsRunning(true);
     } catch (err) {
       console.error(`热更新失败：${String(err)}`);
@@ -329,8 +329,8 @@ export function ProxyStoreProvider({ children }: { children: ReactNode }) {
   const stopGateway = async () => {
     setGlobalBusy(true);
     try {
-      await stopProxy(listenPort);
-      await updateTrayStatus(false, listenPort, 0);
+      await stopProxy();
+      await updateTrayStatus(0);
       setIsRunning(false);
     } catch (err) {
       console.error(`停止失败：${String(err)}`);
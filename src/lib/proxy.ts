@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
-import { LogEntry, PersistedConfig, NetworkInfo } from "@/types";
+import { PersistedConfig, NetworkInfo } from "@/types";
+import type { AppMetrics, BudgetStatus, ConfigImport, CurlTarget, DailySummary, ExportFormat, FailoverDrill, GatewayImport, GroupStats, KeyExpiryStatus, KeyImportSummary, LogFilter, LogPage, ProviderStatus, ProxyLogEntry, QueuedJob, SpendSummary, StatsGroupBy, UpstreamBalance, UsageReconciliation } from "@/types/backend";
 
 export async function loadSettings() {
   return invoke<PersistedConfig | null>("load_settings");
@@ -17,30 +18,171 @@ export async function reloadProxy(config: PersistedConfig) {
   return invoke("reload_proxy", { config });
 }
 
-export async function stopProxy(listenPort: number) {
-  return invoke("stop_proxy", { listen_port: listenPort });
+export async function pauseService(serviceId: string) {
+  return invoke("pause_service", { serviceId });
 }
 
-export async function updateTrayStatus(
-  running: boolean,
-  port: number,
-  processingCount?: number
-) {
+export async function resumeService(serviceId: string) {
+  return invoke("resume_service", { serviceId });
+}
+
+export async function setUpstreamEnabled(serviceId: string, upstreamId: string, enabled: boolean) {
+  return invoke("set_upstream_enabled", {
+    serviceId,
+    upstreamId,
+    enabled,
+  });
+}
+
+export async function markUpstreamVerified(serviceId: string, upstreamId: string) {
+  return invoke("mark_upstream_verified", {
+    serviceId,
+    upstreamId,
+  });
+}
+
+export async function importKeys(serviceId: string, text: string) {
+  return invoke<KeyImportSummary>("import_keys", {
+    serviceId,
+    text,
+  });
+}
+
+export async function stopProxy(listenPort?: number) {
+  return invoke("stop_proxy", { listenPort: listenPort ?? null });
+}
+
+export async function updateTrayStatus(processingCount?: number) {
   return invoke("update_tray_status", {
-    running,
-    port,
-    processing_count: processingCount ?? 0,
+    processingCount: processingCount ?? 0,
   });
 }
 
-export async function getLogs(listenPort: number, limit = 180) {
-  return invoke<LogEntry[]>("get_logs", { listen_port: listenPort, limit });
+export async function getLogs(listenPort: number, limit = 180, filter?: LogFilter) {
+  const page = await getLogPage(listenPort, { limit, filter });
+  return page.entries;
+}
+
+export async function getLogPage(
+  listenPort: number,
+  options: { limit?: number; filter?: LogFilter; beforeId?: string; afterId?: string } = {}
+) {
+  return invoke<LogPage>("get_logs", {
+    listenPort,
+    limit: options.limit,
+    filter: options.filter,
+    beforeId: options.beforeId,
+    afterId: options.afterId,
+  });
+}
+
+export async function getLogDetail(logId: string) {
+  return invoke<ProxyLogEntry>("get_log_detail", { logId });
+}
+
+export async function exportConversation(conversationId: string, format: ExportFormat) {
+  return invoke<string>("export_conversation", { conversationId, format });
+}
+
+export async function getCurlCommand(logId: string, target: CurlTarget, revealKey = false) {
+  return invoke<string>("get_curl_command", { logId, target, revealKey });
+}
+
+export async function getStatsBreakdown(groupBy: StatsGroupBy) {
+  return invoke<GroupStats[]>("get_stats_breakdown", { groupBy });
+}
+
+export async function getSpendSummary() {
+  return invoke<SpendSummary>("get_spend_summary");
+}
+
+export async function getBudgetStatus() {
+  return invoke<BudgetStatus[]>("get_budget_status");
+}
+
+export async function getUpstreamBalances(refresh?: boolean) {
+  return invoke<UpstreamBalance[]>("get_upstream_balances", { refresh });
+}
+
+export async function getProviderStatus(refresh?: boolean) {
+  return invoke<ProviderStatus[]>("get_provider_status", { refresh });
+}
+
+export async function reconcileUsage(csv: string, upstreamIds?: string[]) {
+  return invoke<UsageReconciliation>("reconcile_usage", {
+    csv,
+    upstreamIds,
+  });
+}
+
+export async function getDailySummary() {
+  return invoke<DailySummary>("get_daily_summary");
+}
+
+export async function getAppMetrics() {
+  return invoke<AppMetrics>("get_app_metrics");
+}
+
+export async function getKeyExpiryStatus() {
+  return invoke<KeyExpiryStatus[]>("get_key_expiry_status");
+}
+
+export async function pruneArchivedStats() {
+  return invoke<number>("prune_archived_stats");
+}
+
+export async function relinkUpstreamStats(fromId: string, toId: string) {
+  return invoke("relink_upstream_stats", { fromId, toId });
 }
 
 export async function clearLogs() {
   return invoke("clear_logs");
 }
 
+export async function clearCache() {
+  return invoke<number>("clear_cache");
+}
+
+export async function exportConfig(path: string, includeSecrets: boolean) {
+  return invoke("export_config", { path, includeSecrets });
+}
+
+export async function importConfig(path: string) {
+  return invoke<ConfigImport>("import_config", { path });
+}
+
+export async function importGatewayConfig(text: string) {
+  return invoke<GatewayImport>("import_gateway_config", { text });
+}
+
+export async function getSafeMode() {
+  return invoke<boolean>("get_safe_mode");
+}
+
+export async function startFailoverDrill(upstreamId: string, minutes: number) {
+  return invoke<FailoverDrill>("start_failover_drill", { upstreamId, minutes });
+}
+
+export async function stopFailoverDrill(upstreamId: string) {
+  return invoke<boolean>("stop_failover_drill", { upstreamId });
+}
+
+export async function listFailoverDrills() {
+  return invoke<FailoverDrill[]>("list_failover_drills");
+}
+
+export async function getRetryQueue() {
+  return invoke<QueuedJob[]>("get_retry_queue");
+}
+
+export async function getQueuedJob(id: string) {
+  return invoke<QueuedJob>("get_queued_job", { id });
+}
+
+export async function clearFinishedJobs() {
+  return invoke<number>("clear_finished_jobs");
+}
+
 export async function getNetworkInfo() {
   return invoke<NetworkInfo>("get_network_info");
 }
@@ -11,6 +11,14 @@ export const makeId = () =>
     ? crypto.randomUUID()
     : `id-${Date.now()}-${Math.random().toString(16).slice(2)}`;
 
+// 日志时间戳为 RFC3339 UTC，按本地时区显示到毫秒；旧格式无法解析时原样返回
+export const formatTimestamp = (timestamp: string) => {
+  const date = new Date(timestamp);
+  if (Number.isNaN(date.getTime())) return timestamp;
+  const pad = (n: number, len = 2) => String(n).padStart(len, "0");
+  return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}:${pad(date.getSeconds())}.${pad(date.getMilliseconds(), 3)}`;
+};
+
 export const resequenceLinks = (links: RouteLink[]) =>
   links
     .slice()
@@ -4,3 +4,94 @@ export type { ServiceConfig as BackendServiceConfig } from "./generated/ServiceC
 export type { UpstreamEntry } from "./generated/UpstreamEntry";
 export type { UpstreamStats } from "./generated/UpstreamStats";
 export type { NetworkInfo } from "./generated/NetworkInfo";
+export type { LogFilter } from "./generated/LogFilter";
+export type { LogPage } from "./generated/LogPage";
+export type { ProxyStatusEvent } from "./generated/ProxyStatusEvent";
+export type { CurlTarget } from "./generated/CurlTarget";
+export type { RedactionConfig } from "./generated/RedactionConfig";
+export type { RetentionConfig } from "./generated/RetentionConfig";
+export type { TimelineEvent } from "./generated/TimelineEvent";
+export type { TimelineEventKind } from "./generated/TimelineEventKind";
+export type { ErrorKind } from "./generated/ErrorKind";
+export type { ErrorAction } from "./generated/ErrorAction";
+export type { WindowStats } from "./generated/WindowStats";
+export type { StatsGroupBy } from "./generated/StatsGroupBy";
+export type { GroupStats } from "./generated/GroupStats";
+export type { TeeSink } from "./generated/TeeSink";
+export type { TokenUsage } from "./generated/TokenUsage";
+export type { ExportFormat } from "./generated/ExportFormat";
+export type { ModelPrice } from "./generated/ModelPrice";
+export type { SpendItem } from "./generated/SpendItem";
+export type { SpendSummary } from "./generated/SpendSummary";
+export type { LogStorageConfig } from "./generated/LogStorageConfig";
+export type { AdminScope } from "./generated/AdminScope";
+export type { AdminToken } from "./generated/AdminToken";
+export type { BudgetScope } from "./generated/BudgetScope";
+export type { BudgetPeriod } from "./generated/BudgetPeriod";
+export type { BudgetAction } from "./generated/BudgetAction";
+export type { BudgetRule } from "./generated/BudgetRule";
+export type { BudgetStatus } from "./generated/BudgetStatus";
+export type { RateLimitConfig } from "./generated/RateLimitConfig";
+export type { SchedulerStats } from "./generated/SchedulerStats";
+export type { RequestPriority } from "./generated/RequestPriority";
+export type { TracingConfig } from "./generated/TracingConfig";
+export type { AdminApiConfig } from "./generated/AdminApiConfig";
+export type { ListenerConfig } from "./generated/ListenerConfig";
+export type { ChecksumReport } from "./generated/ChecksumReport";
+export type { SyntheticEndpoint } from "./generated/SyntheticEndpoint";
+export type { TimeoutConfig } from "./generated/TimeoutConfig";
+export type { BackoffConfig } from "./generated/BackoffConfig";
+export type { StreamingDetection } from "./generated/StreamingDetection";
+export type { RetryRule } from "./generated/RetryRule";
+export type { RequestRateLimit } from "./generated/RequestRateLimit";
+export type { KeyExpiryConfig } from "./generated/KeyExpiryConfig";
+export type { KeyExpiryStatus } from "./generated/KeyExpiryStatus";
+export type { KeyImportSummary } from "./generated/KeyImportSummary";
+export type { BalanceProvider } from "./generated/BalanceProvider";
+export type { BalanceConfig } from "./generated/BalanceConfig";
+export type { UpstreamBalance } from "./generated/UpstreamBalance";
+export type { ResponseCacheConfig } from "./generated/ResponseCacheConfig";
+export type { DailyUsageVariance } from "./generated/DailyUsageVariance";
+export type { UsageReconciliation } from "./generated/UsageReconciliation";
+export type { MirrorConfig } from "./generated/MirrorConfig";
+export type { ProviderCapabilities } from "./generated/ProviderCapabilities";
+export type { RoutingMode } from "./generated/RoutingMode";
+export type { CompareConfig } from "./generated/CompareConfig";
+export type { CompareReport } from "./generated/CompareReport";
+export type { JsonDifference } from "./generated/JsonDifference";
+export type { MockUpstream } from "./generated/MockUpstream";
+export type { ImageSummary } from "./generated/ImageSummary";
+export type { ChaosConfig } from "./generated/ChaosConfig";
+export type { StructuredOutputMode } from "./generated/StructuredOutputMode";
+export type { OverflowStrategy } from "./generated/OverflowStrategy";
+export type { ContextOverflowRetry } from "./generated/ContextOverflowRetry";
+export type { PathRewriteRule } from "./generated/PathRewriteRule";
+export type { QueryParamRule } from "./generated/QueryParamRule";
+export type { DailySummaryConfig } from "./generated/DailySummaryConfig";
+export type { ServiceDailyUsage } from "./generated/ServiceDailyUsage";
+export type { DailySummary } from "./generated/DailySummary";
+export type { AppMetrics } from "./generated/AppMetrics";
+export type { CorsConfig } from "./generated/CorsConfig";
+export type { RetryQueueConfig } from "./generated/RetryQueueConfig";
+export type { QueuedJob } from "./generated/QueuedJob";
+export type { QueuedJobStatus } from "./generated/QueuedJobStatus";
+export type { QueuedJobResult } from "./generated/QueuedJobResult";
+export type { ModelFilter } from "./generated/ModelFilter";
+export type { PiiMaskConfig } from "./generated/PiiMaskConfig";
+export type { PiiPattern } from "./generated/PiiPattern";
+export type { HookConfig } from "./generated/HookConfig";
+export type { HookEvent } from "./generated/HookEvent";
+export type { HookPayload } from "./generated/HookPayload";
+export type { CustomDimension } from "./generated/CustomDimension";
+export type { DimensionRule } from "./generated/DimensionRule";
+export type { DimensionSource } from "./generated/DimensionSource";
+export type { ConfigEncryption } from "./generated/ConfigEncryption";
+export type { ConfigImport } from "./generated/ConfigImport";
+export type { StreamBridgeConfig } from "./generated/StreamBridgeConfig";
+export type { GatewayImport } from "./generated/GatewayImport";
+export type { FailoverDrill } from "./generated/FailoverDrill";
+export type { AccessSchedule } from "./generated/AccessSchedule";
+export type { QuietWindow } from "./generated/QuietWindow";
+export type { ProviderIncident } from "./generated/ProviderIncident";
+export type { ProviderStatus } from "./generated/ProviderStatus";
+export type { ProviderStatusConfig } from "./generated/ProviderStatusConfig";
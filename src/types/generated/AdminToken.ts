@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AdminScope } from "./AdminScope";
+
+export interface AdminToken { name: string, token: string, scopes: Array<AdminScope>, }
\ No newline at end of file
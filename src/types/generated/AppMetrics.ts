@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface AppMetrics { residentBytes: number | null, activeRequests: number, activeStreams: number, streamBufferBytes: number, logEntries: number, logBufferBytes: number, logStoreBytes: number | null, warnings: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface BackoffConfig { baseDelayMs?: number, multiplier?: number, jitter?: number, maxDelayMs?: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BalanceProvider } from "./BalanceProvider";
+
+export interface BalanceConfig { provider: BalanceProvider, url?: string, intervalSecs?: number, lowThreshold?: number, }
\ No newline at end of file
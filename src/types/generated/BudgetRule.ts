@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BudgetAction } from "./BudgetAction";
+import type { BudgetPeriod } from "./BudgetPeriod";
+import type { BudgetScope } from "./BudgetScope";
+
+export interface BudgetRule { name: string, scope: BudgetScope, period: BudgetPeriod, maxCost?: number, maxTokens?: number, action: BudgetAction, }
\ No newline at end of file
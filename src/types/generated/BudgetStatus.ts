@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BudgetAction } from "./BudgetAction";
+
+export interface BudgetStatus { rule: string, periodKey: string, spentCost: number, spentTokens: number, exceeded: boolean, action: BudgetAction, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ChecksumReport { requestSha256: string, responseSha256: string | null, mismatches: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonDifference } from "./JsonDifference";
+
+export interface CompareReport { primaryLogId: string, primaryUpstreamId: string, secondaryUpstreamId: string, primaryStatus: number, secondaryStatus: number | null, primaryBody: string | null, secondaryBody: string | null, secondaryError: string | null, differences: Array<JsonDifference>, }
\ No newline at end of file
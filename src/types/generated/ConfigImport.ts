@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProxyConfig } from "./ProxyConfig";
+
+export interface ConfigImport { config: ProxyConfig, missingSecrets: Array<string>, droppedHookCommands: Array<string>, }
\ No newline at end of file
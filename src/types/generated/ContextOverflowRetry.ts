@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OverflowStrategy } from "./OverflowStrategy";
+
+export interface ContextOverflowRetry { strategy: OverflowStrategy, maxTokensRatio?: number, trimCount?: number, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface CorsConfig { permissive?: boolean, allowedOrigins?: Array<string>, allowedMethods?: Array<string>, allowedHeaders?: Array<string>, exposeHeaders?: Array<string>, maxAgeSecs?: number, }
\ No newline at end of file
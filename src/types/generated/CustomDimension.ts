@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DimensionRule } from "./DimensionRule";
+
+export interface CustomDimension { name: string, rules: Array<DimensionRule>, fallback?: string, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServiceDailyUsage } from "./ServiceDailyUsage";
+
+export interface DailySummary { date: string, requests: number, errors: number, errorRate: number, tokens: number, cost: number, services: Array<ServiceDailyUsage>, }
\ No newline at end of file
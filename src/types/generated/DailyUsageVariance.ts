@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface DailyUsageVariance { date: string, providerTokens: number | null, localTokens: number, providerCost: number | null, localCost: number, tokenVariance: number | null, costVarianceRatio: number | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DimensionSource } from "./DimensionSource";
+
+export interface DimensionRule { source: DimensionSource, key: string, pattern?: string, }
\ No newline at end of file
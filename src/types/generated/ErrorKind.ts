@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ErrorKind = "invalid_api_key" | "permission_denied" | "insufficient_quota" | "rate_limited" | "content_filter" | "context_length_exceeded" | "invalid_request" | "not_found" | "overloaded" | "timeout" | "server_error" | "protocol_mismatch" | "unknown";
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServiceConfig } from "./ServiceConfig";
+
+export interface GatewayImport { services: Array<ServiceConfig>, warnings: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WindowStats } from "./WindowStats";
+
+export interface GroupStats { key: string, label: string | null, totalRequests: number, successCount: number, errorCount: number, totalDurationMs: number, p50Ms: number, p95Ms: number, p99Ms: number, windows: Array<WindowStats>, promptTokens: number, completionTokens: number, totalTokens: number, totalCost: number, }
\ No newline at end of file
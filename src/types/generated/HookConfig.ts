@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HookEvent } from "./HookEvent";
+
+export interface HookConfig { events: Array<HookEvent>, url?: string, command?: string, }
\ No newline at end of file
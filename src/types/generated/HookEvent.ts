@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type HookEvent = "proxy_started" | "proxy_stopped" | "config_changed" | "upstream_unhealthy" | "upstream_recovered";
\ No newline at end of file
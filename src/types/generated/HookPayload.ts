@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HookEvent } from "./HookEvent";
+
+export interface HookPayload { event: HookEvent, timestamp: string, listenPorts?: Array<number>, serviceName?: string, upstreamId?: string, upstreamLabel?: string, errorRate?: number, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface KeyExpiryStatus { serviceName: string, upstreamId: string, upstreamLabel: string | null, expiresAt: string, daysLeft: number, expired: boolean, }
\ No newline at end of file
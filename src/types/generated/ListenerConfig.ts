@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServiceConfig } from "./ServiceConfig";
+
+export interface ListenerConfig { listenPort: number, services: Array<ServiceConfig>, }
\ No newline at end of file
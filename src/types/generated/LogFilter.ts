@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface LogFilter { statusClass?: string, serviceName?: string, upstreamLabel?: string, method?: string, search?: string, since?: string, until?: string, streamingOnly?: boolean, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProxyLogEntry } from "./ProxyLogEntry";
+
+export interface LogPage { entries: Array<ProxyLogEntry>, total: number, hasMore: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type LogStorageConfig = { "type": "memory" } | { "type": "sqlite", path?: string, } | { "type": "jsonl", dir?: string, } | { "type": "clickhouse", url: string, table: string, user?: string, password?: string, };
\ No newline at end of file
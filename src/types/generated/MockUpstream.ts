@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface MockUpstream { status?: number, body?: string, contentType?: string, streamChunks?: Array<string>, chunkDelayMs?: number, failFirst?: number, failureStatus?: number, }
\ No newline at end of file
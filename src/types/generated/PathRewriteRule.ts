@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PathRewriteRule = { "type": "regex", pattern: string, replacement: string, } | { "type": "replacePrefix", from: string, to: string, } | { "type": "addPrefix", prefix: string, };
\ No newline at end of file
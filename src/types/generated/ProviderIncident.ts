@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ProviderIncident { id: string, name: string, status: string, impact: string | null, url: string | null, startedAt: string | null, }
\ No newline at end of file
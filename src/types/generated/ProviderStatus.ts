@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProviderIncident } from "./ProviderIncident";
+
+export interface ProviderStatus { provider: string, indicator: string | null, description: string | null, incidents: Array<ProviderIncident>, upstreamIds: Array<string>, checkedAt: string, error: string | null, }
\ No newline at end of file
@@ -1,4 +1,28 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AdminApiConfig } from "./AdminApiConfig";
+import type { AdminToken } from "./AdminToken";
+import type { BackoffConfig } from "./BackoffConfig";
+import type { BudgetRule } from "./BudgetRule";
+import type { ConfigEncryption } from "./ConfigEncryption";
+import type { CorsConfig } from "./CorsConfig";
+import type { CustomDimension } from "./CustomDimension";
+import type { DailySummaryConfig } from "./DailySummaryConfig";
+import type { ErrorAction } from "./ErrorAction";
+import type { ErrorKind } from "./ErrorKind";
+import type { HookConfig } from "./HookConfig";
+import type { KeyExpiryConfig } from "./KeyExpiryConfig";
+import type { ListenerConfig } from "./ListenerConfig";
+import type { LogStorageConfig } from "./LogStorageConfig";
+import type { ModelPrice } from "./ModelPrice";
+import type { ProviderCapabilities } from "./ProviderCapabilities";
+import type { ProviderStatusConfig } from "./ProviderStatusConfig";
+import type { RedactionConfig } from "./RedactionConfig";
+import type { RequestRateLimit } from "./RequestRateLimit";
+import type { RetentionConfig } from "./RetentionConfig";
 import type { ServiceConfig } from "./ServiceConfig";
+import type { StreamBridgeConfig } from "./StreamBridgeConfig";
+import type { SyntheticEndpoint } from "./SyntheticEndpoint";
+import type { TeeSink } from "./TeeSink";
+import type { TracingConfig } from "./TracingConfig";
 
-export interface ProxyConfig { listenPort: number, globalKey: string | null, proxyUrl: string | null, fallbackRetries: number, services: Array<ServiceConfig>, }
\ No newline at end of file
+export interface ProxyConfig { listenPort: number, globalKey: string | null, proxyUrl: string | null, fallbackRetries: number, services: Array<ServiceConfig>, redaction?: RedactionConfig, retention?: RetentionConfig, errorActions?: Partial<Record<ErrorKind, ErrorAction>>, streamTee?: TeeSink, pricing?: Array<ModelPrice>, logStorage?: LogStorageConfig, adminTokens?: Array<AdminToken>, adminApi?: AdminApiConfig, budgets?: Array<BudgetRule>, tracing?: TracingConfig, listeners?: Array<ListenerConfig>, verifyChecksums?: boolean, traceHeaders?: boolean, syntheticEndpoints?: Array<SyntheticEndpoint>, backoff?: BackoffConfig, requestRateLimit?: RequestRateLimit, keyExpiry?: KeyExpiryConfig, providerCapabilities?: Array<ProviderCapabilities>, dailySummary?: DailySummaryConfig, cors?: CorsConfig, maxClientTimeoutMs?: number, hooks?: Array<HookConfig>, customDimension?: CustomDimension, configEncryption?: ConfigEncryption, streamBridge?: StreamBridgeConfig, providerStatus?: ProviderStatusConfig, }
\ No newline at end of file
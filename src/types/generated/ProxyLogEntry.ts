@@ -1,3 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChecksumReport } from "./ChecksumReport";
+import type { CompareReport } from "./CompareReport";
+import type { ErrorKind } from "./ErrorKind";
+import type { ImageSummary } from "./ImageSummary";
+import type { TimelineEvent } from "./TimelineEvent";
+import type { TokenUsage } from "./TokenUsage";
 
-export interface ProxyLogEntry { id: string, timestamp: string, method: string, path: string, upstreamUrl: string, listenPort: number, routeKey: string | null, upstreamLabel: string | null, serviceName: string | null, basePath: string | null, status: number | null, durationMs: number, error: string | null, retryAction: string | null, requestHeaders: string | null, requestBody: string | null, responseHeaders: string | null, responseBody: string | null, clientIp: string | null, isStreaming: boolean, }
\ No newline at end of file
+export interface ProxyLogEntry { id: string, timestamp: string, method: string, path: string, upstreamUrl: string, listenPort: number, routeKey: string | null, upstreamLabel: string | null, upstreamId: string | null, serviceName: string | null, basePath: string | null, model: string | null, status: number | null, durationMs: number, error: string | null, retryAction: string | null, requestHeaders: string | null, requestBody: string | null, responseHeaders: string | null, responseBody: string | null, clientIp: string | null, isStreaming: boolean, errorKind: ErrorKind | null, usage: TokenUsage | null, cost: number | null, conversationId: string | null, outboundRequest: string | null, timeline: Array<TimelineEvent>, checksum: ChecksumReport | null, seq: number, traceId: string | null, cacheHit: boolean, deduplicated: boolean, defaultModelApplied: boolean, shadow: boolean, compare: CompareReport | null, images: ImageSummary | null, streamMismatch: boolean, piiMasked: number | null, dimension: string | null, drill: boolean, clientApp: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type QueryParamRule = { "type": "add", name: string, value: string, } | { "type": "override", name: string, value: string, } | { "type": "remove", name: string, };
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QueuedJobResult } from "./QueuedJobResult";
+import type { QueuedJobStatus } from "./QueuedJobStatus";
+
+export interface QueuedJob { id: string, createdAt: string, serviceName: string, method: string, path: string, status: QueuedJobStatus, attempts: number, maxAttempts: number, nextAttemptMs: number, lastError: string | null, result: QueuedJobResult | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface QueuedJobResult { status: number, contentType: string | null, body: string, completedAt: string, }
\ No newline at end of file
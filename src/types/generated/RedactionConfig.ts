@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface RedactionConfig { headerNames: Array<string>, jsonPaths: Array<string>, patterns: Array<string>, responseHeaderAllowlist: Array<string>, responseHeaderDenylist: Array<string>, }
\ No newline at end of file
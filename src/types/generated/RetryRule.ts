@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorAction } from "./ErrorAction";
+
+export interface RetryRule { statuses?: Array<number>, bodyPattern?: string, jsonPath?: string, jsonEquals?: string, action: ErrorAction, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SchedulerStats { requestsPerMinute: number | null, tokensPerMinute: number | null, availableRequests: number | null, availableTokens: number | null, delayedRequests: number, totalDelayMs: number, maxConcurrency: number | null, inFlight: number, queuedInteractive: number, queuedBatch: number, }
\ No newline at end of file
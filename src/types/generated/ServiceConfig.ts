@@ -1,4 +1,17 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AccessSchedule } from "./AccessSchedule";
+import type { CompareConfig } from "./CompareConfig";
+import type { ContextOverflowRetry } from "./ContextOverflowRetry";
+import type { MirrorConfig } from "./MirrorConfig";
+import type { ModelFilter } from "./ModelFilter";
+import type { PathRewriteRule } from "./PathRewriteRule";
+import type { PiiMaskConfig } from "./PiiMaskConfig";
+import type { ResponseCacheConfig } from "./ResponseCacheConfig";
+import type { RetryQueueConfig } from "./RetryQueueConfig";
+import type { RetryRule } from "./RetryRule";
+import type { RoutingMode } from "./RoutingMode";
+import type { StreamingDetection } from "./StreamingDetection";
+import type { TimeoutConfig } from "./TimeoutConfig";
 import type { UpstreamEntry } from "./UpstreamEntry";
 
-export interface ServiceConfig { id: string, name: string, basePath: string, enabled: boolean, upstreams: Array<UpstreamEntry>, }
\ No newline at end of file
+export interface ServiceConfig { id: string, name: string, basePath: string, enabled: boolean, upstreams: Array<UpstreamEntry>, captureBodies?: boolean, logRequestBody?: boolean, logResponseBody?: boolean, paused?: boolean, pausedResponse?: string, timeouts?: TimeoutConfig, answerLocally?: boolean, streaming?: StreamingDetection, retryRules?: Array<RetryRule>, cache?: ResponseCacheConfig, dedupeInFlight?: boolean, defaultModel?: string, mirror?: MirrorConfig, routing?: RoutingMode, compare?: CompareConfig, maxImageBytes?: number, contextOverflow?: ContextOverflowRetry, pathRewrites?: Array<PathRewriteRule>, policyFallbackUpstreamId?: string, headers?: Record<string, string>, blockedRequestHeaders?: Array<string>, blockedResponseHeaders?: Array<string>, maxRequestBytes?: number, retryQueue?: RetryQueueConfig, modelFilter?: ModelFilter, piiMask?: PiiMaskConfig, accessSchedule?: AccessSchedule, }
\ No newline at end of file
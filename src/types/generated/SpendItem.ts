@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SpendItem { key: string, label: string | null, totalRequests: number, totalTokens: number, totalCost: number, }
\ No newline at end of file
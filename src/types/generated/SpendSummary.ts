@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SpendItem } from "./SpendItem";
+
+export interface SpendSummary { totalCost: number, byUpstream: Array<SpendItem>, byService: Array<SpendItem>, byDimension: Array<SpendItem>, }
\ No newline at end of file
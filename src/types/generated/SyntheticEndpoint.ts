@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SyntheticEndpoint { path: string, method?: string, status?: number, body: string, contentType?: string, public?: boolean, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TimelineEventKind } from "./TimelineEventKind";
+
+export interface TimelineEvent { kind: TimelineEventKind, offsetMs: number, attempt: number | null, upstreamId: string | null, detail: string | null, }
\ No newline at end of file
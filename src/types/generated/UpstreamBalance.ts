@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BalanceProvider } from "./BalanceProvider";
+
+export interface UpstreamBalance { upstreamId: string, upstreamLabel: string | null, provider: BalanceProvider, balance: number | null, currency: string | null, checkedAt: string, error: string | null, low: boolean, }
\ No newline at end of file
@@ -1,3 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BalanceConfig } from "./BalanceConfig";
+import type { ChaosConfig } from "./ChaosConfig";
+import type { MockUpstream } from "./MockUpstream";
+import type { QueryParamRule } from "./QueryParamRule";
+import type { RateLimitConfig } from "./RateLimitConfig";
+import type { StructuredOutputMode } from "./StructuredOutputMode";
+import type { TimeoutConfig } from "./TimeoutConfig";
 
-export interface UpstreamEntry { id: string, label: string | null, upstreamBase: string, apiKey: string | null, priority: number, enabled: boolean, }
\ No newline at end of file
+export interface UpstreamEntry { id: string, label: string | null, upstreamBase: string, apiKey: string | null, priority: number, enabled: boolean, rateLimit?: RateLimitConfig, userAgent?: string, headers?: Record<string, string>, auditOutbound?: boolean, timeouts?: TimeoutConfig, notes?: string, color?: string, tags?: Array<string>, createdAt?: string, lastVerifiedAt?: string, keyExpiresAt?: string, balance?: BalanceConfig, provider?: string, weight?: number, mock?: MockUpstream, chaos?: ChaosConfig, structuredOutput?: StructuredOutputMode, queryParams?: Array<QueryParamRule>, hostOverride?: string, modelMap?: Record<string, string>, }
\ No newline at end of file
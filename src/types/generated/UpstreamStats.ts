@@ -1,3 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SchedulerStats } from "./SchedulerStats";
+import type { WindowStats } from "./WindowStats";
 
-export interface UpstreamStats { upstreamId: string, upstreamLabel: string | null, totalRequests: number, successCount: number, errorCount: number, totalDurationMs: number, }
\ No newline at end of file
+export interface UpstreamStats { upstreamId: string, upstreamLabel: string | null, totalRequests: number, successCount: number, errorCount: number, totalDurationMs: number, minDurationMs: number, maxDurationMs: number, p50Ms: number, p95Ms: number, p99Ms: number, windows: Array<WindowStats>, scheduler: SchedulerStats | null, promptTokens: number, completionTokens: number, totalTokens: number, totalCost: number, archived: boolean, weightShare?: number, trafficShare?: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DailyUsageVariance } from "./DailyUsageVariance";
+
+export interface UsageReconciliation { days: Array<DailyUsageVariance>, providerTokens: number, localTokens: number, providerCost: number, localCost: number, calibrationFactor: number | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface WindowStats { windowSecs: number, requests: number, errors: number, requestsPerMinute: number, errorRate: number, avgDurationMs: number, maxDurationMs: number, }
\ No newline at end of file